@@ -0,0 +1,44 @@
+//! Tests of the asynchronous probes of procinfo, run on a tokio runtime.
+//!
+//! The tests are in a crate of their own, built with the `async` feature of procinfo and the 2021
+//! edition, since the `async fn` of `#[tokio::test]` is not available in the 2015 edition of
+//! procinfo. Run them with `cargo test -p procinfo-async-tests`.
+
+#![cfg(test)]
+
+use procinfo::meminfo_async;
+use procinfo::pid::{limits, limits_async, limits_self, limits_self_async, mountinfo_async, mountinfo_self_async};
+
+/// Test that the system meminfo file can be read asynchronously.
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_meminfo_async() {
+    assert!(meminfo_async().await.unwrap().mem_total > 0);
+}
+
+/// Test that the mounts of the current process can be read asynchronously.
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_mountinfo_async() {
+    assert!(!mountinfo_self_async().await.unwrap().is_empty());
+    mountinfo_async(1).await.unwrap();
+}
+
+/// Test that the async variants return the same limits as the blocking probes.
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_limits_async() {
+    assert_eq!(limits_self().unwrap(), limits_self_async().await.unwrap());
+    assert_eq!(limits(1).unwrap(), limits_async(1).await.unwrap());
+}
+
+/// Test that the async probes are unsupported on other operating systems.
+#[cfg(not(target_os = "linux"))]
+#[tokio::test]
+async fn test_async_unsupported() {
+    use std::io::ErrorKind;
+
+    use procinfo::loadavg_async;
+
+    assert_eq!(ErrorKind::Unsupported, loadavg_async().await.unwrap_err().kind());
+}
@@ -3,6 +3,7 @@ extern crate rustc_version;
 use rustc_version::{Channel, version_meta};
 
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(rustc_nightly)");
     if let Channel::Nightly = version_meta().unwrap().channel {
         println!("cargo:rustc-cfg=rustc_nightly");
     }
@@ -0,0 +1,253 @@
+//! Checks that `/proc` is mounted and usable, and explains the errors caused by its mount options.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use pid::{Mountinfo, mountinfo_self};
+use platform::{self, gid_t};
+
+/// The filesystem magic number of procfs, see `statfs(2)`.
+const PROC_SUPER_MAGIC: u64 = 0x9fa0;
+
+/// The `hidepid` mount option of procfs, which restricts access to the directories of the
+/// processes of other users.
+///
+/// Processes in the group of the `gid` mount option are not restricted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HidePid {
+    /// Every process directory is accessible (`hidepid=0` or `hidepid=off`).
+    #[default]
+    Off,
+    /// The directories of the processes of other users are listed, but their files cannot be
+    /// read (`hidepid=1` or `hidepid=noaccess`).
+    NoAccess,
+    /// The directories of the processes of other users are invisible (`hidepid=2` or
+    /// `hidepid=invisible`).
+    Invisible,
+    /// The directories of the processes which cannot be traced are invisible (`hidepid=4` or
+    /// `hidepid=ptraceable`, since Linux 5.8).
+    Ptraceable,
+}
+
+impl HidePid {
+    /// Parses the value of the `hidepid` mount option, in the numeric form or (since Linux 5.8)
+    /// the named form.
+    fn parse(value: &str) -> Option<HidePid> {
+        match value {
+            "0" | "off" => Some(HidePid::Off),
+            "1" | "noaccess" => Some(HidePid::NoAccess),
+            "2" | "invisible" => Some(HidePid::Invisible),
+            "4" | "ptraceable" => Some(HidePid::Ptraceable),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the option value in the numeric form accepted by every kernel.
+impl fmt::Display for HidePid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match *self {
+            HidePid::Off => 0,
+            HidePid::NoAccess => 1,
+            HidePid::Invisible => 2,
+            HidePid::Ptraceable => 4,
+        };
+        write!(f, "hidepid={}", value)
+    }
+}
+
+/// The procfs mount at `/proc`, and the options which restrict it.
+///
+/// The default value is an unrestricted mount at `/proc`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProcMount {
+    /// The mount point.
+    pub mount_point: PathBuf,
+    /// The `hidepid` mount option.
+    pub hidepid: HidePid,
+    /// The group whose members are exempt from `hidepid`, from the `gid` mount option.
+    pub gid: Option<gid_t>,
+    /// Whether only the process directories are available (`subset=pid`, since Linux 5.8), so that
+    /// system-wide files such as `/proc/meminfo` are missing.
+    pub subset_pid: bool,
+}
+
+impl Default for ProcMount {
+    fn default() -> ProcMount {
+        ProcMount { mount_point: PathBuf::from("/proc"), hidepid: HidePid::Off, gid: None, subset_pid: false }
+    }
+}
+
+impl ProcMount {
+    /// Returns the procfs mount at `/proc` from the mounts of a process, or `None` if there is
+    /// none.
+    ///
+    /// If several procfs mounts are stacked on `/proc`, the last one, which is visible, is used.
+    fn from_mounts(mounts: &[Mountinfo]) -> Option<ProcMount> {
+        let mount = mounts.iter().rev().find(|mount| {
+            mount.mount_point == Path::new("/proc") && mount.fs_type.0 == "proc"
+        })?;
+        let mut proc_mount = ProcMount::default();
+        for option in &mount.super_opts {
+            let (name, value) = match option.find('=') {
+                Some(idx) => (&option[..idx], &option[idx + 1..]),
+                None => (&option[..], ""),
+            };
+            match name {
+                "hidepid" => proc_mount.hidepid = HidePid::parse(value).unwrap_or(HidePid::Off),
+                "gid" => proc_mount.gid = value.parse().ok(),
+                "subset" => proc_mount.subset_pid = value == "pid",
+                _ => (),
+            }
+        }
+        Some(proc_mount)
+    }
+
+    /// Returns an explanation of how the mount options of `/proc` may have caused an error
+    /// returned by a probe, or `None` if they are unrelated.
+    pub fn explain(&self, error: &Error) -> Option<String> {
+        let exemption = match self.gid {
+            Some(gid) => format!(", except to members of group {}", gid),
+            None => String::new(),
+        };
+        match (error.kind(), self.hidepid) {
+            (ErrorKind::PermissionDenied, HidePid::NoAccess) => {
+                Some(format!("{} is set on {}, the processes of other users are not accessible{}",
+                             self.hidepid, self.mount_point.display(), exemption))
+            }
+            (ErrorKind::NotFound, HidePid::Invisible) | (ErrorKind::PermissionDenied, HidePid::Invisible) => {
+                Some(format!("{} is set on {}, the processes of other users are invisible{}",
+                             self.hidepid, self.mount_point.display(), exemption))
+            }
+            (ErrorKind::NotFound, HidePid::Ptraceable) | (ErrorKind::PermissionDenied, HidePid::Ptraceable) => {
+                Some(format!("{} is set on {}, processes which cannot be traced are invisible{}",
+                             self.hidepid, self.mount_point.display(), exemption))
+            }
+            (ErrorKind::NotFound, _) if self.subset_pid => {
+                Some(format!("subset=pid is set on {}, only process directories are available",
+                             self.mount_point.display()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checks that procfs is mounted at `/proc`, and returns its restricting mount options.
+///
+/// The result is meant to be logged at startup, so that the errors returned by the probes can be
+/// understood, see `explain_error`.
+///
+/// # Errors
+///
+/// Returns an error of kind `ErrorKind::NotFound` if `/proc` does not exist, is not a procfs
+/// mount, or is missing from `/proc/self/mountinfo`.
+pub fn check() -> Result<ProcMount> {
+    let filesystem_type = match platform::filesystem_type("/proc") {
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            return Err(Error::new(ErrorKind::NotFound, "/proc does not exist"));
+        }
+        result => result?,
+    };
+    if filesystem_type != PROC_SUPER_MAGIC {
+        return Err(Error::new(ErrorKind::NotFound,
+                              format!("/proc is not a procfs mount (filesystem type {:#x})", filesystem_type)));
+    }
+    ProcMount::from_mounts(&mountinfo_self()?)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "no procfs mount at /proc in /proc/self/mountinfo"))
+}
+
+/// Returns the message of an error returned by a probe, with an explanation if it may have been
+/// caused by `/proc` being missing or restricted by its mount options.
+///
+/// For example, a `PermissionDenied` error reading the status of another user's process is
+/// explained by `hidepid=1 is set on /proc, the processes of other users are not accessible`.
+pub fn explain_error(error: &Error) -> String {
+    match check() {
+        Ok(mount) => match mount.explain(error) {
+            Some(explanation) => format!("{} ({})", error, explanation),
+            None => error.to_string(),
+        },
+        Err(ref check_error) if check_error.kind() != ErrorKind::Unsupported => {
+            format!("{} ({})", error, check_error)
+        }
+        Err(_) => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind};
+    use std::path::PathBuf;
+
+    use pid::Mountinfo;
+    use super::{HidePid, ProcMount, check, explain_error};
+
+    fn mounts(lines: &[&str]) -> Vec<Mountinfo> {
+        lines.iter().map(|line| Mountinfo::parse(line.as_bytes()).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_from_mounts() {
+        let root = "1 0 8:1 / / rw,relatime - ext4 /dev/sda1 rw";
+        let sys = "21 1 0:20 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw";
+
+        assert_eq!(Some(ProcMount::default()),
+                   ProcMount::from_mounts(&mounts(&[root, sys, "22 1 0:21 / /proc rw,nosuid,nodev,noexec,relatime \
+                                                                shared:13 - proc proc rw"])));
+        assert_eq!(Some(ProcMount { hidepid: HidePid::Invisible, gid: Some(1001), ..ProcMount::default() }),
+                   ProcMount::from_mounts(&mounts(&[root, "22 1 0:21 / /proc rw,relatime - proc proc \
+                                                           rw,hidepid=2,gid=1001"])));
+        assert_eq!(Some(ProcMount { hidepid: HidePid::Ptraceable, subset_pid: true, ..ProcMount::default() }),
+                   ProcMount::from_mounts(&mounts(&[root, "22 1 0:21 / /proc rw,relatime - proc proc \
+                                                           rw,hidepid=ptraceable,subset=pid"])));
+        assert_eq!(Some(ProcMount { hidepid: HidePid::NoAccess, ..ProcMount::default() }),
+                   ProcMount::from_mounts(&mounts(&[root, "22 1 0:21 / /proc rw - proc proc rw,hidepid=noaccess"])));
+
+        // The last of stacked mounts is visible.
+        assert_eq!(Some(ProcMount { hidepid: HidePid::Invisible, ..ProcMount::default() }),
+                   ProcMount::from_mounts(&mounts(&["22 1 0:21 / /proc rw - proc proc rw",
+                                                    "23 22 0:22 / /proc rw - proc proc rw,hidepid=invisible"])));
+
+        // procfs mounted elsewhere, or another filesystem at /proc.
+        assert_eq!(None, ProcMount::from_mounts(&mounts(&[root, "22 1 0:21 / /mnt/proc rw - proc proc rw"])));
+        assert_eq!(None, ProcMount::from_mounts(&mounts(&[root, "22 1 0:21 / /proc rw - tmpfs tmpfs rw"])));
+        assert_eq!(None, ProcMount::from_mounts(&[]));
+    }
+
+    #[test]
+    fn test_explain() {
+        let denied = Error::from(ErrorKind::PermissionDenied);
+        let missing = Error::from(ErrorKind::NotFound);
+
+        assert_eq!(None, ProcMount::default().explain(&denied));
+        assert_eq!(None, ProcMount::default().explain(&missing));
+
+        let invisible = ProcMount { hidepid: HidePid::Invisible, ..ProcMount::default() };
+        assert_eq!(Some("hidepid=2 is set on /proc, the processes of other users are invisible".to_owned()),
+                   invisible.explain(&missing));
+        assert_eq!(None, invisible.explain(&Error::from(ErrorKind::InvalidInput)));
+
+        let no_access = ProcMount { hidepid: HidePid::NoAccess, gid: Some(10), ..ProcMount::default() };
+        assert_eq!(Some("hidepid=1 is set on /proc, the processes of other users are not accessible, except \
+                         to members of group 10".to_owned()),
+                   no_access.explain(&denied));
+        assert_eq!(None, no_access.explain(&missing));
+
+        let subset = ProcMount { subset_pid: true, ..ProcMount::default() };
+        assert_eq!(Some("subset=pid is set on /proc, only process directories are available".to_owned()),
+                   subset.explain(&missing));
+    }
+
+    /// Test that procfs is mounted at `/proc` on the test system.
+    #[test]
+    fn test_check() {
+        let mount = check().unwrap();
+        assert_eq!(PathBuf::from("/proc"), mount.mount_point);
+
+        let error = Error::new(ErrorKind::InvalidInput, "bad input");
+        assert_eq!("bad input", explain_error(&error));
+    }
+}
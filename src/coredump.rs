@@ -0,0 +1,242 @@
+//! Core dump configuration from `/proc/sys/kernel/core_*` and `/proc/self/limits`.
+
+use std::io::Result;
+
+use pid::{Limit, limits_self};
+use sys;
+use sysconf::page_size;
+
+/// Where the kernel writes core dumps, as configured by `kernel.core_pattern`.
+///
+/// See `core(5)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CorePattern {
+    /// Core dumps are written to a file named by the pattern, which may contain `%` specifiers,
+    /// such as `%p` for the pid. A relative pattern is relative to the working directory of the
+    /// crashing process.
+    File(String),
+    /// Core dumps are piped to the standard input of a program, with `|` as the first character of
+    /// the pattern.
+    Pipe {
+        /// The path of the program.
+        program: String,
+        /// The arguments of the program, which may contain `%` specifiers.
+        args: Vec<String>,
+    },
+}
+
+/// Defaults to the kernel's default pattern, `core`.
+impl Default for CorePattern {
+    fn default() -> CorePattern {
+        CorePattern::File("core".to_owned())
+    }
+}
+
+/// Splits the command line of a pipe pattern into arguments, like `format_corename` in
+/// `Linux/fs/coredump.c`.
+///
+/// Arguments are separated by whitespace. The character following a `%` is part of the specifier,
+/// even if it is whitespace, so the specifiers are kept intact.
+fn split_pipe_args(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            if !arg.is_empty() {
+                args.push(arg);
+                arg = String::new();
+            }
+            continue;
+        }
+        arg.push(c);
+        if c == '%' {
+            arg.extend(chars.next());
+        }
+    }
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
+
+impl CorePattern {
+    /// Parses the value of `kernel.core_pattern`.
+    pub fn parse(pattern: &str) -> CorePattern {
+        if let Some(command) = pattern.strip_prefix('|') {
+            let mut args = split_pipe_args(command);
+            let program = if args.is_empty() { String::new() } else { args.remove(0) };
+            CorePattern::Pipe { program, args }
+        } else {
+            CorePattern::File(pattern.to_owned())
+        }
+    }
+}
+
+/// What happens when the current process dumps core, see `CoreConfig::effective`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CoreDump {
+    /// No core dump is produced.
+    Disabled,
+    /// The core dump is written to a file.
+    File {
+        /// The file name pattern, including the `.%p` suffix added by `kernel.core_uses_pid`.
+        pattern: String,
+        /// The maximum size of the core dump in bytes, or `None` if unlimited.
+        max_size: Option<u64>,
+    },
+    /// The core dump is piped to a program, regardless of its size.
+    Pipe {
+        /// The path of the program.
+        program: String,
+        /// The arguments of the program, which may contain `%` specifiers.
+        args: Vec<String>,
+    },
+}
+
+/// The core dump configuration of the system and of the current process.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CoreConfig {
+    /// The pattern of core dumps (`kernel.core_pattern`).
+    pub pattern: CorePattern,
+    /// Whether `.<pid>` is appended to core dump file names which do not contain `%p`
+    /// (`kernel.core_uses_pid`).
+    pub uses_pid: bool,
+    /// The maximum number of core dumps which may be piped to programs concurrently, or 0 if
+    /// unlimited (`kernel.core_pipe_limit`).
+    pub pipe_limit: u32,
+    /// The core file size limit of the current process.
+    pub core_file_size: Limit<u64>,
+}
+
+impl CoreConfig {
+    /// Returns whether, and where, the kernel dumps the core of the current process.
+    ///
+    /// A core dump file is only written if the soft core file size limit is at least a page. A
+    /// core dump is piped regardless of the limit, unless it is 1, which crash handlers use to
+    /// disable core dumps of their own crashes. An empty file pattern disables core dumps, unless
+    /// `uses_pid` is set.
+    ///
+    /// Core dumps may still fail, e.g. if the working directory is not writable, and are not
+    /// produced for set-user-ID programs unless `fs.suid_dumpable` allows it.
+    pub fn effective(&self) -> CoreDump {
+        let soft = self.core_file_size.soft;
+        match self.pattern {
+            CorePattern::Pipe { ref program, ref args } => {
+                if soft == Some(1) || program.is_empty() {
+                    CoreDump::Disabled
+                } else {
+                    CoreDump::Pipe { program: program.clone(), args: args.clone() }
+                }
+            }
+            CorePattern::File(ref pattern) => {
+                if soft.is_some_and(|soft| soft < page_size()) || (pattern.is_empty() && !self.uses_pid) {
+                    return CoreDump::Disabled;
+                }
+                let mut pattern = pattern.clone();
+                if self.uses_pid && !pattern.contains("%p") {
+                    pattern.push_str(".%p");
+                }
+                CoreDump::File { pattern, max_size: soft }
+            }
+        }
+    }
+}
+
+/// Returns the core dump configuration of the system and of the current process.
+pub fn core_config() -> Result<CoreConfig> {
+    let pattern: String = sys::read("kernel.core_pattern")?;
+    Ok(CoreConfig {
+        pattern: CorePattern::parse(&pattern),
+        uses_pid: sys::read::<u32>("kernel.core_uses_pid")? != 0,
+        pipe_limit: sys::read("kernel.core_pipe_limit")?,
+        core_file_size: limits_self()?.max_core_file_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pid::{Limit, limits_self};
+    use sysconf::page_size;
+    use super::{CoreConfig, CoreDump, CorePattern, core_config, split_pipe_args};
+
+    fn pipe(program: &str, args: &[&str]) -> CorePattern {
+        CorePattern::Pipe { program: program.to_owned(), args: args.iter().map(|&arg| arg.to_owned()).collect() }
+    }
+
+    #[test]
+    fn test_parse_core_pattern() {
+        assert_eq!(CorePattern::File("core".to_owned()), CorePattern::parse("core"));
+        assert_eq!(CorePattern::File("/var/crash/core.%e.%p.%t".to_owned()),
+                   CorePattern::parse("/var/crash/core.%e.%p.%t"));
+
+        // systemd-coredump.
+        assert_eq!(pipe("/lib/systemd/systemd-coredump", &["%P", "%u", "%g", "%s", "%t", "9223372036854775808", "%h"]),
+                   CorePattern::parse("|/lib/systemd/systemd-coredump %P %u %g %s %t 9223372036854775808 %h"));
+        // apport, with whitespace after the `|`.
+        assert_eq!(pipe("/usr/share/apport/apport",
+                        &["-p%p", "-s%s", "-c%c", "-d%d", "-P%P", "-u%u", "-g%g", "--", "%E"]),
+                   CorePattern::parse("| /usr/share/apport/apport -p%p -s%s -c%c -d%d -P%P -u%u -g%g -- %E"));
+        assert_eq!(pipe("", &[]), CorePattern::parse("|"));
+    }
+
+    #[test]
+    fn test_split_pipe_args() {
+        assert_eq!(vec!["/bin/handler", "%p", "%%", "x%e"], split_pipe_args("/bin/handler  %p\t%% x%e "));
+        // The character following a `%` does not separate arguments.
+        assert_eq!(vec!["a% b", "c"], split_pipe_args("a% b c"));
+        assert_eq!(vec!["a%"], split_pipe_args("a%"));
+        assert!(split_pipe_args("  ").is_empty());
+    }
+
+    #[test]
+    fn test_effective() {
+        let unlimited = Limit { soft: None, hard: None };
+        let config = CoreConfig {
+            pattern: CorePattern::parse("core"),
+            uses_pid: true,
+            pipe_limit: 0,
+            core_file_size: Limit { soft: Some(0), hard: None },
+        };
+        assert_eq!(CoreDump::Disabled, config.effective());
+
+        let config = CoreConfig { core_file_size: unlimited, ..config };
+        assert_eq!(CoreDump::File { pattern: "core.%p".to_owned(), max_size: None }, config.effective());
+
+        let config = CoreConfig {
+            pattern: CorePattern::parse("/var/crash/%e.%p"),
+            core_file_size: Limit { soft: Some(page_size() * 1024), hard: None },
+            ..config
+        };
+        assert_eq!(CoreDump::File { pattern: "/var/crash/%e.%p".to_owned(), max_size: Some(page_size() * 1024) },
+                   config.effective());
+
+        let config = CoreConfig { pattern: CorePattern::parse(""), uses_pid: false, ..config };
+        assert_eq!(CoreDump::Disabled, config.effective());
+        let config = CoreConfig { uses_pid: true, ..config };
+        assert_eq!(CoreDump::File { pattern: ".%p".to_owned(), max_size: Some(page_size() * 1024) },
+                   config.effective());
+
+        // Piped core dumps ignore the limit, except for 1.
+        let config = CoreConfig {
+            pattern: CorePattern::parse("|/usr/lib/handler %p"),
+            core_file_size: Limit { soft: Some(0), hard: Some(0) },
+            ..config
+        };
+        assert_eq!(CoreDump::Pipe { program: "/usr/lib/handler".to_owned(), args: vec!["%p".to_owned()] },
+                   config.effective());
+        let config = CoreConfig { core_file_size: Limit { soft: Some(1), hard: Some(1) }, ..config };
+        assert_eq!(CoreDump::Disabled, config.effective());
+    }
+
+    /// Test that the core dump configuration of the system can be read.
+    #[test]
+    fn test_core_config() {
+        let config = core_config().unwrap();
+        assert_eq!(limits_self().unwrap().max_core_file_size.hard, config.core_file_size.hard);
+        config.effective();
+    }
+}
@@ -0,0 +1,205 @@
+//! Deltas of monotonic kernel counters between two samples.
+
+use std::io::{Error, ErrorKind, Result};
+use std::time::{Duration, Instant};
+
+/// An irregularity of a counter between two samples.
+///
+/// Warnings are ordered by severity, so the most severe of several warnings is their maximum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeltaWarning {
+    /// The counter wrapped around, and its increase was corrected for the wrap.
+    Wrapped,
+    /// The counter decreased by more than a wrap explains, e.g. because of a kernel accounting bug,
+    /// or because the process or system it counts for was replaced between the samples.
+    Reset,
+}
+
+/// The change of a monotonic counter between two samples, see `monotonic_delta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeltaResult {
+    /// The counter increased by the value, which may be zero.
+    Increase(u64),
+    /// The counter wrapped around, and increased by the value.
+    Wrapped(u64),
+    /// The counter was reset, so its increase is unknown.
+    Reset,
+}
+
+impl DeltaResult {
+    /// Returns the increase, corrected for a wrap, or `None` if the counter was reset.
+    pub fn corrected(&self) -> Option<u64> {
+        match *self {
+            DeltaResult::Increase(increase) | DeltaResult::Wrapped(increase) => Some(increase),
+            DeltaResult::Reset => None,
+        }
+    }
+
+    /// Returns the increase, corrected for a wrap, or 0 if the counter was reset.
+    pub fn saturating(&self) -> u64 {
+        self.corrected().unwrap_or(0)
+    }
+
+    /// Returns the increase, or an error of kind `ErrorKind::InvalidInput` if the counter wrapped
+    /// or was reset.
+    pub fn strict(&self) -> Result<u64> {
+        match *self {
+            DeltaResult::Increase(increase) => Ok(increase),
+            DeltaResult::Wrapped(_) => Err(Error::new(ErrorKind::InvalidInput, "counter wrapped between samples")),
+            DeltaResult::Reset => Err(Error::new(ErrorKind::InvalidInput, "counter decreased between samples")),
+        }
+    }
+
+    /// Returns the warning about the change, if it was not a plain increase.
+    pub fn warning(&self) -> Option<DeltaWarning> {
+        match *self {
+            DeltaResult::Increase(_) => None,
+            DeltaResult::Wrapped(_) => Some(DeltaWarning::Wrapped),
+            DeltaResult::Reset => Some(DeltaWarning::Reset),
+        }
+    }
+}
+
+/// Returns the change of a monotonic counter from an earlier sample to a later one.
+///
+/// A decrease is a wrap if it is plausible: the counter fits in 32 bits, as the jiffy counters of
+/// 32-bit kernels do, or uses the full 64 bits, and the corrected increase is less than half of
+/// the range of the counter. Any other decrease is a reset.
+pub fn monotonic_delta(prev: u64, cur: u64) -> DeltaResult {
+    if cur >= prev {
+        return DeltaResult::Increase(cur - prev);
+    }
+    let (increase, half_range) = if prev <= u64::from(u32::MAX) {
+        (cur + (1 << 32) - prev, 1 << 31)
+    } else {
+        (cur.wrapping_sub(prev), 1 << 63)
+    };
+    if increase < half_range { DeltaResult::Wrapped(increase) } else { DeltaResult::Reset }
+}
+
+/// Computes the deltas of several counters, keeping the most severe warning.
+#[derive(Debug, Default)]
+pub struct DeltaTracker {
+    /// The most severe warning of the deltas computed so far.
+    pub warning: Option<DeltaWarning>,
+}
+
+impl DeltaTracker {
+    /// Returns the change of a counter, see `monotonic_delta`.
+    pub fn delta(&mut self, prev: u64, cur: u64) -> DeltaResult {
+        let delta = monotonic_delta(prev, cur);
+        self.warning = self.warning.max(delta.warning());
+        delta
+    }
+}
+
+/// Returns the time elapsed from an earlier sample to a later one.
+///
+/// Each sample is given with the identity of what it was taken of, e.g. the pid and start time of a
+/// process, which distinguish it from a later process reusing the pid. `kind` names the samples in
+/// the error messages, e.g. `"I/O"`.
+///
+/// # Errors
+///
+/// Returns an error of kind `ErrorKind::InvalidInput` if the samples have different identities, or
+/// if the later sample was taken before the earlier one.
+pub fn sample_elapsed<T: PartialEq>(kind: &str, earlier: (T, Instant), later: (T, Instant)) -> Result<Duration> {
+    if earlier.0 != later.0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{} samples are of different processes", kind)));
+    }
+    if later.1 < earlier.1 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("{} samples are out of order", kind)));
+    }
+    Ok(later.1 - earlier.1)
+}
+
+/// Returns the rate per second of an increase over the elapsed time, or zero if no time elapsed.
+pub fn per_sec(increase: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs == 0.0 { 0.0 } else { increase as f64 / secs }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::time::{Duration, Instant};
+
+    use super::{DeltaResult, DeltaTracker, DeltaWarning, monotonic_delta, per_sec, sample_elapsed};
+
+    #[test]
+    fn test_monotonic_delta() {
+        assert_eq!(DeltaResult::Increase(10), monotonic_delta(5, 15));
+        assert_eq!(DeltaResult::Increase(0), monotonic_delta(5, 5));
+        assert_eq!(DeltaResult::Increase(u64::MAX), monotonic_delta(0, u64::MAX));
+
+        // A 32-bit counter which wrapped.
+        assert_eq!(DeltaResult::Wrapped(10), monotonic_delta(0xffff_fffb, 5));
+        assert_eq!(DeltaResult::Wrapped(1), monotonic_delta(u64::from(u32::MAX), 0));
+        assert_eq!(DeltaResult::Wrapped((1 << 31) - 1), monotonic_delta(u64::from(u32::MAX), (1 << 31) - 2));
+        // A 64-bit counter which wrapped.
+        assert_eq!(DeltaResult::Wrapped(10), monotonic_delta(u64::MAX - 4, 5));
+
+        // Decreases which are not plausible wraps, such as a counter of a reused pid.
+        assert_eq!(DeltaResult::Reset, monotonic_delta(1000, 0));
+        assert_eq!(DeltaResult::Reset, monotonic_delta(1 << 31, (1 << 31) - 1));
+        assert_eq!(DeltaResult::Reset, monotonic_delta(1 << 40, 5));
+        assert_eq!(DeltaResult::Reset, monotonic_delta(1 << 63, 1));
+    }
+
+    #[test]
+    fn test_delta_policies() {
+        let increase = DeltaResult::Increase(10);
+        assert_eq!(Some(10), increase.corrected());
+        assert_eq!(10, increase.saturating());
+        assert_eq!(10, increase.strict().unwrap());
+        assert_eq!(None, increase.warning());
+
+        let wrapped = DeltaResult::Wrapped(10);
+        assert_eq!(Some(10), wrapped.corrected());
+        assert_eq!(10, wrapped.saturating());
+        assert_eq!(ErrorKind::InvalidInput, wrapped.strict().unwrap_err().kind());
+        assert_eq!(Some(DeltaWarning::Wrapped), wrapped.warning());
+
+        let reset = DeltaResult::Reset;
+        assert_eq!(None, reset.corrected());
+        assert_eq!(0, reset.saturating());
+        assert_eq!(ErrorKind::InvalidInput, reset.strict().unwrap_err().kind());
+        assert_eq!(Some(DeltaWarning::Reset), reset.warning());
+    }
+
+    #[test]
+    fn test_delta_tracker() {
+        let mut tracker = DeltaTracker::default();
+        tracker.delta(1, 2);
+        assert_eq!(None, tracker.warning);
+        tracker.delta(0xffff_ffff, 1);
+        assert_eq!(Some(DeltaWarning::Wrapped), tracker.warning);
+        tracker.delta(2, 1);
+        tracker.delta(0xffff_ffff, 1);
+        assert_eq!(Some(DeltaWarning::Reset), tracker.warning);
+    }
+
+    #[test]
+    fn test_sample_elapsed() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(2);
+        assert_eq!(Duration::from_secs(2), sample_elapsed("test", ((42, 1000), now), ((42, 1000), later)).unwrap());
+        assert_eq!(Duration::from_secs(0), sample_elapsed("test", ((), now), ((), now)).unwrap());
+
+        let error = sample_elapsed("test", ((42, 1000), now), ((42, 2000), later)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!("test samples are of different processes", error.to_string());
+        let error = sample_elapsed("test", ((), later), ((), now)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!("test samples are out of order", error.to_string());
+    }
+
+    #[test]
+    fn test_per_sec() {
+        assert_eq!(125.0, per_sec(500, Duration::from_secs(4)));
+        assert_eq!(2000.0, per_sec(1, Duration::from_micros(500)));
+        assert_eq!(0.0, per_sec(500, Duration::from_secs(0)));
+    }
+}
@@ -0,0 +1,431 @@
+//! Block device I/O statistics from `/proc/diskstats`, and rates computed from samples of them.
+
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+use std::time::{Duration, Instant};
+
+use delta::{DeltaTracker, DeltaWarning, per_sec, sample_elapsed};
+use parsers::read_to_end;
+use platform;
+
+/// The size of a sector in `/proc/diskstats`, regardless of the sector size of the device.
+const SECTOR_SIZE: u64 = 512;
+
+/// The I/O statistics of a block device or partition.
+///
+/// Times are in milliseconds. Counters which are not reported by the kernel are zero.
+///
+/// See `Linux/Documentation/admin-guide/iostats.rst`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiskStat {
+    /// The major number of the device.
+    pub major: u32,
+    /// The minor number of the device.
+    pub minor: u32,
+    /// The name of the device, e.g. `sda` or `dm-0`.
+    pub name: String,
+    /// Number of reads completed.
+    pub reads_completed: u64,
+    /// Number of adjacent reads merged into a single request.
+    pub reads_merged: u64,
+    /// Number of 512-byte sectors read.
+    pub sectors_read: u64,
+    /// Time spent on reads.
+    pub read_time: u64,
+    /// Number of writes completed.
+    pub writes_completed: u64,
+    /// Number of adjacent writes merged into a single request.
+    pub writes_merged: u64,
+    /// Number of 512-byte sectors written.
+    pub sectors_written: u64,
+    /// Time spent on writes.
+    pub write_time: u64,
+    /// Number of I/Os currently in progress.
+    pub ios_in_progress: u64,
+    /// Time during which I/Os were in progress.
+    pub io_time: u64,
+    /// Time spent on I/Os, weighted by the number of I/Os in progress.
+    pub weighted_io_time: u64,
+    /// Number of discards completed (since Linux 4.18).
+    pub discards_completed: u64,
+    /// Number of adjacent discards merged into a single request (since Linux 4.18).
+    pub discards_merged: u64,
+    /// Number of 512-byte sectors discarded (since Linux 4.18).
+    pub sectors_discarded: u64,
+    /// Time spent on discards (since Linux 4.18).
+    pub discard_time: u64,
+    /// Number of flushes completed (since Linux 5.5).
+    pub flushes_completed: u64,
+    /// Time spent on flushes (since Linux 5.5).
+    pub flush_time: u64,
+}
+
+/// Returns the error for a diskstats line which could not be parsed.
+fn parse_error(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse diskstats line: {:?}", line))
+}
+
+impl DiskStat {
+    /// Parses a line of a `/proc/diskstats` file.
+    pub fn parse(input: &[u8]) -> Result<DiskStat> {
+        let line = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        let mut fields = line.split_whitespace();
+        let (major, minor, name) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(major), Some(minor), Some(name)) => {
+                (major.parse().map_err(|_| parse_error(line))?,
+                 minor.parse().map_err(|_| parse_error(line))?,
+                 name.to_owned())
+            }
+            _ => return Err(parse_error(line)),
+        };
+        let counters = fields.map(|field| field.parse().ok()).collect::<Option<Vec<u64>>>();
+        let counters = match counters {
+            Some(ref counters) if counters.len() >= 11 => counters,
+            _ => return Err(parse_error(line)),
+        };
+        let counter = |idx: usize| counters.get(idx).cloned().unwrap_or(0);
+        Ok(DiskStat {
+            major,
+            minor,
+            name,
+            reads_completed: counter(0),
+            reads_merged: counter(1),
+            sectors_read: counter(2),
+            read_time: counter(3),
+            writes_completed: counter(4),
+            writes_merged: counter(5),
+            sectors_written: counter(6),
+            write_time: counter(7),
+            ios_in_progress: counter(8),
+            io_time: counter(9),
+            weighted_io_time: counter(10),
+            discards_completed: counter(11),
+            discards_merged: counter(12),
+            sectors_discarded: counter(13),
+            discard_time: counter(14),
+            flushes_completed: counter(15),
+            flush_time: counter(16),
+        })
+    }
+
+    /// Returns `true` if the statistics are of the same device as `other`.
+    fn same_device(&self, other: &DiskStat) -> bool {
+        self.major == other.major && self.minor == other.minor && self.name == other.name
+    }
+}
+
+/// Parses the diskstats file format.
+pub fn parse_diskstats(input: &[u8]) -> Result<Vec<DiskStat>> {
+    input.split(|&b| b == b'\n')
+         .filter(|line| line.iter().any(|b| !b.is_ascii_whitespace()))
+         .map(DiskStat::parse)
+         .collect()
+}
+
+/// Returns the I/O statistics of every block device and partition.
+pub fn diskstats() -> Result<Vec<DiskStat>> {
+    let mut buf = Vec::with_capacity(8192);
+    let mut file = platform::open("/proc/diskstats")?;
+    parse_diskstats(read_to_end(&mut file, &mut buf)?)
+}
+
+/// A timestamped sample of the I/O statistics of every block device.
+///
+/// Samples are taken with `DiskSample::take`, and compared with `DiskSample::delta`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DiskSample {
+    /// The I/O statistics of the devices.
+    pub disks: Vec<DiskStat>,
+    /// When the sample was taken.
+    pub timestamp: Instant,
+}
+
+/// The I/O rates of a block device between two samples.
+///
+/// The default value has every rate set to zero, and no latencies.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiskRate {
+    /// The major number of the device.
+    pub major: u32,
+    /// The minor number of the device.
+    pub minor: u32,
+    /// The name of the device.
+    pub name: String,
+    /// Reads completed per second.
+    pub reads_per_sec: f64,
+    /// Writes completed per second.
+    pub writes_per_sec: f64,
+    /// Bytes read per second.
+    pub read_bytes_per_sec: f64,
+    /// Bytes written per second.
+    pub write_bytes_per_sec: f64,
+    /// The average time a read took, including queueing, or `None` if no reads completed.
+    pub read_latency: Option<Duration>,
+    /// The average time a write took, including queueing, or `None` if no writes completed.
+    pub write_latency: Option<Duration>,
+    /// The fraction of the time during which I/Os were in progress, from 0 to 1. A device which
+    /// serves requests in parallel, such as an SSD, may have spare capacity at 1.
+    pub utilization: f64,
+    /// The most severe irregularity of the counters of the device between the samples.
+    pub warning: Option<DeltaWarning>,
+}
+
+/// The I/O rates of the block devices between two samples, see `DiskSample::delta`.
+///
+/// The default value has no elapsed time and no devices.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiskRates {
+    /// Wall-clock time between the samples.
+    pub elapsed: Duration,
+    /// The rates of the devices present in both samples, in the order of the later sample.
+    pub devices: Vec<DiskRate>,
+    /// The names of the devices which are only present in the later sample.
+    pub added: Vec<String>,
+    /// The names of the devices which are only present in the earlier sample.
+    pub removed: Vec<String>,
+}
+
+/// Returns the rates of a device between two samples taken `elapsed` apart.
+///
+/// Counters which wrap are corrected for the wrap, and counters which are otherwise reset are
+/// treated as unchanged.
+fn disk_rate(earlier: &DiskStat, later: &DiskStat, elapsed: Duration) -> DiskRate {
+    let mut tracker = DeltaTracker::default();
+    let mut delta = |earlier: u64, later: u64| tracker.delta(earlier, later).saturating();
+    let reads = delta(earlier.reads_completed, later.reads_completed);
+    let writes = delta(earlier.writes_completed, later.writes_completed);
+    let sectors_read = delta(earlier.sectors_read, later.sectors_read);
+    let sectors_written = delta(earlier.sectors_written, later.sectors_written);
+    let read_time = delta(earlier.read_time, later.read_time);
+    let write_time = delta(earlier.write_time, later.write_time);
+    let io_time = delta(earlier.io_time, later.io_time);
+
+    let per_sec = |count: u64| per_sec(count, elapsed);
+    let latency = |time: u64, ops: u64| time.saturating_mul(1000).checked_div(ops).map(Duration::from_micros);
+    DiskRate {
+        major: later.major,
+        minor: later.minor,
+        name: later.name.clone(),
+        reads_per_sec: per_sec(reads),
+        writes_per_sec: per_sec(writes),
+        read_bytes_per_sec: per_sec(sectors_read * SECTOR_SIZE),
+        write_bytes_per_sec: per_sec(sectors_written * SECTOR_SIZE),
+        read_latency: latency(read_time, reads),
+        write_latency: latency(write_time, writes),
+        // The I/O time is accounted in jiffies, so it may slightly exceed the elapsed time.
+        utilization: f64::min(per_sec(io_time) / 1000.0, 1.0),
+        warning: tracker.warning,
+    }
+}
+
+impl DiskSample {
+    /// Samples the I/O statistics of every block device.
+    pub fn take() -> Result<DiskSample> {
+        Ok(DiskSample { disks: diskstats()?, timestamp: Instant::now() })
+    }
+
+    /// Returns the I/O rates of the block devices between this sample and a later one.
+    ///
+    /// Devices which were added or removed between the samples, e.g. by hotplug or by
+    /// device-mapper, have no rates, and are listed in `added` and `removed` instead. A device
+    /// whose major or minor number changed is both removed and added.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::InvalidInput` if `later` was taken before this sample.
+    pub fn delta(&self, later: &DiskSample) -> Result<DiskRates> {
+        let elapsed = sample_elapsed("disk", ((), self.timestamp), ((), later.timestamp))?;
+
+        let mut rates = DiskRates { elapsed, ..DiskRates::default() };
+        for disk in &later.disks {
+            match self.disks.iter().find(|earlier| earlier.same_device(disk)) {
+                Some(earlier) => rates.devices.push(disk_rate(earlier, disk, elapsed)),
+                None => rates.added.push(disk.name.clone()),
+            }
+        }
+        rates.removed = self.disks
+                            .iter()
+                            .filter(|earlier| !later.disks.iter().any(|disk| disk.same_device(earlier)))
+                            .map(|earlier| earlier.name.clone())
+                            .collect();
+        Ok(rates)
+    }
+}
+
+/// Computes the I/O rates of the block devices between consecutive samples, like `iostat`.
+#[derive(Clone, Debug)]
+pub struct DiskSampler {
+    previous: DiskSample,
+}
+
+impl DiskSampler {
+    /// Returns a sampler, taking its first sample.
+    pub fn new() -> Result<DiskSampler> {
+        Ok(DiskSampler { previous: DiskSample::take()? })
+    }
+
+    /// Takes a sample, and returns the rates since the previous sample.
+    pub fn sample(&mut self) -> Result<DiskRates> {
+        let sample = DiskSample::take()?;
+        let rates = self.previous.delta(&sample)?;
+        self.previous = sample;
+        Ok(rates)
+    }
+
+    /// Returns the previous sample.
+    pub fn previous(&self) -> &DiskSample {
+        &self.previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use delta::DeltaWarning;
+    use fixtures::fixture;
+    use super::{DiskRate, DiskSample, DiskSampler, DiskStat, diskstats, parse_diskstats};
+
+    #[test]
+    fn test_parse_diskstats() {
+        let disks = parse_diskstats(&fixture("6.18", "diskstats")).unwrap();
+        assert_eq!(11, disks.len());
+        assert_eq!(DiskStat {
+                       major: 254,
+                       minor: 0,
+                       name: "vda".to_owned(),
+                       reads_completed: 43344,
+                       reads_merged: 17637,
+                       sectors_read: 3457906,
+                       read_time: 18199,
+                       writes_completed: 32568,
+                       writes_merged: 39999,
+                       sectors_written: 35099536,
+                       write_time: 56015,
+                       ios_in_progress: 0,
+                       io_time: 19424,
+                       weighted_io_time: 79793,
+                       discards_completed: 19090,
+                       discards_merged: 0,
+                       sectors_discarded: 30070776,
+                       discard_time: 5567,
+                       flushes_completed: 310,
+                       flush_time: 10,
+                   },
+                   disks[8]);
+
+        // Kernels before 4.18 report 11 counters, and kernels before 5.5 report 15.
+        let disks = parse_diskstats(b"   8       1 sda1 31600 9327 2458674 13526 67417 54281 4536610 83297 0 74536 \
+                                      96823\n\
+                                      \x20253       0 dm-0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15\n")
+            .unwrap();
+        assert_eq!("sda1", disks[0].name);
+        assert_eq!(96823, disks[0].weighted_io_time);
+        assert_eq!(0, disks[0].discards_completed);
+        assert_eq!(15, disks[1].discard_time);
+        assert_eq!(0, disks[1].flushes_completed);
+
+        assert!(parse_diskstats(b"").unwrap().is_empty());
+        assert!(parse_diskstats(b"8 0 sda 1 2 3\n").is_err());
+        assert!(parse_diskstats(b"8 0 sda 1 2 3 4 5 6 7 8 9 10 x\n").is_err());
+    }
+
+    fn disk(name: &str, minor: u32, reads: u64, writes: u64, time: u64, io_time: u64) -> DiskStat {
+        DiskStat {
+            major: 8,
+            minor,
+            name: name.to_owned(),
+            reads_completed: reads,
+            sectors_read: reads * 8,
+            read_time: reads * time,
+            writes_completed: writes,
+            sectors_written: writes * 16,
+            write_time: writes * time * 2,
+            io_time,
+            ..DiskStat::default()
+        }
+    }
+
+    #[test]
+    fn test_delta() {
+        let now = Instant::now();
+        let earlier = DiskSample {
+            disks: vec![disk("sda", 0, 1000, 500, 2, 10000), disk("sdb", 16, 0, 0, 0, 0),
+                        disk("sdc", 32, 10, 10, 1, 100)],
+            timestamp: now,
+        };
+        // Over two seconds, sda completed 200 reads of 4 KiB taking 2 ms each, and 100 writes of
+        // 8 KiB taking 4 ms each, and was busy for half of the time. sdb was idle, sdc was
+        // removed, and sdd was added.
+        let later = DiskSample {
+            disks: vec![disk("sda", 0, 1200, 600, 2, 11000), disk("sdb", 16, 0, 0, 0, 0),
+                        disk("sdd", 48, 0, 0, 0, 0)],
+            timestamp: now + Duration::from_secs(2),
+        };
+
+        let rates = earlier.delta(&later).unwrap();
+        assert_eq!(Duration::from_secs(2), rates.elapsed);
+        assert_eq!(vec!["sdd".to_owned()], rates.added);
+        assert_eq!(vec!["sdc".to_owned()], rates.removed);
+        assert_eq!(2, rates.devices.len());
+        assert_eq!(DiskRate {
+                       major: 8,
+                       minor: 0,
+                       name: "sda".to_owned(),
+                       reads_per_sec: 100.0,
+                       writes_per_sec: 50.0,
+                       read_bytes_per_sec: 409600.0,
+                       write_bytes_per_sec: 409600.0,
+                       read_latency: Some(Duration::from_millis(2)),
+                       write_latency: Some(Duration::from_millis(4)),
+                       utilization: 0.5,
+                       warning: None,
+                   },
+                   rates.devices[0]);
+        // An idle device has no latency.
+        assert_eq!(DiskRate { major: 8, minor: 16, name: "sdb".to_owned(), ..DiskRate::default() },
+                   rates.devices[1]);
+
+        assert!(later.delta(&earlier).is_err());
+    }
+
+    #[test]
+    fn test_delta_edge_cases() {
+        let now = Instant::now();
+        let earlier = DiskSample { disks: vec![disk("sda", 0, 1000, 500, 2, 10000)], timestamp: now };
+
+        // No time elapsed.
+        let rates = earlier.delta(&DiskSample { timestamp: now, ..earlier.clone() }).unwrap();
+        assert_eq!(0.0, rates.devices[0].reads_per_sec);
+        assert_eq!(0.0, rates.devices[0].utilization);
+
+        // The busy time exceeds the elapsed time, and the write counter of a 32-bit kernel wrapped.
+        let mut wrapping = earlier.clone();
+        wrapping.disks[0].writes_completed = 0xffff_fff0;
+        let mut busy = disk("sda", 0, 1000, 500, 2, 12000);
+        busy.writes_completed = 4;
+        let rates = wrapping.delta(&DiskSample { disks: vec![busy], timestamp: now + Duration::from_secs(1) })
+                            .unwrap();
+        assert_eq!(1.0, rates.devices[0].utilization);
+        assert_eq!(20.0, rates.devices[0].writes_per_sec);
+        assert_eq!(Some(DeltaWarning::Wrapped), rates.devices[0].warning);
+
+        // A device whose minor number changed is replaced.
+        let rates = earlier.delta(&DiskSample { disks: vec![disk("sda", 1, 0, 0, 0, 0)], timestamp: now })
+                           .unwrap();
+        assert!(rates.devices.is_empty());
+        assert_eq!(vec!["sda".to_owned()], rates.added);
+        assert_eq!(vec!["sda".to_owned()], rates.removed);
+    }
+
+    #[test]
+    fn test_diskstats() {
+        diskstats().unwrap();
+        let mut sampler = DiskSampler::new().unwrap();
+        let rates = sampler.sample().unwrap();
+        assert_eq!(rates.devices.len() + rates.added.len(), sampler.previous().disks.len());
+    }
+}
@@ -0,0 +1,217 @@
+//! Captured `/proc` files, and golden tests which run the probes and parsers against them.
+//!
+//! The fixtures are in `tests/fixtures/[kernel]/`, laid out as the files are in `/proc`, with the
+//! files of a process in `self/`. Each fixture has a golden file at the same path in
+//! `tests/golden/[kernel]/`, holding the `{:#?}` representation of its parsed value. `test_golden`
+//! reads every fixture with the reader registered for its path in `READERS`, and compares the
+//! result with the golden file. Module tests can read a fixture with `fixture`, and assert on its
+//! key fields.
+//!
+//! The readers run the probes which can be pointed at a proc directory, e.g. `loadavg_in`, against
+//! the fixture tree, and read the files of the fixture process through a `PidDir`, as `Batch` does.
+//! The files of the other probes are read from the tree and passed to their `parse` function.
+//!
+//! To add the fixtures of a kernel version, capture the files into a new directory named after the
+//! version, e.g. with `cat /proc/self/limits > tests/fixtures/6.1/self/limits`, so that the files of
+//! `self/` are those of the `cat` process. Then generate the golden files with
+//! `PROCINFO_BLESS=1 cargo test golden`, and review them before committing. A fixture of a file
+//! without a registered reader fails the test, so a new parser is covered by registering it and
+//! dropping its file into the tree.
+
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use diskstats::parse_diskstats;
+use loadavg::loadavg_in;
+use meminfo::MemInfo;
+use net::dev_snmp6_in;
+use pid::{Cgroup, Io, Mountinfo, PidDir, PidFile, Schedstat, Stat, Statm, Status, limits_in};
+use probe_support::kernel_version_in;
+use stat::KernelStat;
+use uptime::Uptime;
+use vmstat::VmStat;
+use zoneinfo::parse_zoneinfo;
+
+/// Returns the `{:#?}` representation of a parsed value.
+fn debug<T: Debug>(parsed: Result<T>) -> Result<String> {
+    parsed.map(|parsed| format!("{:#?}\n", parsed))
+}
+
+/// A reader of a fixture, returning the `{:#?}` representation of the value read from the fixture
+/// at a path relative to the root of a kernel directory.
+type Reader = fn(&Path, &str) -> Result<String>;
+
+/// Parses a fixture with the parser of its file.
+fn parse<T: Debug>(root: &Path, path: &str, parse: fn(&[u8]) -> Result<T>) -> Result<String> {
+    debug(parse(&fs::read(root.join(path))?))
+}
+
+/// Parses each line of a fixture with the parser of a line of its file.
+fn parse_lines<T: Debug>(root: &Path, path: &str, parse: fn(&[u8]) -> Result<T>) -> Result<String> {
+    let input = fs::read(root.join(path))?;
+    debug(input.split(|&b| b == b'\n').filter(|line| !line.is_empty()).map(parse).collect::<Result<Vec<T>>>())
+}
+
+/// Reads a file of the fixture process through a `PidDir` of `self/`.
+fn read_self<P: PidFile>(root: &Path) -> Result<String> where P::Output: Debug {
+    let dir = PidDir::open_path(0, root.join("self"))?;
+    debug(dir.read::<P>(&mut Vec::new()))
+}
+
+/// Reads the statistics of an interface with `dev_snmp6_in`.
+fn read_dev_snmp6(root: &Path, path: &str) -> Result<String> {
+    let name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let mut interfaces = dev_snmp6_in(&root.join("net/dev_snmp6"))?;
+    debug(interfaces.remove(name).ok_or_else(|| Error::new(ErrorKind::NotFound, path.to_owned())))
+}
+
+/// The readers of the fixtures, by path relative to the root of a kernel directory. A path ending
+/// with `/` matches every file in the directory.
+const READERS: &[(&str, Reader)] = &[
+    ("diskstats", |root, path| parse(root, path, parse_diskstats)),
+    ("loadavg", |root, _| debug(loadavg_in(root))),
+    ("meminfo", |root, path| parse(root, path, MemInfo::parse)),
+    ("net/dev_snmp6/", read_dev_snmp6),
+    ("self/cgroup", |root, path| parse_lines(root, path, Cgroup::parse)),
+    ("self/io", |root, _| read_self::<Io>(root)),
+    ("self/limits", |root, _| debug(limits_in(&root.join("self")))),
+    ("self/mountinfo", |root, path| parse_lines(root, path, Mountinfo::parse)),
+    ("self/schedstat", |root, _| read_self::<Schedstat>(root)),
+    ("self/stat", |root, _| read_self::<Stat>(root)),
+    ("self/statm", |root, _| read_self::<Statm>(root)),
+    ("self/status", |root, _| read_self::<Status>(root)),
+    ("stat", |root, path| parse(root, path, KernelStat::parse)),
+    ("sys/kernel/osrelease", |root, _| debug(kernel_version_in(root))),
+    ("uptime", |root, path| parse(root, path, Uptime::parse)),
+    ("vmstat", |root, path| parse(root, path, VmStat::parse)),
+    ("zoneinfo", |root, path| parse(root, path, parse_zoneinfo)),
+];
+
+/// Returns a directory of the test tree, `fixtures` or `golden`.
+fn tree(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+/// Returns the path of a fixture file or directory of a kernel, e.g.
+/// `fixture_path("6.18", "net/dev_snmp6")`.
+pub fn fixture_path(kernel: &str, path: &str) -> PathBuf {
+    tree("fixtures").join(kernel).join(path)
+}
+
+/// Returns the contents of a fixture of a kernel, e.g. `fixture("6.18", "self/limits")`.
+pub fn fixture(kernel: &str, path: &str) -> Vec<u8> {
+    let path = fixture_path(kernel, path);
+    fs::read(&path).unwrap_or_else(|error| panic!("unable to read fixture {:?}: {}", path, error))
+}
+
+/// Returns the paths of the files in a directory, relative to `base`.
+fn walk(base: &Path, dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            walk(base, &path, files);
+        } else {
+            files.push(path.strip_prefix(base).unwrap().to_str().unwrap().to_owned());
+        }
+    }
+}
+
+/// Returns the reader registered for a fixture path.
+fn reader(path: &str) -> Option<Reader> {
+    READERS.iter()
+           .find(|&&(pattern, _)| {
+               pattern == path ||
+               (pattern.ends_with('/') && path.starts_with(pattern) && !path[pattern.len()..].contains('/'))
+           })
+           .map(|&(_, parse)| parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use pid::{Stat, StatRef, Statm};
+    use super::{fixture, reader, tree, walk};
+    use sysconf::page_size;
+    use zoneinfo::parse_zoneinfo;
+
+    #[test]
+    fn test_reader() {
+        assert!(reader("self/limits").is_some());
+        assert!(reader("net/dev_snmp6/eth0").is_some());
+        assert!(reader("net/dev_snmp6").is_none());
+        assert!(reader("self/limits/x").is_none());
+        assert!(reader("net/dev_snmp6/lo/x").is_none());
+        assert_eq!(b"0.33 0.42 0.44 2/75 23185\n".to_vec(), fixture("6.18", "loadavg"));
+    }
+
+    /// Test that every memory quantity counted in pages has a `_bytes` accessor which converts the
+    /// right field with the page size.
+    ///
+    /// The expected sizes are those of the fixtures, which were captured with 4 KiB pages, and are
+    /// scaled to the page size of the host.
+    #[test]
+    fn test_bytes_accessors() {
+        let stat_text = fixture("6.18", "self/stat");
+        let stat = Stat::parse(&stat_text).unwrap();
+        let stat_ref = StatRef::parse(&stat_text).unwrap();
+        let statm = Statm::parse(&fixture("6.18", "self/statm")).unwrap();
+        let zones = parse_zoneinfo(&fixture("6.18", "zoneinfo")).unwrap();
+        let zone = zones.iter().find(|zone| zone.name == "DMA32").unwrap();
+
+        let accessors = [
+            ("Stat::rss", stat.rss_bytes(), 1_159_168),
+            ("StatRef::rss", stat_ref.rss_bytes(), 1_159_168),
+            ("Statm::size", statm.size_bytes(), 2_703_360),
+            ("Statm::resident", statm.resident_bytes(), 1_421_312),
+            ("Statm::share", statm.share_bytes(), 1_318_912),
+            ("Statm::text", statm.text_bytes(), 20_480),
+            ("Statm::data", statm.data_bytes(), 503_808),
+            ("Zone::free", zone.free_bytes(), 1_392_541_696),
+            ("Zone::min", zone.min_bytes(), 34_807_808),
+            ("Zone::low", zone.low_bytes(), 43_507_712),
+            ("Zone::high", zone.high_bytes(), 52_207_616),
+            ("Zone::spanned", zone.spanned_bytes(), 4_278_190_080),
+            ("Zone::present", zone.present_bytes(), 3_204_448_256),
+            ("Zone::managed", zone.managed_bytes(), 3_171_672_064),
+            ("Zone::reserved", zone.reserved_bytes(), 64_389_120),
+        ];
+        for &(name, bytes, bytes_4k) in &accessors {
+            assert_eq!(bytes_4k / 4096 * page_size(), bytes, "{}", name);
+        }
+    }
+
+    /// Test that every fixture parses to its golden value.
+    #[test]
+    fn test_golden() {
+        let bless = env::var_os("PROCINFO_BLESS").is_some();
+        let mut failures = Vec::new();
+        for kernel in fs::read_dir(tree("fixtures")).unwrap() {
+            let kernel = kernel.unwrap().file_name();
+            let dir = tree("fixtures").join(&kernel);
+            let mut fixtures = Vec::new();
+            walk(&dir, &dir, &mut fixtures);
+            assert!(!fixtures.is_empty(), "no fixtures in {:?}", dir);
+            for path in fixtures {
+                let fixture = dir.join(&path);
+                let read = reader(&path).unwrap_or_else(|| panic!("no reader registered for {:?}", fixture));
+                let parsed = read(&dir, &path)
+                    .unwrap_or_else(|error| panic!("unable to read {:?}: {}", fixture, error));
+                let golden = tree("golden").join(&kernel).join(&path);
+                if bless {
+                    fs::create_dir_all(golden.parent().unwrap()).unwrap();
+                    fs::write(&golden, &parsed).unwrap();
+                } else if fs::read_to_string(&golden).ok().as_ref() != Some(&parsed) {
+                    failures.push(fixture);
+                }
+            }
+        }
+        assert!(failures.is_empty(),
+                "fixtures do not match their golden files, rerun with PROCINFO_BLESS=1 to update them: {:?}",
+                failures);
+    }
+}
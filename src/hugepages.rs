@@ -0,0 +1,204 @@
+//! Huge page pools from `/proc/meminfo` and `/sys/kernel/mm/hugepages/`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::str;
+
+use meminfo::{MemInfo, meminfo};
+use parsers::read_to_end;
+use platform;
+
+/// A pool of huge pages of a single size.
+///
+/// The counts are numbers of huge pages. See `Linux/Documentation/admin-guide/mm/hugetlbpage.rst`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HugePagePool {
+    /// Size of the huge pages in bytes.
+    pub size: u64,
+    /// Number of huge pages in the pool (`nr_hugepages`).
+    pub total: u64,
+    /// Number of huge pages in the pool which are not yet allocated (`free_hugepages`).
+    pub free: u64,
+    /// Number of huge pages which are reserved but not yet allocated (`resv_hugepages`).
+    pub reserved: u64,
+    /// Number of huge pages above the configured size of the pool (`surplus_hugepages`).
+    pub surplus: u64,
+}
+
+impl HugePagePool {
+    /// Returns the memory consumed by the pool, in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.size.saturating_mul(self.total)
+    }
+}
+
+/// Huge page configuration and usage.
+///
+/// The counts are those of the pool of the default huge page size, as reported by
+/// `/proc/meminfo`. The pools of every configured size are read from sysfs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HugePages {
+    /// Number of huge pages in the default pool.
+    pub total: u64,
+    /// Number of huge pages in the default pool which are not yet allocated.
+    pub free: u64,
+    /// Number of huge pages in the default pool which are reserved but not yet allocated.
+    pub reserved: u64,
+    /// Number of huge pages in the default pool above its configured size.
+    pub surplus: u64,
+    /// Size of the default huge page in bytes.
+    pub default_size: u64,
+    /// Memory consumed by huge pages of every size in bytes (since Linux 4.16).
+    pub hugetlb: u64,
+    /// The pools of every supported huge page size, sorted by size. Empty if sysfs is not
+    /// mounted.
+    pub pools: Vec<HugePagePool>,
+}
+
+impl HugePages {
+    /// Combines the huge page fields of `/proc/meminfo` with the pools of every size.
+    pub fn from_meminfo(meminfo: &MemInfo, pools: Vec<HugePagePool>) -> HugePages {
+        HugePages {
+            total: meminfo.hugepages_total,
+            free: meminfo.hugepages_free,
+            reserved: meminfo.hugepages_rsvd,
+            surplus: meminfo.hugepages_surp,
+            default_size: meminfo.hugepage_size,
+            hugetlb: meminfo.hugetlb,
+            pools,
+        }
+    }
+
+    /// Returns the pool of huge pages of the provided size in bytes, or `None` if the size is not
+    /// supported.
+    pub fn pool(&self, size: u64) -> Option<&HugePagePool> {
+        self.pools.iter().find(|pool| pool.size == size)
+    }
+}
+
+/// Parses the name of a pool directory, such as `hugepages-2048kB`, returning the size in bytes.
+fn parse_pool_size(name: &str) -> Option<u64> {
+    let kb: u64 = name.strip_prefix("hugepages-")?.strip_suffix("kB")?.parse().ok()?;
+    kb.checked_mul(1024)
+}
+
+/// Reads a counter of a pool directory.
+fn read_count(path: &Path) -> Result<u64> {
+    let mut buf = Vec::with_capacity(32);
+    let contents = read_to_end(&mut platform::open(path)?, &mut buf)?;
+    str::from_utf8(contents).ok()
+                            .and_then(|contents| contents.trim_end().parse().ok())
+                            .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                                                      format!("unable to parse huge page count: {:?}", path)))
+}
+
+/// Reads the pools of a hugepages directory, such as `/sys/kernel/mm/hugepages`, or returns no
+/// pools if it does not exist.
+fn read_pools(dir: &Path) -> Result<Vec<HugePagePool>> {
+    let entries = match platform::read_dir(dir) {
+        Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        result => result?,
+    };
+    let mut pools = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let size = match entry.file_name().to_str().and_then(parse_pool_size) {
+            Some(size) => size,
+            None => continue,
+        };
+        let path = entry.path();
+        pools.push(HugePagePool {
+            size,
+            total: read_count(&path.join("nr_hugepages"))?,
+            free: read_count(&path.join("free_hugepages"))?,
+            reserved: read_count(&path.join("resv_hugepages"))?,
+            surplus: read_count(&path.join("surplus_hugepages"))?,
+        });
+    }
+    pools.sort_by_key(|pool| pool.size);
+    Ok(pools)
+}
+
+/// Returns the huge page configuration and usage, from `/proc/meminfo` and the pools in
+/// `/sys/kernel/mm/hugepages/`.
+///
+/// Transparent huge pages are not included, see `MemInfo::anon_huge_pages` and
+/// `pid::thp_usage`.
+pub fn hugepages() -> Result<HugePages> {
+    let meminfo = meminfo()?;
+    Ok(HugePages::from_meminfo(&meminfo, read_pools(Path::new("/sys/kernel/mm/hugepages"))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::{env, fs, process};
+
+    use meminfo::MemInfo;
+    use super::{HugePagePool, HugePages, hugepages, parse_pool_size, read_pools};
+
+    #[test]
+    fn test_parse_pool_size() {
+        assert_eq!(Some(2 << 20), parse_pool_size("hugepages-2048kB"));
+        assert_eq!(Some(1 << 30), parse_pool_size("hugepages-1048576kB"));
+        assert_eq!(None, parse_pool_size("hugepages-2048"));
+        assert_eq!(None, parse_pool_size("hugepages-kB"));
+        assert_eq!(None, parse_pool_size("2048kB"));
+    }
+
+    /// Test a host with 16 pages of 2 MiB, of which 2 are reserved, and 2 pages of 1 GiB.
+    #[test]
+    fn test_hugepages_fixture() {
+        let dir = env::temp_dir().join(format!("procinfo-hugepages-{}", process::id()));
+        for &(name, counts) in &[("hugepages-2048kB", ["16", "12", "2", "0"]),
+                                 ("hugepages-1048576kB", ["2", "1", "0", "0"])] {
+            let pool = dir.join(name);
+            fs::create_dir_all(&pool).unwrap();
+            for (file, count) in ["nr_hugepages", "free_hugepages", "resv_hugepages", "surplus_hugepages"]
+                                 .iter().zip(counts.iter()) {
+                fs::write(pool.join(file), format!("{}\n", count)).unwrap();
+            }
+        }
+        fs::write(dir.join("README"), b"").unwrap();
+        let pools = read_pools(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let meminfo = MemInfo::parse(b"MemTotal:       16318504 kB\n\
+                                       HugePages_Total:      16\n\
+                                       HugePages_Free:       12\n\
+                                       HugePages_Rsvd:        2\n\
+                                       HugePages_Surp:        0\n\
+                                       Hugepagesize:       2048 kB\n\
+                                       Hugetlb:         2129920 kB\n").unwrap();
+        let hugepages = HugePages::from_meminfo(&meminfo, pools.unwrap());
+        let two_mib = HugePagePool { size: 2 << 20, total: 16, free: 12, reserved: 2, surplus: 0 };
+        let one_gib = HugePagePool { size: 1 << 30, total: 2, free: 1, reserved: 0, surplus: 0 };
+        assert_eq!(HugePages {
+                       total: 16,
+                       free: 12,
+                       reserved: 2,
+                       surplus: 0,
+                       default_size: 2 << 20,
+                       hugetlb: 2080 << 20,
+                       pools: vec![two_mib, one_gib],
+                   },
+                   hugepages);
+        assert_eq!(Some(&one_gib), hugepages.pool(1 << 30));
+        assert_eq!(None, hugepages.pool(4096));
+        assert_eq!(hugepages.hugetlb, hugepages.pools.iter().map(HugePagePool::bytes).sum::<u64>());
+
+        assert!(read_pools(Path::new("/nonexistent/hugepages")).unwrap().is_empty());
+    }
+
+    /// Test that the system huge page configuration can be read.
+    #[test]
+    fn test_hugepages() {
+        let hugepages = hugepages().unwrap();
+        assert!(hugepages.free <= hugepages.total + hugepages.surplus);
+        if !hugepages.pools.is_empty() {
+            assert_eq!(hugepages.total, hugepages.pool(hugepages.default_size).unwrap().total);
+        }
+    }
+}
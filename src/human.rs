@@ -0,0 +1,72 @@
+//! Human-readable formatting of quantities.
+
+use std::fmt;
+
+/// Formats a quantity of bytes using binary unit prefixes.
+///
+/// Quantities of at least 1 KiB are rendered with a single decimal digit, e.g. `8388608`
+/// is rendered as `8.0 MiB`. Optional quantities render `None` as `unlimited`, matching the
+/// convention used for resource limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HumanBytes<T>(pub T);
+
+const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+fn fmt_bytes(bytes: u64, f: &mut fmt::Formatter) -> fmt::Result {
+    if bytes < 1024 {
+        return write!(f, "{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    write!(f, "{:.1} {}", value, UNITS[unit])
+}
+
+impl fmt::Display for HumanBytes<u64> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_bytes(self.0, f)
+    }
+}
+
+impl fmt::Display for HumanBytes<usize> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_bytes(self.0 as u64, f)
+    }
+}
+
+impl fmt::Display for HumanBytes<Option<u64>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(bytes) => fmt_bytes(bytes, f),
+            None => f.write_str("unlimited"),
+        }
+    }
+}
+
+impl fmt::Display for HumanBytes<Option<usize>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        HumanBytes(self.0.map(|bytes| bytes as u64)).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HumanBytes;
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!("0 B", HumanBytes(0u64).to_string());
+        assert_eq!("1023 B", HumanBytes(1023u64).to_string());
+        assert_eq!("1.0 KiB", HumanBytes(1024u64).to_string());
+        assert_eq!("1.5 KiB", HumanBytes(1536u64).to_string());
+        assert_eq!("8.0 MiB", HumanBytes(8388608u64).to_string());
+        assert_eq!("800.0 KiB", HumanBytes(819200usize).to_string());
+        assert_eq!("4.0 GiB", HumanBytes(4u64 << 30).to_string());
+        assert_eq!("16.0 EiB", HumanBytes(u64::MAX).to_string());
+        assert_eq!("8.0 MiB", HumanBytes(Some(8388608usize)).to_string());
+        assert_eq!("unlimited", HumanBytes(None::<u64>).to_string());
+    }
+}
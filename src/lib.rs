@@ -1,7 +1,10 @@
 #![recursion_limit = "1000"]
-#![cfg_attr(rustc_nightly, feature(test))]
+#![cfg_attr(all(test, rustc_nightly), feature(test))]
 
 #![allow(dead_code)] // TODO: remove
+#![allow(unused_doc_comments)] // nom's `named!` does not attach doc comments to the parser.
+#![allow(unknown_lints)] // The lint below is unknown to older compilers.
+#![allow(semicolon_in_expressions_from_non_local_macros)] // nom 2 ends its macro expressions with semicolons.
 
 #[macro_use]
 extern crate nom;
@@ -9,10 +12,234 @@ extern crate nom;
 extern crate byteorder;
 extern crate libc;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
 #[macro_use]
 mod parsers;
 
+mod check;
+mod coredump;
+mod delta;
+mod diskstats;
+#[cfg(test)]
+mod fixtures;
+mod human;
+mod hugepages;
 mod loadavg;
+mod meminfo;
+pub mod net;
 pub mod pid;
+mod platform;
+mod probe_support;
+mod snapshot;
+mod socket;
+mod stat;
+pub mod sys;
+mod sysconf;
+mod taint;
+mod uptime;
+mod vmstat;
+mod zoneinfo;
+
+pub use check::{HidePid, ProcMount, check, explain_error};
+pub use coredump::{CoreConfig, CoreDump, CorePattern, core_config};
+pub use delta::{DeltaResult, DeltaWarning, monotonic_delta};
+pub use diskstats::{DiskRate, DiskRates, DiskSample, DiskSampler, DiskStat, diskstats};
+pub use human::HumanBytes;
+pub use hugepages::{HugePagePool, HugePages, hugepages};
+pub use loadavg::{LoadAverages, LoadAvg, LoadAvgSampler, LoadAvgSamplerHandle, NormalizedLoad, loadavg};
+pub use meminfo::{MemInfo, meminfo};
+pub use probe_support::{KernelVersion, ProbeSupport, kernel_version, probe_support};
+pub use snapshot::{CpuUtilization, Probe, Snapshot, SnapshotDelta};
+pub use stat::{CpuTime, KernelStat, boot_time, cpu_count_online, kernel_stat};
+pub use sysconf::{page_size, ticks_per_second};
+pub use taint::{TaintFlag, TaintFlags, TaintFlagsIter, kernel_tainted};
+pub use uptime::{Uptime, uptime};
+pub use vmstat::{VmStat, vmstat};
+pub use zoneinfo::{Zone, zoneinfo};
+
+#[cfg(feature = "async")]
+pub use loadavg::loadavg_async;
+#[cfg(feature = "async")]
+pub use meminfo::meminfo_async;
+#[cfg(feature = "async")]
+pub use stat::kernel_stat_async;
+#[cfg(feature = "async")]
+pub use uptime::uptime_async;
+#[cfg(feature = "async")]
+pub use vmstat::vmstat_async;
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+
+    use super::{CoreConfig, CoreDump, CorePattern, CpuTime, CpuUtilization, DeltaResult, DeltaWarning, DiskRate,
+                DiskRates, DiskSample, DiskStat, HugePagePool, HugePages, HumanBytes, KernelStat, KernelVersion,
+                LoadAverages, LoadAvg, MemInfo, NormalizedLoad, Probe, ProbeSupport, SnapshotDelta, TaintFlag,
+                TaintFlags, Uptime, VmStat};
+    use pid::{Auxv, AuxvEntry, AuxvKey, Capabilities, Capability, Cgroup, ChangedBounds, CpuSet, CpuTimeSummary,
+              CpuUsage, CtxtSwitchRate, CtxtSwitches, ExitSnapshot, FdInfo, FdInfoExtension, IdMap, IdMapRange, Ids,
+              Io, IoRate, Limit, LimitChange, Limits, Mountinfo, Ownership, ProcFlag, ProcFlags, Residency, Resource,
+              Schedstat, SeccompMode, SignalSet, SpeculationIndirectBranch, SpeculationStoreBypass, Stat, StatRef,
+              State, Statm, Status, ThreadCpu, ThreadStates, ThreadSummary, Tty, WaitOutcome};
+
+    fn assert_clone_default<T: Clone + Default>() {}
+    fn assert_copy<T: Copy>() {}
+    fn assert_hash<T: Hash + Eq>() {}
+
+    /// Test that the public types implement the common traits.
+    #[test]
+    fn test_derives() {
+        assert_clone_default::<Auxv>();
+        assert_clone_default::<Capabilities>();
+        assert_clone_default::<Cgroup>();
+        assert_clone_default::<ExitSnapshot>();
+        assert_clone_default::<IdMap>();
+        assert_clone_default::<Ids<u32>>();
+        assert_clone_default::<Ownership>();
+        assert_clone_default::<CoreConfig>();
+        assert_clone_default::<CorePattern>();
+        assert_clone_default::<CpuSet>();
+        assert_clone_default::<CpuTime>();
+        assert_clone_default::<CpuTimeSummary>();
+        assert_clone_default::<CpuUsage>();
+        assert_clone_default::<CpuUtilization>();
+        assert_clone_default::<CtxtSwitchRate>();
+        assert_clone_default::<CtxtSwitches>();
+        assert_clone_default::<DiskRate>();
+        assert_clone_default::<DiskRates>();
+        assert_clone_default::<DiskStat>();
+        assert_clone_default::<FdInfo>();
+        assert_clone_default::<FdInfoExtension>();
+        assert_clone_default::<HugePagePool>();
+        assert_clone_default::<HugePages>();
+        assert_clone_default::<Io>();
+        assert_clone_default::<IoRate>();
+        assert_clone_default::<KernelStat>();
+        assert_clone_default::<LoadAverages>();
+        assert_clone_default::<LoadAvg>();
+        assert_clone_default::<Limit<u64>>();
+        assert_clone_default::<Limits>();
+        assert_clone_default::<MemInfo>();
+        assert_clone_default::<Mountinfo>();
+        assert_clone_default::<NormalizedLoad>();
+        assert_clone_default::<ProbeSupport>();
+        assert_clone_default::<ProcFlags>();
+        assert_clone_default::<Residency>();
+        assert_clone_default::<Schedstat>();
+        assert_clone_default::<SeccompMode>();
+        assert_clone_default::<SignalSet>();
+        assert_clone_default::<SnapshotDelta>();
+        assert_clone_default::<SpeculationIndirectBranch>();
+        assert_clone_default::<SpeculationStoreBypass>();
+        assert_clone_default::<Stat>();
+        assert_clone_default::<State>();
+        assert_clone_default::<StatRef<'static>>();
+        assert_clone_default::<Statm>();
+        assert_clone_default::<Status>();
+        assert_clone_default::<ThreadCpu>();
+        assert_clone_default::<ThreadStates>();
+        assert_clone_default::<ThreadSummary>();
+        assert_clone_default::<TaintFlags>();
+        assert_clone_default::<Uptime>();
+        assert_clone_default::<VmStat>();
+
+        assert_copy::<AuxvEntry>();
+        assert_copy::<KernelVersion>();
+        assert_copy::<AuxvKey>();
+        assert_copy::<Capabilities>();
+        assert_copy::<ExitSnapshot>();
+        assert_copy::<DeltaResult>();
+        assert_copy::<DeltaWarning>();
+        assert_copy::<IdMapRange>();
+        assert_copy::<Ownership>();
+        assert_copy::<Capability>();
+        assert_copy::<ChangedBounds>();
+        assert_copy::<CpuTime>();
+        assert_copy::<CpuTimeSummary>();
+        assert_copy::<CpuUsage>();
+        assert_copy::<CpuUtilization>();
+        assert_copy::<CtxtSwitchRate>();
+        assert_copy::<CtxtSwitches>();
+        assert_copy::<HumanBytes<u64>>();
+        assert_copy::<Io>();
+        assert_copy::<IoRate>();
+        assert_copy::<LoadAverages>();
+        assert_copy::<LoadAvg>();
+        assert_copy::<Limit<u64>>();
+        assert_copy::<LimitChange>();
+        assert_copy::<MemInfo>();
+        assert_copy::<NormalizedLoad>();
+        assert_copy::<Probe>();
+        assert_copy::<ProbeSupport>();
+        assert_copy::<ProcFlag>();
+        assert_copy::<ProcFlags>();
+        assert_copy::<Residency>();
+        assert_copy::<Resource>();
+        assert_copy::<Schedstat>();
+        assert_copy::<SeccompMode>();
+        assert_copy::<State>();
+        assert_copy::<SignalSet>();
+        assert_copy::<Statm>();
+        assert_copy::<StatRef<'static>>();
+        assert_copy::<ThreadStates>();
+        assert_copy::<TaintFlag>();
+        assert_copy::<TaintFlags>();
+        assert_copy::<Tty>();
+        assert_copy::<Uptime>();
+        assert_copy::<WaitOutcome>();
 
-pub use loadavg::{LoadAvg, loadavg};
+        assert_hash::<Auxv>();
+        assert_hash::<KernelVersion>();
+        assert_hash::<Capabilities>();
+        assert_hash::<ExitSnapshot>();
+        assert_hash::<DeltaResult>();
+        assert_hash::<DeltaWarning>();
+        assert_hash::<DiskSample>();
+        assert_hash::<DiskStat>();
+        assert_hash::<IdMap>();
+        assert_hash::<Ownership>();
+        assert_hash::<Capability>();
+        assert_hash::<Cgroup>();
+        assert_hash::<ChangedBounds>();
+        assert_hash::<CoreConfig>();
+        assert_hash::<CoreDump>();
+        assert_hash::<CpuSet>();
+        assert_hash::<CpuTime>();
+        assert_hash::<CpuTimeSummary>();
+        assert_hash::<CtxtSwitches>();
+        assert_hash::<FdInfo>();
+        assert_hash::<Io>();
+        assert_hash::<KernelStat>();
+        assert_hash::<LimitChange>();
+        assert_hash::<Limits>();
+        assert_hash::<MemInfo>();
+        assert_hash::<Mountinfo>();
+        assert_hash::<Probe>();
+        assert_hash::<ProbeSupport>();
+        assert_hash::<Residency>();
+        assert_hash::<Resource>();
+        assert_hash::<Schedstat>();
+        assert_hash::<SeccompMode>();
+        assert_hash::<SignalSet>();
+        assert_hash::<SpeculationIndirectBranch>();
+        assert_hash::<SpeculationStoreBypass>();
+        assert_hash::<Stat>();
+        assert_hash::<StatRef<'static>>();
+        assert_hash::<State>();
+        assert_hash::<Status>();
+        assert_hash::<ThreadSummary>();
+        assert_hash::<TaintFlag>();
+        assert_hash::<TaintFlags>();
+        assert_hash::<Tty>();
+        assert_hash::<Uptime>();
+        assert_hash::<VmStat>();
+        assert_hash::<WaitOutcome>();
+    }
+}
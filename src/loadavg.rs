@@ -1,12 +1,22 @@
 //! System load and task statistics from `/proc/loadavg`.
 
-use std::fs::File;
-use std::io::Result;
+use std::collections::VecDeque;
+use std::collections::vec_deque;
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{Error, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use libc::pid_t;
 use nom::{line_ending, space};
 
-use parsers::{map_result, parse_f32, parse_i32, parse_u32, read_to_end};
+use parsers::{map_result_strict, parse_f32, parse_i32, parse_u32, read_to_end};
+use stat::cpu_count_online;
+use platform::{self, pid_t};
 
 /// System load and task statistics.
 ///
@@ -14,48 +24,355 @@ use parsers::{map_result, parse_f32, parse_i32, parse_u32, read_to_end};
 /// tasks on the system.
 ///
 /// See `man 5 proc` and `Linux/fs/proc/loadavg.c`.
-#[derive(Debug, Default, PartialEq)]
+///
+/// The default value has every load average and task count set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LoadAvg {
     /// Load average over the last minute.
     pub load_avg_1_min: f32,
-    /// Load average of the last 5 minutes.
+    /// Load average over the last 5 minutes.
     pub load_avg_5_min: f32,
-    /// Load average of the last 10 minutes
-    pub load_avg_10_min: f32,
-    /// the number of currently runnable kernel scheduling entities (processes, threads).
+    /// Load average over the last 15 minutes.
+    pub load_avg_15_min: f32,
+    /// The number of currently runnable kernel scheduling entities (processes, threads).
     pub tasks_runnable: u32,
-    /// the number of kernel scheduling entities that currently exist on the system.
+    /// The number of kernel scheduling entities that currently exist on the system.
     pub tasks_total: u32,
-    /// the PID of the process that was most recently created on the system.
+    /// The PID of the process that was most recently created on the system.
     pub last_created_pid: pid_t,
 }
 
+impl LoadAvg {
+    /// Parses the contents of a `/proc/loadavg` file.
+    pub fn parse(input: &[u8]) -> Result<LoadAvg> {
+        map_result_strict(input, parse_loadavg)
+    }
+
+    /// Returns the load averages divided by the number of online CPUs.
+    ///
+    /// The number of CPUs is read from `/proc/stat`, see `cpu_count_online()`.
+    pub fn normalized(&self) -> Result<NormalizedLoad> {
+        Ok(self.normalized_by(cpu_count_online()?))
+    }
+
+    /// Returns the load averages divided by the provided number of CPUs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpus` is zero.
+    pub fn normalized_by(&self, cpus: u32) -> NormalizedLoad {
+        assert!(cpus > 0, "cannot normalize load by zero CPUs");
+        let cpus_f32 = cpus as f32;
+        NormalizedLoad {
+            load_avg_1_min: self.load_avg_1_min / cpus_f32,
+            load_avg_5_min: self.load_avg_5_min / cpus_f32,
+            load_avg_15_min: self.load_avg_15_min / cpus_f32,
+            cpus,
+        }
+    }
+}
+
+/// Load averages per online CPU.
+///
+/// A normalized load of 1.0 means that, on average, every CPU had one runnable or uninterruptible
+/// task.
+///
+/// The default value has every load average and the CPU count set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NormalizedLoad {
+    /// Load average over the last minute, per CPU.
+    pub load_avg_1_min: f32,
+    /// Load average over the last 5 minutes, per CPU.
+    pub load_avg_5_min: f32,
+    /// Load average over the last 15 minutes, per CPU.
+    pub load_avg_15_min: f32,
+    /// The number of CPUs the load averages were divided by.
+    pub cpus: u32,
+}
+
+impl NormalizedLoad {
+    /// Returns `true` if the load average over the last minute, per CPU, exceeds `threshold`.
+    pub fn is_overloaded(&self, threshold: f32) -> bool {
+        self.load_avg_1_min > threshold
+    }
+}
+
+/// Formats the load average in the same format as `/proc/loadavg`.
+impl fmt::Display for LoadAvg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} {:.2} {:.2} {}/{} {}",
+               self.load_avg_1_min, self.load_avg_5_min, self.load_avg_15_min,
+               self.tasks_runnable, self.tasks_total, self.last_created_pid)
+    }
+}
+
 /// Parses the loadavg file format.
 named!(parse_loadavg<LoadAvg>,
-       chain!(load_avg_1_min:   parse_f32   ~ space ~
-              load_avg_5_min:   parse_f32   ~ space ~
-              load_avg_10_min:  parse_f32   ~ space ~
-              tasks_runnable:   parse_u32   ~ tag!("/") ~
-              tasks_total:      parse_u32   ~ space ~
-              last_created_pid: parse_i32   ~ line_ending,
-              || { LoadAvg { load_avg_1_min: load_avg_1_min,
-                             load_avg_5_min: load_avg_5_min,
-                             load_avg_10_min: load_avg_10_min,
-                             tasks_runnable: tasks_runnable,
-                             tasks_total: tasks_total,
-                             last_created_pid: last_created_pid } }));
+       do_parse!(load_avg_1_min:   parse_f32   >> space >>
+                 load_avg_5_min:   parse_f32   >> space >>
+                 load_avg_15_min:  parse_f32   >> space >>
+                 tasks_runnable:   parse_u32   >> tag!("/") >>
+                 tasks_total:      parse_u32   >> space >>
+                 last_created_pid: parse_i32   >> line_ending >>
+                 (LoadAvg { load_avg_1_min,
+                            load_avg_5_min,
+                            load_avg_15_min,
+                            tasks_runnable,
+                            tasks_total,
+                            last_created_pid })));
 
 /// Returns the system load average.
 pub fn loadavg() -> Result<LoadAvg> {
-    let mut buf = [0; 128]; // A typical loadavg file is about 32 bytes.
-    let mut file = try!(File::open("/proc/loadavg"));
-    map_result(parse_loadavg(try!(read_to_end(&mut file, &mut buf))))
+    loadavg_in(Path::new("/proc"))
+}
+
+/// Reads the system load average from a proc directory.
+pub fn loadavg_in(root: &Path) -> Result<LoadAvg> {
+    let mut buf = Vec::with_capacity(128); // A typical loadavg file is about 32 bytes.
+    let mut file = platform::open(root.join("loadavg"))?;
+    LoadAvg::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// Returns the system load average, reading `/proc/loadavg` asynchronously.
+#[cfg(feature = "async")]
+pub fn loadavg_async() -> impl Future<Output = Result<LoadAvg>> + Send {
+    platform::read_async("/proc/loadavg".to_owned(), LoadAvg::parse)
+}
+
+/// The three load averages of a `LoadAvg`, e.g. aggregated over the samples of a
+/// `LoadAvgSampler`.
+///
+/// The default value has every load average set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LoadAverages {
+    /// Load average over the last minute.
+    pub load_avg_1_min: f32,
+    /// Load average over the last 5 minutes.
+    pub load_avg_5_min: f32,
+    /// Load average over the last 15 minutes.
+    pub load_avg_15_min: f32,
+}
+
+impl LoadAverages {
+    fn from_loadavg(loadavg: &LoadAvg) -> LoadAverages {
+        LoadAverages {
+            load_avg_1_min: loadavg.load_avg_1_min,
+            load_avg_5_min: loadavg.load_avg_5_min,
+            load_avg_15_min: loadavg.load_avg_15_min,
+        }
+    }
+
+    /// Combines the corresponding load averages of two values.
+    fn combine<F>(&self, other: &LoadAverages, f: F) -> LoadAverages where F: Fn(f32, f32) -> f32 {
+        LoadAverages {
+            load_avg_1_min: f(self.load_avg_1_min, other.load_avg_1_min),
+            load_avg_5_min: f(self.load_avg_5_min, other.load_avg_5_min),
+            load_avg_15_min: f(self.load_avg_15_min, other.load_avg_15_min),
+        }
+    }
+}
+
+/// Records load average samples at a fixed interval into a bounded ring buffer.
+///
+/// The sampler can be driven by the caller, by calling `tick()` periodically, or by a background
+/// thread started with `spawn()`. Once the buffer is full, each new sample replaces the oldest
+/// one, so the statistics cover the most recent `capacity` samples.
+#[derive(Clone, Debug)]
+pub struct LoadAvgSampler {
+    samples: VecDeque<LoadAvg>,
+    capacity: usize,
+    interval: Duration,
+    last_sample: Option<Instant>,
+}
+
+impl LoadAvgSampler {
+    /// Returns an empty sampler retaining up to `capacity` samples taken every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize, interval: Duration) -> LoadAvgSampler {
+        assert!(capacity > 0, "LoadAvgSampler capacity must be positive");
+        LoadAvgSampler {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+            last_sample: None,
+        }
+    }
+
+    /// Samples the load average if at least the interval has elapsed since the previous sample,
+    /// or if no sample has been taken yet.
+    ///
+    /// Returns whether a sample was taken.
+    pub fn tick(&mut self) -> Result<bool> {
+        let now = Instant::now();
+        if let Some(last_sample) = self.last_sample {
+            if now.duration_since(last_sample) < self.interval {
+                return Ok(false);
+            }
+        }
+        self.record(loadavg()?);
+        self.last_sample = Some(now);
+        Ok(true)
+    }
+
+    /// Adds a sample to the buffer, replacing the oldest sample if the buffer is full.
+    pub fn record(&mut self, sample: LoadAvg) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Returns the maximum number of retained samples.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the interval between samples.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the number of retained samples.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if no samples are retained.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns an iterator over the retained samples, from oldest to newest.
+    pub fn samples(&self) -> vec_deque::Iter<'_, LoadAvg> {
+        self.samples.iter()
+    }
+
+    /// Returns the most recent sample.
+    pub fn latest(&self) -> Option<&LoadAvg> {
+        self.samples.back()
+    }
+
+    fn fold<F>(&self, f: F) -> Option<LoadAverages> where F: Fn(f32, f32) -> f32 {
+        let mut samples = self.samples.iter().map(LoadAverages::from_loadavg);
+        let first = samples.next()?;
+        Some(samples.fold(first, |acc, sample| acc.combine(&sample, &f)))
+    }
+
+    /// Returns the minimum of each load average over the retained samples.
+    pub fn min(&self) -> Option<LoadAverages> {
+        self.fold(f32::min)
+    }
+
+    /// Returns the maximum of each load average over the retained samples.
+    pub fn max(&self) -> Option<LoadAverages> {
+        self.fold(f32::max)
+    }
+
+    /// Returns the mean of each load average over the retained samples.
+    pub fn mean(&self) -> Option<LoadAverages> {
+        let len = self.samples.len() as f32;
+        self.fold(|acc, value| acc + value)
+            .map(|sum| sum.combine(&sum, |sum, _| sum / len))
+    }
+
+    /// Starts sampling on a background thread, once per interval.
+    ///
+    /// The thread stops when the returned handle is stopped or dropped, or if reading the load
+    /// average fails.
+    pub fn spawn(self) -> Result<LoadAvgSamplerHandle> {
+        let interval = self.interval;
+        let sampler = Arc::new(Mutex::new(self));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let sampler = sampler.clone();
+            let stop = stop.clone();
+            thread::Builder::new().name("loadavg-sampler".to_owned()).spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    let sample = loadavg()?;
+                    let now = Instant::now();
+                    {
+                        let mut sampler = sampler.lock().unwrap();
+                        sampler.record(sample);
+                        sampler.last_sample = Some(now);
+                    }
+                    let deadline = now + interval;
+                    loop {
+                        let now = Instant::now();
+                        if now >= deadline || stop.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        thread::park_timeout(deadline - now);
+                    }
+                }
+                Ok(())
+            })?
+        };
+
+        Ok(LoadAvgSamplerHandle { sampler, stop, thread: Some(thread) })
+    }
+}
+
+/// A handle to a `LoadAvgSampler` running on a background thread.
+///
+/// Dropping the handle stops the thread.
+#[derive(Debug)]
+pub struct LoadAvgSamplerHandle {
+    sampler: Arc<Mutex<LoadAvgSampler>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl LoadAvgSamplerHandle {
+    /// Returns a copy of the sampler in its current state.
+    pub fn snapshot(&self) -> LoadAvgSampler {
+        self.sampler.lock().unwrap().clone()
+    }
+
+    /// Stops the background thread, and returns the sampler.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error which stopped the thread, if reading the load average failed.
+    pub fn stop(mut self) -> Result<LoadAvgSampler> {
+        self.join()?;
+        Ok(self.snapshot())
+    }
+
+    fn join(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        match self.thread.take() {
+            Some(thread) => {
+                thread.thread().unpark();
+                thread.join().unwrap_or_else(|_| {
+                    Err(Error::other("load average sampler thread panicked"))
+                })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for LoadAvgSamplerHandle {
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{loadavg, parse_loadavg};
+    use std::thread;
+    use std::time::Duration;
+
+    use fixtures::fixture;
     use parsers::tests::unwrap;
+    use super::{LoadAverages, LoadAvg, LoadAvgSampler, NormalizedLoad, loadavg, parse_loadavg};
 
     /// Test that the system loadavg file can be parsed.
     #[test]
@@ -65,14 +382,121 @@ mod tests {
 
     #[test]
     fn test_parse_loadavg() {
-        let loadavg_text = b"0.46 0.33 0.28 34/625 8435\n";
+        let loadavg_text = &fixture("6.18", "loadavg");
         let loadavg = unwrap(parse_loadavg(loadavg_text));
-        assert_eq!(0.46, loadavg.load_avg_1_min);
-        assert_eq!(0.33, loadavg.load_avg_5_min);
-        assert_eq!(0.28, loadavg.load_avg_10_min);
-        assert_eq!(34, loadavg.tasks_runnable);
-        assert_eq!(625, loadavg.tasks_total);
-        assert_eq!(8435, loadavg.last_created_pid);
+        assert_eq!(0.33, loadavg.load_avg_1_min);
+        assert_eq!(0.42, loadavg.load_avg_5_min);
+        assert_eq!(0.44, loadavg.load_avg_15_min);
+        assert_eq!(2, loadavg.tasks_runnable);
+        assert_eq!(75, loadavg.tasks_total);
+        assert_eq!(23185, loadavg.last_created_pid);
+        assert_eq!(loadavg, LoadAvg::parse(loadavg_text).unwrap());
+    }
+
+    /// Test that every field of a line is parsed, including large task counts and pids.
+    #[test]
+    fn test_parse_loadavg_fields() {
+        let loadavg = unwrap(parse_loadavg(b"12.04 8.50 3.01 128/104857 4194303\n"));
+        assert_eq!(LoadAvg {
+                       load_avg_1_min: 12.04,
+                       load_avg_5_min: 8.50,
+                       load_avg_15_min: 3.01,
+                       tasks_runnable: 128,
+                       tasks_total: 104857,
+                       last_created_pid: 4194303,
+                   },
+                   loadavg);
+    }
+
+    #[test]
+    fn test_normalized() {
+        let loadavg = unwrap(parse_loadavg(b"6.00 3.00 1.50 34/625 8435\n"));
+        assert_eq!(NormalizedLoad { load_avg_1_min: 1.5, load_avg_5_min: 0.75, load_avg_15_min: 0.375,
+                                    cpus: 4 },
+                   loadavg.normalized_by(4));
+
+        // A single CPU leaves the load averages unchanged.
+        let single = loadavg.normalized_by(1);
+        assert_eq!((6.0, 3.0, 1.5), (single.load_avg_1_min, single.load_avg_5_min, single.load_avg_15_min));
+
+        assert!(loadavg.normalized_by(4).is_overloaded(1.0));
+        assert!(!loadavg.normalized_by(8).is_overloaded(1.0));
+        assert!(!loadavg.normalized_by(6).is_overloaded(1.0));
+
+        assert!(loadavg.normalized().unwrap().cpus >= 1);
+    }
+
+    fn sample(load_avg_1_min: f32, load_avg_5_min: f32, load_avg_15_min: f32) -> LoadAvg {
+        LoadAvg { load_avg_1_min, load_avg_5_min, load_avg_15_min, ..Default::default() }
+    }
+
+    #[test]
+    fn test_sampler_statistics() {
+        let mut sampler = LoadAvgSampler::new(3, Duration::from_secs(1));
+        assert!(sampler.is_empty());
+        assert_eq!(None, sampler.min());
+        assert_eq!(None, sampler.max());
+        assert_eq!(None, sampler.mean());
+
+        sampler.record(sample(1.0, 4.0, 0.5));
+        sampler.record(sample(3.0, 2.0, 0.5));
+        sampler.record(sample(2.0, 3.0, 2.0));
+        assert_eq!(3, sampler.len());
+        assert_eq!(Some(LoadAverages { load_avg_1_min: 1.0, load_avg_5_min: 2.0, load_avg_15_min: 0.5 }),
+                   sampler.min());
+        assert_eq!(Some(LoadAverages { load_avg_1_min: 3.0, load_avg_5_min: 4.0, load_avg_15_min: 2.0 }),
+                   sampler.max());
+        assert_eq!(Some(LoadAverages { load_avg_1_min: 2.0, load_avg_5_min: 3.0, load_avg_15_min: 1.0 }),
+                   sampler.mean());
+
+        // The oldest sample is replaced once the buffer is full.
+        sampler.record(sample(6.0, 3.0, 1.0));
+        assert_eq!(3, sampler.len());
+        assert_eq!(vec![3.0, 2.0, 6.0],
+                   sampler.samples().map(|sample| sample.load_avg_1_min).collect::<Vec<_>>());
+        assert_eq!(Some(&sample(6.0, 3.0, 1.0)), sampler.latest());
+        assert_eq!(2.0, sampler.min().unwrap().load_avg_1_min);
+        assert_eq!(11.0 / 3.0, sampler.mean().unwrap().load_avg_1_min);
+    }
+
+    #[test]
+    fn test_sampler_tick() {
+        let mut sampler = LoadAvgSampler::new(2, Duration::from_secs(3600));
+        assert!(sampler.tick().unwrap());
+        assert!(!sampler.tick().unwrap());
+        assert_eq!(1, sampler.len());
+
+        let mut sampler = LoadAvgSampler::new(2, Duration::from_secs(0));
+        for _ in 0..3 {
+            assert!(sampler.tick().unwrap());
+        }
+        assert_eq!(2, sampler.len());
+    }
+
+    #[test]
+    fn test_sampler_spawn() {
+        let handle = LoadAvgSampler::new(4, Duration::from_millis(1)).spawn().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(!handle.snapshot().is_empty());
+        let sampler = handle.stop().unwrap();
+        assert!(!sampler.is_empty());
+        assert!(sampler.len() <= 4);
+    }
+
+    #[test]
+    fn test_display_loadavg() {
+        let loadavg = unwrap(parse_loadavg(b"0.46 0.30 12.28 34/625 8435\n"));
+        assert_eq!("0.46 0.30 12.28 34/625 8435", loadavg.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_loadavg() {
+        use serde_json;
+
+        let loadavg = unwrap(parse_loadavg(b"0.46 0.33 0.28 34/625 8435\n"));
+        let json = serde_json::to_string(&loadavg).unwrap();
+        assert_eq!(loadavg, serde_json::from_str(&json).unwrap());
     }
 }
 
@@ -80,9 +504,6 @@ mod tests {
 mod benches {
     extern crate test;
 
-    use std::fs::File;
-
-    use parsers::read_to_end;
     use super::{loadavg, parse_loadavg};
 
     #[bench]
@@ -92,8 +513,6 @@ mod benches {
 
     #[bench]
     fn bench_loadavg_parse(b: &mut test::Bencher) {
-        let mut buf = [0; 128];
-        let statm = read_to_end(&mut File::open("/proc/loadavg").unwrap(), &mut buf).unwrap();
-        b.iter(|| test::black_box(parse_loadavg(statm)));
+        b.iter(|| test::black_box(parse_loadavg(b"0.46 0.33 0.28 34/625 8435\n")));
     }
 }
@@ -0,0 +1,421 @@
+//! System memory usage from `/proc/meminfo`.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::cmp;
+use std::io::Result;
+use std::str;
+
+use nom::{IResult, line_ending, not_line_ending};
+
+use parsers::{map_result, parse_kb_value, read_to_end};
+use platform;
+use sysconf::page_size;
+use zoneinfo::{Zone, zoneinfo};
+
+/// System memory usage.
+///
+/// See `man 5 proc` and `Linux/Documentation/filesystems/proc.txt`.
+///
+/// All sizes are in bytes; the `hugepages_*` fields are counts of huge pages. Fields which are not
+/// reported by the kernel, e.g. because they were added in a newer version or depend on a kernel
+/// configuration option, are zero.
+///
+/// The default value has every field set to zero, and `mem_available` set to `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemInfo {
+    /// Total usable RAM, i.e. physical RAM less reserved memory and the kernel binary code.
+    pub mem_total: u64,
+    /// Unused RAM.
+    pub mem_free: u64,
+    /// Estimate of the memory available for starting new applications without swapping (since
+    /// Linux 3.14).
+    pub mem_available: Option<u64>,
+    /// Memory in buffer cache for raw disk blocks.
+    pub buffers: u64,
+    /// Memory in the page cache, excluding `swap_cached`.
+    pub cached: u64,
+    /// Memory which was swapped out and back in, and is still in the swap file.
+    pub swap_cached: u64,
+    /// Memory which has been used recently and is usually not reclaimed.
+    pub active: u64,
+    /// Memory which has been used less recently and is more eligible to be reclaimed.
+    pub inactive: u64,
+    /// Anonymous memory which has been used recently (since Linux 2.6.28).
+    pub active_anon: u64,
+    /// Anonymous memory which is a candidate for reclaim (since Linux 2.6.28).
+    pub inactive_anon: u64,
+    /// Page cache memory which has been used recently (since Linux 2.6.28).
+    pub active_file: u64,
+    /// Page cache memory which is a candidate for reclaim (since Linux 2.6.28).
+    pub inactive_file: u64,
+    /// Memory which cannot be reclaimed, e.g. because it is locked (since Linux 2.6.28).
+    pub unevictable: u64,
+    /// Memory locked with `mlock(2)` (since Linux 2.6.28).
+    pub mlocked: u64,
+    /// Total swap space.
+    pub swap_total: u64,
+    /// Unused swap space.
+    pub swap_free: u64,
+    /// Memory waiting to be written back to disk.
+    pub dirty: u64,
+    /// Memory actively being written back to disk.
+    pub writeback: u64,
+    /// Non-file backed pages mapped into userspace page tables (since Linux 2.6.18).
+    pub anon_pages: u64,
+    /// Files which have been mapped into memory, such as libraries.
+    pub mapped: u64,
+    /// Memory used by shared memory and tmpfs (since Linux 2.6.32).
+    pub shmem: u64,
+    /// Kernel allocations which the kernel will attempt to reclaim under memory pressure,
+    /// including `s_reclaimable` (since Linux 4.20).
+    pub k_reclaimable: u64,
+    /// In-kernel data structures cache.
+    pub slab: u64,
+    /// Part of `slab` which might be reclaimed, such as caches (since Linux 2.6.19).
+    pub s_reclaimable: u64,
+    /// Part of `slab` which cannot be reclaimed under memory pressure (since Linux 2.6.19).
+    pub s_unreclaim: u64,
+    /// Memory used by kernel stacks (since Linux 2.6.32).
+    pub kernel_stack: u64,
+    /// Memory used by the lowest level of page tables (since Linux 2.6.18).
+    pub page_tables: u64,
+    /// Total memory which can be allocated under the current overcommit policy (since Linux
+    /// 2.6.10).
+    pub commit_limit: u64,
+    /// Memory currently allocated on the system, whether or not it has been used.
+    pub committed_as: u64,
+    /// Total size of the vmalloc memory area.
+    pub vmalloc_total: u64,
+    /// Used size of the vmalloc memory area.
+    pub vmalloc_used: u64,
+    /// Non-file backed huge pages mapped into userspace page tables (since Linux 2.6.38).
+    pub anon_huge_pages: u64,
+    /// Shared memory and tmpfs allocated with huge pages (since Linux 4.8).
+    pub shmem_huge_pages: u64,
+    /// Number of huge pages in the pool.
+    pub hugepages_total: u64,
+    /// Number of huge pages in the pool which are not yet allocated.
+    pub hugepages_free: u64,
+    /// Number of huge pages which are reserved but not yet allocated (since Linux 2.6.17).
+    pub hugepages_rsvd: u64,
+    /// Number of huge pages in the pool above the configured number of huge pages (since Linux
+    /// 2.6.24).
+    pub hugepages_surp: u64,
+    /// Size of the default huge page.
+    pub hugepage_size: u64,
+    /// Total memory consumed by huge pages of all sizes (since Linux 4.16).
+    pub hugetlb: u64,
+}
+
+impl MemInfo {
+    /// Parses the contents of a `/proc/meminfo` file.
+    pub fn parse(input: &[u8]) -> Result<MemInfo> {
+        map_result(input, parse_meminfo)
+    }
+
+    /// Returns the memory available for starting new applications without swapping.
+    ///
+    /// This is `mem_available` if the kernel reports it (since Linux 3.14). Otherwise the estimate
+    /// is computed as the kernel does, which requires reading the zone watermarks from
+    /// `/proc/zoneinfo`; see `estimate_available`. If `/proc/zoneinfo` can not be read, the
+    /// estimate is computed without the watermarks.
+    pub fn available(&self) -> u64 {
+        self.mem_available.unwrap_or_else(|| {
+            self.estimate_available(&zoneinfo().unwrap_or_default(), page_size())
+        })
+    }
+
+    /// Estimates the memory available for starting new applications without swapping, from the
+    /// memory zones of the system and the page size.
+    ///
+    /// This is the computation of `MemAvailable` introduced in Linux 3.14: the free memory, less
+    /// the reserved pages of each zone, plus the page cache and the reclaimable slab, each less the
+    /// part which can not be reclaimed without dropping below the low watermarks.
+    pub fn estimate_available(&self, zones: &[Zone], page_size: u64) -> u64 {
+        let wmark_low = zones.iter().map(|zone| zone.low).sum::<u64>().saturating_mul(page_size);
+        let reserved = zones.iter().map(Zone::reserved).sum::<u64>().saturating_mul(page_size);
+
+        let page_cache = self.active_file + self.inactive_file;
+        let page_cache = page_cache - cmp::min(page_cache / 2, wmark_low);
+        let slab = self.s_reclaimable - cmp::min(self.s_reclaimable / 2, wmark_low);
+
+        (self.mem_free + page_cache + slab).saturating_sub(reserved)
+    }
+
+    /// Returns the memory which is in use, that is `mem_total` less the available memory.
+    ///
+    /// This is the `used` column of `free(1)` since procps-ng 4.0.
+    pub fn used(&self) -> u64 {
+        self.mem_total.saturating_sub(self.available())
+    }
+
+    /// Returns the fraction of the total memory which is available, from 0 to 1.
+    pub fn available_fraction(&self) -> f64 {
+        if self.mem_total == 0 {
+            return 0.0;
+        }
+        (self.available() as f64 / self.mem_total as f64).min(1.0)
+    }
+}
+
+/// Parses the label of a meminfo line, including the trailing colon.
+named!(parse_label<&'a str>, map_res!(take_until_and_consume!(":"), str::from_utf8));
+
+/// Parses the meminfo file format.
+///
+/// Lines which are not recognized are skipped.
+fn parse_meminfo(input: &[u8]) -> IResult<&[u8], MemInfo> {
+    let mut meminfo = MemInfo::default();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (i, label) = try_parse!(rest, parse_label);
+        let field = match label {
+            "MemTotal"        => &mut meminfo.mem_total,
+            "MemFree"         => &mut meminfo.mem_free,
+            "Buffers"         => &mut meminfo.buffers,
+            "Cached"          => &mut meminfo.cached,
+            "SwapCached"      => &mut meminfo.swap_cached,
+            "Active"          => &mut meminfo.active,
+            "Inactive"        => &mut meminfo.inactive,
+            "Active(anon)"    => &mut meminfo.active_anon,
+            "Inactive(anon)"  => &mut meminfo.inactive_anon,
+            "Active(file)"    => &mut meminfo.active_file,
+            "Inactive(file)"  => &mut meminfo.inactive_file,
+            "Unevictable"     => &mut meminfo.unevictable,
+            "Mlocked"         => &mut meminfo.mlocked,
+            "SwapTotal"       => &mut meminfo.swap_total,
+            "SwapFree"        => &mut meminfo.swap_free,
+            "Dirty"           => &mut meminfo.dirty,
+            "Writeback"       => &mut meminfo.writeback,
+            "AnonPages"       => &mut meminfo.anon_pages,
+            "Mapped"          => &mut meminfo.mapped,
+            "Shmem"           => &mut meminfo.shmem,
+            "KReclaimable"    => &mut meminfo.k_reclaimable,
+            "Slab"            => &mut meminfo.slab,
+            "SReclaimable"    => &mut meminfo.s_reclaimable,
+            "SUnreclaim"      => &mut meminfo.s_unreclaim,
+            "KernelStack"     => &mut meminfo.kernel_stack,
+            "PageTables"      => &mut meminfo.page_tables,
+            "CommitLimit"     => &mut meminfo.commit_limit,
+            "Committed_AS"    => &mut meminfo.committed_as,
+            "VmallocTotal"    => &mut meminfo.vmalloc_total,
+            "VmallocUsed"     => &mut meminfo.vmalloc_used,
+            "AnonHugePages"   => &mut meminfo.anon_huge_pages,
+            "ShmemHugePages"  => &mut meminfo.shmem_huge_pages,
+            "HugePages_Total" => &mut meminfo.hugepages_total,
+            "HugePages_Free"  => &mut meminfo.hugepages_free,
+            "HugePages_Rsvd"  => &mut meminfo.hugepages_rsvd,
+            "HugePages_Surp"  => &mut meminfo.hugepages_surp,
+            "Hugepagesize"    => &mut meminfo.hugepage_size,
+            "Hugetlb"         => &mut meminfo.hugetlb,
+            "MemAvailable" => {
+                let (i, value) = try_parse!(i, terminated!(parse_kb_value, line_ending));
+                meminfo.mem_available = Some(value);
+                rest = i;
+                continue;
+            }
+            _ => {
+                rest = try_parse!(i, terminated!(not_line_ending, line_ending)).0;
+                continue;
+            }
+        };
+        let (i, value) = try_parse!(i, terminated!(parse_kb_value, line_ending));
+        *field = value;
+        rest = i;
+    }
+    IResult::Done(rest, meminfo)
+}
+
+/// Returns the system memory usage.
+pub fn meminfo() -> Result<MemInfo> {
+    let mut buf = Vec::with_capacity(2048); // A typical meminfo file is about 1.5KiB.
+    let mut file = platform::open("/proc/meminfo")?;
+    MemInfo::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// Returns the system memory usage, reading `/proc/meminfo` asynchronously.
+#[cfg(feature = "async")]
+pub fn meminfo_async() -> impl Future<Output = Result<MemInfo>> + Send {
+    platform::read_async("/proc/meminfo".to_owned(), MemInfo::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use parsers::tests::unwrap;
+    use zoneinfo::parse_zoneinfo;
+    use zoneinfo::tests::ZONEINFO;
+    use super::{MemInfo, meminfo, parse_meminfo};
+
+    /// Test that the system meminfo file can be parsed.
+    #[test]
+    fn test_meminfo() {
+        let meminfo = meminfo().unwrap();
+        assert!(meminfo.mem_total > 0);
+        assert!(meminfo.mem_free <= meminfo.mem_total);
+
+        let available = meminfo.available();
+        assert!(available <= meminfo.mem_total);
+        assert_eq!(meminfo.mem_total - available, meminfo.used());
+        assert!(meminfo.available_fraction() > 0.0 && meminfo.available_fraction() <= 1.0);
+
+        // The estimate for kernels which do not report the available memory.
+        let estimate = MemInfo { mem_available: None, ..meminfo }.available();
+        assert!(estimate > 0 && estimate <= meminfo.mem_total);
+    }
+
+    #[test]
+    fn test_estimate_available() {
+        let meminfo = MemInfo {
+            mem_total: 8 << 30,
+            mem_free: 2 << 30,
+            mem_available: None,
+            active_file: 1 << 30,
+            inactive_file: 1 << 30,
+            s_reclaimable: 64 << 20,
+            ..MemInfo::default()
+        };
+        let zones = parse_zoneinfo(ZONEINFO).unwrap();
+
+        // The low watermarks are 52 + 10622 pages, and the reserved pages 3840 + 15720 pages.
+        let wmark_low = (52 + 10622) * 4096;
+        let reserved = (3840 + 15720) * 4096;
+        let expected = (2 << 30) - reserved + (2 << 30) - wmark_low + (32 << 20);
+        assert_eq!(4204683264, expected);
+        assert_eq!(expected, meminfo.estimate_available(&zones, 4096));
+
+        // Without zones, all of the page cache and slab is assumed to be reclaimable.
+        assert_eq!((2 << 30) + (2 << 30) + (64 << 20), meminfo.estimate_available(&[], 4096));
+
+        // The estimate can not be negative.
+        let reserved_only = MemInfo { mem_free: 4096, ..MemInfo::default() };
+        assert_eq!(0, reserved_only.estimate_available(&zones, 4096));
+
+        // The reported value is preferred.
+        let reported = MemInfo { mem_available: Some(5 << 30), ..meminfo };
+        assert_eq!(5 << 30, reported.available());
+        assert_eq!(3 << 30, reported.used());
+        assert_eq!(0.625, reported.available_fraction());
+        assert_eq!(0.0, MemInfo::default().available_fraction());
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let text = b"MemTotal:        6147400 kB\n\
+                     MemFree:         3680544 kB\n\
+                     MemAvailable:    5585596 kB\n\
+                     Buffers:           56684 kB\n\
+                     Cached:          2048748 kB\n\
+                     SwapCached:            0 kB\n\
+                     Active:           718840 kB\n\
+                     Inactive:        1590584 kB\n\
+                     Active(anon):         24 kB\n\
+                     Inactive(anon):   213016 kB\n\
+                     Active(file):     718816 kB\n\
+                     Inactive(file):  1377568 kB\n\
+                     Unevictable:       10020 kB\n\
+                     Mlocked:           10020 kB\n\
+                     SwapTotal:       2097148 kB\n\
+                     SwapFree:        2097148 kB\n\
+                     Zswap:                 0 kB\n\
+                     Dirty:             15464 kB\n\
+                     Writeback:             0 kB\n\
+                     AnonPages:        214120 kB\n\
+                     Mapped:           149216 kB\n\
+                     Shmem:              9048 kB\n\
+                     KReclaimable:      40956 kB\n\
+                     Slab:              59840 kB\n\
+                     SReclaimable:      40956 kB\n\
+                     SUnreclaim:        18884 kB\n\
+                     KernelStack:        1184 kB\n\
+                     PageTables:         2500 kB\n\
+                     CommitLimit:     3073700 kB\n\
+                     Committed_AS:     388820 kB\n\
+                     VmallocTotal:   34359738367 kB\n\
+                     VmallocUsed:       15956 kB\n\
+                     AnonHugePages:         0 kB\n\
+                     ShmemHugePages:        0 kB\n\
+                     HugePages_Total:      16\n\
+                     HugePages_Free:       12\n\
+                     HugePages_Rsvd:        2\n\
+                     HugePages_Surp:        0\n\
+                     Hugepagesize:       2048 kB\n\
+                     Hugetlb:           32768 kB\n\
+                     DirectMap4k:       26624 kB\n";
+        let meminfo = unwrap(parse_meminfo(text));
+        assert_eq!(meminfo, MemInfo::parse(text).unwrap());
+
+        assert_eq!(6147400 * 1024, meminfo.mem_total);
+        assert_eq!(3680544 * 1024, meminfo.mem_free);
+        assert_eq!(Some(5585596 * 1024), meminfo.mem_available);
+        assert_eq!(2048748 * 1024, meminfo.cached);
+        assert_eq!(24 * 1024, meminfo.active_anon);
+        assert_eq!(1377568 * 1024, meminfo.inactive_file);
+        assert_eq!(2097148 * 1024, meminfo.swap_free);
+        assert_eq!(40956 * 1024, meminfo.s_reclaimable);
+        assert_eq!(388820 * 1024, meminfo.committed_as);
+        // Larger than a 32-bit usize.
+        assert_eq!(34359738367 * 1024, meminfo.vmalloc_total);
+        assert_eq!(16, meminfo.hugepages_total);
+        assert_eq!(12, meminfo.hugepages_free);
+        assert_eq!(2, meminfo.hugepages_rsvd);
+        assert_eq!(0, meminfo.hugepages_surp);
+        assert_eq!(2 << 20, meminfo.hugepage_size);
+        assert_eq!(32 << 20, meminfo.hugetlb);
+    }
+
+    /// Test that a meminfo file from a kernel older than 3.14 has no `MemAvailable`.
+    #[test]
+    fn test_parse_meminfo_old_kernel() {
+        let text = b"MemTotal:        2061364 kB\n\
+                     MemFree:          400256 kB\n\
+                     Buffers:          114752 kB\n\
+                     Cached:          1002872 kB\n";
+        let meminfo = unwrap(parse_meminfo(text));
+        assert_eq!(2061364 * 1024, meminfo.mem_total);
+        assert_eq!(None, meminfo.mem_available);
+        assert_eq!(0, meminfo.shmem);
+    }
+}
+
+#[cfg(all(test, rustc_nightly))]
+mod benches {
+    extern crate test;
+
+    use super::{meminfo, parse_meminfo};
+
+    /// Returns a meminfo file with the fields of a recent kernel.
+    fn meminfo_fixture() -> Vec<u8> {
+        let labels = ["MemTotal", "MemFree", "MemAvailable", "Buffers", "Cached", "SwapCached",
+                      "Active", "Inactive", "Active(anon)", "Inactive(anon)", "Active(file)",
+                      "Inactive(file)", "Unevictable", "Mlocked", "SwapTotal", "SwapFree", "Zswap",
+                      "Zswapped", "Dirty", "Writeback", "AnonPages", "Mapped", "Shmem",
+                      "KReclaimable", "Slab", "SReclaimable", "SUnreclaim", "KernelStack",
+                      "PageTables", "SecPageTables", "NFS_Unstable", "Bounce", "WritebackTmp",
+                      "CommitLimit", "Committed_AS", "VmallocTotal", "VmallocUsed", "VmallocChunk",
+                      "Percpu", "AnonHugePages", "ShmemHugePages", "ShmemPmdMapped",
+                      "FileHugePages", "FilePmdMapped", "Hugepagesize", "Hugetlb", "DirectMap4k",
+                      "DirectMap2M", "DirectMap1G"];
+        let mut text = String::new();
+        for (idx, label) in labels.iter().enumerate() {
+            text.push_str(&format!("{:<16}{:>8} kB\n", format!("{}:", label), idx * 104729));
+        }
+        for label in &["HugePages_Total", "HugePages_Free", "HugePages_Rsvd", "HugePages_Surp"] {
+            text.push_str(&format!("{:<16}{:>8}\n", format!("{}:", label), 0));
+        }
+        text.into_bytes()
+    }
+
+    #[bench]
+    fn bench_meminfo(b: &mut test::Bencher) {
+        b.iter(|| test::black_box(meminfo()));
+    }
+
+    #[bench]
+    fn bench_meminfo_parse(b: &mut test::Bencher) {
+        let meminfo = meminfo_fixture();
+        b.iter(|| test::black_box(parse_meminfo(&meminfo)));
+    }
+}
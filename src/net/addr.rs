@@ -0,0 +1,152 @@
+//! Decoding of the hexadecimal socket addresses of the socket tables in `/proc/net/`.
+//!
+//! The socket tables, such as `/proc/net/tcp` and `/proc/net/udp6`, format an address as
+//! `<address>:<port>`. The address is formatted as the 32-bit words of the address in network
+//! byte order, each printed as a hexadecimal integer in host byte order, so the bytes of each word
+//! appear reversed on little-endian hosts: `127.0.0.1` is formatted as `0100007F`. The port is
+//! converted to host byte order before it is printed, so port 443 is formatted as `01BB`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+/// Returns the error for an address which could not be parsed.
+fn parse_error(input: &[u8]) -> Error {
+    Error::new(ErrorKind::InvalidInput,
+               format!("unable to parse hex socket address: {:?}", String::from_utf8_lossy(input)))
+}
+
+/// Parses a hexadecimal integer of exactly `input.len()` digits.
+fn parse_hex(input: &[u8]) -> Option<u32> {
+    if input.is_empty() || input.len() > 8 || !input.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u32::from_str_radix(str::from_utf8(input).ok()?, 16).ok()
+}
+
+/// Parses a 32-bit word of an address, returning its bytes in network byte order.
+fn parse_word(input: &[u8]) -> Option<[u8; 4]> {
+    parse_hex(input).map(u32::to_ne_bytes)
+}
+
+/// Parses an IPv4 address formatted as 8 hexadecimal digits, e.g. `0100007F` for `127.0.0.1`.
+pub fn parse_ipv4_hex(input: &[u8]) -> Result<Ipv4Addr> {
+    if input.len() != 8 {
+        return Err(parse_error(input));
+    }
+    parse_word(input).map(Ipv4Addr::from).ok_or_else(|| parse_error(input))
+}
+
+/// Parses an IPv6 address formatted as 32 hexadecimal digits, e.g.
+/// `00000000000000000000000001000000` for `::1`.
+///
+/// The address is formatted as four 32-bit words, each in the same byte order as an IPv4 address.
+pub fn parse_ipv6_hex(input: &[u8]) -> Result<Ipv6Addr> {
+    if input.len() != 32 {
+        return Err(parse_error(input));
+    }
+    let mut octets = [0; 16];
+    for (octets, word) in octets.chunks_mut(4).zip(input.chunks(8)) {
+        octets.copy_from_slice(&parse_word(word).ok_or_else(|| parse_error(input))?);
+    }
+    Ok(Ipv6Addr::from(octets))
+}
+
+/// Parses a port formatted as 4 hexadecimal digits, e.g. `01BB` for 443.
+pub fn parse_port_hex(input: &[u8]) -> Result<u16> {
+    if input.len() != 4 {
+        return Err(parse_error(input));
+    }
+    parse_hex(input).map(|port| port as u16).ok_or_else(|| parse_error(input))
+}
+
+/// Parses an IPv4 or IPv6 address formatted as 8 or 32 hexadecimal digits.
+pub fn parse_ip_hex(input: &[u8]) -> Result<IpAddr> {
+    match input.len() {
+        8 => parse_ipv4_hex(input).map(IpAddr::V4),
+        32 => parse_ipv6_hex(input).map(IpAddr::V6),
+        _ => Err(parse_error(input)),
+    }
+}
+
+/// Parses a socket address formatted as `<address>:<port>`, e.g. `0100007F:01BB` for
+/// `127.0.0.1:443`.
+pub fn parse_socket_addr_hex(input: &[u8]) -> Result<SocketAddr> {
+    let colon = input.iter().position(|&b| b == b':').ok_or_else(|| parse_error(input))?;
+    let ip = parse_ip_hex(&input[..colon]).map_err(|_| parse_error(input))?;
+    let port = parse_port_hex(&input[colon + 1..]).map_err(|_| parse_error(input))?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    use super::{parse_ip_hex, parse_ipv4_hex, parse_ipv6_hex, parse_port_hex, parse_socket_addr_hex};
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_parse_ipv4_hex() {
+        assert_eq!(Ipv4Addr::new(127, 0, 0, 1), parse_ipv4_hex(b"0100007F").unwrap());
+        assert_eq!(Ipv4Addr::new(127, 0, 0, 1), parse_ipv4_hex(b"0100007f").unwrap());
+        assert_eq!(Ipv4Addr::new(0, 0, 0, 0), parse_ipv4_hex(b"00000000").unwrap());
+        assert_eq!(Ipv4Addr::new(192, 168, 1, 10), parse_ipv4_hex(b"0A01A8C0").unwrap());
+        assert_eq!(Ipv4Addr::new(255, 255, 255, 255), parse_ipv4_hex(b"FFFFFFFF").unwrap());
+
+        for input in &[&b""[..], b"0100007", b"0100007F0", b"+100007F", b"0100007G", b"0100 07F"] {
+            assert_eq!(ErrorKind::InvalidInput, parse_ipv4_hex(input).unwrap_err().kind(), "{:?}", input);
+        }
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_parse_ipv6_hex() {
+        assert_eq!(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1),
+                   parse_ipv6_hex(b"00000000000000000000000001000000").unwrap());
+        assert_eq!(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0),
+                   parse_ipv6_hex(b"00000000000000000000000000000000").unwrap());
+        assert_eq!(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1),
+                   parse_ipv6_hex(b"B80D0120000000000000000001000000").unwrap());
+        assert_eq!(Ipv6Addr::new(0xfe80, 0, 0, 0, 0x0211, 0x22ff, 0xfe33, 0x4455),
+                   parse_ipv6_hex(b"000080FE00000000FF221102554433FE").unwrap());
+        // An IPv4-mapped address, as reported for IPv4 connections to IPv6 sockets.
+        assert_eq!(Ipv4Addr::new(127, 0, 0, 1).to_ipv6_mapped(),
+                   parse_ipv6_hex(b"0000000000000000FFFF00000100007F").unwrap());
+
+        for input in &[&b""[..], b"0100007F", b"0000000000000000000000000100000",
+                       b"000000000000000000000000010000000", b"0000000000000000000000000100000G"] {
+            assert_eq!(ErrorKind::InvalidInput, parse_ipv6_hex(input).unwrap_err().kind(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_port_hex() {
+        assert_eq!(443, parse_port_hex(b"01BB").unwrap());
+        assert_eq!(22, parse_port_hex(b"0016").unwrap());
+        assert_eq!(0, parse_port_hex(b"0000").unwrap());
+        assert_eq!(65535, parse_port_hex(b"ffff").unwrap());
+
+        for input in &[&b""[..], b"1BB", b"001BB", b"-1BB", b"01BX"] {
+            assert_eq!(ErrorKind::InvalidInput, parse_port_hex(input).unwrap_err().kind(), "{:?}", input);
+        }
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_parse_socket_addr_hex() {
+        assert_eq!("127.0.0.1:443".parse::<SocketAddr>().unwrap(),
+                   parse_socket_addr_hex(b"0100007F:01BB").unwrap());
+        assert_eq!("[::1]:22".parse::<SocketAddr>().unwrap(),
+                   parse_socket_addr_hex(b"00000000000000000000000001000000:0016").unwrap());
+        assert_eq!("[2001:db8::1]:443".parse::<SocketAddr>().unwrap(),
+                   parse_socket_addr_hex(b"B80D0120000000000000000001000000:01BB").unwrap());
+        assert_eq!(parse_ip_hex(b"0100007F").unwrap(),
+                   parse_socket_addr_hex(b"0100007F:0000").unwrap().ip());
+
+        for input in &[&b""[..], b"0100007F", b"0100007F:", b":01BB", b"0100007F:01BB:", b"01007F:01BB"] {
+            assert_eq!(ErrorKind::InvalidInput,
+                       parse_socket_addr_hex(input).unwrap_err().kind(), "{:?}", input);
+        }
+    }
+}
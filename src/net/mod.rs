@@ -0,0 +1,113 @@
+//! Network information, and the processes which own sockets.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use parsers::is_exited;
+use platform::{self, pid_t};
+
+pub mod addr;
+mod snmp6;
+
+pub use self::snmp6::{Snmp6Stats, dev_snmp6};
+pub use socket::{InetSocket, TcpState, UnixSocket, tcp, udp, unix};
+
+// The probe which reads from a fixture tree, for the golden tests of `fixtures`.
+#[cfg(test)]
+pub use self::snmp6::dev_snmp6_in;
+
+/// Returns the inode of the socket referred to by the target of a `/proc/[pid]/fd/` link, such as
+/// `socket:[12345]`, or `None` if the file descriptor is not a socket.
+fn parse_socket_inode(target: &Path) -> Option<u64> {
+    let target = target.to_str()?;
+    if !target.starts_with("socket:[") || !target.ends_with(']') {
+        return None;
+    }
+    target["socket:[".len()..target.len() - 1].parse().ok()
+}
+
+/// Returns whether an error reading the file descriptors of a process should skip the process,
+/// because the process has exited or belongs to another user.
+fn is_skippable(error: &Error) -> bool {
+    is_exited(error) || error.kind() == ErrorKind::PermissionDenied
+}
+
+/// Adds the sockets owned by the process with the provided pid to `owners`.
+fn add_socket_owner(owners: &mut HashMap<u64, Vec<pid_t>>, pid: pid_t) -> Result<()> {
+    for fd in platform::read_dir(format!("/proc/{}/fd", pid))? {
+        let target = match fd.and_then(|fd| platform::read_link(fd.path())) {
+            Ok(target) => target,
+            // The file descriptor was closed while the directory was read.
+            Err(ref error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        if let Some(inode) = parse_socket_inode(&target) {
+            let pids = owners.entry(inode).or_default();
+            // A process may own several file descriptors for the same socket.
+            if pids.last() != Some(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the pids of the processes which own each socket, by socket inode.
+///
+/// The inodes match the `inode` column of the socket tables in `/proc/net/`, such as
+/// `/proc/net/tcp`, so this is the equivalent of `ss -p` or `lsof -i`. The file descriptors of
+/// every process are read in a single pass, which is expensive on systems with many processes, so
+/// the result should be reused for several lookups.
+///
+/// Processes whose file descriptors can not be read, because they belong to another user and the
+/// current process lacks `CAP_SYS_PTRACE`, or because they exited during the walk, are skipped.
+/// Sockets shared by several processes, such as a listening socket inherited by worker processes,
+/// are owned by each of them.
+pub fn socket_owners() -> Result<HashMap<u64, Vec<pid_t>>> {
+    let mut owners = HashMap::new();
+    for entry in platform::read_dir("/proc")? {
+        let pid = match entry?.file_name().to_str().and_then(|name| name.parse::<pid_t>().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        match add_socket_owner(&mut owners, pid) {
+            Err(ref error) if is_skippable(error) => continue,
+            result => result?,
+        }
+    }
+    Ok(owners)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::TcpListener;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::process;
+
+    use libc::pid_t;
+
+    use super::{parse_socket_inode, socket_owners};
+
+    #[test]
+    fn test_parse_socket_inode() {
+        assert_eq!(Some(12345), parse_socket_inode(Path::new("socket:[12345]")));
+        assert_eq!(None, parse_socket_inode(Path::new("pipe:[12345]")));
+        assert_eq!(None, parse_socket_inode(Path::new("socket:[]")));
+        assert_eq!(None, parse_socket_inode(Path::new("socket:[12345")));
+        assert_eq!(None, parse_socket_inode(Path::new("/dev/null")));
+    }
+
+    /// Test that the current process owns a socket which it listens on.
+    #[test]
+    fn test_socket_owners() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let inode = fs::metadata(format!("/proc/self/fd/{}", listener.as_raw_fd())).unwrap().ino();
+
+        let owners = socket_owners().unwrap();
+        assert_eq!(Some(&vec![process::id() as pid_t]), owners.get(&inode));
+    }
+}
@@ -0,0 +1,237 @@
+//! Per-interface IPv6 statistics from `/proc/net/dev_snmp6/`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::str;
+
+use parsers::read_to_end;
+use platform;
+
+/// The IPv6 and ICMPv6 statistics of a network interface.
+///
+/// The common counters are typed fields, named after the MIB counters of RFC 4293 and RFC 4292
+/// without their `Ip6` prefix. The counters which are not typed, such as the reassembly and
+/// per-message-type ICMPv6 counters, are kept by name. Counters which are not reported by the
+/// kernel are zero.
+///
+/// See `Linux/net/ipv6/proc.c`.
+///
+/// The default value has every counter set to zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snmp6Stats {
+    /// The index of the interface (`ifIndex`).
+    pub if_index: u32,
+    /// Number of datagrams received, including those received in error (`Ip6InReceives`).
+    pub in_receives: u64,
+    /// Number of datagrams discarded because of errors in their headers (`Ip6InHdrErrors`).
+    pub in_hdr_errors: u64,
+    /// Number of datagrams discarded because no route could be found (`Ip6InNoRoutes`).
+    pub in_no_routes: u64,
+    /// Number of datagrams discarded because of an invalid destination address
+    /// (`Ip6InAddrErrors`).
+    pub in_addr_errors: u64,
+    /// Number of datagrams discarded without errors, e.g. for lack of buffer space
+    /// (`Ip6InDiscards`).
+    pub in_discards: u64,
+    /// Number of datagrams delivered to upper-layer protocols (`Ip6InDelivers`).
+    pub in_delivers: u64,
+    /// Number of datagrams forwarded (`Ip6OutForwDatagrams`).
+    pub out_forw_datagrams: u64,
+    /// Number of datagrams supplied for transmission by local upper-layer protocols
+    /// (`Ip6OutRequests`).
+    pub out_requests: u64,
+    /// Number of outgoing datagrams discarded without errors (`Ip6OutDiscards`).
+    pub out_discards: u64,
+    /// Number of outgoing datagrams discarded because no route could be found (`Ip6OutNoRoutes`).
+    pub out_no_routes: u64,
+    /// Number of multicast datagrams received (`Ip6InMcastPkts`).
+    pub in_mcast_pkts: u64,
+    /// Number of multicast datagrams transmitted (`Ip6OutMcastPkts`).
+    pub out_mcast_pkts: u64,
+    /// Number of bytes received in datagrams (`Ip6InOctets`).
+    pub in_octets: u64,
+    /// Number of bytes transmitted in datagrams (`Ip6OutOctets`).
+    pub out_octets: u64,
+    /// Number of ICMPv6 messages received, including those received in error (`Icmp6InMsgs`).
+    pub icmp_in_msgs: u64,
+    /// Number of ICMPv6 messages received with errors (`Icmp6InErrors`).
+    pub icmp_in_errors: u64,
+    /// Number of ICMPv6 messages transmitted (`Icmp6OutMsgs`).
+    pub icmp_out_msgs: u64,
+    /// Number of ICMPv6 messages which could not be transmitted because of errors
+    /// (`Icmp6OutErrors`).
+    pub icmp_out_errors: u64,
+    /// The counters which are not typed fields, by name.
+    pub other: BTreeMap<String, u64>,
+}
+
+/// Returns the error for a dev_snmp6 line which could not be parsed.
+fn parse_error(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse dev_snmp6 line: {:?}", line))
+}
+
+impl Snmp6Stats {
+    /// Parses the contents of a `/proc/net/dev_snmp6/[interface]` file.
+    pub fn parse(input: &[u8]) -> Result<Snmp6Stats> {
+        let input = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        let mut stats = Snmp6Stats::default();
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let (name, value) = match (fields.next(), fields.next().and_then(|value| value.parse().ok()),
+                                       fields.next()) {
+                (Some(name), Some(value), None) => (name, value),
+                _ => return Err(parse_error(line)),
+            };
+            match name {
+                "ifIndex" => stats.if_index = u32::try_from(value).map_err(|_| parse_error(line))?,
+                "Ip6InReceives" => stats.in_receives = value,
+                "Ip6InHdrErrors" => stats.in_hdr_errors = value,
+                "Ip6InNoRoutes" => stats.in_no_routes = value,
+                "Ip6InAddrErrors" => stats.in_addr_errors = value,
+                "Ip6InDiscards" => stats.in_discards = value,
+                "Ip6InDelivers" => stats.in_delivers = value,
+                "Ip6OutForwDatagrams" => stats.out_forw_datagrams = value,
+                "Ip6OutRequests" => stats.out_requests = value,
+                "Ip6OutDiscards" => stats.out_discards = value,
+                "Ip6OutNoRoutes" => stats.out_no_routes = value,
+                "Ip6InMcastPkts" => stats.in_mcast_pkts = value,
+                "Ip6OutMcastPkts" => stats.out_mcast_pkts = value,
+                "Ip6InOctets" => stats.in_octets = value,
+                "Ip6OutOctets" => stats.out_octets = value,
+                "Icmp6InMsgs" => stats.icmp_in_msgs = value,
+                "Icmp6InErrors" => stats.icmp_in_errors = value,
+                "Icmp6OutMsgs" => stats.icmp_out_msgs = value,
+                "Icmp6OutErrors" => stats.icmp_out_errors = value,
+                _ => {
+                    stats.other.insert(name.to_owned(), value);
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Returns the counter which is not a typed field with the provided name, or `None` if the
+    /// kernel does not report it.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.other.get(name).cloned()
+    }
+}
+
+/// Reads the statistics of every interface in a dev_snmp6 directory, or returns no interfaces if
+/// it does not exist.
+pub fn dev_snmp6_in(dir: &Path) -> Result<HashMap<String, Snmp6Stats>> {
+    let entries = match platform::read_dir(dir) {
+        Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+        result => result?,
+    };
+    let mut interfaces = HashMap::new();
+    let mut buf = Vec::with_capacity(4096);
+    for entry in entries {
+        let entry = entry?;
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        buf.clear();
+        let stats = match platform::open(entry.path()).and_then(|mut file| {
+            Snmp6Stats::parse(read_to_end(&mut file, &mut buf)?)
+        }) {
+            Ok(stats) => stats,
+            // The interface was removed while the directory was read.
+            Err(ref error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        interfaces.insert(name, stats);
+    }
+    Ok(interfaces)
+}
+
+/// Returns the IPv6 statistics of each network interface, by interface name.
+///
+/// Interfaces without IPv6, e.g. because it is disabled with the `disable_ipv6` sysctl, have no
+/// statistics and are absent from the result. If IPv6 is disabled entirely, the result is empty.
+/// Interfaces which are added or removed while the statistics are read may or may not be present.
+pub fn dev_snmp6() -> Result<HashMap<String, Snmp6Stats>> {
+    dev_snmp6_in(Path::new("/proc/net/dev_snmp6"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fixtures::fixture_path;
+    use super::{Snmp6Stats, dev_snmp6, dev_snmp6_in};
+
+    #[test]
+    fn test_parse_snmp6() {
+        let stats = Snmp6Stats::parse(b"ifIndex                         \t2\n\
+                                        Ip6InReceives                   \t1842\n\
+                                        Ip6InHdrErrors                  \t1\n\
+                                        Ip6InDiscards                   \t3\n\
+                                        Ip6InDelivers                   \t1790\n\
+                                        Ip6OutRequests                  \t1502\n\
+                                        Ip6OutDiscards                  \t4\n\
+                                        Ip6InOctets                     \t263811\n\
+                                        Ip6OutOctets                    \t154032\n\
+                                        Ip6ReasmOKs                     \t0\n\
+                                        Icmp6InMsgs                     \t95\n\
+                                        Icmp6InErrors                   \t2\n\
+                                        Icmp6OutMsgs                    \t61\n\
+                                        Icmp6InNeighborSolicits         \t12\n").unwrap();
+        assert_eq!(2, stats.if_index);
+        assert_eq!(1842, stats.in_receives);
+        assert_eq!(1, stats.in_hdr_errors);
+        assert_eq!(3, stats.in_discards);
+        assert_eq!(1790, stats.in_delivers);
+        assert_eq!(1502, stats.out_requests);
+        assert_eq!(4, stats.out_discards);
+        assert_eq!(263811, stats.in_octets);
+        assert_eq!(154032, stats.out_octets);
+        assert_eq!(95, stats.icmp_in_msgs);
+        assert_eq!(2, stats.icmp_in_errors);
+        assert_eq!(61, stats.icmp_out_msgs);
+        assert_eq!(0, stats.icmp_out_errors);
+        assert_eq!(2, stats.other.len());
+        assert_eq!(Some(0), stats.get("Ip6ReasmOKs"));
+        assert_eq!(Some(12), stats.get("Icmp6InNeighborSolicits"));
+        assert_eq!(None, stats.get("Ip6InReceives"));
+
+        assert_eq!(Snmp6Stats::default(), Snmp6Stats::parse(b"").unwrap());
+        assert!(Snmp6Stats::parse(b"Ip6InReceives\n").is_err());
+        assert!(Snmp6Stats::parse(b"Ip6InReceives x\n").is_err());
+        assert!(Snmp6Stats::parse(b"ifIndex 4294967296\n").is_err());
+    }
+
+    #[test]
+    fn test_dev_snmp6_fixture() {
+        let interfaces = dev_snmp6_in(&fixture_path("6.18", "net/dev_snmp6")).unwrap();
+        assert_eq!(2, interfaces.len());
+        assert_eq!(1, interfaces["lo"].if_index);
+        assert_eq!(0, interfaces["lo"].in_receives);
+
+        let eth0 = &interfaces["eth0"];
+        assert_eq!(4, eth0.if_index);
+        assert_eq!(3, eth0.in_receives);
+        assert_eq!(5, eth0.out_requests);
+        assert_eq!(224, eth0.in_octets);
+        assert_eq!(456, eth0.out_octets);
+        assert_eq!(5, eth0.icmp_out_msgs);
+        assert_eq!(Some(4), eth0.get("Icmp6OutMLDv2Reports"));
+        assert_eq!(Some(1), eth0.get("Icmp6OutType135"));
+
+        assert!(dev_snmp6_in(Path::new("/nonexistent/dev_snmp6")).unwrap().is_empty());
+    }
+
+    /// Test that the statistics of the loopback interface can be read, if IPv6 is enabled.
+    #[test]
+    fn test_dev_snmp6() {
+        let interfaces = dev_snmp6().unwrap();
+        if let Some(lo) = interfaces.get("lo") {
+            // The loopback interface is the first interface of every network namespace.
+            assert_eq!(1, lo.if_index);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 //! Parsers and utility functions.
+//!
+//! Fields which may contain arbitrary bytes, such as process commands and mount paths, are not
+//! required to be valid UTF-8. Paths are parsed from the raw bytes, and other strings are converted
+//! lossily, replacing invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
 
-use std::borrow::ToOwned;
+use std::cmp;
 use std::fs::File;
-use std::io::{Error, ErrorKind, Read, Result};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 use std::str::{self, FromStr};
 
 use byteorder::{ByteOrder, LittleEndian};
-use libc::clock_t;
+use libc;
 use nom::{
     alphanumeric,
     digit,
@@ -16,14 +20,16 @@ use nom::{
     not_line_ending,
     space
 };
+use nom::ErrorKind as NomErrorKind;
 use nom::ErrorKind::Digit;
 
-/// Read all bytes in the file until EOF, placing them into `buf`.
+use platform::{self, clock_t, pid_t};
+
+/// Read all bytes in the file until EOF, appending them to `buf`.
 ///
-/// All bytes read from this source will be written to `buf`.  If `buf` is not large enough an
-/// underflow error will be returned. This function will continuously call `read` to append more
-/// data to `buf` until read returns either `Ok(0)`, or an error of non-`ErrorKind::Interrupted`
-/// kind.
+/// `buf` is grown as necessary to hold the entire file, so the full contents are returned
+/// regardless of the file's size. Callers should create `buf` with a capacity matching the typical
+/// size of the file being read, so that the common case requires only a single allocation.
 ///
 /// If successful, this function will return the slice of read bytes.
 ///
@@ -34,49 +40,123 @@ use nom::ErrorKind::Digit;
 ///
 /// If any other read error is encountered then this function immediately returns.  Any bytes which
 /// have already been read will be written to `buf`.
+pub fn read_to_end<'a>(file: &mut File, buf: &'a mut Vec<u8>) -> Result<&'a [u8]> {
+    file.read_to_end(buf)?;
+    Ok(&buf[..])
+}
+
+/// Reads a file of the directory of a process, e.g. `stat`, into `buf`, replacing its contents.
 ///
-/// If `buf` is not large enough to hold the file, an underflow error will be returned.
-pub fn read_to_end<'a>(file: &mut File, buf: &'a mut [u8]) -> Result<&'a mut [u8]> {
-    let mut from = 0;
+/// The path is formatted into `buf` before the file is read, so no allocation is made once `buf`
+/// has grown to fit the file.
+pub fn read_pid_file<'a>(buf: &'a mut Vec<u8>, pid: pid_t, name: &str) -> Result<&'a [u8]> {
+    buf.clear();
+    write!(buf, "/proc/{}/{}", pid, name)?;
+    let mut file = platform::open(str::from_utf8(buf).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?)?;
+    buf.clear();
+    read_to_end(&mut file, buf)
+}
 
-    loop {
-        if from == buf.len() {
-            return Err(Error::new(ErrorKind::Other, "read underflow"));
-        }
-        match file.read(&mut buf[from..]) {
-            Ok(0) => return Ok(&mut buf[..from]),
-            Ok(n) => from += n,
-            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
-            Err(e) => return Err(e),
-        }
+/// Returns whether an error reading the files of a process or thread means that it has exited.
+///
+/// A process which exits before its file is opened has no directory in `/proc`, and one which
+/// exits while its file is read fails the read with `ESRCH`.
+pub fn is_exited(error: &Error) -> bool {
+    error.kind() == ErrorKind::NotFound || error.raw_os_error() == Some(libc::ESRCH)
+}
+
+/// The maximum number of bytes of unparsed input included in parse error messages.
+const ERROR_CONTEXT_LEN: usize = 32;
+
+/// Returns the offset of `region` within `input`, if it is a subslice of `input`.
+fn offset_of(input: &[u8], region: &[u8]) -> Option<usize> {
+    let offset = (region.as_ptr() as usize).wrapping_sub(input.as_ptr() as usize);
+    if offset <= input.len() && offset + region.len() <= input.len() {
+        Some(offset)
+    } else {
+        None
     }
 }
 
-/// Transforms a `nom` parse result into a io result.
+/// Formats the beginning of an unparsed region of input for inclusion in an error message.
+fn context(region: &[u8]) -> String {
+    let len = cmp::min(region.len(), ERROR_CONTEXT_LEN);
+    format!("{:?}{}", String::from_utf8_lossy(&region[..len]),
+            if region.len() > len { "..." } else { "" })
+}
+
+/// Returns the error kind and input position of the innermost error in a verbose nom error chain.
+fn innermost<'e, 'a>(err: &'e Err<&'a [u8]>) -> (&'e NomErrorKind, Option<&'a [u8]>) {
+    match *err {
+        Err::Code(ref kind) => (kind, None),
+        Err::Node(ref kind, ref next) => match innermost(next) {
+            (_, None) => (kind, None),
+            inner => inner,
+        },
+        Err::Position(ref kind, position) => (kind, Some(position)),
+        Err::NodePosition(ref kind, position, ref next) => match innermost(next) {
+            (_, None) => (kind, Some(position)),
+            inner => inner,
+        },
+    }
+}
+
+/// Parses `input` with `parser`, transforming the `nom` parse result into an io result.
 ///
-/// The parser must completely consume the input.
-pub fn map_result<T>(result: IResult<&[u8], T>) -> Result<T> {
-    match result {
-        IResult::Done(remaining, val) => {
-            if remaining.is_empty() {
-                Ok(val)
-            } else {
-                let remaining = str::from_utf8(remaining);
-                Err(Error::new(ErrorKind::InvalidInput,
-                               format!("unable to parse whole input, remaining: {:?}", remaining)))
-            }
-        }
-        IResult::Error(err) => Err(Error::new(ErrorKind::InvalidInput,
-                                              format!("unable to parse input: {:?}", err))),
-        _ => Err(Error::new(ErrorKind::InvalidInput, "unable to parse input")),
+/// Input left over after a successful parse is ignored. Parse failures are returned as errors
+/// describing the failing `nom` error kind and the offset and content of the failing region, and
+/// input which ends unexpectedly is returned as an `ErrorKind::UnexpectedEof` error.
+pub fn map_result<'a, T, P>(input: &'a [u8], parser: P) -> Result<T>
+where P: FnOnce(&'a [u8]) -> IResult<&'a [u8], T> {
+    map_rest(input, parser).map(|(_, val)| val)
+}
+
+/// Parses `input` with `parser`, transforming the `nom` parse result into an io result.
+///
+/// Like `map_result`, but the parser must also completely consume the input. This is used for
+/// files with a fixed format, where trailing input indicates that the format has changed.
+pub fn map_result_strict<'a, T, P>(input: &'a [u8], parser: P) -> Result<T>
+where P: FnOnce(&'a [u8]) -> IResult<&'a [u8], T> {
+    let (rest, val) = map_rest(input, parser)?;
+    if rest.is_empty() {
+        Ok(val)
+    } else {
+        Err(Error::new(ErrorKind::InvalidInput,
+                       format!("unable to parse whole input: {} unexpected trailing bytes at offset \
+                                {}: {}",
+                               rest.len(), input.len() - rest.len(), context(rest))))
     }
 }
 
+/// Parses `input` with `parser`, returning the remaining input and the parsed value.
+fn map_rest<'a, T, P>(input: &'a [u8], parser: P) -> Result<(&'a [u8], T)>
+where P: FnOnce(&'a [u8]) -> IResult<&'a [u8], T> {
+    match parser(input) {
+        IResult::Done(rest, val) => Ok((rest, val)),
+        IResult::Error(err) => {
+            let message = match innermost(&err) {
+                (kind, Some(region)) => match offset_of(input, region) {
+                    Some(offset) => format!("unable to parse input: {:?} error at offset {}: {}",
+                                            kind, offset, context(region)),
+                    None => format!("unable to parse input: {:?} error at {}", kind, context(region)),
+                },
+                (kind, None) => format!("unable to parse input: {:?} error", kind),
+            };
+            Err(Error::new(ErrorKind::InvalidInput, message))
+        }
+        IResult::Incomplete(_) => {
+            Err(Error::new(ErrorKind::UnexpectedEof,
+                           format!("unable to parse input: unexpected end of input after {} bytes, \
+                                    the input may be truncated",
+                                   input.len())))
+        }
+    }
+}
 
 /// Recognizes numerical characters: 0-9, and periods: '.'.
 fn fdigit(input: &[u8]) -> IResult<&[u8], &[u8]> {
     for idx in 0..input.len() {
-        if (!is_digit(input[idx])) && ('.' as u8 != input[idx]) {
+        if (!is_digit(input[idx])) && (b'.' != input[idx]) {
             return IResult::Done(&input[idx..], &input[0..idx])
         }
     }
@@ -89,7 +169,7 @@ pub fn sdigit(input:&[u8]) -> IResult<&[u8], &[u8]> {
         return IResult::Done(b"", input)
     }
 
-    let start = if input[0] == '-' as u8 { 1 } else { 0 };
+    let start = if input[0] == b'-' { 1 } else { 0 };
     for (idx, item) in input.iter().enumerate().skip(start) {
         if !is_digit(*item) {
             if idx == start {
@@ -102,9 +182,19 @@ pub fn sdigit(input:&[u8]) -> IResult<&[u8], &[u8]> {
     IResult::Done(b"", input)
 }
 
-/// Parses a line to a string.
-named!(pub parse_line<String>,
-       map!(map_res!(not_line_ending, str::from_utf8), ToOwned::to_owned));
+/// Converts bytes to a string, replacing invalid UTF-8 sequences.
+pub fn to_string_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Parses a line to a string, replacing invalid UTF-8 sequences.
+named!(pub parse_line<String>, map!(not_line_ending, to_string_lossy));
+
+/// Parses a word terminated by whitespace to its raw bytes.
+named!(pub parse_word_bytes, is_not!(" \t\r\n"));
+
+/// Parses a word terminated by whitespace to a string, replacing invalid UTF-8 sequences.
+named!(pub parse_word<String>, map!(parse_word_bytes, to_string_lossy));
 
 /// Parses a clock_t in base-10 format.
 named!(pub parse_clock<clock_t>,
@@ -118,10 +208,6 @@ named!(pub parse_i32<i32>,
 named!(pub parse_i64<i64>,
        map_res!(map_res!(sdigit, str::from_utf8), FromStr::from_str));
 
-/// Parses an isize in base-10 format.
-named!(pub parse_isize<isize>,
-       map_res!(map_res!(sdigit, str::from_utf8), FromStr::from_str));
-
 /// Parses a u32 in base-10 format.
 named!(pub parse_u32<u32>,
        map_res!(map_res!(digit, str::from_utf8), FromStr::from_str));
@@ -130,10 +216,6 @@ named!(pub parse_u32<u32>,
 named!(pub parse_u64<u64>,
        map_res!(map_res!(digit, str::from_utf8), FromStr::from_str));
 
-/// Parses a usize in base-10 format.
-named!(pub parse_usize<usize>,
-       map_res!(map_res!(digit, str::from_utf8), FromStr::from_str));
-
 /// Parses a f32 in base-10 format.
 named!(pub parse_f32<f32>,
       map_res!(map_res!(fdigit, str::from_utf8), FromStr::from_str));
@@ -141,6 +223,9 @@ named!(pub parse_f32<f32>,
 /// Parses a sequence of whitespace seperated u32s.
 named!(pub parse_u32s<Vec<u32> >, separated_list!(space, complete!(parse_u32)));
 
+/// Parses a sequence of whitespace seperated u64s.
+named!(pub parse_u64s<Vec<u64> >, separated_list!(space, complete!(parse_u64)));
+
 /// Parses a sequence of whitespace seperated i32s.
 named!(pub parse_i32s<Vec<i32> >, separated_list!(space, parse_i32));
 
@@ -150,9 +235,22 @@ named!(pub parse_bit<bool>, alt!(
         | char!('1') => { |_| true }
 ));
 
-/// Parses a usize followed by a kB unit tag.
-named!(pub parse_kb<usize>,
-       chain!(space ~ bytes: parse_usize ~ space ~ tag!("kB"), || { bytes }));
+/// Parses the unit of a memory size into its multiplier.
+named!(parse_size_unit<u64>, alt!(
+          tag!("kB") => { |_| 1 << 10 }
+        | tag!("KB") => { |_| 1 << 10 }
+        | tag!("mB") => { |_| 1 << 20 }
+));
+
+/// Parses a memory size with an optional unit suffix, e.g. `   1234 kB`, into bytes.
+///
+/// Values without a suffix, such as the `HugePages_*` counts in `/proc/meminfo`, are returned
+/// unchanged. Values which overflow a u64 when converted to bytes saturate at `u64::MAX`.
+named!(pub parse_kb_value<u64>,
+       do_parse!(opt!(space) >>
+                 value: parse_u64 >>
+                 multiplier: opt!(complete!(preceded!(space, parse_size_unit))) >>
+                 (value.saturating_mul(multiplier.unwrap_or(1)))));
 
 /// Parses a u32 in base-16 format.
 named!(pub parse_u32_hex<u32>,
@@ -226,16 +324,95 @@ macro_rules! take_until_right_and_consume(
 
 #[cfg(test)]
 pub mod tests {
-    use std::u32;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{Error, ErrorKind, Write};
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-    use nom::IResult;
+    use libc;
+    use nom::{IResult, line_ending, space};
 
-    use super::{map_result, parse_f32, parse_i32, parse_i32s, parse_bit, parse_i64, parse_u32_hex,
-                parse_u32_mask_list, parse_u32s, reverse};
+    use super::{is_exited, map_result, map_result_strict, parse_f32, parse_i32, parse_i32s, parse_bit, parse_i64,
+                parse_kb_value, parse_line, parse_u32, parse_u32_hex, parse_u32_mask_list, parse_u32s,
+                parse_word, parse_word_bytes, read_to_end, reverse};
 
     /// Unwrap a complete parse result.
     pub fn unwrap<T>(result: IResult<&[u8], T>) -> T {
-        map_result(result).unwrap()
+        match result {
+            IResult::Done(rest, val) => {
+                assert!(rest.is_empty(), "unable to parse whole input, remaining: {:?}",
+                        String::from_utf8_lossy(rest));
+                val
+            }
+            IResult::Error(err) => panic!("unable to parse input: {:?}", err),
+            IResult::Incomplete(needed) => panic!("incomplete input: {:?}", needed),
+        }
+    }
+
+    /// An allocator which counts the allocations of each thread, see `allocations`.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Calls `f`, returning its result and the number of allocations and reallocations it made on
+    /// the current thread.
+    pub fn allocations<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    /// Returns an open file containing the provided contents.
+    ///
+    /// The file is unlinked before being returned, so it does not outlive the test.
+    pub fn fixture_file(contents: &[u8]) -> File {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!("procinfo-fixture-{}-{}",
+                                                process::id(),
+                                                COUNTER.fetch_add(1, Ordering::SeqCst)));
+        File::create(&path).unwrap().write_all(contents).unwrap();
+        let file = File::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_read_to_end() {
+        let contents: Vec<u8> = (0..10000u32).map(|i| i as u8).collect();
+        let mut buf = Vec::with_capacity(16);
+        assert_eq!(&contents[..], read_to_end(&mut fixture_file(&contents), &mut buf).unwrap());
+    }
+
+    #[test]
+    fn test_is_exited() {
+        assert!(is_exited(&Error::from(ErrorKind::NotFound)));
+        assert!(is_exited(&Error::from_raw_os_error(libc::ESRCH)));
+        assert!(!is_exited(&Error::from(ErrorKind::PermissionDenied)));
+        assert!(!is_exited(&Error::from_raw_os_error(libc::EIO)));
     }
 
     #[test]
@@ -320,7 +497,76 @@ pub mod tests {
 
     #[test]
     fn test_parse_bit() {
-        assert_eq!(true, unwrap(parse_bit(b"1")));
-        assert_eq!(false, unwrap(parse_bit(b"0")));
+        assert!(unwrap(parse_bit(b"1")));
+        assert!(!unwrap(parse_bit(b"0")));
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!("foo bar", unwrap(parse_line(b"foo bar")));
+        assert_eq!("foo\u{FFFD}bar", unwrap(parse_line(b"foo\xffbar")));
+        assert_eq!(IResult::Done(&b"\nbaz"[..], "foo".to_owned()), parse_line(b"foo\nbaz"));
+    }
+
+    #[test]
+    fn test_parse_word() {
+        assert_eq!(IResult::Done(&b" bar"[..], "foo".to_owned()), parse_word(b"foo bar"));
+        assert_eq!("foo\u{FFFD}", unwrap(parse_word(b"foo\xfe")));
+        assert_eq!(IResult::Done(&b"\n"[..], &b"\xff\xfe"[..]), parse_word_bytes(b"\xff\xfe\n"));
+    }
+
+    #[test]
+    fn test_parse_kb_value() {
+        assert_eq!(0, unwrap(parse_kb_value(b"0 kB")));
+        assert_eq!(8192, unwrap(parse_kb_value(b"       8 kB")));
+        assert_eq!(8192, unwrap(parse_kb_value(b"\t8 KB")));
+        assert_eq!(3 << 20, unwrap(parse_kb_value(b"3 mB")));
+        // Larger than a 32-bit usize once converted to bytes.
+        assert_eq!(16 << 40, unwrap(parse_kb_value(b"17179869184 kB")));
+        assert_eq!(u64::MAX, unwrap(parse_kb_value(b"18446744073709551615 kB")));
+
+        // Values without a unit are not scaled.
+        assert_eq!(0, unwrap(parse_kb_value(b"0")));
+        assert_eq!(12, unwrap(parse_kb_value(b"     12")));
+
+        assert!(map_result_strict(b"8 MB", parse_kb_value).is_err());
+        assert!(map_result_strict(b"kB", parse_kb_value).is_err());
+    }
+
+    named!(parse_u32_pair<(u32, u32)>, separated_pair!(parse_u32, space, parse_u32));
+    named!(parse_u32_line<u32>, terminated!(parse_u32, line_ending));
+
+    #[test]
+    fn test_map_result() {
+        assert_eq!(42, map_result(b"42", parse_u32).unwrap());
+        assert_eq!(42, map_result(b"42 trailing", parse_u32).unwrap());
+
+        let error = map_result(b"trailing", parse_u32).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!("unable to parse input: Digit error at offset 0: \"trailing\"", error.to_string());
+
+        let error = map_result(b"1 x 3", parse_u32_pair).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!("unable to parse input: Digit error at offset 2: \"x 3\"", error.to_string());
+
+        let error = map_result(b"10", parse_u32_line).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, error.kind());
+        assert_eq!("unable to parse input: unexpected end of input after 2 bytes, the input may be \
+                    truncated", error.to_string());
+    }
+
+    #[test]
+    fn test_map_result_strict() {
+        assert_eq!(42, map_result_strict(b"42", parse_u32).unwrap());
+
+        let error = map_result_strict(b"42 trailing", parse_u32).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!("unable to parse whole input: 9 unexpected trailing bytes at offset 2: \" trailing\"",
+                   error.to_string());
+
+        let long = [b'x'; 64];
+        let error = map_result_strict(&long, parse_u32s).unwrap_err();
+        assert!(error.to_string().ends_with(&format!("{:?}...", String::from_utf8_lossy(&long[..32]))),
+                "{}", error);
     }
 }
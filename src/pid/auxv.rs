@@ -0,0 +1,315 @@
+//! The auxiliary vector of a process from `/proc/[pid]/auxv`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::mem;
+
+use byteorder::{ByteOrder, NativeEndian};
+
+use parsers::read_to_end;
+use platform::{self, pid_t};
+
+/// The key of an auxiliary vector entry.
+///
+/// See `getauxval(3)` and `Linux/include/uapi/linux/auxvec.h`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AuxvKey {
+    /// The entry should be ignored (`AT_IGNORE`).
+    Ignore,
+    /// The file descriptor of the program (`AT_EXECFD`).
+    ExecFd,
+    /// The address of the program headers of the program (`AT_PHDR`).
+    Phdr,
+    /// The size of a program header entry (`AT_PHENT`).
+    Phent,
+    /// The number of program headers (`AT_PHNUM`).
+    Phnum,
+    /// The page size in bytes (`AT_PAGESZ`).
+    PageSize,
+    /// The base address of the program interpreter (`AT_BASE`).
+    Base,
+    /// Flags (`AT_FLAGS`).
+    Flags,
+    /// The entry point of the program (`AT_ENTRY`).
+    Entry,
+    /// The program is not ELF (`AT_NOTELF`).
+    NotElf,
+    /// The real user ID (`AT_UID`).
+    Uid,
+    /// The effective user ID (`AT_EUID`).
+    Euid,
+    /// The real group ID (`AT_GID`).
+    Gid,
+    /// The effective group ID (`AT_EGID`).
+    Egid,
+    /// The address of a string identifying the platform (`AT_PLATFORM`).
+    Platform,
+    /// A mask of the hardware capabilities of the CPU (`AT_HWCAP`).
+    Hwcap,
+    /// The frequency of `times(2)` (`AT_CLKTCK`).
+    ClockTicks,
+    /// Whether the program is run in secure mode, e.g. because it is set-user-ID (`AT_SECURE`).
+    Secure,
+    /// The address of a string identifying the base platform (`AT_BASE_PLATFORM`).
+    BasePlatform,
+    /// The address of 16 random bytes (`AT_RANDOM`).
+    Random,
+    /// A second mask of the hardware capabilities of the CPU (`AT_HWCAP2`).
+    Hwcap2,
+    /// The size of the rseq features supported by the kernel (`AT_RSEQ_FEATURE_SIZE`, since Linux
+    /// 6.3).
+    RseqFeatureSize,
+    /// The alignment of the rseq area (`AT_RSEQ_ALIGN`, since Linux 6.3).
+    RseqAlign,
+    /// The address of the path of the program (`AT_EXECFN`).
+    ExecFn,
+    /// The address of the system call entry point of the vDSO (`AT_SYSINFO`, 32-bit x86 only).
+    Sysinfo,
+    /// The address of the vDSO (`AT_SYSINFO_EHDR`).
+    SysinfoEhdr,
+    /// The minimal stack size of a signal handler (`AT_MINSIGSTKSZ`).
+    MinSigStackSize,
+    /// A key which is not known to this library.
+    Other(u64),
+}
+
+/// Every known key, with its value.
+const KEYS: [(AuxvKey, u64); 27] = [
+    (AuxvKey::Ignore,           1),
+    (AuxvKey::ExecFd,           2),
+    (AuxvKey::Phdr,             3),
+    (AuxvKey::Phent,            4),
+    (AuxvKey::Phnum,            5),
+    (AuxvKey::PageSize,         6),
+    (AuxvKey::Base,             7),
+    (AuxvKey::Flags,            8),
+    (AuxvKey::Entry,            9),
+    (AuxvKey::NotElf,          10),
+    (AuxvKey::Uid,             11),
+    (AuxvKey::Euid,            12),
+    (AuxvKey::Gid,             13),
+    (AuxvKey::Egid,            14),
+    (AuxvKey::Platform,        15),
+    (AuxvKey::Hwcap,           16),
+    (AuxvKey::ClockTicks,      17),
+    (AuxvKey::Secure,          23),
+    (AuxvKey::BasePlatform,    24),
+    (AuxvKey::Random,          25),
+    (AuxvKey::Hwcap2,          26),
+    (AuxvKey::RseqFeatureSize, 27),
+    (AuxvKey::RseqAlign,       28),
+    (AuxvKey::ExecFn,          31),
+    (AuxvKey::Sysinfo,         32),
+    (AuxvKey::SysinfoEhdr,     33),
+    (AuxvKey::MinSigStackSize, 51),
+];
+
+impl AuxvKey {
+    /// Returns the key with the provided `AT_*` value.
+    pub fn from_raw(raw: u64) -> AuxvKey {
+        KEYS.iter().find(|&&(_, value)| value == raw).map_or(AuxvKey::Other(raw), |&(key, _)| key)
+    }
+
+    /// Returns the `AT_*` value of the key.
+    pub fn raw(&self) -> u64 {
+        match *self {
+            AuxvKey::Other(raw) => raw,
+            key => KEYS.iter().find(|&&(k, _)| k == key).map(|&(_, value)| value).unwrap(),
+        }
+    }
+}
+
+/// An entry of the auxiliary vector.
+///
+/// Values such as those of `AuxvKey::ExecFn` and `AuxvKey::Random` are addresses in the address
+/// space of the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AuxvEntry {
+    /// The key of the entry.
+    pub key: AuxvKey,
+    /// The value of the entry.
+    pub value: u64,
+}
+
+/// The auxiliary vector which the kernel passed to a program when it was executed, see
+/// `getauxval(3)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Auxv {
+    /// The entries of the vector, in order, without the `AT_NULL` terminator.
+    pub entries: Vec<AuxvEntry>,
+}
+
+/// Decodes an auxiliary vector of words of the provided size, in native byte order.
+fn parse_words(input: &[u8], word_size: usize) -> Result<Auxv> {
+    let read_word = |word: &[u8]| if word_size == 8 {
+        NativeEndian::read_u64(word)
+    } else {
+        u64::from(NativeEndian::read_u32(word))
+    };
+
+    let mut entries = Vec::new();
+    if input.is_empty() {
+        return Ok(Auxv { entries });
+    }
+    for pair in input.chunks(word_size * 2) {
+        if pair.len() < word_size * 2 {
+            break;
+        }
+        let (key, value) = (read_word(&pair[..word_size]), read_word(&pair[word_size..]));
+        if key == 0 {
+            return Ok(Auxv { entries });
+        }
+        entries.push(AuxvEntry { key: AuxvKey::from_raw(key), value });
+    }
+    Err(Error::new(ErrorKind::UnexpectedEof,
+                   format!("unable to parse auxv: no AT_NULL terminator in {} bytes, the input may be truncated",
+                           input.len())))
+}
+
+impl Auxv {
+    /// Parses the contents of a `/proc/[pid]/auxv` file.
+    ///
+    /// The file is a sequence of key and value pairs of native-width unsigned integers in native
+    /// byte order, terminated by an `AT_NULL` key. The width of the integers is that of the process
+    /// which the file describes, so the file of a 32-bit process can not be parsed by a 64-bit
+    /// process. The file of a kernel thread is empty.
+    pub fn parse(input: &[u8]) -> Result<Auxv> {
+        parse_words(input, mem::size_of::<usize>())
+    }
+
+    /// Returns the value of the first entry with the provided key.
+    pub fn get(&self, key: AuxvKey) -> Option<u64> {
+        self.entries.iter().find(|entry| entry.key == key).map(|entry| entry.value)
+    }
+
+    /// Returns the page size in bytes (`AT_PAGESZ`).
+    pub fn page_size(&self) -> Option<u64> {
+        self.get(AuxvKey::PageSize)
+    }
+
+    /// Returns the number of clock ticks per second, in which `Stat` times are measured
+    /// (`AT_CLKTCK`).
+    pub fn clock_ticks(&self) -> Option<u64> {
+        self.get(AuxvKey::ClockTicks)
+    }
+
+    /// Returns whether the program runs in secure mode (`AT_SECURE`).
+    pub fn is_secure(&self) -> Option<bool> {
+        self.get(AuxvKey::Secure).map(|secure| secure != 0)
+    }
+
+    /// Returns the hardware capabilities of the CPU (`AT_HWCAP`), see `Linux/arch/*/include/uapi/asm/hwcap.h`.
+    pub fn hwcap(&self) -> Option<u64> {
+        self.get(AuxvKey::Hwcap)
+    }
+
+    /// Returns the second mask of the hardware capabilities of the CPU (`AT_HWCAP2`).
+    pub fn hwcap2(&self) -> Option<u64> {
+        self.get(AuxvKey::Hwcap2)
+    }
+}
+
+fn auxv_file(path: &str) -> Result<Auxv> {
+    let mut buf = Vec::with_capacity(512); // A typical auxv file is about 350 bytes.
+    Auxv::parse(read_to_end(&mut platform::open(path)?, &mut buf)?)
+}
+
+/// Returns the auxiliary vector of the process with the provided pid.
+///
+/// Reading the auxiliary vector of another process requires that the current process may trace it,
+/// and that the process has the same bitness as the current process.
+pub fn auxv(pid: pid_t) -> Result<Auxv> {
+    auxv_file(&format!("/proc/{}/auxv", pid))
+}
+
+/// Returns the auxiliary vector of the current process.
+pub fn auxv_self() -> Result<Auxv> {
+    auxv_file("/proc/self/auxv")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::process;
+
+    use byteorder::{ByteOrder, NativeEndian};
+    use libc::pid_t;
+
+    use sysconf::{page_size, ticks_per_second};
+    use super::{Auxv, AuxvEntry, AuxvKey, KEYS, auxv, auxv_self, parse_words};
+
+    /// Encodes key and value pairs as 64-bit words in native byte order.
+    fn words(pairs: &[(u64, u64)]) -> Vec<u8> {
+        let mut bytes = vec![0; pairs.len() * 16];
+        for (chunk, &(key, value)) in bytes.chunks_mut(16).zip(pairs) {
+            NativeEndian::write_u64(&mut chunk[..8], key);
+            NativeEndian::write_u64(&mut chunk[8..], value);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_auxv_keys() {
+        for &(key, raw) in KEYS.iter() {
+            assert_eq!(key, AuxvKey::from_raw(raw));
+            assert_eq!(raw, key.raw());
+        }
+        assert_eq!(AuxvKey::Other(99), AuxvKey::from_raw(99));
+        assert_eq!(99, AuxvKey::Other(99).raw());
+    }
+
+    #[test]
+    fn test_parse_auxv() {
+        let input = words(&[(33, 0x7ffd_a9f4_2000), (16, 0x178b_fbff), (6, 4096), (17, 100), (23, 0),
+                            (99, 7), (31, 0x7ffd_a9f3_bfe6), (0, 0)]);
+        let auxv = parse_words(&input, 8).unwrap();
+        assert_eq!(7, auxv.entries.len());
+        assert_eq!(AuxvEntry { key: AuxvKey::SysinfoEhdr, value: 0x7ffd_a9f4_2000 }, auxv.entries[0]);
+        assert_eq!(AuxvEntry { key: AuxvKey::Other(99), value: 7 }, auxv.entries[5]);
+        assert_eq!(Some(4096), auxv.page_size());
+        assert_eq!(Some(100), auxv.clock_ticks());
+        assert_eq!(Some(false), auxv.is_secure());
+        assert_eq!(Some(0x178b_fbff), auxv.hwcap());
+        assert_eq!(None, auxv.hwcap2());
+        assert_eq!(Some(0x7ffd_a9f3_bfe6), auxv.get(AuxvKey::ExecFn));
+
+        // Entries after the terminator are ignored.
+        let mut input = words(&[(6, 4096), (0, 0), (17, 100)]);
+        assert_eq!(vec![AuxvEntry { key: AuxvKey::PageSize, value: 4096 }], parse_words(&input, 8).unwrap().entries);
+
+        // The vector of a kernel thread is empty.
+        assert_eq!(Auxv::default(), parse_words(b"", 8).unwrap());
+
+        // A vector without a terminator is truncated.
+        input.truncate(16);
+        assert_eq!(ErrorKind::UnexpectedEof, parse_words(&input, 8).unwrap_err().kind());
+        input.truncate(12);
+        assert_eq!(ErrorKind::UnexpectedEof, parse_words(&input, 8).unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_parse_auxv_32_bit() {
+        let mut input = vec![0; 24];
+        NativeEndian::write_u32(&mut input[0..], 6);
+        NativeEndian::write_u32(&mut input[4..], 4096);
+        NativeEndian::write_u32(&mut input[8..], 17);
+        NativeEndian::write_u32(&mut input[12..], 100);
+        let auxv = parse_words(&input, 4).unwrap();
+        assert_eq!(Some(4096), auxv.page_size());
+        assert_eq!(Some(100), auxv.clock_ticks());
+        assert_eq!(2, auxv.entries.len());
+    }
+
+    /// Test that the auxiliary vector of the current process agrees with `sysconf`.
+    #[test]
+    fn test_auxv() {
+        let vector = auxv_self().unwrap();
+        assert_eq!(Some(page_size()), vector.page_size());
+        assert_eq!(Some(ticks_per_second()), vector.clock_ticks());
+        assert_eq!(Some(false), vector.is_secure());
+        assert!(vector.get(AuxvKey::Random).is_some());
+        assert_eq!(vector, auxv(process::id() as pid_t).unwrap());
+    }
+}
@@ -0,0 +1,363 @@
+//! Reading a per-process file of many processes at once.
+//!
+//! `batch` opens the files by path, which is the fastest way to read each file once. A `Batch`
+//! instead opens a `PidDir` handle of each process up front, and reads the files through the
+//! handles, so that polling the processes repeatedly, or reading several of their files, reads
+//! the processes which had the pids when the batch was opened, even after their pids are reused.
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result};
+
+use pid::{Io, Limits, Schedstat, Stat, Statm, Status};
+use pid::dir::PidDir;
+use platform::{self, pid_t};
+
+/// A file of `/proc/[pid]/` which can be read for many processes with `batch`.
+pub trait PidFile {
+    /// The type of the parsed file.
+    type Output;
+
+    /// The name of the file in the directory of a process, e.g. `stat`.
+    const FILE: &'static str;
+
+    /// Parses the contents of the file.
+    fn parse(input: &[u8]) -> Result<Self::Output>;
+}
+
+macro_rules! impl_pid_file {
+    ($type:ident, $file:expr) => {
+        impl PidFile for $type {
+            type Output = $type;
+            const FILE: &'static str = $file;
+
+            fn parse(input: &[u8]) -> Result<$type> {
+                $type::parse(input)
+            }
+        }
+    };
+}
+
+impl_pid_file!(Io, "io");
+impl_pid_file!(Limits, "limits");
+impl_pid_file!(Schedstat, "schedstat");
+impl_pid_file!(Stat, "stat");
+impl_pid_file!(Statm, "statm");
+impl_pid_file!(Status, "status");
+
+/// Appends the decimal digits of a pid to a path.
+fn push_pid(path: &mut String, pid: pid_t) {
+    if pid < 0 {
+        path.push('-');
+    }
+    let mut digits = [0u8; 10];
+    let mut n = pid.unsigned_abs();
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    for &digit in &digits[start..] {
+        path.push(digit as char);
+    }
+}
+
+/// Reads a file into `buf`, which is expected to be empty.
+///
+/// Unlike `File::read_to_end`, this does not query the size and position of the file before
+/// reading it, which for a file of `/proc` costs two system calls to learn that its size is 0.
+fn read_file<'a>(file: &mut File, buf: &'a mut Vec<u8>) -> Result<&'a [u8]> {
+    let mut len = 0;
+    loop {
+        if len == buf.capacity() {
+            buf.reserve(4096);
+        }
+        let capacity = buf.capacity();
+        buf.resize(capacity, 0);
+        match file.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref error) if error.kind() == ErrorKind::Interrupted => (),
+            Err(error) => {
+                buf.truncate(len);
+                return Err(error);
+            },
+        }
+    }
+    buf.truncate(len);
+    Ok(&buf[..])
+}
+
+/// Reads a file of each process in a proc directory into `results`.
+fn batch_in<P: PidFile>(root: &str, pids: &[pid_t], results: &mut Vec<(pid_t, Result<P::Output>)>) {
+    results.clear();
+    results.reserve(pids.len());
+    let mut path = String::with_capacity(root.len() + 32);
+    path.push_str(root);
+    path.push('/');
+    let prefix = path.len();
+    let mut buf = Vec::with_capacity(4096);
+    for &pid in pids {
+        path.truncate(prefix);
+        push_pid(&mut path, pid);
+        path.push('/');
+        path.push_str(P::FILE);
+        buf.clear();
+        let result = platform::open(&path).and_then(|mut file| P::parse(read_file(&mut file, &mut buf)?));
+        results.push((pid, result));
+    }
+}
+
+/// Reads a file of each of the processes with the provided pids, e.g. `batch::<Stat>(&pids)`.
+///
+/// This is equivalent to calling the probe for each pid, but reuses its path and read buffers
+/// across the processes. The results are in the order of the pids, and a process whose file can not
+/// be read or parsed, e.g. because it exited, has an error without affecting the others.
+///
+/// A single file is read per process, so the results can not mix the files of a process and of
+/// another which reused its pid.
+pub fn batch<P: PidFile>(pids: &[pid_t]) -> Vec<(pid_t, Result<P::Output>)> {
+    let mut results = Vec::new();
+    batch_into::<P>(pids, &mut results);
+    results
+}
+
+/// Reads a file of each of the processes with the provided pids into `results`, replacing its
+/// contents.
+///
+/// This is `batch`, but reuses the results of a previous batch, so that polling the same processes
+/// repeatedly does not allocate the results again.
+pub fn batch_into<P: PidFile>(pids: &[pid_t], results: &mut Vec<(pid_t, Result<P::Output>)>) {
+    batch_in::<P>("/proc", pids, results)
+}
+
+/// The open `/proc/[pid]` directories of many processes, for reading their files repeatedly.
+///
+/// Unlike `batch`, which opens the files by path on every call, the directory of each process is
+/// opened once by `Batch::open`, and every read goes through it, see the module documentation. A
+/// process which has since exited has an `ESRCH` error rather than the files of a process which
+/// reused its pid.
+#[derive(Debug)]
+pub struct Batch {
+    dirs: Vec<(pid_t, Result<PidDir>)>,
+    buf: Vec<u8>,
+}
+
+impl Batch {
+    /// Opens the directories of the processes with the provided pids.
+    ///
+    /// A pid without a process is not an error: its reads fail with the error of opening its
+    /// directory, like those of `batch`.
+    pub fn open(pids: &[pid_t]) -> Batch {
+        Batch::open_in("/proc", pids)
+    }
+
+    /// Opens the directories of the processes in a proc directory.
+    fn open_in(root: &str, pids: &[pid_t]) -> Batch {
+        let dirs = pids.iter().map(|&pid| (pid, PidDir::open_path(pid, format!("{}/{}", root, pid)))).collect();
+        Batch { dirs, buf: Vec::with_capacity(4096) }
+    }
+
+    /// Reads a file of each process, e.g. `batch.read::<Stat>()`.
+    pub fn read<P: PidFile>(&mut self) -> Vec<(pid_t, Result<P::Output>)> {
+        let mut results = Vec::new();
+        self.read_into::<P>(&mut results);
+        results
+    }
+
+    /// Reads a file of each process into `results`, replacing its contents, see `batch_into`.
+    pub fn read_into<P: PidFile>(&mut self, results: &mut Vec<(pid_t, Result<P::Output>)>) {
+        results.clear();
+        results.reserve(self.dirs.len());
+        let buf = &mut self.buf;
+        for &(pid, ref dir) in &self.dirs {
+            let result = match *dir {
+                Ok(ref dir) => {
+                    buf.clear();
+                    dir.open_file(P::FILE).and_then(|mut file| P::parse(read_file(&mut file, buf)?))
+                }
+                Err(ref error) => Err(match error.raw_os_error() {
+                    Some(code) => Error::from_raw_os_error(code),
+                    None => Error::from(error.kind()),
+                }),
+            };
+            results.push((pid, result));
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+    use std::process::{self, Command};
+
+    use libc::{ESRCH, pid_t};
+
+    use pid::{Stat, Statm, stat_self};
+    use super::{Batch, batch, batch_in, batch_into, push_pid};
+
+    /// Creates a fixture proc directory with a copy of the stat file of the current process for
+    /// each of the provided pids.
+    pub fn stat_fixture(name: &str, pids: &[pid_t]) -> PathBuf {
+        let root = env::temp_dir().join(format!("procinfo-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let stat = fs::read("/proc/self/stat").unwrap();
+        for pid in pids {
+            fs::create_dir_all(root.join(pid.to_string())).unwrap();
+            fs::write(root.join(pid.to_string()).join("stat"), &stat).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_batch_in() {
+        let root = stat_fixture("batch", &[10, 20]);
+        // The statm file of the second process is shorter than that of the first.
+        fs::write(root.join("10").join("statm"), "123456 1000 500 10 0 2000 0\n").unwrap();
+        fs::write(root.join("20").join("statm"), "1 2 3 4 0 5 0\n").unwrap();
+        let mut stats = Vec::new();
+        let mut statms = Vec::new();
+        batch_in::<Stat>(root.to_str().unwrap(), &[10, 15, 20], &mut stats);
+        batch_in::<Statm>(root.to_str().unwrap(), &[10, 20, 30], &mut statms);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(vec![10, 15, 20], stats.iter().map(|&(pid, _)| pid).collect::<Vec<_>>());
+        assert_eq!(process::id() as pid_t, stats[0].1.as_ref().unwrap().pid);
+        assert_eq!(ErrorKind::NotFound, stats[1].1.as_ref().unwrap_err().kind());
+        assert_eq!(process::id() as pid_t, stats[2].1.as_ref().unwrap().pid);
+
+        assert_eq!(123456, statms[0].1.as_ref().unwrap().size);
+        assert_eq!(1, statms[1].1.as_ref().unwrap().size);
+        assert_eq!(5, statms[1].1.as_ref().unwrap().data);
+        assert!(statms[2].1.is_err());
+    }
+
+    #[test]
+    fn test_batch() {
+        let results = batch::<Stat>(&[process::id() as pid_t, pid_t::MAX]);
+        assert_eq!(stat_self().unwrap().start_time, results[0].1.as_ref().unwrap().start_time);
+        assert_eq!(ErrorKind::NotFound, results[1].1.as_ref().unwrap_err().kind());
+
+        assert!(batch::<Statm>(&[process::id() as pid_t])[0].1.as_ref().unwrap().size > 0);
+        assert!(batch::<Statm>(&[]).is_empty());
+
+        // The results of a previous batch are replaced.
+        let mut results = batch::<Stat>(&[pid_t::MAX, pid_t::MAX]);
+        batch_into::<Stat>(&[process::id() as pid_t], &mut results);
+        assert_eq!(1, results.len());
+        assert_eq!(process::id() as pid_t, results[0].1.as_ref().unwrap().pid);
+    }
+
+    #[test]
+    fn test_batch_open_in() {
+        let root = stat_fixture("batch-open", &[10, 20]);
+        let mut batch = Batch::open_in(root.to_str().unwrap(), &[10, 15, 20]);
+        let stats = batch.read::<Stat>();
+        assert_eq!(vec![10, 15, 20], stats.iter().map(|&(pid, _)| pid).collect::<Vec<_>>());
+        assert_eq!(process::id() as pid_t, stats[0].1.as_ref().unwrap().pid);
+        assert_eq!(ErrorKind::NotFound, stats[1].1.as_ref().unwrap_err().kind());
+        assert_eq!(process::id() as pid_t, stats[2].1.as_ref().unwrap().pid);
+
+        // A directory which replaces that of the second process is not read through the batch.
+        fs::remove_dir_all(root.join("20")).unwrap();
+        fs::create_dir(root.join("20")).unwrap();
+        fs::write(root.join("20").join("stat"), fs::read("/proc/self/stat").unwrap()).unwrap();
+        let mut stats = Vec::new();
+        batch.read_into::<Stat>(&mut stats);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(stats[0].1.is_ok());
+        assert_eq!(ErrorKind::NotFound, stats[1].1.as_ref().unwrap_err().kind());
+        assert_eq!(ErrorKind::NotFound, stats[2].1.as_ref().unwrap_err().kind());
+    }
+
+    /// Test that a batch does not read the files of a process which reuses the pid of a reaped one.
+    #[test]
+    fn test_batch_stale() {
+        let mut child = Command::new("sleep").arg("60").spawn().unwrap();
+        let mut batch = Batch::open(&[process::id() as pid_t, child.id() as pid_t]);
+        assert!(batch.read::<Stat>().iter().all(|(_, stat)| stat.is_ok()));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        // Whether or not a new process has taken the pid, the batch reads nothing from it.
+        let mut respawned = Command::new("sleep").arg("60").spawn().unwrap();
+
+        let stats = batch.read::<Stat>();
+        assert_eq!(process::id() as pid_t, stats[0].1.as_ref().unwrap().pid);
+        assert_eq!(Some(ESRCH), stats[1].1.as_ref().unwrap_err().raw_os_error());
+
+        respawned.kill().unwrap();
+        respawned.wait().unwrap();
+    }
+
+    #[test]
+    fn test_push_pid() {
+        for &pid in &[0, 1, 9, 10, 4_194_304, pid_t::MAX, -1, pid_t::MIN] {
+            let mut path = String::from("/proc/");
+            push_pid(&mut path, pid);
+            assert_eq!(format!("/proc/{}", pid), path);
+        }
+    }
+}
+
+#[cfg(all(test, rustc_nightly))]
+mod benches {
+    extern crate test;
+
+    use std::fs;
+
+    use libc::pid_t;
+
+    use pid::Stat;
+    use super::{Batch, batch_in};
+    use super::tests::stat_fixture;
+
+    /// Reads the stat files of 500 fixture processes with a batch.
+    #[bench]
+    fn bench_batch(b: &mut test::Bencher) {
+        let pids = (1..501).collect::<Vec<pid_t>>();
+        let root = stat_fixture("bench-batch", &pids);
+        let root_str = root.to_str().unwrap().to_owned();
+        let mut results = Vec::with_capacity(pids.len());
+        b.iter(|| {
+            batch_in::<Stat>(&root_str, &pids, &mut results);
+            test::black_box(&results);
+        });
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Reads the stat files of 500 fixture processes through the handles of an open batch.
+    #[bench]
+    fn bench_batch_open(b: &mut test::Bencher) {
+        let pids = (1..501).collect::<Vec<pid_t>>();
+        let root = stat_fixture("bench-batch-open", &pids);
+        let mut batch = Batch::open_in(root.to_str().unwrap(), &pids);
+        let mut results = Vec::with_capacity(pids.len());
+        b.iter(|| {
+            batch.read_into::<Stat>(&mut results);
+            test::black_box(&results);
+        });
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Reads the stat files of 500 fixture processes one at a time, as the `stat` probe does.
+    #[bench]
+    fn bench_batch_naive(b: &mut test::Bencher) {
+        let pids = (1..501).collect::<Vec<pid_t>>();
+        let root = stat_fixture("bench-batch-naive", &pids);
+        b.iter(|| {
+            test::black_box(pids.iter()
+                                .map(|&pid| (pid, fs::read(root.join(pid.to_string()).join("stat"))
+                                                    .and_then(|stat| Stat::parse(&stat))))
+                                .collect::<Vec<_>>())
+        });
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
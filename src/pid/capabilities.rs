@@ -0,0 +1,267 @@
+//! Process capability sets, as reported by `/proc/[pid]/status`.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+/// A Linux capability.
+///
+/// See `man 7 capabilities` and `Linux/include/uapi/linux/capability.h`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Capability {
+    Chown,
+    DacOverride,
+    DacReadSearch,
+    Fowner,
+    Fsetid,
+    Kill,
+    Setgid,
+    Setuid,
+    Setpcap,
+    LinuxImmutable,
+    NetBindService,
+    NetBroadcast,
+    NetAdmin,
+    NetRaw,
+    IpcLock,
+    IpcOwner,
+    SysModule,
+    SysRawio,
+    SysChroot,
+    SysPtrace,
+    SysPacct,
+    SysAdmin,
+    SysBoot,
+    SysNice,
+    SysResource,
+    SysTime,
+    SysTtyConfig,
+    Mknod,
+    Lease,
+    AuditWrite,
+    AuditControl,
+    Setfcap,
+    MacOverride,
+    MacAdmin,
+    Syslog,
+    WakeAlarm,
+    BlockSuspend,
+    /// Since Linux 3.16.
+    AuditRead,
+    /// Since Linux 5.8.
+    Perfmon,
+    /// Since Linux 5.8.
+    Bpf,
+    /// Since Linux 5.9.
+    CheckpointRestore,
+}
+
+/// Every capability, indexed by capability number.
+const CAPABILITIES: [(Capability, &str); 41] = [
+    (Capability::Chown,             "CAP_CHOWN"),
+    (Capability::DacOverride,       "CAP_DAC_OVERRIDE"),
+    (Capability::DacReadSearch,     "CAP_DAC_READ_SEARCH"),
+    (Capability::Fowner,            "CAP_FOWNER"),
+    (Capability::Fsetid,            "CAP_FSETID"),
+    (Capability::Kill,              "CAP_KILL"),
+    (Capability::Setgid,            "CAP_SETGID"),
+    (Capability::Setuid,            "CAP_SETUID"),
+    (Capability::Setpcap,           "CAP_SETPCAP"),
+    (Capability::LinuxImmutable,    "CAP_LINUX_IMMUTABLE"),
+    (Capability::NetBindService,    "CAP_NET_BIND_SERVICE"),
+    (Capability::NetBroadcast,      "CAP_NET_BROADCAST"),
+    (Capability::NetAdmin,          "CAP_NET_ADMIN"),
+    (Capability::NetRaw,            "CAP_NET_RAW"),
+    (Capability::IpcLock,           "CAP_IPC_LOCK"),
+    (Capability::IpcOwner,          "CAP_IPC_OWNER"),
+    (Capability::SysModule,         "CAP_SYS_MODULE"),
+    (Capability::SysRawio,          "CAP_SYS_RAWIO"),
+    (Capability::SysChroot,         "CAP_SYS_CHROOT"),
+    (Capability::SysPtrace,         "CAP_SYS_PTRACE"),
+    (Capability::SysPacct,          "CAP_SYS_PACCT"),
+    (Capability::SysAdmin,          "CAP_SYS_ADMIN"),
+    (Capability::SysBoot,           "CAP_SYS_BOOT"),
+    (Capability::SysNice,           "CAP_SYS_NICE"),
+    (Capability::SysResource,       "CAP_SYS_RESOURCE"),
+    (Capability::SysTime,           "CAP_SYS_TIME"),
+    (Capability::SysTtyConfig,      "CAP_SYS_TTY_CONFIG"),
+    (Capability::Mknod,             "CAP_MKNOD"),
+    (Capability::Lease,             "CAP_LEASE"),
+    (Capability::AuditWrite,        "CAP_AUDIT_WRITE"),
+    (Capability::AuditControl,      "CAP_AUDIT_CONTROL"),
+    (Capability::Setfcap,           "CAP_SETFCAP"),
+    (Capability::MacOverride,       "CAP_MAC_OVERRIDE"),
+    (Capability::MacAdmin,          "CAP_MAC_ADMIN"),
+    (Capability::Syslog,            "CAP_SYSLOG"),
+    (Capability::WakeAlarm,         "CAP_WAKE_ALARM"),
+    (Capability::BlockSuspend,      "CAP_BLOCK_SUSPEND"),
+    (Capability::AuditRead,         "CAP_AUDIT_READ"),
+    (Capability::Perfmon,           "CAP_PERFMON"),
+    (Capability::Bpf,               "CAP_BPF"),
+    (Capability::CheckpointRestore, "CAP_CHECKPOINT_RESTORE"),
+];
+
+impl Capability {
+    /// Returns the capability with the provided number, if it is known.
+    pub fn from_number(number: u32) -> Option<Capability> {
+        CAPABILITIES.get(number as usize).map(|&(capability, _)| capability)
+    }
+
+    /// Returns the capability number, which is its bit in a capability set.
+    pub fn number(&self) -> u32 {
+        *self as u32
+    }
+
+    /// Returns the name of the capability, e.g. `CAP_CHOWN`.
+    pub fn name(&self) -> &'static str {
+        CAPABILITIES[*self as usize].1
+    }
+}
+
+/// Formats the capability's name, e.g. `CAP_CHOWN`.
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A set of capabilities.
+///
+/// Bits which do not correspond to a known capability, e.g. those added by a newer kernel, are
+/// preserved, and are included in `raw()`.
+///
+/// The default value is the empty set. When serialized with the `serde` feature, the set is
+/// represented as its raw bitmask.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Capabilities(u64);
+
+impl Capabilities {
+    /// Returns the set of capabilities with the provided bitmask.
+    pub fn from_raw(raw: u64) -> Capabilities {
+        Capabilities(raw)
+    }
+
+    /// Returns the bitmask of the set, including unknown capabilities.
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the bits of the set which do not correspond to a known capability.
+    pub fn unknown(&self) -> u64 {
+        self.0 & !((1 << CAPABILITIES.len()) - 1)
+    }
+
+    /// Returns `true` if the set contains the capability.
+    pub fn has(&self, capability: Capability) -> bool {
+        self.0 & (1 << capability.number()) != 0
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the known capabilities in the set, in order of capability number.
+    pub fn iter(&self) -> CapabilitiesIter {
+        CapabilitiesIter { raw: self.0, number: 0 }
+    }
+}
+
+/// Parses a capability set from its hexadecimal bitmask, e.g. `0000003fffffffff`.
+impl FromStr for Capabilities {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Capabilities> {
+        u64::from_str_radix(s, 16)
+            .map(Capabilities)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput,
+                                    format!("invalid capability set: {:?}", s)))
+    }
+}
+
+/// Formats the capability set as a 16 digit hexadecimal bitmask, in the same format as
+/// `/proc/[pid]/status`.
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl IntoIterator for &Capabilities {
+    type Item = Capability;
+    type IntoIter = CapabilitiesIter;
+
+    fn into_iter(self) -> CapabilitiesIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the known capabilities in a set.
+#[derive(Clone, Debug)]
+pub struct CapabilitiesIter {
+    raw: u64,
+    number: u32,
+}
+
+impl Iterator for CapabilitiesIter {
+    type Item = Capability;
+
+    fn next(&mut self) -> Option<Capability> {
+        while let Some(capability) = Capability::from_number(self.number) {
+            self.number += 1;
+            if self.raw & (1 << capability.number()) != 0 {
+                return Some(capability);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Capability, CAPABILITIES};
+
+    #[test]
+    fn test_capability_numbers() {
+        for (number, &(capability, _)) in CAPABILITIES.iter().enumerate() {
+            assert_eq!(number as u32, capability.number());
+            assert_eq!(Some(capability), Capability::from_number(number as u32));
+        }
+        assert_eq!(21, Capability::SysAdmin.number());
+        assert_eq!(40, Capability::CheckpointRestore.number());
+        assert_eq!(None, Capability::from_number(41));
+        assert_eq!("CAP_NET_BIND_SERVICE", Capability::NetBindService.to_string());
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        // The full set of capabilities in Linux 3.16 to 5.7.
+        let root: Capabilities = "0000003fffffffff".parse().unwrap();
+        assert_eq!(0x3fffffffff, root.raw());
+        assert!(root.has(Capability::Chown));
+        assert!(root.has(Capability::BlockSuspend));
+        assert!(root.has(Capability::AuditRead));
+        assert!(!root.has(Capability::Perfmon));
+        assert_eq!(38, root.iter().count());
+        assert_eq!("0000003fffffffff", root.to_string());
+
+        let net: Capabilities = "0000000000003000".parse().unwrap();
+        assert_eq!(vec![Capability::NetAdmin, Capability::NetRaw], net.iter().collect::<Vec<_>>());
+        assert_eq!(0, net.unknown());
+
+        assert!("".parse::<Capabilities>().is_err());
+        assert!("000000000000300g".parse::<Capabilities>().is_err());
+        assert!(Capabilities::default().is_empty());
+    }
+
+    /// Test that unknown capabilities from newer kernels are preserved.
+    #[test]
+    fn test_unknown_capabilities() {
+        let caps: Capabilities = "000003ffffffffff".parse().unwrap();
+        assert_eq!(0x3ffffffffff, caps.raw());
+        assert_eq!(0x20000000000, caps.unknown());
+        assert_eq!(41, (&caps).into_iter().count());
+    }
+}
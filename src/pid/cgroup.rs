@@ -0,0 +1,270 @@
+//! Control group membership from `/proc/[pid]/cgroup`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+use parsers::{read_to_end, to_string_lossy};
+use pid::Mountinfo;
+use platform::{self, pid_t};
+
+/// The membership of a process in a cgroup hierarchy, see `cgroups(7)`.
+///
+/// The default value is the root cgroup of the unified (cgroup v2) hierarchy.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cgroup {
+    /// The ID of the hierarchy, which is zero for the unified hierarchy.
+    pub hierarchy_id: u32,
+    /// The controllers bound to a cgroup v1 hierarchy, including named hierarchies such as
+    /// `name=systemd`. Empty for the unified hierarchy.
+    pub controllers: Vec<String>,
+    /// The path of the cgroup in the hierarchy, relative to the root of the cgroup namespace of the
+    /// process which read the file. Paths outside of that namespace start with `/..`.
+    pub path: PathBuf,
+}
+
+fn parse_error(line: &[u8]) -> Error {
+    Error::new(ErrorKind::InvalidInput,
+               format!("unable to parse cgroup line: {:?}", String::from_utf8_lossy(line)))
+}
+
+impl Cgroup {
+    /// Parses a single line of a `/proc/[pid]/cgroup` file, excluding the line ending.
+    pub fn parse(input: &[u8]) -> Result<Cgroup> {
+        // The path may itself contain colons, so it is everything after the second one.
+        let mut fields = input.splitn(3, |&b| b == b':');
+        let (id, controllers, path) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(id), Some(controllers), Some(path)) if !path.is_empty() => (id, controllers, path),
+            _ => return Err(parse_error(input)),
+        };
+        let hierarchy_id = to_string_lossy(id).parse().map_err(|_| parse_error(input))?;
+        let controllers = controllers.split(|&b| b == b',')
+                                     .filter(|controller| !controller.is_empty())
+                                     .map(to_string_lossy)
+                                     .collect();
+        Ok(Cgroup { hierarchy_id, controllers, path: platform::path_from_bytes(path) })
+    }
+
+    /// Returns `true` if this is the membership of the unified (cgroup v2) hierarchy.
+    pub fn is_unified(&self) -> bool {
+        self.hierarchy_id == 0 && self.controllers.is_empty()
+    }
+
+    /// Returns `true` if a mount is of the hierarchy of this cgroup: a `cgroup2` mount for the
+    /// unified hierarchy, or a `cgroup` mount with every controller of the hierarchy in its
+    /// superblock options, since co-mounted controllers such as `cpu,cpuacct` share a mount.
+    fn is_mounted_by(&self, mount: &Mountinfo) -> bool {
+        if self.is_unified() {
+            mount.fs_type.0 == "cgroup2"
+        } else {
+            mount.fs_type.0 == "cgroup" &&
+                self.controllers.iter().all(|controller| mount.super_opts.contains(controller))
+        }
+    }
+
+    /// Returns the absolute path of the cgroup directory, in which its controller files such as
+    /// `cpu.stat` can be read, given the mounts of a process, e.g. from `mountinfo_self`.
+    ///
+    /// The cgroup mount of the hierarchy whose root contains the cgroup is located, and the path of
+    /// the cgroup below that root is joined to its mount point. Containers often mount only the
+    /// subtree of their own cgroup, so the root of a mount is not always the root of the hierarchy.
+    /// When several mounts contain the cgroup, the one with the deepest root is used, and of those
+    /// the last mounted.
+    ///
+    /// Both the mounts and the cgroup must be read by the same process, since the mount roots and the
+    /// cgroup path are relative to the cgroup namespace of the reader. Returns `None` if the
+    /// hierarchy is not mounted, or if the cgroup is outside the roots of all its mounts, e.g. a
+    /// cgroup of the host seen from a container.
+    pub fn fs_path(&self, mounts: &[Mountinfo]) -> Option<PathBuf> {
+        let path = normalize(&self.path);
+        let mut found: Option<(&Mountinfo, usize)> = None;
+        for mount in mounts.iter().filter(|mount| self.is_mounted_by(mount)) {
+            let root = normalize(&mount.root);
+            if !path.starts_with(&root) || path[root.len()..].contains(&Component::ParentDir) {
+                continue;
+            }
+            if found.is_none_or(|(_, depth)| root.len() >= depth) {
+                found = Some((mount, root.len()));
+            }
+        }
+        found.map(|(mount, depth)| {
+            path[depth..].iter().fold(mount.mount_point.clone(), |fs_path, component| fs_path.join(component))
+        })
+    }
+}
+
+/// Returns the components of an absolute path below the root, resolving `.` and `..` lexically.
+/// A `..` which would leave the root is kept, since cgroup paths outside of the namespace of the
+/// reader start with `/..`.
+fn normalize(path: &Path) -> Vec<Component<'_>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => components.push(component),
+            Component::ParentDir => match components.last() {
+                Some(&Component::Normal(_)) => { components.pop(); },
+                _ => components.push(component),
+            },
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => (),
+        }
+    }
+    components
+}
+
+/// Parses the contents of a cgroup file, one hierarchy per line.
+fn parse_cgroups(input: &[u8]) -> Result<Vec<Cgroup>> {
+    input.split(|&b| b == b'\n')
+         .filter(|line| !line.is_empty())
+         .map(Cgroup::parse)
+         .collect()
+}
+
+fn cgroup_file(path: &str) -> Result<Vec<Cgroup>> {
+    let mut buf = Vec::with_capacity(512); // A typical cgroup file is a few hundred bytes.
+    parse_cgroups(read_to_end(&mut platform::open(path)?, &mut buf)?)
+}
+
+/// Returns the cgroups of the process with the provided pid, one per hierarchy.
+pub fn cgroup(pid: pid_t) -> Result<Vec<Cgroup>> {
+    cgroup_file(&format!("/proc/{}/cgroup", pid))
+}
+
+/// Returns the cgroups of the current process, one per hierarchy.
+pub fn cgroup_self() -> Result<Vec<Cgroup>> {
+    cgroup_file("/proc/self/cgroup")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use fixtures::fixture;
+    use pid::Mountinfo;
+    use super::{Cgroup, cgroup, cgroup_self, parse_cgroups};
+
+    fn parse_mounts(lines: &[&str]) -> Vec<Mountinfo> {
+        lines.iter().map(|line| Mountinfo::parse(line.as_bytes()).unwrap()).collect()
+    }
+
+    fn parse(line: &str) -> Cgroup {
+        Cgroup::parse(line.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cgroup() {
+        let cgroup = parse("4:cpu,cpuacct:/user.slice/user-1000.slice");
+        assert_eq!(4, cgroup.hierarchy_id);
+        assert_eq!(vec!["cpu", "cpuacct"], cgroup.controllers);
+        assert_eq!(Path::new("/user.slice/user-1000.slice"), cgroup.path);
+        assert!(!cgroup.is_unified());
+
+        let cgroup = parse("0::/system.slice/foo:bar.service");
+        assert!(cgroup.is_unified());
+        assert_eq!(Path::new("/system.slice/foo:bar.service"), cgroup.path);
+        assert_eq!(Cgroup { path: "/".into(), ..Cgroup::default() }, parse("0::/"));
+
+        assert!(Cgroup::parse(b"0::").is_err());
+        assert!(Cgroup::parse(b"0:/").is_err());
+        assert!(Cgroup::parse(b"x::/").is_err());
+    }
+
+    #[test]
+    fn test_parse_cgroups_fixture() {
+        let cgroups = parse_cgroups(&fixture("6.18", "self/cgroup")).unwrap();
+        assert_eq!(10, cgroups.len());
+        assert_eq!(vec!["name=systemd"], cgroups[0].controllers);
+        assert!(cgroups[9].is_unified());
+
+        let mounts = fixture("6.18", "self/mountinfo");
+        let mounts = mounts.split(|&b| b == b'\n')
+                           .filter(|line| !line.is_empty())
+                           .map(|line| Mountinfo::parse(line).unwrap())
+                           .collect::<Vec<_>>();
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/systemd")), cgroups[0].fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/memory/system.slice/session-f46b022d6a41.scope")),
+                   cgroups[5].fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/unified")), cgroups[9].fs_path(&mounts));
+    }
+
+    /// Test a hybrid host, with the v1 controllers and the unified hierarchy mounted side by side.
+    #[test]
+    fn test_fs_path_v1() {
+        let mounts = parse_mounts(&[
+            "32 24 0:28 / /sys/fs/cgroup rw,relatime - tmpfs tmpfs rw,mode=755",
+            "33 32 0:29 / /sys/fs/cgroup/cpu,cpuacct rw,relatime - cgroup cgroup rw,cpu,cpuacct",
+            "36 32 0:32 / /sys/fs/cgroup/memory rw,relatime - cgroup cgroup rw,memory",
+            "41 32 0:37 / /sys/fs/cgroup/systemd rw,relatime - cgroup cgroup rw,xattr,name=systemd",
+            "42 32 0:38 / /sys/fs/cgroup/unified rw,relatime - cgroup2 cgroup2 rw,nsdelegate",
+        ]);
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/cpu,cpuacct/user.slice")),
+                   parse("4:cpu,cpuacct:/user.slice").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/memory")), parse("6:memory:/").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/systemd/init.scope")),
+                   parse("1:name=systemd:/init.scope").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/unified/init.scope")),
+                   parse("0::/init.scope").fs_path(&mounts));
+        // A controller which is not mounted, and one which is only co-mounted with another.
+        assert_eq!(None, parse("7:pids:/").fs_path(&mounts));
+        assert_eq!(None, parse("8:cpu,cpuset:/").fs_path(&mounts));
+
+        let mounts = parse_mounts(&[]);
+        assert_eq!(None, parse("0::/").fs_path(&mounts));
+    }
+
+    #[test]
+    fn test_fs_path_v2() {
+        let mounts = parse_mounts(&[
+            "24 28 0:23 / /sys rw,relatime - sysfs sysfs rw",
+            "35 24 0:30 / /sys/fs/cgroup rw,nosuid,nodev,noexec,relatime shared:9 - cgroup2 cgroup2 rw,nsdelegate",
+        ]);
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/user.slice/user-1000.slice/session-2.scope")),
+                   parse("0::/user.slice/user-1000.slice/session-2.scope").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup")), parse("0::/").fs_path(&mounts));
+        assert_eq!(None, parse("4:memory:/").fs_path(&mounts));
+    }
+
+    /// Test containers, which mount the subtree of their cgroup, with and without a cgroup
+    /// namespace.
+    #[test]
+    fn test_fs_path_container() {
+        // Without a cgroup namespace, the container's cgroup is the root of its mount.
+        let mounts = parse_mounts(&[
+            "1040 1038 0:30 /system.slice/docker-a1b2.scope /sys/fs/cgroup ro,nosuid - cgroup2 cgroup2 rw",
+        ]);
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup")),
+                   parse("0::/system.slice/docker-a1b2.scope").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/worker")),
+                   parse("0::/system.slice/docker-a1b2.scope/worker").fs_path(&mounts));
+        // A cgroup of the host, which is outside the root of the mount.
+        assert_eq!(None, parse("0::/system.slice/sshd.service").fs_path(&mounts));
+        assert_eq!(None, parse("0::/system.slice").fs_path(&mounts));
+        assert_eq!(None, parse("0::/system.slice/docker-a1b2.scope/../sshd.service").fs_path(&mounts));
+
+        // With a cgroup namespace, paths are relative to the namespace root, and cgroups outside it
+        // start with `/..`.
+        let mounts = parse_mounts(&[
+            "1040 1038 0:30 / /sys/fs/cgroup rw,nosuid - cgroup2 cgroup2 rw",
+            "1041 1038 0:30 /../.. /mnt/host-cgroup rw,nosuid - cgroup2 cgroup2 rw",
+        ]);
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup")), parse("0::/").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/mnt/host-cgroup/system.slice/sshd.service")),
+                   parse("0::/../../system.slice/sshd.service").fs_path(&mounts));
+        assert_eq!(None, parse("0::/../../..").fs_path(&mounts));
+
+        // Of several mounts of the hierarchy, the one with the deepest root is used.
+        let mounts = parse_mounts(&[
+            "33 32 0:29 / /sys/fs/cgroup/memory rw - cgroup cgroup rw,memory",
+            "50 32 0:29 /docker /docker-memory rw - cgroup cgroup rw,memory",
+        ]);
+        assert_eq!(Some(PathBuf::from("/docker-memory/a1b2")), parse("4:memory:/docker/a1b2").fs_path(&mounts));
+        assert_eq!(Some(PathBuf::from("/sys/fs/cgroup/memory/user.slice")),
+                   parse("4:memory:/user.slice").fs_path(&mounts));
+    }
+
+    /// Test that the system cgroup files can be parsed.
+    #[test]
+    fn test_cgroup() {
+        assert!(!cgroup_self().unwrap().is_empty());
+        cgroup(1).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+//! Process CPU usage computed from samples of `/proc/[pid]/stat` and `/proc/stat`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::time::{Duration, Instant};
+
+use delta::{DeltaTracker, DeltaWarning, sample_elapsed};
+use pid::stat;
+use stat::kernel_stat;
+use platform::pid_t;
+use sysconf::{ticks_per_second, ticks_to_duration};
+
+/// A sample of the CPU time consumed by a process and by the system as a whole.
+///
+/// Samples are taken with `CpuUsage::sample`, and compared with `CpuSample::delta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CpuSample {
+    /// The sampled process.
+    pub pid: pid_t,
+    /// The start time of the sampled process in clock ticks after boot, which distinguishes it
+    /// from a later process reusing the same pid.
+    pub start_time: u64,
+    /// Time the process has been scheduled in user mode, in clock ticks.
+    pub utime: u64,
+    /// Time the process has been scheduled in kernel mode, in clock ticks.
+    pub stime: u64,
+    /// Total time accounted by all CPUs, in clock ticks.
+    pub system_ticks: u64,
+    /// Number of online CPUs.
+    pub num_cpus: u32,
+    /// Number of clock ticks per second.
+    pub ticks_per_second: u64,
+    /// When the sample was taken.
+    pub timestamp: Instant,
+}
+
+/// CPU usage of a process between two samples.
+///
+/// Fractions are relative to the total CPU time available on all CPUs, as accounted by the
+/// kernel, so they range from 0 to 1 regardless of the number of CPUs. Use
+/// `percent_of_one_core` for the `top`-style percentage, which exceeds 100 for a process using
+/// more than one CPU.
+///
+/// The default value has no CPU time and an elapsed time of zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuUsage {
+    /// Time the process was scheduled in user mode.
+    pub user: Duration,
+    /// Time the process was scheduled in kernel mode.
+    pub system: Duration,
+    /// Wall-clock time between the samples.
+    pub elapsed: Duration,
+    /// Fraction of the available CPU time spent in user mode.
+    pub user_fraction: f64,
+    /// Fraction of the available CPU time spent in kernel mode.
+    pub system_fraction: f64,
+    /// Number of online CPUs when the later sample was taken.
+    pub num_cpus: u32,
+    /// `DeltaWarning::Wrapped` if a counter wrapped between the samples.
+    pub warning: Option<DeltaWarning>,
+}
+
+impl CpuUsage {
+    /// Samples the CPU time of the process with the provided pid.
+    pub fn sample(pid: pid_t) -> Result<CpuSample> {
+        let stat = stat(pid)?;
+        let kernel_stat = kernel_stat()?;
+        Ok(CpuSample {
+            pid,
+            start_time: stat.start_time,
+            utime: stat.utime as u64,
+            stime: stat.stime as u64,
+            system_ticks: kernel_stat.cpu.total(),
+            num_cpus: kernel_stat.cpus.len() as u32,
+            ticks_per_second: ticks_per_second(),
+            timestamp: Instant::now(),
+        })
+    }
+
+    /// Returns the total time the process was scheduled.
+    pub fn total(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// Returns the fraction of the available CPU time used by the process.
+    pub fn total_fraction(&self) -> f64 {
+        self.user_fraction + self.system_fraction
+    }
+
+    /// Returns the CPU usage as a percentage of a single CPU.
+    pub fn percent_of_one_core(&self) -> f64 {
+        self.total_fraction() * f64::from(self.num_cpus) * 100.0
+    }
+
+    /// Returns the CPU usage as a percentage of all CPUs.
+    pub fn percent_of_all_cores(&self) -> f64 {
+        self.total_fraction() * 100.0
+    }
+}
+
+impl CpuSample {
+    /// Returns the CPU usage of the process between this sample and a later one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::InvalidInput` if the samples are of different
+    /// processes (including a process which exited and whose pid was reused), if `later` was
+    /// taken before this sample, or if a counter was reset between the samples. A counter which
+    /// wrapped, such as the 32-bit jiffy counters of older kernels, is corrected for the wrap, and
+    /// flagged by `CpuUsage::warning`.
+    pub fn delta(&self, later: &CpuSample) -> Result<CpuUsage> {
+        let elapsed = sample_elapsed("CPU",
+                                     ((self.pid, self.start_time), self.timestamp),
+                                     ((later.pid, later.start_time), later.timestamp))?;
+        if later.ticks_per_second == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "CPU sample has no clock tick rate"));
+        }
+
+        let mut tracker = DeltaTracker::default();
+        let mut counter = |earlier: u64, later: u64| {
+            tracker.delta(earlier, later).corrected().ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "CPU time counter decreased between samples")
+            })
+        };
+        let utime = counter(self.utime, later.utime)?;
+        let stime = counter(self.stime, later.stime)?;
+        let system_ticks = counter(self.system_ticks, later.system_ticks)?;
+
+        // Samples taken within the same clock tick carry no usage information.
+        let fraction = |ticks: u64| {
+            if system_ticks == 0 { 0.0 } else { ticks as f64 / system_ticks as f64 }
+        };
+
+        Ok(CpuUsage {
+            user: ticks_to_duration(utime, later.ticks_per_second),
+            system: ticks_to_duration(stime, later.ticks_per_second),
+            elapsed,
+            user_fraction: fraction(utime),
+            system_fraction: fraction(stime),
+            num_cpus: later.num_cpus,
+            warning: tracker.warning,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use std::time::{Duration, Instant};
+
+    use libc::pid_t;
+
+    use delta::DeltaWarning;
+    use super::{CpuSample, CpuUsage};
+
+    fn sample(utime: u64, stime: u64, system_ticks: u64, timestamp: Instant) -> CpuSample {
+        CpuSample {
+            pid: 42,
+            start_time: 1000,
+            utime,
+            stime,
+            system_ticks,
+            num_cpus: 2,
+            ticks_per_second: 100,
+            timestamp,
+        }
+    }
+
+    /// Test that the current process can be sampled.
+    #[test]
+    fn test_cpu_usage() {
+        let pid = process::id() as pid_t;
+        let earlier = CpuUsage::sample(pid).unwrap();
+        let usage = earlier.delta(&CpuUsage::sample(pid).unwrap()).unwrap();
+        assert!(usage.total_fraction() >= 0.0);
+    }
+
+    #[test]
+    fn test_delta() {
+        let now = Instant::now();
+        let earlier = sample(1000, 500, 100000, now);
+        // One second on two CPUs, during which the process used 0.5s user and 0.25s system time.
+        let later = sample(1050, 525, 100200, now + Duration::from_secs(1));
+
+        let usage = earlier.delta(&later).unwrap();
+        assert_eq!(Duration::from_millis(500), usage.user);
+        assert_eq!(Duration::from_millis(250), usage.system);
+        assert_eq!(Duration::from_millis(750), usage.total());
+        assert_eq!(Duration::from_secs(1), usage.elapsed);
+        assert_eq!(0.25, usage.user_fraction);
+        assert_eq!(0.125, usage.system_fraction);
+        assert_eq!(37.5, usage.percent_of_all_cores());
+        assert_eq!(75.0, usage.percent_of_one_core());
+    }
+
+    #[test]
+    fn test_delta_no_ticks() {
+        let now = Instant::now();
+        let usage = sample(1000, 500, 100000, now).delta(&sample(1000, 500, 100000, now)).unwrap();
+        assert_eq!(CpuUsage { num_cpus: 2, ..Default::default() }, usage);
+    }
+
+    #[test]
+    fn test_delta_errors() {
+        let now = Instant::now();
+        let earlier = sample(1000, 500, 100000, now);
+
+        let reused = CpuSample { start_time: 2000, ..sample(10, 5, 100200, now) };
+        assert!(earlier.delta(&reused).is_err());
+        let other = CpuSample { pid: 43, ..sample(1050, 525, 100200, now) };
+        assert!(earlier.delta(&other).is_err());
+
+        let later = sample(1050, 525, 100200, now + Duration::from_secs(1));
+        assert!(later.delta(&earlier).is_err());
+    }
+
+    #[test]
+    fn test_delta_counters() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+
+        // The utime of a 32-bit kernel wrapped.
+        let usage = sample(0xffff_ffe7, 500, 100000, now).delta(&sample(25, 525, 100200, later)).unwrap();
+        assert_eq!(Duration::from_millis(500), usage.user);
+        assert_eq!(Some(DeltaWarning::Wrapped), usage.warning);
+        assert_eq!(None, sample(1000, 500, 100000, now).delta(&sample(1050, 525, 100200, later)).unwrap().warning);
+
+        // The stime decreased, which is not a wrap.
+        assert!(sample(1000, 500, 100000, now).delta(&sample(1050, 400, 100200, later)).is_err());
+        assert!(sample(1 << 40, 500, 100000, now).delta(&sample(5, 525, 100200, later)).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Sets of CPUs and memory nodes, as reported by `/proc/[pid]/status`.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+/// A set of CPU or memory node indices.
+///
+/// The `Cpus_allowed` and `Mems_allowed` fields of `/proc/[pid]/status` represent sets as a mask
+/// of comma-separated 32-bit hexadecimal words, most-significant word first, e.g.
+/// `00000001,0000000f`. The `_list` variants of the fields represent the same sets as a list of
+/// indices and ranges, e.g. `0-3,32`. Both representations can be parsed, and the set is
+/// formatted in the list representation.
+///
+/// The default value is the empty set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuSet {
+    /// The bits of the set, least-significant word first, without trailing zero words.
+    words: Vec<u64>,
+}
+
+fn invalid(kind: &str, s: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("invalid {}: {:?}", kind, s))
+}
+
+impl CpuSet {
+    /// Returns an empty set.
+    pub fn new() -> CpuSet {
+        CpuSet::default()
+    }
+
+    /// Parses a set from its mask representation, e.g. `ffffffff,00000001`.
+    pub fn from_mask(s: &str) -> Result<CpuSet> {
+        let mut set = CpuSet::new();
+        for (idx, word) in s.rsplit(',').enumerate() {
+            if word.is_empty() || word.len() > 8 {
+                return Err(invalid("CPU mask", s));
+            }
+            let word = u32::from_str_radix(word, 16).map_err(|_| invalid("CPU mask", s))?;
+            let shift = (idx % 2) * 32;
+            if idx % 2 == 0 {
+                set.words.push(0);
+            }
+            set.words[idx / 2] |= u64::from(word) << shift;
+        }
+        set.trim();
+        Ok(set)
+    }
+
+    /// Parses a set from its list representation, e.g. `0-3,8`.
+    ///
+    /// An empty string is the empty set.
+    pub fn from_list(s: &str) -> Result<CpuSet> {
+        let mut set = CpuSet::new();
+        if s.is_empty() {
+            return Ok(set);
+        }
+        for range in s.split(',') {
+            let parse = |index: &str| index.parse::<usize>().map_err(|_| invalid("CPU list", s));
+            let (start, end) = match range.find('-') {
+                Some(idx) => (parse(&range[..idx])?, parse(&range[idx + 1..])?),
+                None => {
+                    let index = parse(range)?;
+                    (index, index)
+                }
+            };
+            if start > end {
+                return Err(invalid("CPU list", s));
+            }
+            for index in start..=end {
+                set.insert(index);
+            }
+        }
+        Ok(set)
+    }
+
+    /// Removes trailing zero words, so that equal sets have equal representations.
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+
+    /// Adds an index to the set.
+    pub fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (index % 64);
+    }
+
+    /// Returns `true` if the set contains the index.
+    pub fn contains(&self, index: usize) -> bool {
+        self.words.get(index / 64).is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    /// Returns the number of indices in the set.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns an iterator over the indices in the set, in increasing order.
+    pub fn iter(&self) -> CpuSetIter<'_> {
+        CpuSetIter { set: self, index: 0 }
+    }
+
+    /// Returns the set in its mask representation, e.g. `00000001,0000000f`.
+    ///
+    /// Every word is padded to eight digits, and the empty set is `00000000`.
+    pub fn to_mask(&self) -> String {
+        let mut halves: Vec<u32> = self.words.iter()
+                                             .flat_map(|&word| vec![word as u32, (word >> 32) as u32])
+                                             .collect();
+        while halves.len() > 1 && halves.last() == Some(&0) {
+            halves.pop();
+        }
+        if halves.is_empty() {
+            halves.push(0);
+        }
+        halves.iter().rev().map(|half| format!("{:08x}", half)).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Parses a set from its list representation, e.g. `0-3,8`.
+impl FromStr for CpuSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<CpuSet> {
+        CpuSet::from_list(s)
+    }
+}
+
+/// Formats the set in its list representation, e.g. `0-3,8`.
+impl fmt::Display for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut indices = self.iter().peekable();
+        let mut first = true;
+        while let Some(start) = indices.next() {
+            let mut end = start;
+            while indices.peek() == Some(&(end + 1)) {
+                end = indices.next().unwrap();
+            }
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuSet {
+    type Item = usize;
+    type IntoIter = CpuSetIter<'a>;
+
+    fn into_iter(self) -> CpuSetIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the indices in a set.
+#[derive(Clone, Debug)]
+pub struct CpuSetIter<'a> {
+    set: &'a CpuSet,
+    index: usize,
+}
+
+impl<'a> Iterator for CpuSetIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some(&word) = self.set.words.get(self.index / 64) {
+            let remaining = word >> (self.index % 64);
+            if remaining == 0 {
+                self.index = (self.index / 64 + 1) * 64;
+                continue;
+            }
+            let index = self.index + remaining.trailing_zeros() as usize;
+            self.index = index + 1;
+            return Some(index);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CpuSet;
+
+    fn indices(set: &CpuSet) -> Vec<usize> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn test_from_mask() {
+        let set = CpuSet::from_mask("ffff").unwrap();
+        assert_eq!((0..16).collect::<Vec<_>>(), indices(&set));
+
+        let set = CpuSet::from_mask("00000000,000e3862").unwrap();
+        assert_eq!(vec![1, 5, 6, 11, 12, 13, 17, 18, 19], indices(&set));
+
+        assert!(CpuSet::from_mask("00000000").unwrap().is_empty());
+        assert!(CpuSet::from_mask("").is_err());
+        assert!(CpuSet::from_mask("0000000g").is_err());
+        assert!(CpuSet::from_mask("00000001,,00000000").is_err());
+        assert!(CpuSet::from_mask("100000000").is_err());
+    }
+
+    /// Test masks of machines with more than 64 CPUs, which span several words.
+    #[test]
+    fn test_from_mask_multi_word() {
+        let set = CpuSet::from_mask("00000001,00000000,00000000,80000000").unwrap();
+        assert_eq!(vec![31, 96], indices(&set));
+        assert!(set.contains(96) && !set.contains(64) && !set.contains(1000));
+        assert_eq!("00000001,00000000,00000000,80000000", set.to_mask());
+
+        let set = CpuSet::from_mask("ff,ffffffff,ffffffff").unwrap();
+        assert_eq!(72, set.count());
+        assert_eq!("0-71", set.to_string());
+        assert_eq!("000000ff,ffffffff,ffffffff", set.to_mask());
+
+        // Leading zero words don't affect the set.
+        assert_eq!(CpuSet::from_mask("0000000f").unwrap(),
+                   CpuSet::from_mask("00000000,00000000,00000000,0000000f").unwrap());
+    }
+
+    #[test]
+    fn test_from_list() {
+        let set: CpuSet = "0-3,8,64-65,130".parse().unwrap();
+        assert_eq!(vec![0, 1, 2, 3, 8, 64, 65, 130], indices(&set));
+        assert_eq!(CpuSet::from_mask("00000004,00000000,00000003,00000000,0000010f").unwrap(), set);
+
+        // Single element ranges.
+        assert_eq!(vec![5], indices(&"5".parse().unwrap()));
+        assert_eq!(vec![5], indices(&"5-5".parse().unwrap()));
+        assert!("".parse::<CpuSet>().unwrap().is_empty());
+
+        // Open-ended and reversed ranges are not valid.
+        assert!("4-".parse::<CpuSet>().is_err());
+        assert!("-4".parse::<CpuSet>().is_err());
+        assert!("0-N".parse::<CpuSet>().is_err());
+        assert!("3-1".parse::<CpuSet>().is_err());
+        assert!("1,".parse::<CpuSet>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for list in &["0", "0-15", "0-3,8", "1,3,5-7,63-64,127"] {
+            assert_eq!(*list, list.parse::<CpuSet>().unwrap().to_string());
+        }
+        assert_eq!("", CpuSet::new().to_string());
+        assert_eq!("00000000", CpuSet::new().to_mask());
+    }
+}
@@ -0,0 +1,202 @@
+//! Context switch counts and rates of a process, from `/proc/[pid]/status`.
+
+use std::io::Result;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use delta::{DeltaTracker, DeltaWarning, per_sec, sample_elapsed};
+use pid::{Stat, Status, status, status_self};
+use pid::dir::PidDir;
+use platform::pid_t;
+
+/// The number of context switches of a process.
+///
+/// The counts are those of the main thread of the process; the other threads have their own counts
+/// in `/proc/[pid]/task/[tid]/status`.
+///
+/// The default value has both counts set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CtxtSwitches {
+    /// Number of voluntary context switches, in which the process gave up the CPU, e.g. to wait
+    /// for I/O or a lock.
+    pub voluntary: u64,
+    /// Number of involuntary context switches, in which the process was preempted, e.g. because
+    /// its time slice expired while other processes were runnable.
+    pub nonvoluntary: u64,
+}
+
+impl<'a> From<&'a Status> for CtxtSwitches {
+    fn from(status: &'a Status) -> CtxtSwitches {
+        CtxtSwitches { voluntary: status.voluntary_ctxt_switches, nonvoluntary: status.nonvoluntary_ctxt_switches }
+    }
+}
+
+/// Returns the context switch counts of the process with the provided pid.
+pub fn ctxt_switches(pid: pid_t) -> Result<CtxtSwitches> {
+    status(pid).map(|status| CtxtSwitches::from(&status))
+}
+
+/// Returns the context switch counts of the current process.
+pub fn ctxt_switches_self() -> Result<CtxtSwitches> {
+    status_self().map(|status| CtxtSwitches::from(&status))
+}
+
+/// A timestamped sample of the context switch counts of a process.
+///
+/// Samples are taken with `CtxtSwitchRate::sample`, and compared with `CtxtSwitchSample::delta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CtxtSwitchSample {
+    /// The sampled process.
+    pub pid: pid_t,
+    /// The start time of the sampled process in clock ticks after boot, which distinguishes it
+    /// from a later process reusing the same pid.
+    pub start_time: u64,
+    /// The context switch counts of the process.
+    pub switches: CtxtSwitches,
+    /// When the sample was taken.
+    pub timestamp: Instant,
+}
+
+/// Context switch rates of a process between two samples.
+///
+/// A high rate of involuntary switches means that the process was often runnable but preempted,
+/// which is the usual sign of contention for the CPU.
+///
+/// Counters which wrap between the samples are corrected for the wrap, counters which are otherwise
+/// reset are treated as unchanged, and either is flagged by `warning`. If no time elapsed between
+/// the samples every rate is zero.
+///
+/// The default value has every rate and the elapsed time set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CtxtSwitchRate {
+    /// Voluntary context switches per second.
+    pub voluntary_per_sec: f64,
+    /// Involuntary context switches per second.
+    pub nonvoluntary_per_sec: f64,
+    /// Wall-clock time between the samples.
+    pub elapsed: Duration,
+    /// The most severe irregularity of the counters between the samples.
+    pub warning: Option<DeltaWarning>,
+}
+
+impl CtxtSwitchRate {
+    /// Samples the context switch counts of the process with the provided pid.
+    pub fn sample(pid: pid_t) -> Result<CtxtSwitchSample> {
+        // Both files are read through the same directory, so they are of the same process.
+        let dir = PidDir::open(pid)?;
+        let mut buf = Vec::with_capacity(2048);
+        let start_time = dir.read::<Stat>(&mut buf)?.start_time;
+        let switches = CtxtSwitches::from(&dir.read::<Status>(&mut buf)?);
+        Ok(CtxtSwitchSample { pid, start_time, switches, timestamp: Instant::now() })
+    }
+
+    /// Returns the context switch rates of the process with the provided pid over the provided
+    /// interval.
+    ///
+    /// This blocks the current thread for the duration of the interval.
+    pub fn sample_over(pid: pid_t, interval: Duration) -> Result<CtxtSwitchRate> {
+        let earlier = CtxtSwitchRate::sample(pid)?;
+        thread::sleep(interval);
+        earlier.delta(&CtxtSwitchRate::sample(pid)?)
+    }
+}
+
+impl CtxtSwitchSample {
+    /// Returns the context switch rates of the process between this sample and a later one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::InvalidInput` if the samples are of different
+    /// processes (including a process which exited and whose pid was reused), or if `later` was
+    /// taken before this sample.
+    pub fn delta(&self, later: &CtxtSwitchSample) -> Result<CtxtSwitchRate> {
+        let elapsed = sample_elapsed("context switch",
+                                     ((self.pid, self.start_time), self.timestamp),
+                                     ((later.pid, later.start_time), later.timestamp))?;
+
+        let (earlier, later) = (&self.switches, &later.switches);
+        let mut tracker = DeltaTracker::default();
+        let voluntary = tracker.delta(earlier.voluntary, later.voluntary).saturating();
+        let nonvoluntary = tracker.delta(earlier.nonvoluntary, later.nonvoluntary).saturating();
+
+        Ok(CtxtSwitchRate {
+            voluntary_per_sec: per_sec(voluntary, elapsed),
+            nonvoluntary_per_sec: per_sec(nonvoluntary, elapsed),
+            elapsed,
+            warning: tracker.warning,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use std::time::{Duration, Instant};
+
+    use libc::pid_t;
+
+    use delta::DeltaWarning;
+    use pid::Status;
+    use super::{CtxtSwitchRate, CtxtSwitchSample, CtxtSwitches, ctxt_switches, ctxt_switches_self};
+
+    /// Test that the context switch counts of the system processes can be read.
+    #[test]
+    fn test_ctxt_switches() {
+        ctxt_switches(1).unwrap();
+        assert!(ctxt_switches_self().unwrap().voluntary > 0);
+    }
+
+    #[test]
+    fn test_from_status() {
+        let status = Status { voluntary_ctxt_switches: 242129, nonvoluntary_ctxt_switches: 1748, ..Status::default() };
+        assert_eq!(CtxtSwitches { voluntary: 242129, nonvoluntary: 1748 }, CtxtSwitches::from(&status));
+    }
+
+    fn sample(voluntary: u64, nonvoluntary: u64, timestamp: Instant) -> CtxtSwitchSample {
+        CtxtSwitchSample { pid: 42, start_time: 1000, switches: CtxtSwitches { voluntary, nonvoluntary }, timestamp }
+    }
+
+    #[test]
+    fn test_delta() {
+        let now = Instant::now();
+        let rate = sample(1000, 10, now).delta(&sample(1500, 410, now + Duration::from_secs(4))).unwrap();
+        assert_eq!(CtxtSwitchRate {
+                       voluntary_per_sec: 125.0,
+                       nonvoluntary_per_sec: 100.0,
+                       elapsed: Duration::from_secs(4),
+                       warning: None,
+                   },
+                   rate);
+    }
+
+    #[test]
+    fn test_delta_edge_cases() {
+        let now = Instant::now();
+        let earlier = sample(1000, 10, now);
+
+        assert_eq!(CtxtSwitchRate::default(), earlier.delta(&sample(2000, 20, now)).unwrap());
+
+        let decreased = sample(1500, 5, now + Duration::from_secs(1));
+        let rate = earlier.delta(&decreased).unwrap();
+        assert_eq!(500.0, rate.voluntary_per_sec);
+        assert_eq!(0.0, rate.nonvoluntary_per_sec);
+        assert_eq!(Some(DeltaWarning::Reset), rate.warning);
+
+        // The counters of a 32-bit kernel wrapped.
+        let rate = sample(0xffff_fff0, 10, now).delta(&sample(0x10, 10, now + Duration::from_secs(2))).unwrap();
+        assert_eq!(16.0, rate.voluntary_per_sec);
+        assert_eq!(Some(DeltaWarning::Wrapped), rate.warning);
+
+        assert!(decreased.delta(&earlier).is_err());
+        assert!(earlier.delta(&CtxtSwitchSample { pid: 43, ..decreased }).is_err());
+        assert!(earlier.delta(&CtxtSwitchSample { start_time: 2000, ..decreased }).is_err());
+    }
+
+    #[test]
+    fn test_sample_over() {
+        let rate = CtxtSwitchRate::sample_over(process::id() as pid_t, Duration::from_millis(10)).unwrap();
+        assert!(rate.elapsed >= Duration::from_millis(10));
+    }
+}
@@ -1,21 +1,20 @@
 //! Concerning the current working directory of a process, from
 //! `/proc/[pid]/cwd`.
 
-use std::fs;
 use std::io::Result;
 use std::path::PathBuf;
 
-use libc::pid_t;
+use platform::{self, pid_t};
 
 /// Gets path of current working directory for the process with the provided
 /// pid.
 pub fn cwd(pid: pid_t) -> Result<PathBuf> {
-    fs::read_link(format!("/proc/{}/cwd", pid))
+    platform::read_link(format!("/proc/{}/cwd", pid))
 }
 
 /// Gets path of current working directory for the current process.
 pub fn cwd_self() -> Result<PathBuf> {
-    fs::read_link("/proc/self/cwd")
+    platform::read_link("/proc/self/cwd")
 }
 
 #[cfg(test)]
@@ -0,0 +1,259 @@
+//! Descendants of a process from `/proc/[pid]/task/[tid]/children`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+
+use parsers::{is_exited, read_to_end};
+use pid::stat;
+use platform::{self, pid_t};
+
+/// Where the children of a process are read from.
+enum Source {
+    /// The children files of the tasks of each process, in the provided proc directory.
+    Children(String),
+    /// An index of the children of every process, by parent pid.
+    Index(HashMap<pid_t, Vec<pid_t>>),
+}
+
+/// Parses the contents of a children file: the pids of the children, separated by spaces.
+fn parse_children(input: &[u8]) -> Result<Vec<pid_t>> {
+    str::from_utf8(input)
+        .ok()
+        .and_then(|input| input.split_whitespace().map(|pid| pid.parse().ok()).collect())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                                  format!("unable to parse children: {:?}", String::from_utf8_lossy(input))))
+}
+
+/// Reads the children of every task of a process, in increasing order of pid.
+///
+/// A process or task which exits while it is read has no children.
+fn read_children(root: &str, pid: pid_t, buf: &mut Vec<u8>) -> Result<Vec<pid_t>> {
+    let mut children = Vec::new();
+    let tasks = match platform::read_dir(format!("{}/{}/task", root, pid)) {
+        Ok(tasks) => tasks,
+        Err(ref error) if is_exited(error) => return Ok(children),
+        Err(error) => return Err(error),
+    };
+    for task in tasks {
+        let task = match task {
+            Ok(task) => task,
+            Err(ref error) if is_exited(error) => continue,
+            Err(error) => return Err(error),
+        };
+        let path = task.path().join("children");
+        buf.clear();
+        match platform::open(path).and_then(|mut file| read_to_end(&mut file, buf).map(|_| ())) {
+            Ok(()) => children.extend(parse_children(buf)?),
+            Err(ref error) if is_exited(error) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    children.sort_unstable();
+    Ok(children)
+}
+
+/// Builds an index of the children of every process from the parent pids in `/proc/[pid]/stat`.
+///
+/// Processes which exit during the scan are skipped.
+fn scan_children() -> Result<HashMap<pid_t, Vec<pid_t>>> {
+    let mut index = HashMap::<pid_t, Vec<pid_t>>::new();
+    for entry in platform::read_dir("/proc")? {
+        let pid = match entry?.file_name().to_str().and_then(|name| name.parse::<pid_t>().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        match stat(pid) {
+            Ok(stat) => index.entry(stat.ppid).or_default().push(pid),
+            Err(ref error) if is_exited(error) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(index)
+}
+
+/// An iterator over the descendants of a process, in breadth-first order.
+///
+/// The children of each process are read when the iterator reaches it, so processes which exit
+/// during the walk may be missing, and each pid is yielded at most once. The iterator yields an
+/// error if the children of a process can not be read, after which it yields no further items.
+pub struct Descendants {
+    source: Source,
+    buf: Vec<u8>,
+    /// Descendants which have been found, but not yet yielded.
+    found: VecDeque<pid_t>,
+    /// Descendants which have been yielded, but whose children have not yet been read.
+    unread: VecDeque<pid_t>,
+    seen: HashSet<pid_t>,
+    done: bool,
+}
+
+impl Descendants {
+    fn new(source: Source, pid: pid_t) -> Descendants {
+        let mut seen = HashSet::new();
+        seen.insert(pid);
+        Descendants {
+            source,
+            buf: Vec::with_capacity(64),
+            found: VecDeque::new(),
+            unread: vec![pid].into(),
+            seen,
+            done: false,
+        }
+    }
+
+    /// Returns the descendants of the process with the provided pid in a proc directory, reading
+    /// the children files if they are available.
+    fn from_root(root: &str, pid: pid_t) -> Result<Descendants> {
+        let children = format!("{}/{}/task/{}/children", root, pid, pid);
+        match platform::open(children) {
+            Ok(_) => Ok(Descendants::new(Source::Children(root.to_owned()), pid)),
+            // The process exists, but the kernel was built without `CONFIG_PROC_CHILDREN`.
+            Err(ref error) if error.kind() == ErrorKind::NotFound => {
+                platform::open(format!("{}/{}/stat", root, pid))?;
+                Ok(Descendants::new(Source::Index(scan_children()?), pid))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn children(&mut self, pid: pid_t) -> Result<Vec<pid_t>> {
+        match self.source {
+            Source::Children(ref root) => read_children(root, pid, &mut self.buf),
+            Source::Index(ref index) => Ok(index.get(&pid).cloned().unwrap_or_default()),
+        }
+    }
+}
+
+impl Iterator for Descendants {
+    type Item = Result<pid_t>;
+
+    fn next(&mut self) -> Option<Result<pid_t>> {
+        while !self.done {
+            if let Some(pid) = self.found.pop_front() {
+                self.unread.push_back(pid);
+                return Some(Ok(pid));
+            }
+            let pid = match self.unread.pop_front() {
+                Some(pid) => pid,
+                None => break,
+            };
+            match self.children(pid) {
+                Ok(children) => {
+                    for child in children {
+                        if self.seen.insert(child) {
+                            self.found.push_back(child);
+                        }
+                    }
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+/// Returns an iterator over the descendants of the process with the provided pid: its children,
+/// their children, and so on.
+///
+/// The children of each process are read from `/proc/[pid]/task/[tid]/children`, which is provided
+/// by kernels built with `CONFIG_PROC_CHILDREN` (or `CONFIG_CHECKPOINT_RESTORE` before Linux 4.2).
+/// If the kernel does not provide them, the parent pids of every process are read once from
+/// `/proc/[pid]/stat` instead. Either way, the descendants are
+/// not an atomic snapshot: processes which are created or exit during the walk may be missing.
+///
+/// Returns an `ErrorKind::NotFound` error if there is no process with the pid.
+pub fn descendants(pid: pid_t) -> Result<Descendants> {
+    Descendants::from_root("/proc", pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, ErrorKind};
+    use std::path::Path;
+    use std::process::{self, Command, Stdio};
+
+    use libc::{self, pid_t};
+
+    use super::{Descendants, Source, descendants, parse_children};
+
+    /// Writes a children file of a task in a fixture proc directory.
+    fn write_children(root: &Path, pid: pid_t, tid: pid_t, children: &str) {
+        let dir = root.join(pid.to_string()).join("task").join(tid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("children"), children).unwrap();
+    }
+
+    #[test]
+    fn test_parse_children() {
+        assert_eq!(vec![12, 345], parse_children(b"12 345 ").unwrap());
+        assert!(parse_children(b"").unwrap().is_empty());
+        assert!(parse_children(b"12 x").is_err());
+    }
+
+    /// Test that the children of every task are walked breadth-first, skipping vanished and
+    /// repeated pids.
+    #[test]
+    fn test_descendants_children() {
+        let root = env::temp_dir().join(format!("procinfo-descendants-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write_children(&root, 10, 10, "20 ");
+        // A child of a second thread, and a child which exits before it is read.
+        write_children(&root, 10, 11, "21 22 ");
+        write_children(&root, 20, 20, "30 ");
+        // The pid of a child is listed twice, e.g. after a reparenting during the walk.
+        write_children(&root, 21, 21, "30 31 ");
+        write_children(&root, 30, 30, "");
+        write_children(&root, 31, 31, "");
+
+        let pids = Descendants::from_root(root.to_str().unwrap(), 10).unwrap()
+                                                                      .collect::<Result<Vec<_>, _>>();
+        let missing = Descendants::from_root(root.to_str().unwrap(), 99).err().map(|error| error.kind());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(vec![20, 21, 22, 30, 31], pids.unwrap());
+        assert_eq!(Some(ErrorKind::NotFound), missing);
+    }
+
+    #[test]
+    fn test_descendants_index() {
+        let mut index = HashMap::new();
+        index.insert(1, vec![10, 2]);
+        index.insert(10, vec![20, 21]);
+        index.insert(21, vec![30]);
+        index.insert(2, vec![3]);
+        let pids = Descendants::new(Source::Index(index.clone()), 10).collect::<Result<Vec<_>, _>>();
+        assert_eq!(vec![20, 21, 30], pids.unwrap());
+        assert_eq!(0, Descendants::new(Source::Index(index), 30).count());
+    }
+
+    /// Test that a child, and the grandchild it spawns, are descendants of the current process.
+    #[test]
+    fn test_descendants() {
+        let mut child = Command::new("sh").arg("-c").arg("sleep 10 & echo $!; wait")
+                                          .stdout(Stdio::piped())
+                                          .spawn()
+                                          .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let grandchild: pid_t = line.trim().parse().unwrap();
+
+        let pids = descendants(process::id() as pid_t).and_then(|pids| pids.collect::<Result<Vec<_>, _>>());
+        unsafe { libc::kill(grandchild, libc::SIGKILL) };
+        child.wait().unwrap();
+
+        let pids = pids.unwrap();
+        let child_idx = pids.iter().position(|&pid| pid == child.id() as pid_t).unwrap();
+        let grandchild_idx = pids.iter().position(|&pid| pid == grandchild).unwrap();
+        assert!(child_idx < grandchild_idx, "{:?}", pids);
+
+        assert_eq!(ErrorKind::NotFound, descendants(pid_t::MAX).err().unwrap().kind());
+    }
+}
@@ -0,0 +1,150 @@
+//! A handle of the `/proc/[pid]` directory of a process, for consistent reads of several files.
+//!
+//! A pid identifies a process only while it runs: once the process exits and is reaped, the kernel
+//! may assign its pid to a new process. A probe which reads several files of a process by path, e.g.
+//! `/proc/[pid]/stat` and then `/proc/[pid]/io`, may therefore read the second file from an
+//! unrelated process which reused the pid in between, and silently mix the data of both.
+//!
+//! The directory of a process in `/proc` is bound to the process itself rather than to its pid.
+//! `PidDir` opens the directory once, and opens its files relative to the open directory with
+//! `openat(2)`. After the process is reaped, opening a file through the stale directory, or reading
+//! a file which was already open, fails with `ESRCH`, even if the pid was reused, so every file read
+//! through one handle is of the same process.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use parsers::read_to_end;
+use pid::PidFile;
+use platform::{self, pid_t};
+
+/// An open `/proc/[pid]` directory of a process.
+///
+/// The files of the process are opened relative to the directory, so they are all of the process
+/// which had the pid when the handle was opened, see the module documentation.
+#[derive(Debug)]
+pub struct PidDir {
+    pid: pid_t,
+    path: PathBuf,
+    dir: File,
+    id: (u64, u64),
+}
+
+impl PidDir {
+    /// Opens the directory of the process with the provided pid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::NotFound` if there is no process with the pid.
+    pub fn open(pid: pid_t) -> Result<PidDir> {
+        PidDir::open_path(pid, format!("/proc/{}", pid))
+    }
+
+    /// Opens the directory of a process at the provided path, e.g. in a fixture proc directory.
+    pub fn open_path<P: Into<PathBuf>>(pid: pid_t, path: P) -> Result<PidDir> {
+        let path = path.into();
+        let dir = platform::open_dir(&path)?;
+        let id = platform::file_id(&dir.metadata()?);
+        Ok(PidDir { pid, path, dir, id })
+    }
+
+    /// Returns the pid of the process.
+    pub fn pid(&self) -> pid_t {
+        self.pid
+    }
+
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Opens a file of the process, e.g. `stat`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::NotFound` if the file does not exist, and the OS error
+    /// `ESRCH` if the process has been reaped since the handle was opened.
+    pub fn open_file(&self, name: &str) -> Result<File> {
+        platform::open_at(&self.dir, name)
+    }
+
+    /// Reads and parses a file of the process, e.g. `dir.read::<Stat>(&mut buf)`, into a buffer
+    /// provided by the caller.
+    ///
+    /// Besides the errors of `open_file`, reading the file fails with `ESRCH` if the process is
+    /// reaped after the file is opened.
+    pub fn read<P: PidFile>(&self, buf: &mut Vec<u8>) -> Result<P::Output> {
+        let mut file = self.open_file(P::FILE)?;
+        buf.clear();
+        P::parse(read_to_end(&mut file, buf)?)
+    }
+
+    /// Returns `true` if the path of the directory still refers to the directory of the process,
+    /// by comparing its inode with that of the open directory.
+    ///
+    /// Returns `false` if the process has been reaped, whether or not its pid has since been
+    /// reused by another process.
+    pub fn reopen_check(&self) -> Result<bool> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => Ok(platform::file_id(&metadata) == self.id),
+            Err(ref error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::process::{self, Command};
+
+    use libc::{ESRCH, pid_t};
+
+    use parsers::read_to_end;
+    use pid::{Io, Stat, Status};
+    use super::PidDir;
+
+    /// Test that the files opened through the handle are those of the process.
+    #[test]
+    fn test_pid_dir() {
+        let pid = process::id() as pid_t;
+        let dir = PidDir::open(pid).unwrap();
+        assert_eq!(pid, dir.pid());
+        assert!(dir.reopen_check().unwrap());
+
+        let mut buf = Vec::new();
+        let stat = dir.read::<Stat>(&mut buf).unwrap();
+        assert_eq!(Stat::parse(&fs::read(dir.path().join("stat")).unwrap()).unwrap().start_time, stat.start_time);
+        assert_eq!(pid, dir.read::<Status>(&mut buf).unwrap().pid);
+        dir.read::<Io>(&mut buf).unwrap();
+        assert_eq!(fs::read("/proc/self/cmdline").unwrap(), fs::read(dir.path().join("cmdline")).unwrap());
+
+        assert_eq!(ErrorKind::NotFound, dir.open_file("not-a-file").unwrap_err().kind());
+        assert_eq!(ErrorKind::NotFound, PidDir::open(pid_t::MAX).unwrap_err().kind());
+    }
+
+    /// Test that the handle of a process which has been reaped does not read the files of another
+    /// process, even one which reuses its pid.
+    #[test]
+    fn test_pid_dir_stale() {
+        let mut child = Command::new("sleep").arg("60").spawn().unwrap();
+        let dir = PidDir::open(child.id() as pid_t).unwrap();
+        let mut buf = Vec::new();
+        dir.read::<Stat>(&mut buf).unwrap();
+        let mut stat = dir.open_file("stat").unwrap();
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        // Whether or not a new process has taken the pid, the stale handle reads nothing from it.
+        let mut respawned = Command::new("sleep").arg("60").spawn().unwrap();
+
+        assert!(!dir.reopen_check().unwrap());
+        assert_eq!(Some(ESRCH), dir.read::<Stat>(&mut buf).unwrap_err().raw_os_error());
+        assert_eq!(Some(ESRCH), read_to_end(&mut stat, &mut buf).unwrap_err().raw_os_error());
+
+        respawned.kill().unwrap();
+        respawned.wait().unwrap();
+    }
+}
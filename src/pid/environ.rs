@@ -0,0 +1,150 @@
+//! Process environment variables from `/proc/[pid]/environ`.
+
+use std::ffi::{OsStr, OsString};
+use std::io::Result;
+use std::path::Path;
+
+use parsers::read_to_end;
+use platform::{self, pid_t};
+
+/// Returns the entries of an environ file, which are separated by NUL bytes.
+fn entries(input: &[u8]) -> impl Iterator<Item = &[u8]> {
+    input.split(|&b| b == 0).filter(|entry| !entry.is_empty())
+}
+
+/// Returns the name of an environment entry, which is the part before the first `=`.
+fn entry_name(entry: &[u8]) -> &[u8] {
+    entry.iter().position(|&b| b == b'=').map_or(entry, |idx| &entry[..idx])
+}
+
+/// Returns the value of the first entry of an environ file with the provided name.
+///
+/// Names are compared byte by byte.
+fn environ_value<'a>(input: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    entries(input).find(|entry| entry_name(entry) == name && entry.len() > name.len())
+                  .map(|entry| &entry[name.len() + 1..])
+}
+
+/// Returns the names of the entries of an environ file.
+fn environ_names(input: &[u8]) -> Vec<OsString> {
+    entries(input).map(entry_name).map(platform::os_string_from_bytes).collect()
+}
+
+/// Reads an environ file, and applies `f` to its contents.
+fn read_environ<P, F, T>(path: P, f: F) -> Result<T>
+where P: AsRef<Path>, F: FnOnce(&[u8]) -> T {
+    let mut buf = Vec::with_capacity(4096); // A typical environ file is a few KiB.
+    Ok(f(read_to_end(&mut platform::open(path)?, &mut buf)?))
+}
+
+/// Returns the value of the environment variable `key` of the process with the provided pid, or
+/// `None` if the variable is not set.
+///
+/// The environment is the one the process was started with: changes made by the process, such as
+/// with `setenv(3)`, are not reflected. Reading the environment of another process requires that
+/// the current process may trace it.
+pub fn environ_get(pid: pid_t, key: &OsStr) -> Result<Option<OsString>> {
+    let key = platform::os_str_as_bytes(key);
+    read_environ(format!("/proc/{}/environ", pid),
+                 |input| environ_value(input, &key).map(platform::os_string_from_bytes))
+}
+
+/// Returns the value of the environment variable `key` of the current process, as it was when the
+/// process was started, see `environ_get`.
+pub fn environ_get_self(key: &OsStr) -> Result<Option<OsString>> {
+    let key = platform::os_str_as_bytes(key);
+    read_environ("/proc/self/environ",
+                 |input| environ_value(input, &key).map(platform::os_string_from_bytes))
+}
+
+/// Returns the names of the environment variables of the process with the provided pid, without
+/// their values.
+///
+/// See `environ_get` for which environment is read.
+pub fn environ_keys(pid: pid_t) -> Result<Vec<OsString>> {
+    read_environ(format!("/proc/{}/environ", pid), environ_names)
+}
+
+/// Returns the names of the environment variables of the current process, as it was when the
+/// process was started, without their values.
+pub fn environ_keys_self() -> Result<Vec<OsString>> {
+    read_environ("/proc/self/environ", environ_names)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::{OsStr, OsString};
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::process::{Command, Stdio};
+
+    use libc::pid_t;
+
+    use super::{environ_get, environ_get_self, environ_keys, environ_keys_self, environ_names, environ_value};
+
+    const ENVIRON: &[u8] = b"HOME=/root\0PATH=/usr/bin:/bin\0EMPTY=\0NOVALUE\0EQ==a=b\0\xff\xfe=\xfd\0PATH=/sbin\0";
+
+    #[test]
+    fn test_environ_value() {
+        assert_eq!(Some(&b"/root"[..]), environ_value(ENVIRON, b"HOME"));
+        // The first entry of a duplicated name is used, as by `getenv(3)`.
+        assert_eq!(Some(&b"/usr/bin:/bin"[..]), environ_value(ENVIRON, b"PATH"));
+        assert_eq!(Some(&b""[..]), environ_value(ENVIRON, b"EMPTY"));
+        assert_eq!(Some(&b"=a=b"[..]), environ_value(ENVIRON, b"EQ"));
+        assert_eq!(Some(&b"\xfd"[..]), environ_value(ENVIRON, b"\xff\xfe"));
+
+        // Names are compared exactly, up to the first `=`.
+        assert_eq!(None, environ_value(ENVIRON, b"NOVALUE"));
+        assert_eq!(None, environ_value(ENVIRON, b"home"));
+        assert_eq!(None, environ_value(ENVIRON, b"HOM"));
+        assert_eq!(None, environ_value(ENVIRON, b"HOME=/root"));
+        assert_eq!(None, environ_value(ENVIRON, b"EQ="));
+        assert_eq!(None, environ_value(ENVIRON, b""));
+        assert_eq!(None, environ_value(b"", b"HOME"));
+    }
+
+    #[test]
+    fn test_environ_names() {
+        let names: Vec<OsString> = [&b"HOME"[..], b"PATH", b"EMPTY", b"NOVALUE", b"EQ", b"\xff\xfe", b"PATH"]
+            .iter()
+            .map(|name| OsString::from_vec(name.to_vec()))
+            .collect();
+        assert_eq!(names, environ_names(ENVIRON));
+        assert!(environ_names(b"").is_empty());
+    }
+
+    /// Test that the environment of a child process started with a variable can be read.
+    #[test]
+    fn test_environ_child() {
+        let value = OsStr::from_bytes(b"secret \xff value");
+        let mut child = Command::new("cat").env_clear()
+                                           .env("PROCINFO_TEST_VAR", value)
+                                           .stdin(Stdio::piped())
+                                           .stdout(Stdio::piped())
+                                           .spawn()
+                                           .unwrap();
+        // Until the child has executed `cat`, it has the environment of the test process.
+        let mut line = String::new();
+        child.stdin.as_mut().unwrap().write_all(b"ready\n").unwrap();
+        BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut line).unwrap();
+
+        let pid = child.id() as pid_t;
+        let found = environ_get(pid, OsStr::new("PROCINFO_TEST_VAR"));
+        let missing = environ_get(pid, OsStr::new("PROCINFO_TEST"));
+        let keys = environ_keys(pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(Some(value.to_owned()), found.unwrap());
+        assert_eq!(None, missing.unwrap());
+        assert_eq!(vec![OsString::from_vec(b"PROCINFO_TEST_VAR".to_vec())], keys.unwrap());
+    }
+
+    /// Test that the environment of the current process can be read.
+    #[test]
+    fn test_environ_self() {
+        assert_eq!(env::var_os("PATH"), environ_get_self(OsStr::new("PATH")).unwrap());
+        assert!(environ_keys_self().unwrap().contains(&OsString::from("PATH")));
+    }
+}
@@ -0,0 +1,151 @@
+//! Open file descriptor counts from `/proc/[pid]/fd/`.
+
+use std::io::Result;
+
+use pid::{Limit, limits, limits_self};
+use platform::{self, pid_t};
+
+/// Counts the entries of a file descriptor directory, without reading the links.
+fn count_entries(path: &str) -> Result<usize> {
+    let mut count = 0;
+    for entry in platform::read_dir(path)? {
+        entry?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Returns the number of open file descriptors of the process with the provided pid.
+///
+/// Only the entries of `/proc/[pid]/fd/` are counted, the links are not read.
+pub fn fd_count(pid: pid_t) -> Result<usize> {
+    count_entries(&format!("/proc/{}/fd", pid))
+}
+
+/// Returns the number of open file descriptors of the current process.
+///
+/// The count includes the file descriptor which is used to read `/proc/self/fd/`.
+pub fn fd_count_self() -> Result<usize> {
+    count_entries("/proc/self/fd")
+}
+
+/// The number of open file descriptors of a process, and how close it is to its limit.
+///
+/// The default value has no open file descriptors and no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FdHeadroom {
+    /// The number of open file descriptors.
+    pub used: u64,
+    /// The soft limit on open files, or `None` if unlimited.
+    ///
+    /// Opening a file fails with `EMFILE` once `used` reaches this limit.
+    pub soft: Option<u64>,
+    /// The hard limit on open files, or `None` if unlimited.
+    pub hard: Option<u64>,
+    /// The fraction of the soft limit which is used, or `None` if the soft limit is unlimited.
+    ///
+    /// The fraction may exceed 1 if the soft limit was lowered below the number of open files.
+    pub utilization: Option<f64>,
+}
+
+impl FdHeadroom {
+    fn new(used: u64, limit: Limit<u64>) -> FdHeadroom {
+        FdHeadroom {
+            used,
+            soft: limit.soft,
+            hard: limit.hard,
+            utilization: limit.soft.map(|soft| if soft == 0 { 1.0 } else { used as f64 / soft as f64 }),
+        }
+    }
+
+    /// Returns the number of file descriptors which can be opened before reaching the soft limit,
+    /// or `None` if the soft limit is unlimited.
+    pub fn remaining(&self) -> Option<u64> {
+        self.soft.map(|soft| soft.saturating_sub(self.used))
+    }
+}
+
+/// Returns the number of open file descriptors of the process with the provided pid, and its
+/// limit on open files.
+pub fn fd_headroom(pid: pid_t) -> Result<FdHeadroom> {
+    let used = fd_count(pid)? as u64;
+    Ok(FdHeadroom::new(used, limits(pid)?.max_open_files))
+}
+
+/// Returns the number of open file descriptors of the current process, and its limit on open
+/// files.
+pub fn fd_headroom_self() -> Result<FdHeadroom> {
+    let used = fd_count_self()? as u64;
+    Ok(FdHeadroom::new(used, limits_self()?.max_open_files))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Command, Stdio};
+
+    use libc::pid_t;
+
+    use pid::{Limit, limits};
+    use super::{FdHeadroom, fd_count, fd_count_self, fd_headroom, fd_headroom_self};
+
+    #[test]
+    fn test_headroom() {
+        let headroom = FdHeadroom::new(256, Limit { soft: Some(1024), hard: Some(4096) });
+        assert_eq!(Some(0.25), headroom.utilization);
+        assert_eq!(Some(768), headroom.remaining());
+
+        let unlimited = FdHeadroom::new(256, Limit { soft: None, hard: None });
+        assert_eq!(None, unlimited.utilization);
+        assert_eq!(None, unlimited.remaining());
+
+        // The soft limit may be lowered below the number of open files.
+        let exceeded = FdHeadroom::new(256, Limit { soft: Some(128), hard: Some(4096) });
+        assert_eq!(Some(2.0), exceeded.utilization);
+        assert_eq!(Some(0), exceeded.remaining());
+
+        assert_eq!(Some(1.0), FdHeadroom::new(0, Limit { soft: Some(0), hard: Some(0) }).utilization);
+    }
+
+    /// Test that a child process, which only has its standard streams open, has three file
+    /// descriptors.
+    #[test]
+    fn test_fd_count() {
+        let mut child = Command::new("cat").stdin(Stdio::piped())
+                                           .stdout(Stdio::piped())
+                                           .stderr(Stdio::null())
+                                           .spawn()
+                                           .unwrap();
+        // Until the child has executed `cat`, it may still have the file descriptors of the test
+        // process open, so wait for `cat` to echo a line.
+        let mut line = String::new();
+        child.stdin.as_mut().unwrap().write_all(b"ready\n").unwrap();
+        BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut line).unwrap();
+        assert_eq!("ready\n", line);
+
+        let pid = child.id() as pid_t;
+        let count = fd_count(pid);
+        let headroom = fd_headroom(pid);
+        let limit = limits(pid).map(|limits| limits.max_open_files);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(3, count.unwrap());
+        let headroom = headroom.unwrap();
+        assert_eq!(3, headroom.used);
+        assert_eq!(limit.unwrap(), Limit { soft: headroom.soft, hard: headroom.hard });
+    }
+
+    /// Test that the files opened by the current process are counted.
+    ///
+    /// Other tests open and close files concurrently, so the count is only bounded from below.
+    #[test]
+    fn test_fd_count_self() {
+        let files: Vec<File> = (0..16).map(|_| File::open("/proc/self/stat").unwrap()).collect();
+        // The files, and the directory being read.
+        assert!(fd_count_self().unwrap() > files.len());
+        assert!(fd_headroom_self().unwrap().used > files.len() as u64);
+    }
+}
@@ -0,0 +1,305 @@
+//! File descriptor information from `/proc/[pid]/fdinfo/[fd]`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+
+use parsers::read_to_end;
+use platform::{self, pid_t};
+
+/// A set of signals, such as the mask of a signalfd.
+///
+/// Signal `n` is represented by bit `n - 1`, as in the signal masks of `/proc/[pid]/status`.
+///
+/// The default value is the empty set. When serialized with the `serde` feature, the set is
+/// represented as the raw mask.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignalSet(u64);
+
+impl SignalSet {
+    /// Returns the set with the provided mask.
+    pub fn from_raw(raw: u64) -> SignalSet {
+        SignalSet(raw)
+    }
+
+    /// Returns the mask of the set.
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if the set contains the signal with the provided number, e.g. 15 for
+    /// `SIGTERM`.
+    pub fn contains(&self, signal: u32) -> bool {
+        (1..=64).contains(&signal) && self.0 & (1 << (signal - 1)) != 0
+    }
+
+    /// Returns the numbers of the signals in the set, in increasing order.
+    pub fn signals(&self) -> Vec<u32> {
+        (1..65).filter(|&signal| self.contains(signal)).collect()
+    }
+}
+
+/// Information specific to the type of a file descriptor.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FdInfoExtension {
+    /// A signalfd, see `signalfd(2)`.
+    SignalFd {
+        /// The signals which are accepted by the signalfd.
+        mask: SignalSet,
+    },
+    /// A pidfd, see `pidfd_open(2)` (since Linux 5.3).
+    PidFd {
+        /// The pid of the process in the pid namespace of the process reading the fdinfo, 0 if the
+        /// process is not in that namespace, or -1 if the process has exited.
+        pid: pid_t,
+        /// The pids of the process in each of its pid namespaces, from the namespace of the
+        /// process reading the fdinfo to the innermost namespace.
+        ns_pids: Vec<pid_t>,
+    },
+    /// A dma-buf, see `Linux/Documentation/driver-api/dma-buf.rst` (since Linux 5.3).
+    DmaBuf {
+        /// The size of the buffer in bytes.
+        size: u64,
+        /// The name of the exporter of the buffer, e.g. `udmabuf`.
+        exp_name: String,
+    },
+    /// Any other type of file descriptor, with the lines which follow the common fields.
+    ///
+    /// The lines are empty for regular files, and contain e.g. the watches of an inotify instance
+    /// or the target descriptors of an epoll instance.
+    Other(Vec<String>),
+}
+
+/// Defaults to `Other`, without lines.
+impl Default for FdInfoExtension {
+    fn default() -> FdInfoExtension {
+        FdInfoExtension::Other(Vec::new())
+    }
+}
+
+/// Information about an open file descriptor, see `proc(5)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FdInfo {
+    /// The file offset.
+    pub pos: u64,
+    /// The file access mode and status flags, see `open(2)`.
+    pub flags: u32,
+    /// The ID of the mount containing the file (since Linux 3.15).
+    pub mnt_id: Option<u32>,
+    /// The inode number of the file (since Linux 5.14).
+    pub ino: Option<u64>,
+    /// Information specific to the type of the file descriptor.
+    pub extension: FdInfoExtension,
+}
+
+/// Returns the error for an fdinfo line which could not be parsed.
+fn parse_error(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse fdinfo line: {:?}", line))
+}
+
+/// Parses a value of an fdinfo line.
+fn parse_value<T: str::FromStr>(line: &str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| parse_error(line))
+}
+
+/// Returns the value of the line with the provided key.
+fn find<'a>(lines: &[(&str, &'a str, &str)], key: &str) -> Option<&'a str> {
+    lines.iter().find(|&&(k, _, _)| k == key).map(|&(_, value, _)| value)
+}
+
+/// Decodes the extension lines of an fdinfo file, by the keys which are present.
+fn parse_extension(lines: &[(&str, &str, &str)]) -> Result<FdInfoExtension> {
+    if let Some(mask) = find(lines, "sigmask") {
+        let mask = u64::from_str_radix(mask, 16).map_err(|_| parse_error(mask))?;
+        return Ok(FdInfoExtension::SignalFd { mask: SignalSet::from_raw(mask) });
+    }
+    if let Some(pid) = find(lines, "Pid") {
+        let ns_pids = match find(lines, "NSpid") {
+            Some(ns_pids) => ns_pids.split_whitespace()
+                                    .map(|ns_pid| parse_value(ns_pids, ns_pid))
+                                    .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+        return Ok(FdInfoExtension::PidFd { pid: parse_value(pid, pid)?, ns_pids });
+    }
+    if let (Some(size), Some(exp_name)) = (find(lines, "size"), find(lines, "exp_name")) {
+        return Ok(FdInfoExtension::DmaBuf { size: parse_value(size, size)?, exp_name: exp_name.to_owned() });
+    }
+    Ok(FdInfoExtension::Other(lines.iter().map(|&(_, _, line)| line.to_owned()).collect()))
+}
+
+impl FdInfo {
+    /// Parses the contents of a `/proc/[pid]/fdinfo/[fd]` file.
+    pub fn parse(input: &[u8]) -> Result<FdInfo> {
+        let input = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        let mut fdinfo = FdInfo::default();
+        let (mut pos, mut flags) = (None, None);
+        let mut extension = Vec::new();
+        for line in input.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = match line.find(':') {
+                Some(idx) => (&line[..idx], line[idx + 1..].trim()),
+                None => ("", line),
+            };
+            match key {
+                "pos"    => pos = Some(parse_value(line, value)?),
+                "flags"  => flags = Some(u32::from_str_radix(value, 8).map_err(|_| parse_error(line))?),
+                "mnt_id" => fdinfo.mnt_id = Some(parse_value(line, value)?),
+                "ino"    => fdinfo.ino = Some(parse_value(line, value)?),
+                _ => extension.push((key, value, line)),
+            }
+        }
+        fdinfo.pos = pos.ok_or_else(|| parse_error("pos"))?;
+        fdinfo.flags = flags.ok_or_else(|| parse_error("flags"))?;
+        fdinfo.extension = parse_extension(&extension)?;
+        Ok(fdinfo)
+    }
+}
+
+fn fdinfo_file(path: &str) -> Result<FdInfo> {
+    let mut buf = Vec::with_capacity(256);
+    FdInfo::parse(read_to_end(&mut platform::open(path)?, &mut buf)?)
+}
+
+/// Returns information about an open file descriptor of the process with the provided pid.
+///
+/// Reading the file descriptors of another process requires that the current process may trace
+/// it.
+pub fn fdinfo(pid: pid_t, fd: i32) -> Result<FdInfo> {
+    fdinfo_file(&format!("/proc/{}/fdinfo/{}", pid, fd))
+}
+
+/// Returns information about an open file descriptor of the current process.
+pub fn fdinfo_self(fd: i32) -> Result<FdInfo> {
+    fdinfo_file(&format!("/proc/self/fdinfo/{}", fd))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Error;
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+    use std::process;
+
+    use libc::{self, pid_t};
+
+    use super::{FdInfo, FdInfoExtension, SignalSet, fdinfo, fdinfo_self};
+
+    #[test]
+    fn test_signal_set() {
+        let set = SignalSet::from_raw(0x4a02);
+        assert_eq!(vec![2, 10, 12, 15], set.signals());
+        assert!(set.contains(15) && !set.contains(9));
+        assert!(!set.contains(0) && !set.contains(65));
+        assert_eq!(0x4a02, set.raw());
+        assert!(SignalSet::default().signals().is_empty());
+        assert_eq!(vec![64], SignalSet::from_raw(1 << 63).signals());
+    }
+
+    #[test]
+    fn test_parse_fdinfo_file() {
+        let fdinfo = FdInfo::parse(b"pos:\t4096\nflags:\t0100002\nmnt_id:\t29\nino:\t1311\n").unwrap();
+        assert_eq!(FdInfo {
+                       pos: 4096,
+                       flags: 0o100002,
+                       mnt_id: Some(29),
+                       ino: Some(1311),
+                       extension: FdInfoExtension::Other(Vec::new()),
+                   },
+                   fdinfo);
+
+        // Linux 3.14 and earlier.
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02\n").unwrap();
+        assert_eq!(None, fdinfo.mnt_id);
+        assert_eq!(None, fdinfo.ino);
+
+        assert!(FdInfo::parse(b"").is_err());
+        assert!(FdInfo::parse(b"pos:\t0\n").is_err());
+        assert!(FdInfo::parse(b"pos:\t0\nflags:\t09\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_fdinfo_signalfd() {
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02004002\nmnt_id:\t15\nino:\t1057\n\
+                                     sigmask:\t0000000000004a02\n").unwrap();
+        assert_eq!(0o2004002, fdinfo.flags);
+        assert_eq!(FdInfoExtension::SignalFd { mask: SignalSet::from_raw(0x4a02) }, fdinfo.extension);
+    }
+
+    #[test]
+    fn test_parse_fdinfo_pidfd() {
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\n\
+                                     Pid:\t1234\nNSpid:\t1234\t1\n").unwrap();
+        assert_eq!(FdInfoExtension::PidFd { pid: 1234, ns_pids: vec![1234, 1] }, fdinfo.extension);
+
+        // The process has exited.
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02000002\nmnt_id:\t15\nPid:\t-1\n").unwrap();
+        assert_eq!(FdInfoExtension::PidFd { pid: -1, ns_pids: Vec::new() }, fdinfo.extension);
+
+        assert!(FdInfo::parse(b"pos:\t0\nflags:\t02000002\nPid:\t1234\nNSpid:\t1234\tx\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_fdinfo_dma_buf() {
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02\nmnt_id:\t16\nino:\t5\n\
+                                     size:\t8294400\ncount:\t3\nexp_name:\tudmabuf\nname:\tframebuffer\n")
+            .unwrap();
+        assert_eq!(FdInfoExtension::DmaBuf { size: 8294400, exp_name: "udmabuf".to_owned() }, fdinfo.extension);
+    }
+
+    #[test]
+    fn test_parse_fdinfo_other() {
+        let fdinfo = FdInfo::parse(b"pos:\t0\nflags:\t02\nmnt_id:\t15\nino:\t1057\n\
+                                     tfd:        5 events:       19 data:                5  pos:0 ino:3ea sdev:7\n")
+            .unwrap();
+        assert_eq!(FdInfoExtension::Other(vec![
+                       "tfd:        5 events:       19 data:                5  pos:0 ino:3ea sdev:7".to_owned(),
+                   ]),
+                   fdinfo.extension);
+    }
+
+    /// Test the fdinfo of a regular file and of a signalfd of the current process.
+    #[test]
+    fn test_fdinfo_self() {
+        let file = File::open("/proc/self/stat").unwrap();
+        let info = fdinfo_self(file.as_raw_fd()).unwrap();
+        assert_eq!(0, info.pos);
+        assert_eq!(0, info.flags & libc::O_ACCMODE as u32);
+        assert_eq!(FdInfoExtension::Other(Vec::new()), info.extension);
+
+        let fd = unsafe {
+            let mut mask: libc::sigset_t = mem::zeroed();
+            libc::sigemptyset(&mut mask);
+            libc::sigaddset(&mut mask, libc::SIGUSR1);
+            libc::sigaddset(&mut mask, libc::SIGTERM);
+            libc::signalfd(-1, &mask, libc::SFD_CLOEXEC)
+        };
+        assert!(fd >= 0);
+        let info = fdinfo(process::id() as pid_t, fd);
+        unsafe { libc::close(fd) };
+        assert_eq!(FdInfoExtension::SignalFd { mask: SignalSet::from_raw(1 << 9 | 1 << 14) },
+                   info.unwrap().extension);
+    }
+
+    /// Test the fdinfo of a pidfd, if the kernel supports `pidfd_open(2)`.
+    #[test]
+    fn test_fdinfo_pidfd() {
+        let pid = process::id() as pid_t;
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) } as i32;
+        if fd < 0 {
+            assert_eq!(Some(libc::ENOSYS), Error::last_os_error().raw_os_error());
+            return;
+        }
+        let fdinfo = fdinfo_self(fd);
+        unsafe { libc::close(fd) };
+        match fdinfo.unwrap().extension {
+            FdInfoExtension::PidFd { pid: fd_pid, ns_pids } => {
+                assert_eq!(pid, fd_pid);
+                assert_eq!(Some(&pid), ns_pids.first());
+            }
+            extension => panic!("unexpected extension: {:?}", extension),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Process flags, as reported by `/proc/[pid]/stat`.
+
+/// A kernel process flag.
+///
+/// See the `PF_*` defines in `Linux/include/linux/sched.h`. The mapping targets Linux 4.14 to
+/// 6.12. Bits whose meaning changed in that range are not named, and are only available through
+/// `ProcFlags::raw`:
+///
+/// * `0x00000008`: `PF_EXITPIDONE` until Linux 5.9, `PF_POSTCOREDUMP` since Linux 5.17.
+/// * `0x00004000`: `PF_USED_ASYNC` until Linux 5.15, `PF_USER_WORKER` since Linux 6.4.
+/// * `0x00010000`: `PF_FROZEN` until Linux 6.0, and reused by newer kernels.
+/// * `0x00800000`: `PF_SWAPWRITE` until Linux 5.18, `PF_MEMALLOC_NORECLAIM` since Linux 6.11.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ProcFlag {
+    /// The task is running a virtual CPU.
+    Vcpu,
+    /// The task is an idle thread (since Linux 4.11).
+    Idle,
+    /// The task is exiting.
+    Exiting,
+    /// The task is an io_uring worker (since Linux 5.12).
+    IoWorker,
+    /// The task is a workqueue worker.
+    WqWorker,
+    /// The task was forked but did not execute a new program.
+    ForkNoExec,
+    /// The task has a machine check exception policy.
+    MceProcess,
+    /// The task used superuser privileges.
+    SuperPriv,
+    /// The task dumped core.
+    DumpCore,
+    /// The task was killed by a signal.
+    Signaled,
+    /// The task is allocating memory to free memory.
+    Memalloc,
+    /// `set_user` found `RLIMIT_NPROC` exceeded.
+    NprocExceeded,
+    /// The task used the FPU.
+    UsedMath,
+    /// The task is not frozen during suspend.
+    NoFreeze,
+    /// The task is kswapd.
+    Kswapd,
+    /// Memory allocations of the task do not perform filesystem reclaim.
+    MemallocNofs,
+    /// Memory allocations of the task do not perform I/O.
+    MemallocNoio,
+    /// The task is throttled only against its own dirty pages (`PF_LESS_THROTTLE` before Linux
+    /// 5.10).
+    LocalThrottle,
+    /// The task is a kernel thread.
+    Kthread,
+    /// The address space of the task is randomized.
+    Randomize,
+    /// User space may not change the CPU affinity of the task.
+    NoSetaffinity,
+    /// The task handles machine check exceptions early.
+    MceEarly,
+    /// Memory allocations of the task may be pinned long term (`PF_MEMALLOC_NOCMA` before Linux
+    /// 5.12).
+    MemallocPin,
+    /// The task is frozen by `freeze_processes` during suspend.
+    SuspendTask,
+}
+
+/// Every named flag, with its bit and name.
+const FLAGS: [(ProcFlag, u32, &str); 24] = [
+    (ProcFlag::Vcpu,          0x0000_0001, "PF_VCPU"),
+    (ProcFlag::Idle,          0x0000_0002, "PF_IDLE"),
+    (ProcFlag::Exiting,       0x0000_0004, "PF_EXITING"),
+    (ProcFlag::IoWorker,      0x0000_0010, "PF_IO_WORKER"),
+    (ProcFlag::WqWorker,      0x0000_0020, "PF_WQ_WORKER"),
+    (ProcFlag::ForkNoExec,    0x0000_0040, "PF_FORKNOEXEC"),
+    (ProcFlag::MceProcess,    0x0000_0080, "PF_MCE_PROCESS"),
+    (ProcFlag::SuperPriv,     0x0000_0100, "PF_SUPERPRIV"),
+    (ProcFlag::DumpCore,      0x0000_0200, "PF_DUMPCORE"),
+    (ProcFlag::Signaled,      0x0000_0400, "PF_SIGNALED"),
+    (ProcFlag::Memalloc,      0x0000_0800, "PF_MEMALLOC"),
+    (ProcFlag::NprocExceeded, 0x0000_1000, "PF_NPROC_EXCEEDED"),
+    (ProcFlag::UsedMath,      0x0000_2000, "PF_USED_MATH"),
+    (ProcFlag::NoFreeze,      0x0000_8000, "PF_NOFREEZE"),
+    (ProcFlag::Kswapd,        0x0002_0000, "PF_KSWAPD"),
+    (ProcFlag::MemallocNofs,  0x0004_0000, "PF_MEMALLOC_NOFS"),
+    (ProcFlag::MemallocNoio,  0x0008_0000, "PF_MEMALLOC_NOIO"),
+    (ProcFlag::LocalThrottle, 0x0010_0000, "PF_LOCAL_THROTTLE"),
+    (ProcFlag::Kthread,       0x0020_0000, "PF_KTHREAD"),
+    (ProcFlag::Randomize,     0x0040_0000, "PF_RANDOMIZE"),
+    (ProcFlag::NoSetaffinity, 0x0400_0000, "PF_NO_SETAFFINITY"),
+    (ProcFlag::MceEarly,      0x0800_0000, "PF_MCE_EARLY"),
+    (ProcFlag::MemallocPin,   0x1000_0000, "PF_MEMALLOC_PIN"),
+    (ProcFlag::SuspendTask,   0x8000_0000, "PF_SUSPEND_TASK"),
+];
+
+impl ProcFlag {
+    /// Returns the bit of the flag in the flags word.
+    pub fn bit(&self) -> u32 {
+        FLAGS[*self as usize].1
+    }
+
+    /// Returns the name of the flag, e.g. `PF_KTHREAD`.
+    pub fn name(&self) -> &'static str {
+        FLAGS[*self as usize].2
+    }
+}
+
+/// The kernel flags word of a process.
+///
+/// Bits which do not correspond to a named flag are preserved, and are included in `raw()`.
+///
+/// The default value has no flags set. When serialized with the `serde` feature, the flags are
+/// represented as the raw word.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProcFlags(u32);
+
+impl ProcFlags {
+    /// Returns the flags with the provided word.
+    pub fn from_raw(raw: u32) -> ProcFlags {
+        ProcFlags(raw)
+    }
+
+    /// Returns the flags word, including unnamed bits.
+    pub fn raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the bits of the word which do not correspond to a named flag.
+    pub fn unknown(&self) -> u32 {
+        FLAGS.iter().fold(self.0, |raw, &(_, bit, _)| raw & !bit)
+    }
+
+    /// Returns `true` if the flag is set.
+    pub fn has(&self, flag: ProcFlag) -> bool {
+        self.0 & flag.bit() != 0
+    }
+
+    /// Returns `true` if the process is a kernel thread (`PF_KTHREAD`).
+    ///
+    /// Unlike heuristics based on an empty command line or the parent process, this is reliable
+    /// for every kernel thread, including those which are not children of `kthreadd`.
+    pub fn is_kernel_thread(&self) -> bool {
+        self.has(ProcFlag::Kthread)
+    }
+
+    /// Returns an iterator over the named flags which are set, in order of bit.
+    pub fn iter(&self) -> ProcFlagsIter {
+        ProcFlagsIter { raw: self.0, idx: 0 }
+    }
+}
+
+impl IntoIterator for &ProcFlags {
+    type Item = ProcFlag;
+    type IntoIter = ProcFlagsIter;
+
+    fn into_iter(self) -> ProcFlagsIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the named flags which are set in a flags word.
+#[derive(Clone, Debug)]
+pub struct ProcFlagsIter {
+    raw: u32,
+    idx: usize,
+}
+
+impl Iterator for ProcFlagsIter {
+    type Item = ProcFlag;
+
+    fn next(&mut self) -> Option<ProcFlag> {
+        while let Some(&(flag, bit, _)) = FLAGS.get(self.idx) {
+            self.idx += 1;
+            if self.raw & bit != 0 {
+                return Some(flag);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FLAGS, ProcFlag, ProcFlags};
+
+    #[test]
+    fn test_flag_bits() {
+        for (idx, &(flag, bit, _)) in FLAGS.iter().enumerate() {
+            assert_eq!(idx, flag as usize);
+            assert_eq!(bit, flag.bit());
+            assert_eq!(1, bit.count_ones());
+        }
+        assert_eq!(0x200000, ProcFlag::Kthread.bit());
+        assert_eq!("PF_WQ_WORKER", ProcFlag::WqWorker.name());
+    }
+
+    /// Test the flags of kthreadd, of a workqueue worker, and of user processes, captured on Linux
+    /// 6.1.
+    #[test]
+    fn test_proc_flags() {
+        let kthreadd = ProcFlags::from_raw(2129984);
+        assert!(kthreadd.is_kernel_thread());
+        assert_eq!(vec![ProcFlag::ForkNoExec, ProcFlag::NoFreeze, ProcFlag::Kthread],
+                   kthreadd.iter().collect::<Vec<_>>());
+        assert_eq!(0, kthreadd.unknown());
+
+        let worker = ProcFlags::from_raw(69238880);
+        assert!(worker.is_kernel_thread());
+        assert!(worker.has(ProcFlag::WqWorker));
+        assert!(worker.has(ProcFlag::NoSetaffinity));
+
+        let shell = ProcFlags::from_raw(4194560);
+        assert!(!shell.is_kernel_thread());
+        assert_eq!(vec![ProcFlag::SuperPriv, ProcFlag::Randomize], shell.iter().collect::<Vec<_>>());
+
+        // `PF_USED_ASYNC` before Linux 5.15 is not named.
+        let daemon = ProcFlags::from_raw(4218880);
+        assert_eq!(4218880, daemon.raw());
+        assert_eq!(0x4000, daemon.unknown());
+        assert_eq!(vec![ProcFlag::UsedMath, ProcFlag::Randomize],
+                   (&daemon).into_iter().collect::<Vec<_>>());
+
+        assert_eq!(0, ProcFlags::default().iter().count());
+    }
+}
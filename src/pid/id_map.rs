@@ -0,0 +1,157 @@
+//! User namespace ID mappings from `/proc/[pid]/uid_map` and `/proc/[pid]/gid_map`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+
+use parsers::read_to_end;
+use platform::{self, pid_t};
+
+/// A range of IDs which is mapped between a user namespace and the user namespace of the reader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IdMapRange {
+    /// The first ID of the range in the user namespace of the process.
+    pub inside: u32,
+    /// The first ID of the range in the user namespace of the process which read the map.
+    pub outside: u32,
+    /// The number of IDs in the range.
+    pub count: u32,
+}
+
+impl IdMapRange {
+    /// Returns the ID inside the namespace of an ID outside it, if it is in the range.
+    fn inside_of(&self, id: u32) -> Option<u32> {
+        let offset = u64::from(id).checked_sub(u64::from(self.outside))?;
+        if offset < u64::from(self.count) { self.inside.checked_add(offset as u32) } else { None }
+    }
+
+    /// Returns the ID outside the namespace of an ID inside it, if it is in the range.
+    fn outside_of(&self, id: u32) -> Option<u32> {
+        let offset = u64::from(id).checked_sub(u64::from(self.inside))?;
+        if offset < u64::from(self.count) { self.outside.checked_add(offset as u32) } else { None }
+    }
+}
+
+/// The user or group ID mapping of a user namespace, see `user_namespaces(7)`.
+///
+/// The outside IDs are relative to the user namespace of the process which read the map, or, if
+/// it was read by a process in the same namespace, to the parent namespace.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IdMap {
+    /// The mapped ranges, which do not overlap. A namespace without ranges maps no IDs.
+    pub ranges: Vec<IdMapRange>,
+}
+
+fn parse_error(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse ID map line: {:?}", line))
+}
+
+impl IdMap {
+    /// Parses the contents of a `uid_map` or `gid_map` file.
+    pub fn parse(input: &[u8]) -> Result<IdMap> {
+        let input = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        let mut ranges = Vec::new();
+        for line in input.lines() {
+            let fields = line.split_whitespace().map(|field| field.parse().ok()).collect::<Option<Vec<u32>>>();
+            match fields.as_ref().map(|fields| &fields[..]) {
+                Some(&[inside, outside, count]) => ranges.push(IdMapRange { inside, outside, count }),
+                _ => return Err(parse_error(line)),
+            }
+        }
+        Ok(IdMap { ranges })
+    }
+
+    /// Returns the identity map of the initial user namespace, which maps every ID to itself.
+    pub fn identity() -> IdMap {
+        IdMap { ranges: vec![IdMapRange { inside: 0, outside: 0, count: u32::MAX }] }
+    }
+
+    /// Returns `true` if the map is the identity map.
+    pub fn is_identity(&self) -> bool {
+        *self == IdMap::identity()
+    }
+
+    /// Returns the ID in the user namespace of the process of an ID in the namespace of the reader,
+    /// or `None` if it is not mapped. An unmapped ID is presented in the namespace as the overflow
+    /// ID, usually 65534.
+    pub fn to_inside(&self, id: u32) -> Option<u32> {
+        self.ranges.iter().filter_map(|range| range.inside_of(id)).next()
+    }
+
+    /// Returns the ID in the user namespace of the reader of an ID in the namespace of the process,
+    /// or `None` if it is not mapped.
+    pub fn to_outside(&self, id: u32) -> Option<u32> {
+        self.ranges.iter().filter_map(|range| range.outside_of(id)).next()
+    }
+}
+
+fn id_map_file(path: &str) -> Result<IdMap> {
+    let mut buf = Vec::with_capacity(128);
+    IdMap::parse(read_to_end(&mut platform::open(path)?, &mut buf)?)
+}
+
+/// Returns the user ID mapping of the user namespace of the process with the provided pid.
+pub fn uid_map(pid: pid_t) -> Result<IdMap> {
+    id_map_file(&format!("/proc/{}/uid_map", pid))
+}
+
+/// Returns the user ID mapping of the user namespace of the current process.
+pub fn uid_map_self() -> Result<IdMap> {
+    id_map_file("/proc/self/uid_map")
+}
+
+/// Returns the group ID mapping of the user namespace of the process with the provided pid.
+pub fn gid_map(pid: pid_t) -> Result<IdMap> {
+    id_map_file(&format!("/proc/{}/gid_map", pid))
+}
+
+/// Returns the group ID mapping of the user namespace of the current process.
+pub fn gid_map_self() -> Result<IdMap> {
+    id_map_file("/proc/self/gid_map")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdMap, IdMapRange, gid_map_self, uid_map_self};
+
+    #[test]
+    fn test_parse_id_map() {
+        let map = IdMap::parse(b"         0          0 4294967295\n").unwrap();
+        assert_eq!(vec![IdMapRange { inside: 0, outside: 0, count: u32::MAX }], map.ranges);
+        assert_eq!(IdMap::identity(), map);
+        assert!(map.is_identity());
+        assert_eq!(Some(u32::MAX - 1), map.to_inside(u32::MAX - 1));
+
+        // A rootless container, with its root mapped to the user who started it.
+        let map = IdMap::parse(b"         0       1000          1\n         1     100000      65536\n").unwrap();
+        assert!(!map.is_identity());
+        assert_eq!(2, map.ranges.len());
+        assert_eq!(Some(0), map.to_inside(1000));
+        assert_eq!(Some(1000), map.to_outside(0));
+        // Inside a range, and on its boundaries.
+        assert_eq!(Some(1), map.to_inside(100000));
+        assert_eq!(Some(1001), map.to_inside(101000));
+        assert_eq!(Some(65536), map.to_inside(165535));
+        assert_eq!(Some(165535), map.to_outside(65536));
+        // Outside all ranges.
+        assert_eq!(None, map.to_inside(165536));
+        assert_eq!(None, map.to_inside(99999));
+        assert_eq!(None, map.to_inside(1001));
+        assert_eq!(None, map.to_outside(65537));
+
+        // A namespace whose map has not been written yet.
+        assert_eq!(IdMap::default(), IdMap::parse(b"").unwrap());
+        assert_eq!(None, IdMap::default().to_inside(0));
+
+        assert!(IdMap::parse(b"0 0\n").is_err());
+        assert!(IdMap::parse(b"0 0 1 2\n").is_err());
+        assert!(IdMap::parse(b"0 -1 1\n").is_err());
+    }
+
+    #[test]
+    fn test_id_map() {
+        uid_map_self().unwrap();
+        gid_map_self().unwrap();
+    }
+}
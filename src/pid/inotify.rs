@@ -0,0 +1,124 @@
+//! Inotify usage of a process from `/proc/[pid]/fd/` and `/proc/[pid]/fdinfo/`.
+
+use std::io::{ErrorKind, Result};
+use std::path::Path;
+
+use parsers::read_to_end;
+use platform::{self, pid_t};
+
+/// The inotify instances of a process, and their watches.
+///
+/// The watches of every process of a user count towards `sys::fs::inotify_max_user_watches`, and
+/// the instances towards `sys::fs::inotify_max_user_instances`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InotifyUsage {
+    /// The number of file descriptors referring to an inotify instance.
+    ///
+    /// An instance shared by several file descriptors, such as after `dup(2)`, is counted once per
+    /// file descriptor.
+    pub instances: u32,
+    /// The number of watches of the inotify instances.
+    pub watches: u64,
+}
+
+/// Counts the `inotify wd:` lines of the contents of an fdinfo file, one per watch.
+fn count_watches(input: &[u8]) -> u64 {
+    input.split(|&b| b == b'\n').filter(|line| line.starts_with(b"inotify wd:")).count() as u64
+}
+
+/// Adds the watches of the file descriptor with the provided fdinfo path, if it refers to an
+/// inotify instance.
+fn add_fd(usage: &mut InotifyUsage, link: &Path, fdinfo: &Path, buf: &mut Vec<u8>) -> Result<()> {
+    if platform::read_link(link)? != Path::new("anon_inode:inotify") {
+        return Ok(());
+    }
+    buf.clear();
+    let watches = count_watches(read_to_end(&mut platform::open(fdinfo)?, buf)?);
+    usage.instances += 1;
+    usage.watches += watches;
+    Ok(())
+}
+
+/// Sums the inotify instances and watches of the file descriptors of a process directory.
+///
+/// File descriptors which are closed during the scan, or whose fdinfo can not be read, are
+/// skipped.
+fn inotify_usage_dir(dir: &str) -> Result<InotifyUsage> {
+    let mut usage = InotifyUsage::default();
+    let mut buf = Vec::with_capacity(4096);
+    for entry in platform::read_dir(format!("{}/fd", dir))? {
+        let name = entry?.file_name();
+        let link = Path::new(dir).join("fd").join(&name);
+        let fdinfo = Path::new(dir).join("fdinfo").join(&name);
+        match add_fd(&mut usage, &link, &fdinfo, &mut buf) {
+            Err(ref error) if error.kind() == ErrorKind::NotFound
+                           || error.kind() == ErrorKind::PermissionDenied => continue,
+            result => result?,
+        }
+    }
+    Ok(usage)
+}
+
+/// Returns the inotify instances and watches of the process with the provided pid.
+///
+/// Reading the file descriptors of another process requires that the current process may trace
+/// it.
+pub fn inotify_usage(pid: pid_t) -> Result<InotifyUsage> {
+    inotify_usage_dir(&format!("/proc/{}", pid))
+}
+
+/// Returns the inotify instances and watches of the current process.
+pub fn inotify_usage_self() -> Result<InotifyUsage> {
+    inotify_usage_dir("/proc/self")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::process;
+
+    use libc::{self, pid_t};
+
+    use super::{count_watches, inotify_usage, inotify_usage_self};
+
+    #[test]
+    fn test_count_watches() {
+        let fdinfo = b"pos:\t0\n\
+                       flags:\t02004000\n\
+                       mnt_id:\t15\n\
+                       ino:\t1057\n\
+                       inotify wd:2 ino:1a8 sdev:fe00001 mask:fc6 ignored_mask:0 fhandle-bytes:8 \
+                       fhandle-type:1 f_handle:a801000032a1b1d5\n\
+                       inotify wd:1 ino:2 sdev:fe00001 mask:fc6 ignored_mask:0 fhandle-bytes:8 \
+                       fhandle-type:1 f_handle:0200000000000000\n";
+        assert_eq!(2, count_watches(fdinfo));
+        assert_eq!(0, count_watches(b"pos:\t0\nflags:\t02004000\nmnt_id:\t15\nino:\t1057\n"));
+        assert_eq!(0, count_watches(b""));
+    }
+
+    /// Test that an inotify instance with two watches, and one without watches, are counted.
+    #[test]
+    fn test_inotify_usage() {
+        let before = inotify_usage_self().unwrap();
+
+        let watched = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        let empty = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        assert!(watched >= 0 && empty >= 0);
+        for path in &["/", "/proc"] {
+            let path = CString::new(*path).unwrap();
+            assert!(unsafe { libc::inotify_add_watch(watched, path.as_ptr(), libc::IN_CREATE) } >= 0);
+        }
+
+        let usage = inotify_usage(process::id() as pid_t);
+        unsafe {
+            libc::close(watched);
+            libc::close(empty);
+        }
+
+        // Other tests may create instances concurrently.
+        let usage = usage.unwrap();
+        assert!(usage.instances >= before.instances + 2, "{:?} {:?}", before, usage);
+        assert!(usage.watches >= before.watches + 2, "{:?} {:?}", before, usage);
+    }
+}
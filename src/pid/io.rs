@@ -0,0 +1,345 @@
+//! Process I/O statistics from `/proc/[pid]/io`.
+
+use std::fs::File;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::Result;
+use std::str;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nom::{IResult, line_ending, not_line_ending, space};
+
+use delta::{DeltaTracker, DeltaWarning, per_sec, sample_elapsed};
+use parsers::{map_result, parse_u64, read_pid_file, read_to_end};
+use pid::Stat;
+use pid::dir::PidDir;
+use platform::{self, pid_t};
+
+/// Process I/O statistics.
+///
+/// See `man 5 proc` and `Linux/Documentation/filesystems/proc.txt`.
+///
+/// The default value has every counter set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Io {
+    /// Number of bytes the process passed to `read(2)` and similar system calls, whether or not
+    /// they required storage I/O.
+    pub rchar: u64,
+    /// Number of bytes the process passed to `write(2)` and similar system calls.
+    pub wchar: u64,
+    /// Number of read I/O operations (system calls).
+    pub syscr: u64,
+    /// Number of write I/O operations (system calls).
+    pub syscw: u64,
+    /// Number of bytes the process caused to be fetched from the storage layer.
+    pub read_bytes: u64,
+    /// Number of bytes the process caused to be sent to the storage layer.
+    pub write_bytes: u64,
+    /// Number of bytes the process caused to not be written after all, due to truncation of
+    /// dirty pagecache.
+    pub cancelled_write_bytes: u64,
+}
+
+impl Io {
+    /// Parses the contents of a `/proc/[pid]/io` file.
+    pub fn parse(input: &[u8]) -> Result<Io> {
+        map_result(input, parse_io)
+    }
+}
+
+/// Parses the label of an io line, including the trailing colon.
+named!(parse_label<&'a str>, map_res!(take_until_and_consume!(":"), str::from_utf8));
+
+/// Parses the io file format.
+///
+/// Lines which are not recognized are skipped.
+fn parse_io(input: &[u8]) -> IResult<&[u8], Io> {
+    let mut io = Io::default();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (i, label) = try_parse!(rest, parse_label);
+        let field = match label {
+            "rchar"                 => &mut io.rchar,
+            "wchar"                 => &mut io.wchar,
+            "syscr"                 => &mut io.syscr,
+            "syscw"                 => &mut io.syscw,
+            "read_bytes"            => &mut io.read_bytes,
+            "write_bytes"           => &mut io.write_bytes,
+            "cancelled_write_bytes" => &mut io.cancelled_write_bytes,
+            _ => {
+                rest = try_parse!(i, terminated!(not_line_ending, line_ending)).0;
+                continue;
+            }
+        };
+        let (i, value) = try_parse!(i, delimited!(space, parse_u64, line_ending));
+        *field = value;
+        rest = i;
+    }
+    IResult::Done(rest, io)
+}
+
+/// Parses the provided io file.
+fn io_file(file: &mut File) -> Result<Io> {
+    let mut buf = Vec::with_capacity(256); // A typical io file is about 100 bytes.
+    Io::parse(read_to_end(file, &mut buf)?)
+}
+
+/// Returns I/O statistics for the process with the provided pid.
+pub fn io(pid: pid_t) -> Result<Io> {
+    io_file(&mut platform::open(format!("/proc/{}/io", pid))?)
+}
+
+/// Returns I/O statistics for the current process.
+pub fn io_self() -> Result<Io> {
+    io_file(&mut platform::open("/proc/self/io")?)
+}
+
+/// Returns I/O statistics for the process with the provided pid, read into a buffer provided by
+/// the caller.
+///
+/// Unlike `io`, this does not allocate once `buf` has grown to fit the file. `Io` has no borrowed
+/// form, since it has no string fields.
+pub fn io_with_buf(buf: &mut Vec<u8>, pid: pid_t) -> Result<Io> {
+    Io::parse(read_pid_file(buf, pid, "io")?)
+}
+
+/// Returns I/O statistics for the process with the provided pid, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn io_async(pid: pid_t) -> impl Future<Output = Result<Io>> + Send {
+    platform::read_async(format!("/proc/{}/io", pid), Io::parse)
+}
+
+/// Returns I/O statistics for the current process, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn io_self_async() -> impl Future<Output = Result<Io>> + Send {
+    platform::read_async("/proc/self/io".to_owned(), Io::parse)
+}
+
+/// A timestamped sample of the I/O statistics of a process.
+///
+/// Samples are taken with `IoRate::sample`, and compared with `IoSample::delta`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IoSample {
+    /// The sampled process.
+    pub pid: pid_t,
+    /// The start time of the sampled process in clock ticks after boot, which distinguishes it
+    /// from a later process reusing the same pid.
+    pub start_time: u64,
+    /// The I/O statistics of the process.
+    pub io: Io,
+    /// When the sample was taken.
+    pub timestamp: Instant,
+}
+
+/// I/O rates of a process between two samples.
+///
+/// Counters which wrap between the samples are corrected for the wrap, counters which are otherwise
+/// reset are treated as unchanged, and either is flagged by `warning`. If no time elapsed between
+/// the samples every rate is zero.
+///
+/// The default value has every rate and the elapsed time set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IoRate {
+    /// Bytes per second fetched from the storage layer.
+    pub read_bytes_per_sec: f64,
+    /// Bytes per second sent to the storage layer, less the bytes whose writes were cancelled.
+    ///
+    /// Writes cancelled during the interval may have been accounted before it, so the effective
+    /// number of written bytes is clamped at zero.
+    pub write_bytes_per_sec: f64,
+    /// Bytes per second passed to read system calls.
+    pub rchar_per_sec: f64,
+    /// Bytes per second passed to write system calls.
+    pub wchar_per_sec: f64,
+    /// Read system calls per second.
+    pub syscr_per_sec: f64,
+    /// Write system calls per second.
+    pub syscw_per_sec: f64,
+    /// Wall-clock time between the samples.
+    pub elapsed: Duration,
+    /// The most severe irregularity of the counters between the samples.
+    pub warning: Option<DeltaWarning>,
+}
+
+impl IoRate {
+    /// Samples the I/O statistics of the process with the provided pid.
+    pub fn sample(pid: pid_t) -> Result<IoSample> {
+        // Both files are read through the same directory, so they are of the same process.
+        let dir = PidDir::open(pid)?;
+        let mut buf = Vec::with_capacity(1024);
+        let start_time = dir.read::<Stat>(&mut buf)?.start_time;
+        Ok(IoSample { pid, start_time, io: dir.read::<Io>(&mut buf)?, timestamp: Instant::now() })
+    }
+
+    /// Returns the I/O rates of the process with the provided pid over the provided interval.
+    ///
+    /// This blocks the current thread for the duration of the interval.
+    pub fn sample_over(pid: pid_t, interval: Duration) -> Result<IoRate> {
+        let earlier = IoRate::sample(pid)?;
+        thread::sleep(interval);
+        earlier.delta(&IoRate::sample(pid)?)
+    }
+}
+
+impl IoSample {
+    /// Returns the I/O rates of the process between this sample and a later one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `ErrorKind::InvalidInput` if the samples are of different
+    /// processes (including a process which exited and whose pid was reused), or if `later` was
+    /// taken before this sample.
+    pub fn delta(&self, later: &IoSample) -> Result<IoRate> {
+        let elapsed = sample_elapsed("I/O",
+                                     ((self.pid, self.start_time), self.timestamp),
+                                     ((later.pid, later.start_time), later.timestamp))?;
+
+        let (earlier, later) = (&self.io, &later.io);
+        let mut tracker = DeltaTracker::default();
+        let mut rate = |earlier: u64, later: u64| per_sec(tracker.delta(earlier, later).saturating(), elapsed);
+        let read_bytes_per_sec = rate(earlier.read_bytes, later.read_bytes);
+        let rchar_per_sec = rate(earlier.rchar, later.rchar);
+        let wchar_per_sec = rate(earlier.wchar, later.wchar);
+        let syscr_per_sec = rate(earlier.syscr, later.syscr);
+        let syscw_per_sec = rate(earlier.syscw, later.syscw);
+        let written = tracker.delta(earlier.write_bytes, later.write_bytes).saturating();
+        let cancelled = tracker.delta(earlier.cancelled_write_bytes, later.cancelled_write_bytes).saturating();
+
+        Ok(IoRate {
+            read_bytes_per_sec,
+            write_bytes_per_sec: per_sec(written.saturating_sub(cancelled), elapsed),
+            rchar_per_sec,
+            wchar_per_sec,
+            syscr_per_sec,
+            syscw_per_sec,
+            elapsed,
+            warning: tracker.warning,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+    use std::time::{Duration, Instant};
+
+    use libc::pid_t;
+
+    use delta::DeltaWarning;
+    use parsers::tests::{allocations, unwrap};
+    use super::{Io, IoRate, IoSample, io, io_self, io_with_buf, parse_io};
+
+    /// Test that the system io files can be parsed.
+    #[test]
+    fn test_io() {
+        io_self().unwrap();
+        io(process::id() as pid_t).unwrap();
+    }
+
+    /// Test that reading the io file into a reused buffer does not allocate.
+    #[test]
+    fn test_io_with_buf() {
+        let mut buf = Vec::with_capacity(256);
+        let (io, count) = allocations(|| io_with_buf(&mut buf, process::id() as pid_t));
+        assert!(io.unwrap().rchar > 0);
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn test_parse_io() {
+        let text = b"rchar: 3980\n\
+                     wchar: 12\n\
+                     syscr: 8\n\
+                     syscw: 1\n\
+                     read_bytes: 4096\n\
+                     write_bytes: 8192\n\
+                     cancelled_write_bytes: 4096\n";
+        assert_eq!(Io {
+                       rchar: 3980,
+                       wchar: 12,
+                       syscr: 8,
+                       syscw: 1,
+                       read_bytes: 4096,
+                       write_bytes: 8192,
+                       cancelled_write_bytes: 4096,
+                   },
+                   unwrap(parse_io(text)));
+        assert_eq!(unwrap(parse_io(text)), Io::parse(text).unwrap());
+
+        // Kernels without task I/O accounting only provide the character counts.
+        let io = unwrap(parse_io(b"rchar: 1\nwchar: 2\nsyscr: 3\nsyscw: 4\nfuture_field: 5\n"));
+        assert_eq!(Io { rchar: 1, wchar: 2, syscr: 3, syscw: 4, ..Default::default() }, io);
+    }
+
+    fn sample(io: Io, timestamp: Instant) -> IoSample {
+        IoSample { pid: 42, start_time: 1000, io, timestamp }
+    }
+
+    #[test]
+    fn test_delta() {
+        let now = Instant::now();
+        let earlier = sample(Io { rchar: 1000, wchar: 1000, syscr: 10, syscw: 10, read_bytes: 4096,
+                                  write_bytes: 4096, cancelled_write_bytes: 0 }, now);
+        let later = sample(Io { rchar: 3000, wchar: 2000, syscr: 30, syscw: 20, read_bytes: 12288,
+                                write_bytes: 12288, cancelled_write_bytes: 4096 },
+                           now + Duration::from_secs(2));
+
+        let rate = earlier.delta(&later).unwrap();
+        assert_eq!(IoRate {
+                       read_bytes_per_sec: 4096.0,
+                       write_bytes_per_sec: 2048.0,
+                       rchar_per_sec: 1000.0,
+                       wchar_per_sec: 500.0,
+                       syscr_per_sec: 10.0,
+                       syscw_per_sec: 5.0,
+                       elapsed: Duration::from_secs(2),
+                       warning: None,
+                   },
+                   rate);
+    }
+
+    #[test]
+    fn test_delta_cancelled_writes() {
+        let now = Instant::now();
+        let earlier = sample(Io { write_bytes: 8192, ..Default::default() }, now);
+        // Truncating a file cancels writes which were accounted before the earlier sample.
+        let later = sample(Io { write_bytes: 12288, cancelled_write_bytes: 8192, ..Default::default() },
+                           now + Duration::from_secs(1));
+        assert_eq!(0.0, earlier.delta(&later).unwrap().write_bytes_per_sec);
+    }
+
+    #[test]
+    fn test_delta_edge_cases() {
+        let now = Instant::now();
+        let earlier = sample(Io { rchar: 1000, ..Default::default() }, now);
+
+        let same_time = sample(Io { rchar: 2000, ..Default::default() }, now);
+        assert_eq!(IoRate::default(), earlier.delta(&same_time).unwrap());
+
+        let decreased = sample(Io::default(), now + Duration::from_secs(1));
+        let rate = earlier.delta(&decreased).unwrap();
+        assert_eq!(0.0, rate.rchar_per_sec);
+        assert_eq!(Some(DeltaWarning::Reset), rate.warning);
+
+        // The syscall counters of a 32-bit kernel wrapped.
+        let wrapped = sample(Io { rchar: 1000, syscr: 0xffff_fff0, ..Default::default() }, now);
+        let rate = wrapped.delta(&sample(Io { rchar: 1000, syscr: 0x10, ..Default::default() },
+                                        now + Duration::from_secs(1))).unwrap();
+        assert_eq!(32.0, rate.syscr_per_sec);
+        assert_eq!(Some(DeltaWarning::Wrapped), rate.warning);
+
+        assert!(decreased.delta(&earlier).is_err());
+        assert!(earlier.delta(&IoSample { pid: 43, ..decreased }).is_err());
+        assert!(earlier.delta(&IoSample { start_time: 2000, ..decreased }).is_err());
+    }
+
+    #[test]
+    fn test_sample_over() {
+        let rate = IoRate::sample_over(process::id() as pid_t, Duration::from_millis(10)).unwrap();
+        assert!(rate.elapsed >= Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,114 @@
+//! Files backing the memory mappings of a process, from `/proc/[pid]/map_files/`.
+
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use platform::{self, pid_t};
+
+/// A file-backed memory mapping, from an entry of `/proc/[pid]/map_files/`.
+///
+/// Unlike the pathname of a `MemoryMap`, the link to the backing file follows renames of the file,
+/// and refers to the file in the mount namespace of the process.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MapFileEntry {
+    /// Start address of the mapping in the process's address space.
+    pub start: u64,
+    /// End address (exclusive) of the mapping in the process's address space.
+    pub end: u64,
+    /// The target of the link, that is, the path of the backing file.
+    ///
+    /// If the file has been deleted, the kernel appends ` (deleted)` to the path.
+    pub path: PathBuf,
+}
+
+/// Parses the name of a map_files entry, e.g. `7f4c3e2a1000-7f4c3e2c3000`, into the start and end
+/// addresses of the mapping.
+fn parse_entry_name(name: &OsStr) -> Result<(u64, u64)> {
+    let error = || Error::new(ErrorKind::InvalidInput, format!("unable to parse map_files entry: {:?}", name));
+    let name = name.to_str().ok_or_else(error)?;
+    let (start, end) = name.split_once('-').ok_or_else(error)?;
+    let parse = |address: &str| {
+        if address.is_empty() || !address.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        u64::from_str_radix(address, 16).map_err(|_| error())
+    };
+    Ok((parse(start)?, parse(end)?))
+}
+
+/// Lists the entries of a map_files directory, ordered by address.
+fn map_files_dir(dir: &Path) -> Result<Vec<MapFileEntry>> {
+    let mut entries = Vec::new();
+    for entry in platform::read_dir(dir)? {
+        let entry = entry?;
+        let (start, end) = parse_entry_name(&entry.file_name())?;
+        let path = match platform::read_link(entry.path()) {
+            Ok(path) => path,
+            // The mapping was removed while the directory was read.
+            Err(ref error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        entries.push(MapFileEntry { start, end, path });
+    }
+    entries.sort_by_key(|entry| entry.start);
+    Ok(entries)
+}
+
+/// Returns the file-backed memory mappings of the process with the provided pid, and the files
+/// which back them.
+///
+/// Reading the links of another process requires that the current process may trace it. Before
+/// Linux 4.3, the directory can only be read with `CAP_SYS_ADMIN`. Either restriction is reported
+/// as an `ErrorKind::PermissionDenied` error.
+pub fn map_files(pid: pid_t) -> Result<Vec<MapFileEntry>> {
+    map_files_dir(Path::new(&format!("/proc/{}/map_files", pid)))
+}
+
+/// Returns the file-backed memory mappings of the current process, and the files which back them.
+pub fn map_files_self() -> Result<Vec<MapFileEntry>> {
+    map_files_dir(Path::new("/proc/self/map_files"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::OsStr;
+    use std::io::ErrorKind;
+    use std::process;
+
+    use libc::pid_t;
+
+    use super::{map_files, map_files_self, parse_entry_name};
+
+    #[test]
+    fn test_parse_entry_name() {
+        assert_eq!((0x400000, 0x401000), parse_entry_name(OsStr::new("400000-401000")).unwrap());
+        assert_eq!((0x7f4c3e2a1000, 0x7f4c3e2c3000),
+                   parse_entry_name(OsStr::new("7f4c3e2a1000-7f4c3e2c3000")).unwrap());
+        assert_eq!((0xffffffffff600000, 0xffffffffff601000),
+                   parse_entry_name(OsStr::new("ffffffffff600000-ffffffffff601000")).unwrap());
+
+        for name in &["", "400000", "400000-", "-401000", "400000-401000-", "+400000-401000",
+                      "40000g-401000", "10000000000000000-0"] {
+            assert_eq!(ErrorKind::InvalidInput,
+                       parse_entry_name(OsStr::new(name)).unwrap_err().kind(), "{:?}", name);
+        }
+    }
+
+    /// Test that the executable of the current process is one of its mapped files.
+    #[test]
+    fn test_map_files() {
+        let exe = env::current_exe().unwrap();
+        let entries = map_files_self().unwrap();
+        assert!(entries.iter().any(|entry| entry.path == exe), "{:?}", entries);
+        assert!(entries.iter().all(|entry| entry.start < entry.end));
+        assert!(entries.windows(2).all(|pair| pair[0].end <= pair[1].start));
+
+        let entries = map_files(process::id() as pid_t).unwrap();
+        assert!(entries.iter().any(|entry| entry.path == exe));
+
+        assert_eq!(ErrorKind::NotFound, map_files(-1).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,326 @@
+//! Process memory mappings from `/proc/[pid]/maps`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Result};
+use std::path::PathBuf;
+
+use nom::{IResult, space};
+
+use parsers::{map_result_strict, parse_u32_hex, parse_u64, parse_u64_hex};
+use platform::{self, pid_t};
+
+/// The pathname associated with a memory mapping.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MapPath {
+    /// A mapping backed by a file.
+    ///
+    /// If the file has been deleted, the kernel appends ` (deleted)` to the path.
+    Path(PathBuf),
+    /// The process's heap.
+    Heap,
+    /// The main thread's stack.
+    Stack,
+    /// The virtual dynamically linked shared object (see `vdso(7)`).
+    Vdso,
+    /// Any other pseudo-path, such as `[vvar]`, `[vsyscall]`, or (before Linux 4.5)
+    /// `[stack:<tid>]`, without the surrounding brackets.
+    Pseudo(String),
+    /// An anonymous mapping.
+    #[default]
+    Anonymous,
+}
+
+impl MapPath {
+    fn from_bytes(path: &[u8]) -> MapPath {
+        match path {
+            b"" => MapPath::Anonymous,
+            b"[heap]" => MapPath::Heap,
+            b"[stack]" => MapPath::Stack,
+            b"[vdso]" => MapPath::Vdso,
+            _ if path[0] == b'[' && path[path.len() - 1] == b']' => {
+                MapPath::Pseudo(String::from_utf8_lossy(&path[1..path.len() - 1]).into_owned())
+            }
+            _ => MapPath::Path(platform::path_from_bytes(path)),
+        }
+    }
+}
+
+/// A memory mapping of a process.
+///
+/// See `man 5 proc` and `Linux/fs/proc/task_mmu.c`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoryMap {
+    /// Start address of the mapping in the process's address space.
+    pub start: u64,
+    /// End address (exclusive) of the mapping in the process's address space.
+    pub end: u64,
+    /// Whether the mapping is readable.
+    pub read: bool,
+    /// Whether the mapping is writable.
+    pub write: bool,
+    /// Whether the mapping is executable.
+    pub execute: bool,
+    /// Whether the mapping is shared, as opposed to private (copy on write).
+    pub shared: bool,
+    /// Offset into the backing file.
+    pub offset: u64,
+    /// Major device number of the backing file.
+    pub dev_major: u32,
+    /// Minor device number of the backing file.
+    pub dev_minor: u32,
+    /// Inode of the backing file, or 0 if the mapping is not file-backed.
+    pub inode: u64,
+    /// The pathname associated with the mapping.
+    pub path: MapPath,
+}
+
+impl MemoryMap {
+    /// Parses a single line of a `/proc/[pid]/maps` file, excluding the line ending.
+    pub fn parse(input: &[u8]) -> Result<MemoryMap> {
+        map_result_strict(input, parse_map)
+    }
+}
+
+/// Parses the permissions column of a mapping, e.g. `r-xp`.
+named!(parse_perms<(bool, bool, bool, bool)>,
+       map!(take!(4), |perms: &[u8]| {
+           (perms[0] == b'r', perms[1] == b'w', perms[2] == b'x', perms[3] == b's')
+       }));
+
+/// Parses a mapping line, excluding the line ending.
+///
+/// This format is shared by the header line of each entry in `/proc/[pid]/smaps`.
+pub fn parse_map(input: &[u8]) -> IResult<&[u8], MemoryMap> {
+    let (rest, start)                          = try_parse!(input, parse_u64_hex);
+    let (rest, _)                              = try_parse!(rest, tag!("-"));
+    let (rest, end)                            = try_parse!(rest, parse_u64_hex);
+    let (rest, _)                              = try_parse!(rest, space);
+    let (rest, (read, write, execute, shared)) = try_parse!(rest, parse_perms);
+    let (rest, _)                              = try_parse!(rest, space);
+    let (rest, offset)                         = try_parse!(rest, parse_u64_hex);
+    let (rest, _)                              = try_parse!(rest, space);
+    let (rest, dev_major)                      = try_parse!(rest, parse_u32_hex);
+    let (rest, _)                              = try_parse!(rest, tag!(":"));
+    let (rest, dev_minor)                      = try_parse!(rest, parse_u32_hex);
+    let (rest, _)                              = try_parse!(rest, space);
+    let (rest, inode)                          = try_parse!(rest, parse_u64);
+
+    // The pathname is the remainder of the line after the padding, and may contain spaces.
+    let path = match rest.iter().position(|&b| b != b' ' && b != b'\t') {
+        Some(idx) => &rest[idx..],
+        None => &rest[rest.len()..],
+    };
+
+    IResult::Done(&path[path.len()..], MemoryMap {
+        start,
+        end,
+        read,
+        write,
+        execute,
+        shared,
+        offset,
+        dev_major,
+        dev_minor,
+        inode,
+        path: MapPath::from_bytes(path),
+    })
+}
+
+/// Reads the next line from `reader` into `buf`, without the line ending.
+///
+/// Returns `false` at EOF.
+pub fn read_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> Result<bool> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(false);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+    }
+    Ok(true)
+}
+
+/// An iterator over the memory mappings of a process, parsed lazily from `/proc/[pid]/maps`.
+///
+/// The iterator yields an error if a line fails to parse, after which it yields no further items.
+pub struct MapsIter {
+    reader: BufReader<File>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl MapsIter {
+    fn new(file: File) -> MapsIter {
+        MapsIter { reader: BufReader::new(file), buf: Vec::with_capacity(128), done: false }
+    }
+}
+
+impl Iterator for MapsIter {
+    type Item = Result<MemoryMap>;
+
+    fn next(&mut self) -> Option<Result<MemoryMap>> {
+        if self.done {
+            return None;
+        }
+        let result = match read_line(&mut self.reader, &mut self.buf) {
+            Ok(true) => MemoryMap::parse(&self.buf),
+            Ok(false) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => Err(error),
+        };
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+/// Returns an iterator over the memory mappings of the process with the provided pid.
+pub fn maps_iter(pid: pid_t) -> Result<MapsIter> {
+    Ok(MapsIter::new(platform::open(format!("/proc/{}/maps", pid))?))
+}
+
+/// Returns an iterator over the memory mappings of the current process.
+pub fn maps_iter_self() -> Result<MapsIter> {
+    Ok(MapsIter::new(platform::open("/proc/self/maps")?))
+}
+
+/// Returns the memory mappings of the process with the provided pid.
+pub fn maps(pid: pid_t) -> Result<Vec<MemoryMap>> {
+    maps_iter(pid)?.collect()
+}
+
+/// Returns the memory mappings of the current process.
+pub fn maps_self() -> Result<Vec<MemoryMap>> {
+    maps_iter_self()?.collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+    use std::process;
+
+    use libc::pid_t;
+
+    use parsers::tests::{fixture_file, unwrap};
+    use super::{MapPath, MapsIter, MemoryMap, maps, maps_iter_self, maps_self, parse_map};
+
+    /// Returns a synthetic maps file with the provided number of mappings.
+    pub fn maps_fixture(mappings: u64) -> Vec<u8> {
+        let mut text = Vec::new();
+        for i in 0..mappings {
+            let start = 0x7f0000000000 + i * 0x1000;
+            text.extend(format!("{:x}-{:x} r-xp 00000000 fe:00 {:<10}                 \
+                                 /usr/lib/libfixture{}.so\n",
+                                start, start + 0x1000, i + 1, i).into_bytes());
+        }
+        text
+    }
+
+    /// Test that the system maps files can be parsed.
+    #[test]
+    fn test_maps() {
+        assert!(!maps_self().unwrap().is_empty());
+        maps(process::id() as pid_t).unwrap();
+    }
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(MemoryMap {
+                       start: 0x55ab6d212000,
+                       end: 0x55ab6d218000,
+                       read: true,
+                       write: false,
+                       execute: true,
+                       shared: false,
+                       offset: 0x2000,
+                       dev_major: 0xfe,
+                       dev_minor: 0,
+                       inode: 280762,
+                       path: MapPath::Path(PathBuf::from("/usr/bin/my program")),
+                   },
+                   unwrap(parse_map(b"55ab6d212000-55ab6d218000 r-xp 00002000 fe:00 280762     \
+                                      /usr/bin/my program")));
+
+        let anonymous = unwrap(parse_map(b"7f1d8e2c1000-7f1d8e2c3000 rw-s 00000000 00:00 0 "));
+        assert!(anonymous.read && anonymous.write && !anonymous.execute && anonymous.shared);
+        assert_eq!(MapPath::Anonymous, anonymous.path);
+        assert_eq!(anonymous,
+                   MemoryMap::parse(b"7f1d8e2c1000-7f1d8e2c3000 rw-s 00000000 00:00 0 ").unwrap());
+
+        let heap = b"55ab6e0f4000-55ab6e115000 rw-p 00000000 00:00 0                          [heap]";
+        assert_eq!(MapPath::Heap, unwrap(parse_map(heap)).path);
+        let vvar = b"7ffd2b9e2000-7ffd2b9e6000 r--p 00000000 00:00 0                          [vvar]";
+        assert_eq!(MapPath::Pseudo("vvar".to_owned()), unwrap(parse_map(vvar)).path);
+        let vsyscall = b"ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0          \
+                         [vsyscall]";
+        assert_eq!(0xffffffffff601000, unwrap(parse_map(vsyscall)).end);
+    }
+
+    #[test]
+    fn test_maps_iter() {
+        let maps: Vec<MemoryMap> = MapsIter::new(fixture_file(&maps_fixture(100)))
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(100, maps.len());
+        assert_eq!(0x7f0000063000, maps[99].start);
+        assert_eq!(MapPath::Path(PathBuf::from("/usr/lib/libfixture99.so")), maps[99].path);
+
+        // The current process's stack is always mapped.
+        assert!(maps_iter_self().unwrap().any(|map| map.unwrap().path == MapPath::Stack));
+    }
+
+    #[test]
+    fn test_maps_iter_error() {
+        let mut text = maps_fixture(2);
+        text.extend(b"not a mapping\n".iter());
+        text.extend(maps_fixture(2));
+
+        let mut iter = MapsIter::new(fixture_file(&text));
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
+
+#[cfg(all(test, rustc_nightly))]
+mod benches {
+    extern crate test;
+
+    use std::fs::File;
+    use std::io::{Seek, SeekFrom};
+
+    use parsers::tests::fixture_file;
+    use super::{MapsIter, MemoryMap, maps_self};
+    use super::tests::maps_fixture;
+
+    /// Returns an iterator over the start of the fixture file.
+    fn rewind(file: &mut File) -> MapsIter {
+        file.seek(SeekFrom::Start(0)).unwrap();
+        MapsIter::new(file.try_clone().unwrap())
+    }
+
+    #[bench]
+    fn bench_maps(b: &mut test::Bencher) {
+        b.iter(|| test::black_box(maps_self()));
+    }
+
+    /// Collects every mapping of a large maps file, then searches for one.
+    #[bench]
+    fn bench_maps_vec_find(b: &mut test::Bencher) {
+        let mut file = fixture_file(&maps_fixture(10000));
+        b.iter(|| {
+            let maps: Vec<MemoryMap> = rewind(&mut file).collect::<Result<_, _>>().unwrap();
+            test::black_box(maps.into_iter().find(|map| map.inode == 10))
+        });
+    }
+
+    /// Searches a large maps file for a mapping near the beginning of the file.
+    #[bench]
+    fn bench_maps_iter_find(b: &mut test::Bencher) {
+        let mut file = fixture_file(&maps_fixture(10000));
+        b.iter(|| test::black_box(rewind(&mut file).map(Result::unwrap).find(|map| map.inode == 10)));
+    }
+}
@@ -0,0 +1,429 @@
+//! Process memory usage summaries from `/proc/[pid]/smaps_rollup`, `/proc/[pid]/smaps` and
+//! `/proc/[pid]/status`.
+
+use std::fs::File;
+use std::io::{ErrorKind, Result};
+
+use parsers::read_to_end;
+use pid::{SmapsEntry, Status, smaps_iter, smaps_iter_self};
+use platform::{self, pid_t};
+use sysconf::page_size;
+
+/// The file from which a `MemoryUsage` was computed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MemoryUsageSource {
+    /// `/proc/[pid]/smaps_rollup`, in which the kernel sums the mappings (since Linux 4.14).
+    #[default]
+    SmapsRollup,
+    /// `/proc/[pid]/smaps`, whose mappings are summed by the crate.
+    Smaps,
+}
+
+/// Memory usage of a process, summed over all of its mappings.
+///
+/// All sizes are in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoryUsage {
+    /// Resident set size.
+    pub rss: u64,
+    /// Proportional set size: the resident size, with each page shared by N processes counted as
+    /// 1/N of a page.
+    pub pss: u64,
+    /// Unique set size: the resident pages private to the process, which would be freed if the
+    /// process exited.
+    pub uss: u64,
+    /// Resident pages shared with other processes.
+    pub shared: u64,
+    /// Modified resident pages private to the process.
+    pub private_dirty: u64,
+    /// Modified resident pages shared with other processes.
+    pub shared_dirty: u64,
+    /// Anonymous memory which has been swapped out.
+    pub swap: u64,
+    /// Proportional swap size, analogous to `pss` (since Linux 4.3).
+    pub swap_pss: u64,
+    /// The file from which the usage was computed.
+    pub source: MemoryUsageSource,
+}
+
+impl MemoryUsage {
+    /// Returns the modified resident pages, which must be written back before they can be
+    /// reclaimed.
+    pub fn dirty(&self) -> u64 {
+        self.private_dirty + self.shared_dirty
+    }
+
+    /// Adds the memory usage of a mapping, or of the rollup of all mappings.
+    fn add(&mut self, entry: &SmapsEntry) {
+        self.rss += entry.rss;
+        self.pss += entry.pss;
+        self.uss += entry.private_clean + entry.private_dirty;
+        self.shared += entry.shared_clean + entry.shared_dirty;
+        self.private_dirty += entry.private_dirty;
+        self.shared_dirty += entry.shared_dirty;
+        self.swap += entry.swap;
+        self.swap_pss += entry.swap_pss;
+    }
+
+    /// Sums the memory usage of the mappings of a process.
+    fn from_entries<I>(entries: I, source: MemoryUsageSource) -> Result<MemoryUsage>
+    where I: IntoIterator<Item = Result<SmapsEntry>> {
+        let mut usage = MemoryUsage { source, ..MemoryUsage::default() };
+        for entry in entries {
+            usage.add(&entry?);
+        }
+        Ok(usage)
+    }
+}
+
+/// Computes the memory usage from the opened `smaps_rollup` file, or from `smaps` if the rollup
+/// file does not exist.
+fn memory_usage_file<F, I>(rollup: Result<File>, smaps: F) -> Result<MemoryUsage>
+where F: FnOnce() -> Result<I>,
+      I: IntoIterator<Item = Result<SmapsEntry>> {
+    match rollup {
+        Ok(mut file) => {
+            let mut buf = Vec::with_capacity(1024); // A typical rollup file is about 700 bytes.
+            let entry = SmapsEntry::parse(read_to_end(&mut file, &mut buf)?)?;
+            MemoryUsage::from_entries(Some(Ok(entry)), MemoryUsageSource::SmapsRollup)
+        }
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            MemoryUsage::from_entries(smaps()?, MemoryUsageSource::Smaps)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Returns the memory usage of the process with the provided pid.
+///
+/// The usage is read from `/proc/[pid]/smaps_rollup` if the kernel provides it, and is otherwise
+/// summed over the mappings in `/proc/[pid]/smaps`.
+pub fn memory_usage(pid: pid_t) -> Result<MemoryUsage> {
+    memory_usage_file(platform::open(format!("/proc/{}/smaps_rollup", pid)), || smaps_iter(pid))
+}
+
+/// Returns the memory usage of the current process.
+///
+/// The usage is read from `/proc/self/smaps_rollup` if the kernel provides it, and is otherwise
+/// summed over the mappings in `/proc/self/smaps`.
+pub fn memory_usage_self() -> Result<MemoryUsage> {
+    memory_usage_file(platform::open("/proc/self/smaps_rollup"), smaps_iter_self)
+}
+
+/// The file from which a `SwapUsage` was read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SwapUsageSource {
+    /// The `VmSwap` field of `/proc/[pid]/status` (since Linux 2.6.34).
+    #[default]
+    Status,
+    /// `/proc/[pid]/smaps_rollup`.
+    SmapsRollup,
+    /// `/proc/[pid]/smaps`, whose mappings are summed by the crate.
+    Smaps,
+}
+
+/// The swap usage of a process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SwapUsage {
+    /// Anonymous memory which has been swapped out, in bytes.
+    pub swap: u64,
+    /// The file from which the usage was read.
+    pub source: SwapUsageSource,
+}
+
+/// Returns the `VmSwap` field of the contents of a status file, or `None` if the kernel does not
+/// report it.
+fn status_swap(input: &[u8]) -> Result<Option<u64>> {
+    let status = Status::parse(input)?;
+    let reported = input.split(|&b| b == b'\n').any(|line| line.starts_with(b"VmSwap:"));
+    Ok(if reported { Some(status.vm_swap) } else { None })
+}
+
+/// Reads the swap usage from the opened status file, or from the memory usage if the status file
+/// has no `VmSwap` field.
+fn swap_usage_file<F>(mut status: File, memory_usage: F) -> Result<SwapUsage>
+where F: FnOnce() -> Result<MemoryUsage> {
+    let mut buf = Vec::with_capacity(2048); // A typical status file is about 1000 bytes.
+    if let Some(swap) = status_swap(read_to_end(&mut status, &mut buf)?)? {
+        return Ok(SwapUsage { swap, source: SwapUsageSource::Status });
+    }
+    let usage = memory_usage()?;
+    let source = match usage.source {
+        MemoryUsageSource::SmapsRollup => SwapUsageSource::SmapsRollup,
+        MemoryUsageSource::Smaps => SwapUsageSource::Smaps,
+    };
+    Ok(SwapUsage { swap: usage.swap, source })
+}
+
+/// Returns the swap usage of the process with the provided pid.
+///
+/// The usage is read from the cheapest source which reports it: the `VmSwap` field of
+/// `/proc/[pid]/status`, then `/proc/[pid]/smaps_rollup`, then the sum of the mappings in
+/// `/proc/[pid]/smaps`.
+pub fn swap_usage(pid: pid_t) -> Result<SwapUsage> {
+    swap_usage_file(platform::open(format!("/proc/{}/status", pid))?, || memory_usage(pid))
+}
+
+/// Returns the swap usage of the current process, see `swap_usage`.
+pub fn swap_usage_self() -> Result<SwapUsage> {
+    swap_usage_file(platform::open("/proc/self/status")?, memory_usage_self)
+}
+
+/// Huge page usage of a process, summed over all of its mappings.
+///
+/// All sizes are in bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThpUsage {
+    /// Anonymous memory backed by transparent huge pages.
+    pub anon_huge_pages: u64,
+    /// Shared memory backed by transparent huge pages (since Linux 4.8).
+    pub shmem_pmd_mapped: u64,
+    /// Page cache backed by transparent huge pages (since Linux 5.4).
+    pub file_pmd_mapped: u64,
+    /// Number of mappings with anonymous memory backed by transparent huge pages.
+    pub anon_huge_mappings: u64,
+    /// Memory backed by hugetlbfs pages, shared or private (since Linux 4.4).
+    pub hugetlb: u64,
+    /// The distinct page sizes of the hugetlbfs mappings, from `KernelPageSize`, sorted.
+    pub hugetlb_page_sizes: Vec<u64>,
+}
+
+impl ThpUsage {
+    /// Returns the memory backed by transparent huge pages.
+    pub fn thp(&self) -> u64 {
+        self.anon_huge_pages + self.shmem_pmd_mapped + self.file_pmd_mapped
+    }
+
+    /// Adds the huge page usage of a mapping.
+    fn add(&mut self, entry: &SmapsEntry) {
+        self.anon_huge_pages += entry.anon_huge_pages;
+        self.shmem_pmd_mapped += entry.shmem_pmd_mapped;
+        self.file_pmd_mapped += entry.file_pmd_mapped;
+        if entry.anon_huge_pages > 0 {
+            self.anon_huge_mappings += 1;
+        }
+        let hugetlb = entry.shared_hugetlb + entry.private_hugetlb;
+        self.hugetlb += hugetlb;
+        // Only hugetlbfs mappings have a kernel page size above the base page size.
+        if hugetlb > 0 || entry.kernel_page_size > page_size() {
+            if let Err(idx) = self.hugetlb_page_sizes.binary_search(&entry.kernel_page_size) {
+                self.hugetlb_page_sizes.insert(idx, entry.kernel_page_size);
+            }
+        }
+    }
+
+    /// Sums the huge page usage of the mappings of a process.
+    fn from_entries<I>(entries: I) -> Result<ThpUsage>
+    where I: IntoIterator<Item = Result<SmapsEntry>> {
+        let mut usage = ThpUsage::default();
+        for entry in entries {
+            usage.add(&entry?);
+        }
+        Ok(usage)
+    }
+}
+
+/// Returns the transparent and hugetlbfs huge page usage of the process with the provided pid,
+/// summed over the mappings in `/proc/[pid]/smaps`.
+pub fn thp_usage(pid: pid_t) -> Result<ThpUsage> {
+    ThpUsage::from_entries(smaps_iter(pid)?)
+}
+
+/// Returns the transparent and hugetlbfs huge page usage of the current process.
+pub fn thp_usage_self() -> Result<ThpUsage> {
+    ThpUsage::from_entries(smaps_iter_self()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind, Result};
+    use std::process;
+
+    use libc::pid_t;
+
+    use parsers::tests::fixture_file;
+    use pid::{SmapsEntry, smaps_iter_self};
+    use pid::smaps::tests::smaps_fixture;
+    use super::{MemoryUsage, MemoryUsageSource, SwapUsage, SwapUsageSource, ThpUsage, memory_usage,
+                memory_usage_file, memory_usage_self, swap_usage, swap_usage_file, swap_usage_self, thp_usage,
+                thp_usage_self};
+
+    /// The status file of a process with 48 kB swapped out, without the fields unrelated to memory.
+    const STATUS: &[u8] = b"Name:\tcat\n\
+                            VmPeak:\t    5464 kB\n\
+                            VmSize:\t    5464 kB\n\
+                            VmRSS:\t     1388 kB\n\
+                            VmSwap:\t      48 kB\n\
+                            Threads:\t1\n";
+
+    /// The smaps of the same process, with 16 kB of each of three mappings swapped out.
+    fn smaps_entries() -> Vec<Result<SmapsEntry>> {
+        let fixture = String::from_utf8(smaps_fixture(1)).unwrap()
+            .replace("Swap:                  0 kB", "Swap:                 16 kB")
+            .replace("Private_Dirty:         0 kB", "Private_Dirty:         4 kB");
+        let entry = SmapsEntry::parse(fixture.as_bytes()).unwrap();
+        vec![Ok(entry.clone()), Ok(entry.clone()), Ok(entry)]
+    }
+
+    /// An smaps fallback which must not be used.
+    fn no_smaps() -> Result<Vec<Result<SmapsEntry>>> {
+        panic!("unexpected fallback to smaps");
+    }
+
+    /// Checks the invariants which hold by construction.
+    fn assert_consistent(usage: &MemoryUsage) {
+        assert!(usage.uss <= usage.pss, "{:?}", usage);
+        assert!(usage.pss <= usage.rss, "{:?}", usage);
+        assert!(usage.uss + usage.shared <= usage.rss, "{:?}", usage);
+        assert!(usage.swap_pss <= usage.swap, "{:?}", usage);
+    }
+
+    #[test]
+    fn test_memory_usage_rollup() {
+        let rollup = b"55be28623000-7ffd09098000 ---p 00000000 00:00 0                          [rollup]\n\
+                       Rss:                1388 kB\n\
+                       Pss:                 419 kB\n\
+                       Pss_Dirty:           104 kB\n\
+                       Shared_Clean:       1240 kB\n\
+                       Shared_Dirty:          0 kB\n\
+                       Private_Clean:        44 kB\n\
+                       Private_Dirty:       104 kB\n\
+                       Swap:                 16 kB\n\
+                       SwapPss:               8 kB\n\
+                       Locked:                0 kB\n";
+        let usage = memory_usage_file(Ok(fixture_file(rollup)), no_smaps).unwrap();
+        assert_eq!(MemoryUsage {
+                       rss: 1388 * 1024,
+                       pss: 419 * 1024,
+                       uss: 148 * 1024,
+                       shared: 1240 * 1024,
+                       private_dirty: 104 * 1024,
+                       shared_dirty: 0,
+                       swap: 16 * 1024,
+                       swap_pss: 8 * 1024,
+                       source: MemoryUsageSource::SmapsRollup,
+                   },
+                   usage);
+        assert_eq!(104 * 1024, usage.dirty());
+        assert_consistent(&usage);
+    }
+
+    #[test]
+    fn test_memory_usage_smaps() {
+        let missing = Err(Error::from(ErrorKind::NotFound));
+        let entry = SmapsEntry::parse(&smaps_fixture(1)).unwrap();
+        let entries = vec![Ok(entry.clone()), Ok(entry.clone()), Ok(entry)];
+        let usage = memory_usage_file(missing, || Ok(entries)).unwrap();
+        assert_eq!(MemoryUsage {
+                       rss: 3 * 4096,
+                       pss: 3 * 2048,
+                       uss: 0,
+                       shared: 3 * 4096,
+                       private_dirty: 0,
+                       shared_dirty: 0,
+                       swap: 0,
+                       swap_pss: 0,
+                       source: MemoryUsageSource::Smaps,
+                   },
+                   usage);
+
+        // Other errors are not hidden by the fallback.
+        let denied = Err(Error::from(ErrorKind::PermissionDenied));
+        assert_eq!(ErrorKind::PermissionDenied,
+                   memory_usage_file(denied, no_smaps).unwrap_err().kind());
+    }
+
+    /// Test that the memory usage of the current process is consistent, whether it is read from the
+    /// rollup or summed over the mappings.
+    #[test]
+    fn test_memory_usage() {
+        let usage = memory_usage_self().unwrap();
+        assert!(usage.rss > 0);
+        assert_consistent(&usage);
+        assert_consistent(&memory_usage(process::id() as pid_t).unwrap());
+
+        let summed = MemoryUsage::from_entries(smaps_iter_self().unwrap(), MemoryUsageSource::Smaps)
+            .unwrap();
+        assert!(summed.rss > 0);
+        assert_consistent(&summed);
+    }
+
+    /// Test that the swap usage agrees whether it is read from the status file or summed over the
+    /// mappings of the same process.
+    #[test]
+    fn test_swap_usage_sources() {
+        let no_memory_usage = || -> Result<MemoryUsage> { panic!("unexpected fallback to smaps") };
+        let from_status = swap_usage_file(fixture_file(STATUS), no_memory_usage).unwrap();
+        assert_eq!(SwapUsage { swap: 48 * 1024, source: SwapUsageSource::Status }, from_status);
+
+        let missing = || Err(Error::from(ErrorKind::NotFound));
+        let from_smaps = MemoryUsage::from_entries(smaps_entries(), MemoryUsageSource::Smaps).unwrap();
+        assert_eq!(from_status.swap, from_smaps.swap);
+        assert_eq!(12 * 1024, from_smaps.dirty());
+
+        // Kernels before 2.6.34 do not report VmSwap.
+        let status = String::from_utf8(STATUS.to_vec()).unwrap().replace("VmSwap:\t      48 kB\n", "");
+        let fallback = swap_usage_file(fixture_file(status.as_bytes()),
+                                       || memory_usage_file(missing(), || Ok(smaps_entries())))
+            .unwrap();
+        assert_eq!(SwapUsage { swap: from_status.swap, source: SwapUsageSource::Smaps }, fallback);
+    }
+
+    /// Test that the swap usage of the current process can be read.
+    #[test]
+    fn test_swap_usage() {
+        let usage = swap_usage_self().unwrap();
+        assert_eq!(SwapUsageSource::Status, usage.source);
+        assert_eq!(SwapUsageSource::Status, swap_usage(process::id() as pid_t).unwrap().source);
+    }
+
+    /// Test the huge page usage of a process with a transparent huge page mapping, and hugetlbfs
+    /// mappings of 2 MiB and 1 GiB pages.
+    #[test]
+    fn test_thp_usage() {
+        let fixture = String::from_utf8(smaps_fixture(3)).unwrap();
+        let mut entries: Vec<SmapsEntry> = fixture.split("VmFlags: rd ex mr mw me \n")
+                                                  .filter(|entry| !entry.is_empty())
+                                                  .map(|entry| SmapsEntry::parse(entry.as_bytes()).unwrap())
+                                                  .collect();
+        entries[0].anon_huge_pages = 4 << 20;
+        entries[1].kernel_page_size = 2 << 20;
+        entries[1].mmu_page_size = 2 << 20;
+        entries[1].shared_hugetlb = 8 << 20;
+        entries[2].kernel_page_size = 1 << 30;
+        entries[2].mmu_page_size = 1 << 30;
+        entries[2].private_hugetlb = 1 << 30;
+        let mut thp = entries[0].clone();
+        thp.anon_huge_pages = 2 << 20;
+        thp.shmem_pmd_mapped = 2 << 20;
+        entries.push(thp);
+
+        let usage = ThpUsage::from_entries(entries.into_iter().map(Ok)).unwrap();
+        assert_eq!(ThpUsage {
+                       anon_huge_pages: 6 << 20,
+                       shmem_pmd_mapped: 2 << 20,
+                       file_pmd_mapped: 0,
+                       anon_huge_mappings: 2,
+                       hugetlb: (1 << 30) + (8 << 20),
+                       hugetlb_page_sizes: vec![2 << 20, 1 << 30],
+                   },
+                   usage);
+        assert_eq!(8 << 20, usage.thp());
+
+        let usage = ThpUsage::from_entries(vec![SmapsEntry::parse(&smaps_fixture(1))]).unwrap();
+        assert_eq!(ThpUsage::default(), usage);
+    }
+
+    /// Test that the huge page usage of the current process can be read.
+    #[test]
+    fn test_thp_usage_self() {
+        let usage = thp_usage_self().unwrap();
+        assert_eq!(usage.anon_huge_mappings == 0, usage.anon_huge_pages == 0);
+        thp_usage(process::id() as pid_t).unwrap();
+    }
+}
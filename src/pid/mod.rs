@@ -1,60 +1,341 @@
 //! Process-specific information from `/proc/[pid]/`.
 
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+mod auxv;
+mod batch;
+mod capabilities;
+mod cgroup;
+mod cpu;
+mod cpuset;
+mod ctxt_switches;
 mod cwd;
+mod descendants;
+mod dir;
+mod environ;
+mod fd;
+mod fdinfo;
+mod flags;
+mod id_map;
+mod inotify;
+mod io;
 mod limits;
+mod map_files;
+mod maps;
+mod memory;
 mod mountinfo;
+mod namespaces;
+pub mod net;
+mod oom;
+mod ownership;
+mod reap;
+mod schedstat;
+mod smaps;
 mod stat;
 mod statm;
 mod status;
+mod threads;
+mod wait;
 
+pub use pid::auxv::{Auxv, AuxvEntry, AuxvKey, auxv, auxv_self};
+pub use pid::batch::{Batch, PidFile, batch, batch_into};
+pub use pid::capabilities::{Capabilities, CapabilitiesIter, Capability};
+pub use pid::cgroup::{Cgroup, cgroup, cgroup_self};
+pub use pid::cpu::{CpuSample, CpuUsage};
+pub use pid::cpuset::{CpuSet, CpuSetIter};
+pub use pid::ctxt_switches::{CtxtSwitchRate, CtxtSwitchSample, CtxtSwitches, ctxt_switches, ctxt_switches_self};
 pub use pid::cwd::{cwd, cwd_self};
-pub use pid::limits::{Limit, Limits, limits, limits_self};
+pub use pid::descendants::{Descendants, descendants};
+pub use pid::environ::{environ_get, environ_get_self, environ_keys, environ_keys_self};
+pub use pid::fd::{FdHeadroom, fd_count, fd_count_self, fd_headroom, fd_headroom_self};
+pub use pid::fdinfo::{FdInfo, FdInfoExtension, SignalSet, fdinfo, fdinfo_self};
+pub use pid::flags::{ProcFlag, ProcFlags, ProcFlagsIter};
+pub use pid::id_map::{IdMap, IdMapRange, gid_map, gid_map_self, uid_map, uid_map_self};
+pub use pid::inotify::{InotifyUsage, inotify_usage, inotify_usage_self};
+pub use pid::io::{Io, IoRate, IoSample, io, io_self, io_with_buf};
+pub use pid::limits::{ChangedBounds, Limit, LimitChange, Limits, Resource, limits, limits_self, raise_nofile_limit,
+                      set_limit};
+pub use pid::map_files::{MapFileEntry, map_files, map_files_self};
+pub use pid::maps::{MapPath, MapsIter, MemoryMap, maps, maps_iter, maps_iter_self, maps_self};
+pub use pid::memory::{MemoryUsage, MemoryUsageSource, SwapUsage, SwapUsageSource, ThpUsage, memory_usage,
+                      memory_usage_self, swap_usage, swap_usage_self, thp_usage, thp_usage_self};
 pub use pid::mountinfo::{Mountinfo, mountinfo, mountinfo_self};
-pub use pid::statm::{Statm, statm, statm_self};
-pub use pid::status::{SeccompMode, Status, status, status_self};
-pub use pid::stat::{Stat, stat, stat_self};
+pub use pid::namespaces::{Namespace, namespace_inode, namespace_inode_self};
+pub use pid::oom::{OOM_SCORE_ADJ_MAX, OOM_SCORE_ADJ_MIN, Oom, oom, oom_score, oom_score_adj,
+                   oom_score_adj_self, oom_score_self, oom_self};
+pub use pid::ownership::{Ids, Ownership, ownership};
+pub use pid::reap::{ExitSnapshot, snapshot_before_reap};
+pub use pid::schedstat::{Schedstat, schedstat, schedstat_self};
+pub use pid::smaps::{Residency, SmapsEntry, SmapsIter, file_cache_residency, file_cache_residency_self, smaps,
+                     smaps_at, smaps_at_self, smaps_for_path, smaps_for_path_self, smaps_iter, smaps_iter_self,
+                     smaps_self};
+pub use pid::statm::{Statm, statm, statm_self, statm_with_buf};
+pub use pid::status::{SeccompMode, SpeculationIndirectBranch, SpeculationStoreBypass, Status, status, status_self};
+pub use pid::stat::{CpuTimeSummary, Stat, StatRef, Tty, age, age_self, cpu_time, cpu_time_self, start_time,
+                    start_time_self, stat, stat_self, stat_with_buf};
+pub use pid::threads::{ThreadCpu, ThreadStates, ThreadSummary, thread_summary, thread_summary_self};
+pub use pid::wait::{WaitOutcome, wait_for_exit};
+
+#[cfg(feature = "async")]
+pub use pid::io::{io_async, io_self_async};
+#[cfg(feature = "async")]
+pub use pid::limits::{limits_async, limits_self_async};
+#[cfg(feature = "async")]
+pub use pid::mountinfo::{mountinfo_async, mountinfo_self_async};
+#[cfg(feature = "async")]
+pub use pid::stat::{stat_async, stat_self_async};
+#[cfg(feature = "async")]
+pub use pid::statm::{statm_async, statm_self_async};
+#[cfg(feature = "async")]
+pub use pid::status::{status_async, status_self_async};
+
+// The probes which read from a fixture tree, for the golden tests of `fixtures`.
+#[cfg(test)]
+pub use pid::dir::PidDir;
+#[cfg(test)]
+pub use pid::limits::limits_in;
 
 /// The state of a process.
-#[derive(Debug, PartialEq, Eq, Hash)]
+///
+/// The same states are reported by `/proc/[pid]/stat`, as a single character, and by
+/// `/proc/[pid]/status`, as a character followed by a description, so states from either source
+/// can be compared.
+///
+/// The default state is `Running`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum State {
-    /// Running.
+    /// Running (`R`).
+    #[default]
     Running,
-    /// Sleeping in an interruptible wait.
+    /// Sleeping in an interruptible wait (`S`).
     Sleeping,
-    /// Waiting in uninterruptible disk sleep.
+    /// Waiting in uninterruptible disk sleep (`D`).
     Waiting,
-    /// Zombie.
+    /// Zombie (`Z`).
     Zombie,
-    /// Stopped (on a signal) or (before Linux 2.6.33) trace stopped.
+    /// Stopped (on a signal) or (before Linux 2.6.33) trace stopped (`T`).
     Stopped,
-    /// trace stopped.
+    /// Trace stopped (`t`).
     ///
     /// Linux 2.6.33 onward.
     TraceStopped,
-    /// Paging.
+    /// Paging (`W`).
     ///
-    /// Only before linux 2.6.0.
+    /// Only before Linux 2.6.0.
     Paging,
-    /// Dead.
-    ///
-    /// Linux 2.6.33 to 3.13 only.
+    /// Dead (`X`, or `x` in Linux 2.6.33 to 3.13).
     Dead,
-    /// Wakekill.
+    /// Wakekill (`K`).
     ///
     /// Linux 2.6.33 to 3.13 only.
     Wakekill,
-    /// Waking.
+    /// Waking (`W`).
     ///
-    /// Linux 2.6.33 to 3.13 only.
+    /// Linux 2.6.33 to 3.13 only. The single character state in `/proc/[pid]/stat` cannot be
+    /// distinguished from `Paging`, and is decoded as `Paging`.
     Waking,
-    /// Parked.
+    /// Parked (`P`).
     ///
-    /// Linux 3.9 to 3.13 only.
+    /// Linux 3.9 to 3.13, and Linux 4.14 onward.
     Parked,
+    /// Idle kernel thread (`I`).
+    ///
+    /// Linux 4.14 onward.
+    Idle,
+    /// A state which is not known to this library, e.g. one introduced by a newer kernel.
+    Other(char),
 }
 
-impl Default for State {
-    fn default() -> State {
-        State::Running
+impl State {
+    /// Returns the state corresponding to the single character used by `/proc/[pid]/stat`.
+    pub fn from_char(c: char) -> State {
+        match c {
+            'R' => State::Running,
+            'S' => State::Sleeping,
+            'D' => State::Waiting,
+            'Z' => State::Zombie,
+            'T' => State::Stopped,
+            't' => State::TraceStopped,
+            'W' => State::Paging,
+            'X' | 'x' => State::Dead,
+            'K' => State::Wakekill,
+            'P' => State::Parked,
+            'I' => State::Idle,
+            c => State::Other(c),
+        }
+    }
+
+    /// Returns the single character used for the state by `/proc/[pid]/stat`.
+    pub fn to_char(&self) -> char {
+        match *self {
+            State::Running => 'R',
+            State::Sleeping => 'S',
+            State::Waiting => 'D',
+            State::Zombie => 'Z',
+            State::Stopped => 'T',
+            State::TraceStopped => 't',
+            State::Paging | State::Waking => 'W',
+            State::Dead => 'X',
+            State::Wakekill => 'K',
+            State::Parked => 'P',
+            State::Idle => 'I',
+            State::Other(c) => c,
+        }
+    }
+
+    /// Returns `true` if the process is running or runnable.
+    pub fn is_running(&self) -> bool {
+        *self == State::Running
+    }
+
+    /// Returns `true` if the process is sleeping, in either an interruptible or uninterruptible
+    /// wait, or is an idle kernel thread.
+    pub fn is_sleeping(&self) -> bool {
+        matches!(*self, State::Sleeping | State::Waiting | State::Idle)
+    }
+
+    /// Returns `true` if the process is stopped by a signal or by a tracer.
+    pub fn is_stopped(&self) -> bool {
+        matches!(*self, State::Stopped | State::TraceStopped)
+    }
+
+    /// Returns `true` if the process has exited, but has not yet been reaped by its parent.
+    pub fn is_zombie(&self) -> bool {
+        *self == State::Zombie
+    }
+
+    /// Returns `true` if the process is dead.
+    pub fn is_dead(&self) -> bool {
+        *self == State::Dead
+    }
+}
+
+/// Decodes the single character state used by `/proc/[pid]/stat`.
+///
+/// Fails if the byte is not a printable ASCII character.
+impl TryFrom<u8> for State {
+    type Error = Error;
+
+    fn try_from(c: u8) -> Result<State> {
+        if c.is_ascii_graphic() {
+            Ok(State::from_char(c as char))
+        } else {
+            Err(Error::new(ErrorKind::InvalidInput, format!("invalid process state: {:?}", c)))
+        }
+    }
+}
+
+/// Parses a state in either the `/proc/[pid]/stat` format, e.g. `S`, or the `/proc/[pid]/status`
+/// format, e.g. `S (sleeping)`.
+///
+/// The description is only used to distinguish `Waking` from `Paging`.
+impl FromStr for State {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<State> {
+        let invalid = || Error::new(ErrorKind::InvalidInput, format!("invalid process state: {:?}", s));
+        let mut chars = s.chars();
+        let state = match chars.next() {
+            Some(c) if c.is_ascii_graphic() => State::from_char(c),
+            _ => return Err(invalid()),
+        };
+        match chars.as_str() {
+            "" => Ok(state),
+            " (waking)" if state == State::Paging => Ok(State::Waking),
+            description if description.starts_with(" (") && description.ends_with(')') => Ok(state),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Formats the state in the same format as `/proc/[pid]/status`, e.g. `S (sleeping)`.
+///
+/// States not known to this library are formatted as a single character.
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            State::Running      => "R (running)",
+            State::Sleeping     => "S (sleeping)",
+            State::Waiting      => "D (disk sleep)",
+            State::Zombie       => "Z (zombie)",
+            State::Stopped      => "T (stopped)",
+            State::TraceStopped => "t (tracing stop)",
+            State::Paging       => "W (paging)",
+            State::Dead         => "X (dead)",
+            State::Wakekill     => "K (wakekill)",
+            State::Waking       => "W (waking)",
+            State::Parked       => "P (parked)",
+            State::Idle         => "I (idle)",
+            State::Other(c)     => return write!(f, "{}", c),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::State;
+
+    #[test]
+    fn test_display_state() {
+        assert_eq!("R (running)", State::Running.to_string());
+        assert_eq!("D (disk sleep)", State::Waiting.to_string());
+        assert_eq!("t (tracing stop)", State::TraceStopped.to_string());
+        assert_eq!("I (idle)", State::Idle.to_string());
+        assert_eq!("N", State::Other('N').to_string());
+    }
+
+    /// Test the mapping of state characters, including those of older kernels.
+    #[test]
+    fn test_state_from_char() {
+        let states = [('R', State::Running),
+                      ('S', State::Sleeping),
+                      ('D', State::Waiting),
+                      ('Z', State::Zombie),
+                      ('T', State::Stopped),
+                      ('t', State::TraceStopped),  // Linux 2.6.33 onward.
+                      ('W', State::Paging),        // Before Linux 2.6.0, and waking in 2.6.33 to 3.13.
+                      ('X', State::Dead),
+                      ('x', State::Dead),          // Linux 2.6.33 to 3.13.
+                      ('K', State::Wakekill),      // Linux 2.6.33 to 3.13.
+                      ('P', State::Parked),        // Linux 3.9 to 3.13, and 4.14 onward.
+                      ('I', State::Idle),          // Linux 4.14 onward.
+                      ('N', State::Other('N'))];
+        for &(c, state) in &states {
+            assert_eq!(state, State::from_char(c));
+            assert_eq!(state, State::try_from(c as u8).unwrap());
+            assert_eq!(state, c.to_string().parse().unwrap());
+        }
+        assert_eq!('W', State::Waking.to_char());
+        assert_eq!('X', State::Dead.to_char());
+        assert!(State::try_from(b' ').is_err());
+        assert!(State::try_from(0xff).is_err());
+    }
+
+    #[test]
+    fn test_parse_state() {
+        for &state in &[State::Running, State::Waiting, State::TraceStopped, State::Paging,
+                        State::Waking, State::Parked, State::Idle, State::Other('N')] {
+            assert_eq!(state, state.to_string().parse().unwrap());
+        }
+        assert_eq!(State::Dead, "x (dead)".parse().unwrap());
+        assert!("".parse::<State>().is_err());
+        assert!("SS".parse::<State>().is_err());
+        assert!("S (sleeping".parse::<State>().is_err());
+    }
+
+    #[test]
+    fn test_state_predicates() {
+        assert!(State::Running.is_running());
+        assert!(State::Waiting.is_sleeping() && State::Idle.is_sleeping());
+        assert!(State::TraceStopped.is_stopped());
+        assert!(State::Zombie.is_zombie() && !State::Zombie.is_dead());
+        assert!(State::Dead.is_dead());
+        assert!(!State::Other('N').is_running());
     }
 }
@@ -1,30 +1,35 @@
 //! Information about mounts from `/proc/[pid]/mountinfo`.
 
 use std::fs::File;
-use std::io::{BufRead, BufReader, Result};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::Result;
 use std::path::PathBuf;
 use std::str::{self, FromStr};
 use std::io::{Error, ErrorKind};
 
-use libc::pid_t;
 use nom::{Err, IResult, Needed};
 use nom::ErrorKind::Tag;
 
-use parsers::{map_result, parse_isize, parse_usize};
+use parsers::{map_result_strict, parse_i32, parse_u32, read_to_end, to_string_lossy};
+use platform::{self, pid_t};
 
 /// Process mounts information.
 ///
 /// See `proc(5)` for format details.
-#[derive(Debug, PartialEq, Eq, Hash)]
+///
+/// The default value has zero IDs, empty paths, no options, and an empty filesystem type.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Mountinfo {
     /// Unique ID for the mount.
-    pub mount_id: isize,
+    pub mount_id: i32,
     /// ID of the parent mount.
-    pub parent_id: isize,
+    pub parent_id: i32,
     /// Device major ID (class).
-    pub major: usize,
+    pub major: u32,
     /// Device minor ID (instance).
-    pub minor: usize,
+    pub minor: u32,
     /// Pathname which forms the root of this mount.
     pub root: PathBuf,
     /// Mount pathname relative to the process's root.
@@ -41,28 +46,40 @@ pub struct Mountinfo {
     pub super_opts: Vec<String>,
 }
 
+impl Mountinfo {
+    /// Parses a single line of a `/proc/[pid]/mountinfo` file, excluding the line ending.
+    pub fn parse(input: &[u8]) -> Result<Mountinfo> {
+        map_result_strict(input, parse_mountinfo_entry)
+    }
+}
+
 /// Mountinfo optional field
 ///
 /// See `proc(5)` and `mount_namespace(7)` for more details.
-#[derive(Debug, PartialEq, Eq, Hash)]
+///
+/// The default is `Private`, since a mount without any optional fields is private.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OptionalField {
     /// A mount shared in peer group `ID`
-    Shared(usize),
+    Shared(u32),
     /// A mount which is a slave of shared peer group `ID`
-    Master(usize),
+    Master(u32),
     /// A slave mount which receives propagation events from
     /// shared peer group `ID`
-    PropagateFrom(usize),
+    PropagateFrom(u32),
     /// An unbindable mount
     Unbindable,
     /// A private mount
+    #[default]
     Private
 }
 
 /// Mountpoint option
 ///
 /// See `mount(8)` for more details.
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MountOption {
     /// Do not update inode access time
     Noatime,
@@ -96,9 +113,11 @@ named!(colon, tag!(":"));
 /// Consumes a dot, the fs sub-type separator
 named!(dot, tag!("."));
 
-/// Parses a space-terminated string field in a mountinfo entry
-named!(parse_string_field<String>,
-       map_res!(map_res!(is_not!(" "), str::from_utf8), FromStr::from_str));
+/// Parses a space-terminated string field in a mountinfo entry, replacing invalid UTF-8 sequences.
+named!(parse_string_field<String>, map!(is_not!(" "), to_string_lossy));
+
+/// Parses a space-terminated path field in a mountinfo entry from its raw bytes.
+named!(parse_path_field<PathBuf>, map!(is_not!(" "), platform::path_from_bytes));
 
 
 /// Parses a string of optional fields.
@@ -134,42 +153,35 @@ fn opt_fields(fs: &str) -> Result<Vec<OptionalField>> {
         if t.len() > 2 {
             return Err(Error::new(ErrorKind::InvalidInput, "too many colons"));
         }
-        match (t.get(0), t.get(1)) {
-            (Some(&"shared"), Some(x)) if usize::from_str(x).is_ok() =>
-                v.push(OptionalField::Shared(usize::from_str(x).unwrap())),
-            (Some(&"master"), Some(x)) if usize::from_str(x).is_ok() =>
-                v.push(OptionalField::Master(usize::from_str(x).unwrap())),
-            (Some(&"propagate_from"), Some(x)) if usize::from_str(x).is_ok() =>
-                v.push(OptionalField::PropagateFrom(usize::from_str(x).unwrap())),
+        match (t.first(), t.get(1)) {
+            (Some(&"shared"), Some(x)) if u32::from_str(x).is_ok() =>
+                v.push(OptionalField::Shared(u32::from_str(x).unwrap())),
+            (Some(&"master"), Some(x)) if u32::from_str(x).is_ok() =>
+                v.push(OptionalField::Master(u32::from_str(x).unwrap())),
+            (Some(&"propagate_from"), Some(x)) if u32::from_str(x).is_ok() =>
+                v.push(OptionalField::PropagateFrom(u32::from_str(x).unwrap())),
             (Some(&"unbindable"), None) =>
                 v.push(OptionalField::Unbindable),
             (_, _) => return Err(Error::new(ErrorKind::InvalidInput, "invalid optional value")),
         };
     }
 
-    if v.len() == 0 {
+    if v.is_empty() {
         v.push(OptionalField::Private);
     }
 
-    return Ok(v);
+    Ok(v)
 }
 
 /// Parses a space-separated list of tag:value optional fields.
 fn parse_opt_fields(input: &[u8]) -> IResult<&[u8], Vec<OptionalField>> {
     // look for the mandatory terminator (hypen)
-    let mut hypen = None;
-    for idx in 0..input.len() {
-        if '-' as u8 == input[idx] {
-            hypen = Some(idx);
-            break
-        }
-    }
-    if hypen.is_none() {
-        return IResult::Incomplete(Needed::Unknown);
-    }
+    let term = match input.iter().position(|&b| b == b'-') {
+        Some(idx) => idx,
+        None => return IResult::Incomplete(Needed::Unknown),
+    };
 
     // parse all optional fields
-    let term = hypen.unwrap();
     let fs = str::from_utf8(&input[0..term]);
     match fs {
         Err(_) => IResult::Error(Err::Position(Tag, input)),
@@ -182,7 +194,7 @@ fn parse_opt_fields(input: &[u8]) -> IResult<&[u8], Vec<OptionalField>> {
 
 /// Parses a fs type label, with optional dotted sub-type.
 named!(parse_fs_type<(String, Option<String>)>,
-       do_parse!(k: map_res!(map_res!(take_until_either!(" ."), str::from_utf8), FromStr::from_str) >>
+       do_parse!(k: map!(take_until_either!(" ."), to_string_lossy) >>
                  v: opt!(do_parse!(dot >> s: parse_string_field >> (s))) >>
                  (k, v)
        )
@@ -204,53 +216,74 @@ named!(parse_options<Vec<String> >,
 
 /// Parses a mountpoint entry according to mountinfo file format.
 named!(parse_mountinfo_entry<Mountinfo>,
-    do_parse!(mount_id: parse_isize            >> space >>
-              parent_id: parse_isize           >> space >>
-              major: parse_usize               >> colon >>
-              minor: parse_usize               >> space >>
-              root: parse_string_field         >> space >>
-              mount_point: parse_string_field  >> space >>
+    do_parse!(mount_id: parse_i32              >> space >>
+              parent_id: parse_i32             >> space >>
+              major: parse_u32                 >> colon >>
+              minor: parse_u32                 >> space >>
+              root: parse_path_field           >> space >>
+              mount_point: parse_path_field    >> space >>
               mount_options: parse_mnt_options >> space >>
               opt_fields: parse_opt_fields     >> hypen >> space >>
               fs_type: parse_fs_type           >> space >>
               mount_src: parse_mount_src       >> space >>
               super_opts: parse_options        >>
               ( Mountinfo {
-                            mount_id: mount_id,
-                            parent_id: parent_id,
-                            major: major,
-                            minor: minor,
-                            root: root.into(),
-                            mount_point: mount_point.into(),
-                            mount_options: mount_options,
-                            opt_fields: opt_fields,
-                            fs_type: fs_type,
-                            mount_src: mount_src,
-                            super_opts: super_opts,
+                            mount_id,
+                            parent_id,
+                            major,
+                            minor,
+                            root,
+                            mount_point,
+                            mount_options,
+                            opt_fields,
+                            fs_type,
+                            mount_src,
+                            super_opts,
            } )));
 
+/// Parses the contents of a mountinfo file, one entry per line.
+fn parse_mountinfo(input: &[u8]) -> Result<Vec<Mountinfo>> {
+    input.split(|&b| b == b'\n')
+         .filter(|line| !line.is_empty())
+         .map(Mountinfo::parse)
+         .collect()
+}
+
 /// Parses the provided mountinfo file.
 fn mountinfo_file(file: &mut File) -> Result<Vec<Mountinfo>> {
-    let mut r = Vec::new();
-    for line in BufReader::new(file).lines() {
-        let mi = try!(map_result(parse_mountinfo_entry(try!(line).as_bytes())));
-        r.push(mi);
-    }
-    return Ok(r);
+    let mut buf = Vec::with_capacity(4096); // A typical mountinfo file is a few kilobytes.
+    parse_mountinfo(read_to_end(file, &mut buf)?)
 }
 
 /// Returns mounts information for the process with the provided pid.
 pub fn mountinfo(pid: pid_t) -> Result<Vec<Mountinfo>> {
-    mountinfo_file(&mut try!(File::open(&format!("/proc/{}/mountinfo", pid))))
+    mountinfo_file(&mut platform::open(format!("/proc/{}/mountinfo", pid))?)
 }
 
 /// Returns mounts information for the current process.
 pub fn mountinfo_self() -> Result<Vec<Mountinfo>> {
-    mountinfo_file(&mut try!(File::open("/proc/self/mountinfo")))
+    mountinfo_file(&mut platform::open("/proc/self/mountinfo")?)
+}
+
+/// Returns mounts information for the process with the provided pid, reading the file
+/// asynchronously.
+#[cfg(feature = "async")]
+pub fn mountinfo_async(pid: pid_t) -> impl Future<Output = Result<Vec<Mountinfo>>> + Send {
+    platform::read_async(format!("/proc/{}/mountinfo", pid), parse_mountinfo)
+}
+
+/// Returns mounts information for the current process, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn mountinfo_self_async() -> impl Future<Output = Result<Vec<Mountinfo>>> + Send {
+    platform::read_async("/proc/self/mountinfo".to_owned(), parse_mountinfo)
 }
 
 #[cfg(test)]
 pub mod tests {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
     use super::{Mountinfo, MountOption, OptionalField, mountinfo, mountinfo_self, parse_mountinfo_entry};
 
     /// Test parsing a single mountinfo entry (positive check).
@@ -277,9 +310,20 @@ pub mod tests {
             ],
             fs_type: ("proc".to_string(), Some("sys".to_string())),
             mount_src: Some("proc".to_string()),
-            super_opts: vec!["rw","nosuid"].iter().map(|&s| s.into()).collect(),
+            super_opts: ["rw","nosuid"].iter().map(|&s| s.into()).collect(),
         };
         assert_eq!(got_mi, want_mi);
+        assert_eq!(want_mi, Mountinfo::parse(entry).unwrap());
+    }
+
+    /// Test that paths which are not valid UTF-8 are preserved, and other fields are lossy.
+    #[test]
+    fn test_parse_mountinfo_entry_invalid_utf8() {
+        let entry = b"36 35 98:0 /mnt\xff /mnt2\xfe rw - ext3 /dev/root\xff rw,errors=continue";
+        let mi = parse_mountinfo_entry(entry).unwrap().1;
+        assert_eq!(Path::new(OsStr::from_bytes(b"/mnt\xff")), mi.root);
+        assert_eq!(Path::new(OsStr::from_bytes(b"/mnt2\xfe")), mi.mount_point);
+        assert_eq!(Some("/dev/root\u{FFFD}".to_owned()), mi.mount_src);
     }
 
     /// Test parsing a single mountinfo entry (negative check).
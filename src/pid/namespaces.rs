@@ -0,0 +1,86 @@
+//! Process namespaces from `/proc/[pid]/ns/`.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use platform::{self, pid_t};
+
+/// A kind of namespace, see `namespaces(7)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Namespace {
+    Cgroup,
+    Ipc,
+    Mnt,
+    Net,
+    Pid,
+    Time,
+    User,
+    Uts,
+}
+
+impl Namespace {
+    /// Returns the name of the namespace link in `/proc/[pid]/ns/`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Namespace::Cgroup => "cgroup",
+            Namespace::Ipc => "ipc",
+            Namespace::Mnt => "mnt",
+            Namespace::Net => "net",
+            Namespace::Pid => "pid",
+            Namespace::Time => "time",
+            Namespace::User => "user",
+            Namespace::Uts => "uts",
+        }
+    }
+}
+
+/// Parses the target of a namespace link, such as `net:[4026531833]`, returning the inode.
+fn parse_namespace_link(namespace: Namespace, target: &Path) -> Option<u64> {
+    let target = target.to_str()?;
+    let inode = target.strip_prefix(namespace.name())?.strip_prefix(":[")?.strip_suffix(']')?;
+    inode.parse().ok()
+}
+
+/// Returns the inode of a namespace of the process with the provided pid.
+///
+/// Two processes are in the same namespace if and only if the inodes are equal.
+pub fn namespace_inode(pid: pid_t, namespace: Namespace) -> Result<u64> {
+    let target = platform::read_link(format!("/proc/{}/ns/{}", pid, namespace.name()))?;
+    parse_namespace_link(namespace, &target).ok_or_else(|| {
+        Error::new(ErrorKind::InvalidInput, format!("unable to parse namespace link: {:?}", target))
+    })
+}
+
+/// Returns the inode of a namespace of the current process.
+pub fn namespace_inode_self(namespace: Namespace) -> Result<u64> {
+    let target = platform::read_link(format!("/proc/self/ns/{}", namespace.name()))?;
+    parse_namespace_link(namespace, &target).ok_or_else(|| {
+        Error::new(ErrorKind::InvalidInput, format!("unable to parse namespace link: {:?}", target))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::process;
+
+    use libc::pid_t;
+
+    use super::{Namespace, namespace_inode, namespace_inode_self, parse_namespace_link};
+
+    #[test]
+    fn test_parse_namespace_link() {
+        assert_eq!(Some(4026531833), parse_namespace_link(Namespace::Net, Path::new("net:[4026531833]")));
+        assert_eq!(Some(4026531836), parse_namespace_link(Namespace::Pid, Path::new("pid:[4026531836]")));
+        assert_eq!(None, parse_namespace_link(Namespace::Net, Path::new("pid:[4026531836]")));
+        assert_eq!(None, parse_namespace_link(Namespace::Net, Path::new("net:[]")));
+        assert_eq!(None, parse_namespace_link(Namespace::Net, Path::new("net:[4026531833")));
+    }
+
+    #[test]
+    fn test_namespace_inode() {
+        let inode = namespace_inode_self(Namespace::Net).unwrap();
+        assert_eq!(inode, namespace_inode(process::id() as pid_t, Namespace::Net).unwrap());
+    }
+}
@@ -0,0 +1,160 @@
+//! Socket tables of the network namespace of a process, from `/proc/[pid]/net/`.
+//!
+//! The tables have the same format as the system-wide tables in `/proc/net/`, which are those of
+//! the network namespace of the current process.
+
+use std::collections::BTreeSet;
+use std::io::Result;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use net::{InetSocket, UnixSocket};
+use pid::namespaces::{Namespace, namespace_inode};
+use platform::pid_t;
+use socket::{read_inet_tables, read_unix_table};
+
+/// Returns the TCP sockets of the network namespace of the process with the provided pid, from
+/// `/proc/[pid]/net/tcp` and `/proc/[pid]/net/tcp6`.
+pub fn tcp(pid: pid_t) -> Result<Vec<InetSocket>> {
+    read_inet_tables(&format!("/proc/{}/net", pid), "tcp")
+}
+
+/// Returns the UDP sockets of the network namespace of the process with the provided pid, from
+/// `/proc/[pid]/net/udp` and `/proc/[pid]/net/udp6`.
+pub fn udp(pid: pid_t) -> Result<Vec<InetSocket>> {
+    read_inet_tables(&format!("/proc/{}/net", pid), "udp")
+}
+
+/// Returns the Unix domain sockets of the network namespace of the process with the provided pid,
+/// from `/proc/[pid]/net/unix`.
+pub fn unix(pid: pid_t) -> Result<Vec<UnixSocket>> {
+    read_unix_table(&format!("/proc/{}/net", pid))
+}
+
+/// A socket protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Identifies a socket across network namespaces, by the address it is bound to.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SocketKey {
+    /// A TCP or UDP socket, by its local address and port.
+    Inet { protocol: Protocol, local: SocketAddr },
+    /// A bound Unix domain socket, by its path.
+    Unix { path: PathBuf },
+}
+
+/// The sockets of a network namespace, as keys.
+///
+/// Unbound Unix domain sockets are omitted, since they have no key.
+fn socket_keys(tcp: &[InetSocket], udp: &[InetSocket], unix: &[UnixSocket]) -> BTreeSet<SocketKey> {
+    let inet = |protocol: Protocol| move |socket: &InetSocket| SocketKey::Inet { protocol, local: socket.local };
+    tcp.iter().map(inet(Protocol::Tcp))
+       .chain(udp.iter().map(inet(Protocol::Udp)))
+       .chain(unix.iter().filter_map(|socket| socket.path.clone().map(|path| SocketKey::Unix { path })))
+       .collect()
+}
+
+/// The difference between the sockets of two network namespaces, such as those of a host process
+/// and of a container process.
+///
+/// Several sockets with the same key, such as connections accepted from the same listening
+/// socket, are reported once. The keys are sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NetNamespaceDiff {
+    /// The sockets which are only in the network namespace of the host process.
+    pub host_only: Vec<SocketKey>,
+    /// The sockets which are only in the network namespace of the container process.
+    pub container_only: Vec<SocketKey>,
+}
+
+impl NetNamespaceDiff {
+    /// Compares the sockets of the network namespaces of two processes.
+    ///
+    /// If both processes are in the same network namespace the diff is empty, and the socket
+    /// tables are not read.
+    pub fn compare(host_pid: pid_t, container_pid: pid_t) -> Result<NetNamespaceDiff> {
+        if namespace_inode(host_pid, Namespace::Net)? == namespace_inode(container_pid, Namespace::Net)? {
+            return Ok(NetNamespaceDiff::default());
+        }
+        let host = socket_keys(&tcp(host_pid)?, &udp(host_pid)?, &unix(host_pid)?);
+        let container = socket_keys(&tcp(container_pid)?, &udp(container_pid)?, &unix(container_pid)?);
+        Ok(NetNamespaceDiff::from_keys(&host, &container))
+    }
+
+    fn from_keys(host: &BTreeSet<SocketKey>, container: &BTreeSet<SocketKey>) -> NetNamespaceDiff {
+        NetNamespaceDiff {
+            host_only: host.difference(container).cloned().collect(),
+            container_only: container.difference(host).cloned().collect(),
+        }
+    }
+
+    /// Returns `true` if both network namespaces have the same sockets.
+    pub fn is_empty(&self) -> bool {
+        self.host_only.is_empty() && self.container_only.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process;
+
+    use libc::pid_t;
+
+    use net::{InetSocket, UnixSocket};
+    use socket::parse_table;
+    use socket::tests::{TCP, TCP6, UDP, UNIX};
+    use super::{NetNamespaceDiff, Protocol, SocketKey, socket_keys, tcp, udp, unix};
+
+    fn inet(protocol: Protocol, local: &str) -> SocketKey {
+        SocketKey::Inet { protocol, local: local.parse().unwrap() }
+    }
+
+    #[test]
+    fn test_diff() {
+        let tcp = parse_table(TCP, InetSocket::parse).unwrap();
+        let tcp6 = parse_table(TCP6, InetSocket::parse).unwrap();
+        let udp = parse_table(UDP, InetSocket::parse).unwrap();
+        let unix = parse_table(UNIX, UnixSocket::parse).unwrap();
+
+        // The host has the IPv4 sockets, and the container the IPv6 socket; both have the UDP and
+        // Unix domain sockets.
+        let host = socket_keys(&tcp, &udp, &unix);
+        let container = socket_keys(&tcp6, &udp, &unix);
+        assert_eq!(NetNamespaceDiff {
+                       host_only: vec![inet(Protocol::Tcp, "127.0.0.1:3306")],
+                       container_only: vec![inet(Protocol::Tcp, "[::1]:443")],
+                   },
+                   NetNamespaceDiff::from_keys(&host, &container));
+
+        // The two connections to port 3306 are the same key, and the unbound socket is omitted.
+        assert_eq!(4, host.len());
+        assert!(host.contains(&SocketKey::Unix { path: PathBuf::from("@/org/kernel/udev") }));
+
+        let diff = NetNamespaceDiff::from_keys(&host, &host);
+        assert!(diff.is_empty());
+        assert_eq!(NetNamespaceDiff::default(), diff);
+    }
+
+    /// Test that the current process has the same sockets as itself, without reading the tables.
+    #[test]
+    fn test_compare_same_namespace() {
+        let pid = process::id() as pid_t;
+        assert!(NetNamespaceDiff::compare(pid, pid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_socket_tables() {
+        let pid = process::id() as pid_t;
+        tcp(pid).unwrap();
+        udp(pid).unwrap();
+        unix(pid).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+//! Out-of-memory killer scores from `/proc/[pid]/oom_score` and `/proc/[pid]/oom_score_adj`.
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+
+use nom::line_ending;
+
+use parsers::{map_result_strict, parse_i32, parse_u32, read_to_end};
+use platform::{self, pid_t};
+
+/// The lowest `oom_score_adj`, which exempts a process from the OOM killer.
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+
+/// The highest `oom_score_adj`, which makes a process the preferred target of the OOM killer.
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// The standing of a process with the out-of-memory killer.
+///
+/// See `man 5 proc` and `Linux/mm/oom_kill.c`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Oom {
+    /// The badness score of the process, from `oom_score`. The process with the highest score is
+    /// killed first.
+    ///
+    /// The score is proportional to the memory usage of the process, and includes `score_adj`. It
+    /// ranges from 0 to 1000 before Linux 5.9, and from 0 to 2000 since.
+    pub score: u32,
+    /// The adjustment of the badness score, from `oom_score_adj`, in the range
+    /// `OOM_SCORE_ADJ_MIN` to `OOM_SCORE_ADJ_MAX`.
+    pub score_adj: i32,
+}
+
+impl Oom {
+    /// Returns whether the process may be killed by the OOM killer, that is, whether its score
+    /// adjustment is above `OOM_SCORE_ADJ_MIN`.
+    pub fn is_killable(&self) -> bool {
+        self.score_adj > OOM_SCORE_ADJ_MIN
+    }
+}
+
+/// Parses the oom_score file format.
+named!(parse_oom_score<u32>, terminated!(parse_u32, line_ending));
+
+/// Parses the oom_score_adj file format.
+named!(parse_oom_score_adj<i32>, terminated!(parse_i32, line_ending));
+
+/// Parses the contents of an `oom_score_adj` file, checking that the adjustment is in range.
+fn oom_score_adj_parse(input: &[u8]) -> Result<i32> {
+    let score_adj = map_result_strict(input, parse_oom_score_adj)?;
+    if !(OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&score_adj) {
+        return Err(Error::new(ErrorKind::InvalidInput,
+                              format!("oom_score_adj out of range: {}", score_adj)));
+    }
+    Ok(score_adj)
+}
+
+/// Parses the provided oom_score or oom_score_adj file.
+fn oom_file<T>(file: &mut File, parse: fn(&[u8]) -> Result<T>) -> Result<T> {
+    let mut buf = Vec::with_capacity(16); // A score is at most 5 bytes.
+    parse(read_to_end(file, &mut buf)?)
+}
+
+/// Parses the contents of an `oom_score` file.
+fn oom_score_parse(input: &[u8]) -> Result<u32> {
+    map_result_strict(input, parse_oom_score)
+}
+
+/// Returns the OOM killer badness score of the process with the provided pid.
+pub fn oom_score(pid: pid_t) -> Result<u32> {
+    oom_file(&mut platform::open(format!("/proc/{}/oom_score", pid))?, oom_score_parse)
+}
+
+/// Returns the OOM killer badness score of the current process.
+pub fn oom_score_self() -> Result<u32> {
+    oom_file(&mut platform::open("/proc/self/oom_score")?, oom_score_parse)
+}
+
+/// Returns the OOM killer score adjustment of the process with the provided pid.
+pub fn oom_score_adj(pid: pid_t) -> Result<i32> {
+    oom_file(&mut platform::open(format!("/proc/{}/oom_score_adj", pid))?, oom_score_adj_parse)
+}
+
+/// Returns the OOM killer score adjustment of the current process.
+pub fn oom_score_adj_self() -> Result<i32> {
+    oom_file(&mut platform::open("/proc/self/oom_score_adj")?, oom_score_adj_parse)
+}
+
+/// Returns the OOM killer score and score adjustment of the process with the provided pid.
+pub fn oom(pid: pid_t) -> Result<Oom> {
+    Ok(Oom { score: oom_score(pid)?, score_adj: oom_score_adj(pid)? })
+}
+
+/// Returns the OOM killer score and score adjustment of the current process.
+pub fn oom_self() -> Result<Oom> {
+    Ok(Oom { score: oom_score_self()?, score_adj: oom_score_adj_self()? })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::process;
+
+    use libc::pid_t;
+
+    use super::{OOM_SCORE_ADJ_MAX, OOM_SCORE_ADJ_MIN, Oom, oom, oom_score_adj_parse, oom_score_parse,
+                oom_self};
+
+    #[test]
+    fn test_parse_oom_score() {
+        assert_eq!(0, oom_score_parse(b"0\n").unwrap());
+        assert_eq!(1666, oom_score_parse(b"1666\n").unwrap());
+
+        for input in &[&b"-1\n"[..], b"12 \n", b"12\n\n"] {
+            assert_eq!(ErrorKind::InvalidInput, oom_score_parse(input).unwrap_err().kind(), "{:?}", input);
+        }
+        assert_eq!(ErrorKind::UnexpectedEof, oom_score_parse(b"12").unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_parse_oom_score_adj() {
+        assert_eq!(0, oom_score_adj_parse(b"0\n").unwrap());
+        assert_eq!(OOM_SCORE_ADJ_MIN, oom_score_adj_parse(b"-1000\n").unwrap());
+        assert_eq!(OOM_SCORE_ADJ_MAX, oom_score_adj_parse(b"1000\n").unwrap());
+
+        assert_eq!(ErrorKind::UnexpectedEof, oom_score_adj_parse(b"-17").unwrap_err().kind());
+        for input in &[&b"-1001\n"[..], b"1001\n", b"x\n"] {
+            assert_eq!(ErrorKind::InvalidInput,
+                       oom_score_adj_parse(input).unwrap_err().kind(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_killable() {
+        assert!(Oom { score: 0, score_adj: 0 }.is_killable());
+        assert!(Oom { score: 0, score_adj: OOM_SCORE_ADJ_MIN + 1 }.is_killable());
+        assert!(!Oom { score: 0, score_adj: OOM_SCORE_ADJ_MIN }.is_killable());
+    }
+
+    /// Test that the OOM killer scores of the current process can be read.
+    #[test]
+    fn test_oom() {
+        let oom_self = oom_self().unwrap();
+        assert!(oom_self.score <= 2000);
+        assert_eq!(oom_self.score_adj, oom(process::id() as pid_t).unwrap().score_adj);
+    }
+}
@@ -0,0 +1,149 @@
+//! Process credentials from `/proc/[pid]/status`, translated through the user namespace of the
+//! process.
+
+use std::io::{ErrorKind, Result};
+
+use pid::{IdMap, Namespace, Status, gid_map, namespace_inode, namespace_inode_self, status, uid_map};
+use platform::{gid_t, pid_t, uid_t};
+
+/// The real, effective, saved and filesystem IDs of a process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ids<T> {
+    /// The real ID.
+    pub real: T,
+    /// The effective ID.
+    pub effective: T,
+    /// The saved set ID.
+    pub saved: T,
+    /// The filesystem ID.
+    pub fs: T,
+}
+
+impl<T: Copy> Ids<T> {
+    /// Returns the IDs with a function applied to each of them.
+    fn map<U, F>(&self, f: F) -> Ids<U> where F: Fn(T) -> U {
+        Ids { real: f(self.real), effective: f(self.effective), saved: f(self.saved), fs: f(self.fs) }
+    }
+}
+
+/// The user and group IDs of a process, as seen from the current process and from inside the user
+/// namespace of the process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ownership {
+    /// The user IDs in the user namespace of the current process.
+    pub uids: Ids<uid_t>,
+    /// The group IDs in the user namespace of the current process.
+    pub gids: Ids<gid_t>,
+    /// The user IDs in the user namespace of the process, or `None` for IDs which are not mapped
+    /// into it.
+    pub container_uids: Ids<Option<uid_t>>,
+    /// The group IDs in the user namespace of the process, or `None` for IDs which are not mapped
+    /// into it.
+    pub container_gids: Ids<Option<gid_t>>,
+    /// Whether the user or group IDs are translated, i.e. whether the process is in another user
+    /// namespace than the current process, whose map is not the identity map. If not, the
+    /// container IDs are equal to the IDs.
+    pub translated: bool,
+}
+
+impl Ownership {
+    /// Combines the IDs of a status with the ID maps of the user namespace of the process.
+    fn new(status: &Status, uid_map: &IdMap, gid_map: &IdMap) -> Ownership {
+        let uids = Ids {
+            real: status.uid_real,
+            effective: status.uid_effective,
+            saved: status.uid_saved,
+            fs: status.uid_fs,
+        };
+        let gids = Ids {
+            real: status.gid_real,
+            effective: status.gid_effective,
+            saved: status.gid_saved,
+            fs: status.gid_fs,
+        };
+        Ownership {
+            uids,
+            gids,
+            container_uids: uids.map(|uid| uid_map.to_inside(uid)),
+            container_gids: gids.map(|gid| gid_map.to_inside(gid)),
+            translated: !uid_map.is_identity() || !gid_map.is_identity(),
+        }
+    }
+}
+
+/// Returns the user and group IDs of the process with the provided pid, translated into its user
+/// namespace.
+///
+/// The IDs in `/proc/[pid]/status` are relative to the user namespace of the current process, so
+/// for a process in a container they are the IDs on the host. The container IDs are translated
+/// through `/proc/[pid]/uid_map` and `/proc/[pid]/gid_map`. The files are not read atomically, so
+/// the IDs may be inconsistent if the process changes its credentials concurrently.
+///
+/// The maps of a process in the same user namespace as the current process are relative to the
+/// parent namespace, so its IDs are not translated. The user namespace of a process which the
+/// current process may not trace can not be compared, and is assumed to be a different one.
+pub fn ownership(pid: pid_t) -> Result<Ownership> {
+    let status = status(pid)?;
+    let same_namespace = match namespace_inode(pid, Namespace::User) {
+        Ok(inode) => inode == namespace_inode_self(Namespace::User)?,
+        Err(ref error) if error.kind() == ErrorKind::PermissionDenied => false,
+        Err(error) => return Err(error),
+    };
+    if same_namespace {
+        let identity = IdMap::identity();
+        return Ok(Ownership::new(&status, &identity, &identity));
+    }
+    Ok(Ownership::new(&status, &uid_map(pid)?, &gid_map(pid)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use libc::{self, pid_t};
+
+    use pid::{IdMap, Status};
+    use super::{Ids, Ownership, ownership};
+
+    #[test]
+    fn test_ownership() {
+        let status = Status {
+            uid_real: 100000,
+            uid_effective: 101000,
+            uid_saved: 165535,
+            uid_fs: 165536,
+            gid_real: 1000,
+            gid_effective: 1000,
+            gid_saved: 1000,
+            gid_fs: 99999,
+            ..Status::default()
+        };
+        let map = IdMap::parse(b"0 1000 1\n1 100000 65536\n").unwrap();
+        let ownership = Ownership::new(&status, &map, &map);
+        assert!(ownership.translated);
+        assert_eq!(Ids { real: 100000, effective: 101000, saved: 165535, fs: 165536 }, ownership.uids);
+        // Inside a range, on the boundaries of a range, and outside all ranges.
+        assert_eq!(Ids { real: Some(1), effective: Some(1001), saved: Some(65536), fs: None },
+                   ownership.container_uids);
+        assert_eq!(Ids { real: Some(0), effective: Some(0), saved: Some(0), fs: None }, ownership.container_gids);
+
+        let identity = IdMap::identity();
+        let ownership = Ownership::new(&status, &identity, &identity);
+        assert!(!ownership.translated);
+        assert_eq!(Some(165536), ownership.container_uids.fs);
+        assert_eq!(Some(99999), ownership.container_gids.fs);
+    }
+
+    /// Test that the IDs of the current process, which is in its own user namespace, are not
+    /// translated.
+    #[test]
+    fn test_ownership_self() {
+        let ownership = ownership(process::id() as pid_t).unwrap();
+        assert!(!ownership.translated);
+        assert_eq!(unsafe { libc::getuid() }, ownership.uids.real);
+        assert_eq!(unsafe { libc::getegid() }, ownership.gids.effective);
+        assert_eq!(Some(ownership.uids.real), ownership.container_uids.real);
+    }
+}
@@ -0,0 +1,144 @@
+//! The resource usage of a child process, read before it is reaped.
+
+use std::io::{Error, ErrorKind, Result};
+use std::time::Duration;
+
+use parsers::is_exited;
+use pid::{Io, Schedstat, Stat, State, Status};
+use pid::dir::PidDir;
+use platform::{clock_t, pid_t};
+use sysconf::{ticks_per_second, ticks_to_duration};
+
+/// The resource usage of a process, read just before it is reaped, see `snapshot_before_reap`.
+///
+/// The default value is the snapshot of a running process which used no resources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExitSnapshot {
+    /// The pid of the process.
+    pub pid: pid_t,
+    /// The state of the process when the snapshot was taken: `State::Zombie` if it had exited,
+    /// and otherwise the state of a live process, whose usage may still increase.
+    pub state: State,
+    /// Time the process was scheduled in user mode.
+    pub user_time: Duration,
+    /// Time the process was scheduled in kernel mode.
+    pub system_time: Duration,
+    /// Time the children of the process which it waited for were scheduled in user mode.
+    pub children_user_time: Duration,
+    /// Time the children of the process which it waited for were scheduled in kernel mode.
+    pub children_system_time: Duration,
+    /// The I/O counters of the process, or `None` if they are not available, e.g. because the
+    /// kernel was built without `CONFIG_TASK_IO_ACCOUNTING`.
+    pub io: Option<Io>,
+    /// The scheduler statistics of the process, or `None` if they are not available.
+    pub schedstat: Option<Schedstat>,
+    /// The peak resident set size of the process in bytes, or `None` if it is not available. The
+    /// kernel releases the memory of a process when it exits, so a zombie has no peak RSS; use the
+    /// `ru_maxrss` reported by `wait4(2)` instead.
+    pub peak_rss: Option<u64>,
+}
+
+impl ExitSnapshot {
+    /// Returns `true` if the process had exited when the snapshot was taken, so its usage is final.
+    pub fn is_zombie(&self) -> bool {
+        self.state == State::Zombie
+    }
+}
+
+/// Returns the error of a process which was reaped while its snapshot was taken.
+fn reaped() -> Error {
+    Error::new(ErrorKind::NotFound, "process was reaped while its snapshot was taken")
+}
+
+/// Returns the resource usage of a child process which is about to be reaped.
+///
+/// A zombie remains in `/proc` until its parent reaps it with `waitpid(2)`, so calling this from a
+/// `SIGCHLD` handler, or between the exit of the child and the `waitpid` call, returns the final
+/// usage of the child. Use `waitid(2)` with `WNOWAIT` to wait for the exit without reaping. The
+/// snapshot of a process which has not exited yet is tagged with its live state.
+///
+/// # Errors
+///
+/// Returns an error of kind `ErrorKind::NotFound` if the process was reaped before or while the
+/// snapshot was taken.
+pub fn snapshot_before_reap(pid: pid_t) -> Result<ExitSnapshot> {
+    // The files are read through the same directory, so they are of the same process even if it is
+    // reaped and its pid is reused while the snapshot is taken.
+    let dir = PidDir::open(pid)?;
+    let mut buf = Vec::with_capacity(2048);
+    let process = dir.read::<Stat>(&mut buf).map_err(|error| {
+        if is_exited(&error) { reaped() } else { error }
+    })?;
+    // The optional probes may be missing from the kernel, or unreadable by the current process.
+    let io = dir.read::<Io>(&mut buf).ok();
+    let schedstat = dir.read::<Schedstat>(&mut buf).ok();
+    let peak_rss = dir.read::<Status>(&mut buf).ok().map(|status| status.vm_hwm).filter(|&hwm| hwm > 0);
+
+    // An optional probe which failed may have raced with the reaping of the process, which makes
+    // the snapshot incomplete.
+    if (io.is_none() || schedstat.is_none() || peak_rss.is_none()) && !dir.reopen_check()? {
+        return Err(reaped());
+    }
+
+    let ticks = ticks_per_second();
+    let duration = |time: clock_t| ticks_to_duration(time as u64, ticks);
+    Ok(ExitSnapshot {
+        pid,
+        state: process.state,
+        user_time: duration(process.utime),
+        system_time: duration(process.stime),
+        children_user_time: duration(process.cutime),
+        children_system_time: duration(process.cstime),
+        io,
+        schedstat,
+        peak_rss,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::process::{self, Command};
+    use std::thread;
+    use std::time::Duration;
+
+    use libc::pid_t;
+
+    use pid::{State, stat};
+    use super::snapshot_before_reap;
+
+    /// Test that the usage of a zombie child can be read before it is reaped.
+    #[test]
+    fn test_snapshot_before_reap() {
+        // The child reads 1 MiB.
+        let mut child = Command::new("sh").arg("-c").arg("head -c 1048576 /dev/zero > /dev/null").spawn().unwrap();
+        let pid = child.id() as pid_t;
+
+        // Wait for the child to exit, without reaping it.
+        while stat(pid).unwrap().state != State::Zombie {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let snapshot = snapshot_before_reap(pid);
+        child.wait().unwrap();
+
+        let snapshot = snapshot.unwrap();
+        assert_eq!(pid, snapshot.pid);
+        assert!(snapshot.is_zombie());
+        assert_eq!(None, snapshot.peak_rss);
+        if let Some(io) = snapshot.io {
+            assert!(io.rchar >= 1048576, "{:?}", io);
+        }
+        assert!(snapshot.schedstat.is_none_or(|schedstat| schedstat.run_time > Duration::from_secs(0)));
+
+        assert_eq!(ErrorKind::NotFound, snapshot_before_reap(pid).unwrap_err().kind());
+    }
+
+    /// Test that the snapshot of a live process is tagged with its state.
+    #[test]
+    fn test_snapshot_live() {
+        let snapshot = snapshot_before_reap(process::id() as pid_t).unwrap();
+        assert!(!snapshot.is_zombie());
+        assert!(snapshot.peak_rss.unwrap() > 0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Scheduler statistics from `/proc/[pid]/schedstat`.
+
+use std::io::Result;
+use std::time::Duration;
+
+use nom::{line_ending, space};
+
+use parsers::{map_result_strict, parse_u64, read_to_end};
+use platform::{self, pid_t};
+
+/// Scheduler statistics of a task, see `Linux/Documentation/scheduler/sched-stats.rst`.
+///
+/// The statistics are available if the kernel was built with `CONFIG_SCHED_INFO`, which is
+/// selected by `CONFIG_SCHEDSTATS` and `CONFIG_TASK_DELAY_ACCT`. For a process, they are those of
+/// its main thread; the other threads are in `/proc/[pid]/task/[tid]/schedstat`.
+///
+/// The default value has every statistic set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Schedstat {
+    /// Time spent running on a CPU.
+    pub run_time: Duration,
+    /// Time spent runnable, waiting on a run queue for a CPU.
+    pub wait_time: Duration,
+    /// Number of times the task was scheduled on a CPU.
+    pub timeslices: u64,
+}
+
+impl Schedstat {
+    /// Parses the contents of a `/proc/[pid]/schedstat` file.
+    pub fn parse(input: &[u8]) -> Result<Schedstat> {
+        map_result_strict(input, parse_schedstat)
+    }
+}
+
+/// Parses the schedstat file format, in which the times are in nanoseconds.
+named!(parse_schedstat<Schedstat>,
+       do_parse!(run_time: parse_u64  >> space >>
+                 wait_time: parse_u64 >> space >>
+                 timeslices: parse_u64 >> line_ending >>
+                 (Schedstat {
+                     run_time: Duration::from_nanos(run_time),
+                     wait_time: Duration::from_nanos(wait_time),
+                     timeslices,
+                 })));
+
+/// Reads and parses a schedstat file.
+pub fn schedstat_file(path: &str) -> Result<Schedstat> {
+    let mut buf = Vec::with_capacity(64); // A typical schedstat file is about 30 bytes.
+    Schedstat::parse(read_to_end(&mut platform::open(path)?, &mut buf)?)
+}
+
+/// Returns the scheduler statistics of the main thread of the process with the provided pid.
+pub fn schedstat(pid: pid_t) -> Result<Schedstat> {
+    schedstat_file(&format!("/proc/{}/schedstat", pid))
+}
+
+/// Returns the scheduler statistics of the main thread of the current process.
+pub fn schedstat_self() -> Result<Schedstat> {
+    schedstat_file("/proc/self/schedstat")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Schedstat, schedstat, schedstat_self};
+
+    #[test]
+    fn test_parse_schedstat() {
+        assert_eq!(Schedstat {
+                       run_time: Duration::from_nanos(1_234_567_890),
+                       wait_time: Duration::from_nanos(57858),
+                       timeslices: 42,
+                   },
+                   Schedstat::parse(b"1234567890 57858 42\n").unwrap());
+
+        for input in &[&b""[..], b"1234567890 57858\n", b"1234567890 57858 42", b"1 2 3 4\n", b"-1 2 3\n"] {
+            assert!(Schedstat::parse(input).is_err(), "{:?}", input);
+        }
+    }
+
+    /// Test that the scheduler statistics of the current process can be read.
+    #[test]
+    fn test_schedstat() {
+        let earlier = schedstat_self().unwrap();
+        assert!(earlier.timeslices > 0);
+        assert!(schedstat(1).unwrap().run_time > Duration::from_secs(0));
+    }
+}
@@ -0,0 +1,685 @@
+//! Process memory mapping usage from `/proc/[pid]/smaps`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use parsers::{map_result_strict, parse_kb_value};
+use pid::maps::{MapPath, MemoryMap, read_line};
+use platform::{self, pid_t};
+
+/// Memory usage of a mapping of a process.
+///
+/// All sizes are in bytes. Fields which are not reported by the kernel are zero.
+///
+/// See `man 5 proc` and `Linux/fs/proc/task_mmu.c`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SmapsEntry {
+    /// The mapping.
+    pub map: MemoryMap,
+    /// Size of the mapping.
+    pub size: u64,
+    /// Page size used by the kernel to back the mapping (since Linux 2.6.29).
+    pub kernel_page_size: u64,
+    /// Page size used by the MMU to back the mapping (since Linux 2.6.29).
+    pub mmu_page_size: u64,
+    /// Resident set size.
+    pub rss: u64,
+    /// Proportional set size: the resident size, with each page shared by N processes
+    /// counted as 1/N of a page (since Linux 2.6.25).
+    pub pss: u64,
+    /// Resident pages shared with other processes which have not been modified.
+    pub shared_clean: u64,
+    /// Resident pages shared with other processes which have been modified.
+    pub shared_dirty: u64,
+    /// Resident pages private to the process which have not been modified.
+    pub private_clean: u64,
+    /// Resident pages private to the process which have been modified.
+    pub private_dirty: u64,
+    /// Memory currently marked as referenced or accessed.
+    pub referenced: u64,
+    /// Memory which does not belong to any file.
+    pub anonymous: u64,
+    /// Memory marked as `MADV_FREE` (since Linux 4.12).
+    pub lazy_free: u64,
+    /// Anonymous memory backed by transparent huge pages (since Linux 2.6.38).
+    pub anon_huge_pages: u64,
+    /// Shared memory backed by huge pages (since Linux 4.8).
+    pub shmem_pmd_mapped: u64,
+    /// Page cache mapped into userspace with huge pages (since Linux 5.4).
+    pub file_pmd_mapped: u64,
+    /// Memory backed by shared hugetlbfs pages (since Linux 4.4).
+    pub shared_hugetlb: u64,
+    /// Memory backed by private hugetlbfs pages (since Linux 4.4).
+    pub private_hugetlb: u64,
+    /// Anonymous memory which has been swapped out.
+    pub swap: u64,
+    /// Proportional swap size, analogous to `pss` (since Linux 4.3).
+    pub swap_pss: u64,
+    /// Memory locked into RAM (since Linux 2.6.34).
+    pub locked: u64,
+    /// Two-letter mnemonics of the kernel flags associated with the mapping (since Linux 3.8).
+    pub vm_flags: Vec<String>,
+}
+
+impl SmapsEntry {
+    /// Parses a single entry of a `/proc/[pid]/smaps` file, consisting of a mapping line followed
+    /// by the lines of its fields.
+    pub fn parse(input: &[u8]) -> Result<SmapsEntry> {
+        let mut lines = input.split(|&b| b == b'\n');
+        let mut entry = SmapsEntry {
+            map: MemoryMap::parse(lines.next().unwrap_or(b""))?,
+            ..SmapsEntry::default()
+        };
+        for line in lines.filter(|line| !line.is_empty()) {
+            match split_field(line) {
+                Some((key, value)) => parse_field_line(&mut entry, key, value)?,
+                None => return Err(Error::new(ErrorKind::InvalidInput,
+                                              format!("unexpected mapping line in smaps entry: {:?}",
+                                                      String::from_utf8_lossy(line)))),
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// The memory usage of the mappings of a file in a process, see `file_cache_residency`.
+///
+/// All sizes are in bytes, and are summed across the mappings, so pages which are mapped more than
+/// once are counted once per mapping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Residency {
+    /// The number of mappings of the file.
+    pub mappings: u32,
+    /// The size of the mappings.
+    pub size: u64,
+    /// The part of the mappings which is resident in memory, including pages of the file in the
+    /// page cache, and private copies of them.
+    pub rss: u64,
+    /// The part of the mappings which is marked as referenced or accessed.
+    pub referenced: u64,
+    /// The private copies of pages of the mappings which are swapped out.
+    pub swap: u64,
+}
+
+impl Residency {
+    fn add(&mut self, entry: &SmapsEntry) {
+        self.mappings += 1;
+        self.size += entry.size;
+        self.rss += entry.rss;
+        self.referenced += entry.referenced;
+        self.swap += entry.swap;
+    }
+}
+
+/// Splits a field line into its key and value, or returns `None` if the line is a mapping header.
+fn split_field(line: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = line.iter().position(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))?;
+    if line[end] == b':' {
+        Some((&line[..end], &line[end + 1..]))
+    } else {
+        None
+    }
+}
+
+/// Sets the field with the provided key to the parsed value in the entry.
+///
+/// Unrecognized fields are ignored.
+fn parse_field(entry: &mut SmapsEntry, key: &[u8], value: &[u8]) -> Result<()> {
+    let field = match key {
+        b"Size"            => &mut entry.size,
+        b"KernelPageSize"  => &mut entry.kernel_page_size,
+        b"MMUPageSize"     => &mut entry.mmu_page_size,
+        b"Rss"             => &mut entry.rss,
+        b"Pss"             => &mut entry.pss,
+        b"Shared_Clean"    => &mut entry.shared_clean,
+        b"Shared_Dirty"    => &mut entry.shared_dirty,
+        b"Private_Clean"   => &mut entry.private_clean,
+        b"Private_Dirty"   => &mut entry.private_dirty,
+        b"Referenced"      => &mut entry.referenced,
+        b"Anonymous"       => &mut entry.anonymous,
+        b"LazyFree"        => &mut entry.lazy_free,
+        b"AnonHugePages"   => &mut entry.anon_huge_pages,
+        b"ShmemPmdMapped"  => &mut entry.shmem_pmd_mapped,
+        b"FilePmdMapped"   => &mut entry.file_pmd_mapped,
+        b"Shared_Hugetlb"  => &mut entry.shared_hugetlb,
+        b"Private_Hugetlb" => &mut entry.private_hugetlb,
+        b"Swap"            => &mut entry.swap,
+        b"SwapPss"         => &mut entry.swap_pss,
+        b"Locked"          => &mut entry.locked,
+        b"VmFlags" => {
+            entry.vm_flags = value.split(|&b| b == b' ')
+                                  .filter(|flag| !flag.is_empty())
+                                  .map(|flag| String::from_utf8_lossy(flag).into_owned())
+                                  .collect();
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    *field = map_result_strict(value, parse_kb_value)?;
+    Ok(())
+}
+
+/// Sets the field with the provided key to the parsed value in the entry, naming the field in any
+/// error.
+fn parse_field_line(entry: &mut SmapsEntry, key: &[u8], value: &[u8]) -> Result<()> {
+    parse_field(entry, key, value).map_err(|error| {
+        Error::new(ErrorKind::InvalidInput,
+                   format!("unable to parse smaps field {:?}: {}", String::from_utf8_lossy(key), error))
+    })
+}
+
+/// An iterator over the memory mappings of a process and their memory usage, parsed lazily from
+/// `/proc/[pid]/smaps`.
+///
+/// The iterator yields an error if an entry fails to parse, after which it yields no further
+/// items.
+pub struct SmapsIter {
+    reader: BufReader<File>,
+    buf: Vec<u8>,
+    /// The header of the next entry, if it has already been read.
+    next_map: Option<MemoryMap>,
+    done: bool,
+}
+
+impl SmapsIter {
+    fn new(file: File) -> SmapsIter {
+        SmapsIter {
+            reader: BufReader::new(file),
+            buf: Vec::with_capacity(128),
+            next_map: None,
+            done: false,
+        }
+    }
+
+    /// Reads the mapping line of the next entry, or returns `None` at EOF.
+    fn read_map(&mut self) -> Result<Option<MemoryMap>> {
+        if let Some(map) = self.next_map.take() {
+            return Ok(Some(map));
+        }
+        if !read_line(&mut self.reader, &mut self.buf)? {
+            return Ok(None);
+        }
+        MemoryMap::parse(&self.buf).map(Some)
+    }
+
+    /// Reads the field lines of the current entry into `entry`, or skips them without parsing the
+    /// values if `entry` is `None`.
+    fn read_fields(&mut self, mut entry: Option<&mut SmapsEntry>) -> Result<()> {
+        while read_line(&mut self.reader, &mut self.buf)? {
+            match split_field(&self.buf) {
+                Some((key, value)) => {
+                    if let Some(ref mut entry) = entry {
+                        parse_field_line(entry, key, value)?;
+                    }
+                }
+                None => {
+                    self.next_map = Some(MemoryMap::parse(&self.buf)?);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_entry(&mut self) -> Result<Option<SmapsEntry>> {
+        match self.read_map()? {
+            Some(map) => {
+                let mut entry = SmapsEntry { map, ..SmapsEntry::default() };
+                self.read_fields(Some(&mut entry))?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the next entry whose mapping satisfies `predicate`, skipping the fields of the
+    /// entries which do not.
+    fn find_entry<P>(&mut self, mut predicate: P) -> Result<Option<SmapsEntry>>
+    where P: FnMut(&MemoryMap) -> bool {
+        if self.done {
+            return Ok(None);
+        }
+        let result = self.find_entry_inner(&mut predicate);
+        self.done = !matches!(result, Ok(Some(_)));
+        result
+    }
+
+    fn find_entry_inner<P>(&mut self, predicate: &mut P) -> Result<Option<SmapsEntry>>
+    where P: FnMut(&MemoryMap) -> bool {
+        while let Some(map) = self.read_map()? {
+            if predicate(&map) {
+                let mut entry = SmapsEntry { map, ..SmapsEntry::default() };
+                self.read_fields(Some(&mut entry))?;
+                return Ok(Some(entry));
+            }
+            self.read_fields(None)?;
+        }
+        Ok(None)
+    }
+
+    /// Returns the entries whose mapping is backed by the file at `path`.
+    fn entries_for_path(mut self, path: &Path) -> Result<Vec<SmapsEntry>> {
+        let mut entries = Vec::new();
+        while let Some(entry) = self.find_entry(|map| match map.path {
+            MapPath::Path(ref map_path) => map_path == path,
+            _ => false,
+        })? {
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Sums the memory usage of the entries whose mapping is backed by the file at `path`,
+    /// comparing canonicalized paths.
+    ///
+    /// Each distinct mapping path is canonicalized once. Paths which can not be canonicalized, such
+    /// as those of deleted files, are compared as they are.
+    fn residency_for_path(mut self, path: &Path) -> Result<Residency> {
+        let path = canonicalize(path);
+        let mut matches = HashMap::<PathBuf, bool>::new();
+        let mut residency = Residency::default();
+        while let Some(entry) = self.find_entry(|map| match map.path {
+            MapPath::Path(ref map_path) => {
+                *matches.entry(map_path.clone()).or_insert_with(|| canonicalize(map_path) == path)
+            }
+            _ => false,
+        })? {
+            residency.add(&entry);
+        }
+        Ok(residency)
+    }
+
+    /// Returns the entry whose mapping contains `addr`.
+    ///
+    /// The mappings are sorted by address, so the search stops at the first mapping which ends
+    /// after `addr`.
+    fn entry_at(mut self, addr: u64) -> Result<Option<SmapsEntry>> {
+        Ok(self.find_entry(|map| addr < map.end)?.filter(|entry| entry.map.start <= addr))
+    }
+}
+
+impl Iterator for SmapsIter {
+    type Item = Result<SmapsEntry>;
+
+    fn next(&mut self) -> Option<Result<SmapsEntry>> {
+        if self.done {
+            return None;
+        }
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Returns an iterator over the memory mappings and their memory usage for the process with the
+/// provided pid.
+pub fn smaps_iter(pid: pid_t) -> Result<SmapsIter> {
+    Ok(SmapsIter::new(platform::open(format!("/proc/{}/smaps", pid))?))
+}
+
+/// Returns an iterator over the memory mappings and their memory usage for the current process.
+pub fn smaps_iter_self() -> Result<SmapsIter> {
+    Ok(SmapsIter::new(platform::open("/proc/self/smaps")?))
+}
+
+/// Returns the memory mappings backed by the file at `path`, and their memory usage, for the
+/// process with the provided pid.
+///
+/// The file is streamed, and the fields of the other mappings are not parsed. `path` is compared
+/// with the pathname reported by the kernel, which is absolute and has ` (deleted)` appended if the
+/// file has been deleted.
+pub fn smaps_for_path(pid: pid_t, path: &Path) -> Result<Vec<SmapsEntry>> {
+    smaps_iter(pid)?.entries_for_path(path)
+}
+
+/// Returns the memory mappings backed by the file at `path`, and their memory usage, for the
+/// current process.
+pub fn smaps_for_path_self(path: &Path) -> Result<Vec<SmapsEntry>> {
+    smaps_iter_self()?.entries_for_path(path)
+}
+
+/// Returns the canonical form of a path, or the path itself if it can not be canonicalized.
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Returns how much of the mappings of the file at `path` in the process with the provided pid is
+/// resident in memory, summed across the mappings.
+///
+/// This shows whether a memory-mapped file, such as the index of a database, is in memory, without
+/// calling `mincore(2)` in the process. The path is canonicalized, and is compared with the
+/// canonicalized pathname of each mapping, so a mapping made through a symbolic link is matched.
+/// The pathnames are resolved in the mount namespace of the current process.
+///
+/// The file is streamed, and the fields of the other mappings are not parsed.
+pub fn file_cache_residency(pid: pid_t, path: &Path) -> Result<Residency> {
+    smaps_iter(pid)?.residency_for_path(path)
+}
+
+/// Returns how much of the mappings of the file at `path` in the current process is resident in
+/// memory, see `file_cache_residency`.
+pub fn file_cache_residency_self(path: &Path) -> Result<Residency> {
+    smaps_iter_self()?.residency_for_path(path)
+}
+
+/// Returns the memory mapping containing the address `addr`, and its memory usage, for the
+/// process with the provided pid, or `None` if the address is not mapped.
+///
+/// The file is streamed until the mapping is found, and the fields of the other mappings are not
+/// parsed.
+pub fn smaps_at(pid: pid_t, addr: u64) -> Result<Option<SmapsEntry>> {
+    smaps_iter(pid)?.entry_at(addr)
+}
+
+/// Returns the memory mapping containing the address `addr`, and its memory usage, for the
+/// current process, or `None` if the address is not mapped.
+pub fn smaps_at_self(addr: u64) -> Result<Option<SmapsEntry>> {
+    smaps_iter_self()?.entry_at(addr)
+}
+
+/// Returns the memory mappings and their memory usage for the process with the provided pid.
+pub fn smaps(pid: pid_t) -> Result<Vec<SmapsEntry>> {
+    smaps_iter(pid)?.collect()
+}
+
+/// Returns the memory mappings and their memory usage for the current process.
+pub fn smaps_self() -> Result<Vec<SmapsEntry>> {
+    smaps_iter_self()?.collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::os::unix::fs::symlink;
+    use std::os::unix::io::AsRawFd;
+    use std::path::{Path, PathBuf};
+    use std::process;
+    use std::ptr;
+
+    use libc::{self, pid_t};
+
+    use parsers::tests::fixture_file;
+    use pid::maps::MapPath;
+    use super::{Residency, SmapsEntry, SmapsIter, file_cache_residency, file_cache_residency_self, smaps,
+                smaps_at, smaps_at_self, smaps_for_path, smaps_for_path_self, smaps_self};
+
+    /// Returns a synthetic smaps file with the provided number of mappings.
+    pub fn smaps_fixture(mappings: u64) -> Vec<u8> {
+        let mut text = Vec::new();
+        for i in 0..mappings {
+            let start = 0x7f0000000000 + i * 0x1000;
+            text.extend(format!("{:x}-{:x} r-xp 00000000 fe:00 {:<10}                 \
+                                 /usr/lib/libfixture{}.so\n\
+                                 Size:                  4 kB\n\
+                                 KernelPageSize:        4 kB\n\
+                                 MMUPageSize:           4 kB\n\
+                                 Rss:                   4 kB\n\
+                                 Pss:                   2 kB\n\
+                                 Pss_Dirty:             0 kB\n\
+                                 Shared_Clean:          4 kB\n\
+                                 Shared_Dirty:          0 kB\n\
+                                 Private_Clean:         0 kB\n\
+                                 Private_Dirty:         0 kB\n\
+                                 Referenced:            4 kB\n\
+                                 Anonymous:             0 kB\n\
+                                 KSM:                   0 kB\n\
+                                 LazyFree:              0 kB\n\
+                                 AnonHugePages:         0 kB\n\
+                                 ShmemPmdMapped:        0 kB\n\
+                                 FilePmdMapped:         0 kB\n\
+                                 Shared_Hugetlb:        0 kB\n\
+                                 Private_Hugetlb:       0 kB\n\
+                                 Swap:                  0 kB\n\
+                                 SwapPss:               0 kB\n\
+                                 Locked:                0 kB\n\
+                                 THPeligible:           0\n\
+                                 ProtectionKey:         0\n\
+                                 VmFlags: rd ex mr mw me \n",
+                                start, start + 0x1000, i + 1, i).into_bytes());
+        }
+        text
+    }
+
+    /// Test that the system smaps files can be parsed.
+    #[test]
+    fn test_smaps() {
+        let entries = smaps_self().unwrap();
+        assert!(!entries.is_empty());
+        assert!(entries.iter().any(|entry| entry.rss > 0));
+        smaps(process::id() as pid_t).unwrap();
+    }
+
+    #[test]
+    fn test_smaps_iter() {
+        let entries = SmapsIter::new(fixture_file(&smaps_fixture(3)))
+            .collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(3, entries.len());
+
+        let entry = &entries[2];
+        assert_eq!(0x7f0000002000, entry.map.start);
+        assert_eq!(MapPath::Path(PathBuf::from("/usr/lib/libfixture2.so")), entry.map.path);
+        assert_eq!(4096, entry.size);
+        assert_eq!(4096, entry.rss);
+        assert_eq!(2048, entry.pss);
+        assert_eq!(4096, entry.shared_clean);
+        assert_eq!(0, entry.private_dirty);
+        assert_eq!(vec!["rd", "ex", "mr", "mw", "me"], entry.vm_flags);
+    }
+
+    #[test]
+    fn test_parse_smaps_entry() {
+        let fixture = smaps_fixture(1);
+        let entry = SmapsEntry::parse(&fixture).unwrap();
+        assert_eq!(SmapsIter::new(fixture_file(&fixture)).next().unwrap().unwrap(), entry);
+        assert_eq!(4096, entry.size);
+        assert_eq!(vec!["rd", "ex", "mr", "mw", "me"], entry.vm_flags);
+
+        // An entry may contain only a single mapping.
+        assert!(SmapsEntry::parse(&smaps_fixture(2)).is_err());
+        let error = SmapsEntry::parse(b"00400000-004f4000 r-xp 00000000 08:01 1548 /bin/bash\n\
+                                        Rss: lots kB\n").unwrap_err();
+        assert!(error.to_string().starts_with("unable to parse smaps field \"Rss\""), "{}", error);
+    }
+
+    #[test]
+    fn test_smaps_iter_old_kernel() {
+        // Linux 2.6.32 reports fewer fields, and does not report VmFlags.
+        let text = b"00400000-004f4000 r-xp 00000000 08:01 1548       /bin/bash\n\
+                     Size:                976 kB\n\
+                     Rss:                 584 kB\n\
+                     Pss:                 293 kB\n\
+                     Shared_Clean:        584 kB\n\
+                     Shared_Dirty:          0 kB\n\
+                     Private_Clean:         0 kB\n\
+                     Private_Dirty:         0 kB\n\
+                     Referenced:          584 kB\n\
+                     Swap:                  0 kB\n\
+                     KernelPageSize:        4 kB\n\
+                     MMUPageSize:           4 kB\n\
+                     006f3000-006fc000 rw-p 000f3000 08:01 1548       /bin/bash\n\
+                     Size:                 36 kB\n\
+                     Rss:                  36 kB\n";
+        let entries = SmapsIter::new(fixture_file(text)).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!(293 * 1024, entries[0].pss);
+        assert_eq!(0, entries[0].anonymous);
+        assert!(entries[0].vm_flags.is_empty());
+        assert_eq!(36 * 1024, entries[1].rss);
+    }
+
+    #[test]
+    fn test_smaps_for_path() {
+        let mut text = smaps_fixture(3);
+        text.extend(smaps_fixture(2));
+        let entries = SmapsIter::new(fixture_file(&text))
+            .entries_for_path(Path::new("/usr/lib/libfixture1.so")).unwrap();
+        assert_eq!(2, entries.len());
+        assert!(entries.iter().all(|entry| entry.map.start == 0x7f0000001000 && entry.rss == 4096));
+
+        let entries = SmapsIter::new(fixture_file(&text))
+            .entries_for_path(Path::new("/usr/lib/libfixture3.so")).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_residency_for_path() {
+        let mut text = smaps_fixture(3);
+        text.extend(smaps_fixture(2));
+        let residency = SmapsIter::new(fixture_file(&text))
+            .residency_for_path(Path::new("/usr/lib/libfixture1.so")).unwrap();
+        assert_eq!(Residency { mappings: 2, size: 8192, rss: 8192, referenced: 8192, swap: 0 }, residency);
+
+        let residency = SmapsIter::new(fixture_file(&text))
+            .residency_for_path(Path::new("/usr/lib/libfixture3.so")).unwrap();
+        assert_eq!(Residency::default(), residency);
+    }
+
+    /// Test that the residency of a mapped file is between the touched part of the file and its
+    /// size, including when the file is referred to through a symbolic link.
+    #[test]
+    fn test_file_cache_residency() {
+        const PAGES: usize = 64;
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let path = env::temp_dir().join(format!("procinfo-residency-{}", process::id()));
+        let link = env::temp_dir().join(format!("procinfo-residency-{}.link", process::id()));
+        fs::write(&path, vec![1u8; PAGES * page_size]).unwrap();
+        let _ = fs::remove_file(&link);
+        symlink(&path, &link).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let addr = unsafe {
+            libc::mmap(ptr::null_mut(), PAGES * page_size, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0)
+        };
+        assert_ne!(libc::MAP_FAILED, addr);
+        let mut sum = 0u64;
+        for page in 0..PAGES / 2 {
+            sum += unsafe { ptr::read_volatile((addr as *const u8).add(page * page_size)) } as u64;
+        }
+        assert_eq!((PAGES / 2) as u64, sum);
+
+        let residency = file_cache_residency_self(&link);
+        let by_pid = file_cache_residency(process::id() as pid_t, &path);
+        unsafe { libc::munmap(addr, PAGES * page_size) };
+        fs::remove_file(&link).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let residency = residency.unwrap();
+        assert_eq!(1, residency.mappings);
+        assert_eq!((PAGES * page_size) as u64, residency.size);
+        assert!(residency.rss >= (PAGES / 2 * page_size) as u64, "{:?}", residency);
+        assert!(residency.rss <= residency.size, "{:?}", residency);
+        assert!(residency.referenced > 0);
+        assert_eq!(residency.size, by_pid.unwrap().size);
+    }
+
+    #[test]
+    fn test_smaps_entry_at() {
+        let at = |addr| SmapsIter::new(fixture_file(&smaps_fixture(3))).entry_at(addr).unwrap();
+        assert_eq!(0x7f0000000000, at(0x7f0000000000).unwrap().map.start);
+        assert_eq!(0x7f0000001000, at(0x7f0000001fff).unwrap().map.start);
+        assert_eq!(4096, at(0x7f0000002000).unwrap().rss);
+        // Addresses before, and after, the mappings.
+        assert_eq!(None, at(0x1000));
+        assert_eq!(None, at(0x7f0000003000));
+
+        // An address in a gap between two mappings.
+        let mut text = smaps_fixture(1);
+        text.extend(b"7f0000002000-7f0000003000 rw-p 00000000 00:00 0 \n\
+                      Rss:                   4 kB\n".iter());
+        assert_eq!(None, SmapsIter::new(fixture_file(&text)).entry_at(0x7f0000001000).unwrap());
+    }
+
+    /// Test that the mapping containing the code of the test binary can be found.
+    #[test]
+    fn test_smaps_at() {
+        let addr = test_smaps_at as fn() as usize as u64;
+        let entry = smaps_at_self(addr).unwrap().unwrap();
+        assert!(entry.map.start <= addr && addr < entry.map.end);
+        assert!(entry.map.execute);
+        assert!(entry.rss > 0);
+        assert_eq!(entry, smaps_at(process::id() as pid_t, addr).unwrap().unwrap());
+
+        let exe = env::current_exe().unwrap();
+        assert_eq!(MapPath::Path(exe.clone()), entry.map.path);
+        let entries = smaps_for_path_self(&exe).unwrap();
+        assert!(entries.contains(&entry));
+        assert!(entries.len() > 1);
+        assert_eq!(entries.len(), smaps_for_path(process::id() as pid_t, &exe).unwrap().len());
+
+        // Nothing is mapped at the null page.
+        assert_eq!(None, smaps_at_self(0).unwrap());
+    }
+
+    #[test]
+    fn test_smaps_iter_error() {
+        let mut text = smaps_fixture(1);
+        text.extend(b"Rss:                 lots kB\n".iter());
+        text.extend(smaps_fixture(1));
+
+        let mut iter = SmapsIter::new(fixture_file(&text));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
+
+#[cfg(all(test, rustc_nightly))]
+mod benches {
+    extern crate test;
+
+    use std::fs::File;
+    use std::io::{Seek, SeekFrom};
+
+    use parsers::tests::fixture_file;
+    use super::{SmapsEntry, SmapsIter, smaps_self};
+    use super::tests::smaps_fixture;
+
+    /// Returns an iterator over the start of the fixture file.
+    fn rewind(file: &mut File) -> SmapsIter {
+        file.seek(SeekFrom::Start(0)).unwrap();
+        SmapsIter::new(file.try_clone().unwrap())
+    }
+
+    #[bench]
+    fn bench_smaps(b: &mut test::Bencher) {
+        b.iter(|| test::black_box(smaps_self()));
+    }
+
+    /// Collects every entry of a large smaps file, then sums one field.
+    #[bench]
+    fn bench_smaps_vec_sum(b: &mut test::Bencher) {
+        let mut file = fixture_file(&smaps_fixture(5000));
+        b.iter(|| {
+            let entries: Vec<SmapsEntry> = rewind(&mut file).collect::<Result<_, _>>().unwrap();
+            test::black_box(entries.iter().map(|entry| entry.pss).sum::<u64>())
+        });
+    }
+
+    /// Sums one field of a large smaps file while streaming.
+    #[bench]
+    fn bench_smaps_iter_sum(b: &mut test::Bencher) {
+        let mut file = fixture_file(&smaps_fixture(5000));
+        b.iter(|| test::black_box(rewind(&mut file).map(|entry| entry.unwrap().pss).sum::<u64>()));
+    }
+
+    /// Searches a large smaps file for an entry near the beginning of the file.
+    #[bench]
+    fn bench_smaps_iter_find(b: &mut test::Bencher) {
+        let mut file = fixture_file(&smaps_fixture(5000));
+        b.iter(|| {
+            test::black_box(rewind(&mut file).map(Result::unwrap).find(|entry| entry.map.inode == 10))
+        });
+    }
+}
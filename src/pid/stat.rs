@@ -1,31 +1,43 @@
 //! Process status information from `/proc/[pid]/stat`.
 
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::fs::File;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::path::PathBuf;
 use std::io::Result;
-use std::str::{self, FromStr};
+use std::time::{Duration, SystemTime};
 
-use libc::{clock_t, pid_t};
 use nom::{self, IResult, line_ending, space};
-use pid::State;
+use pid::{ProcFlags, State};
+use stat::boot_time;
+use sysconf::{page_size, pages_to_bytes, ticks_per_second, ticks_to_duration};
+use uptime::uptime;
 
 use parsers::{
-    map_result,
+    map_result_strict,
     parse_clock,
     parse_i32,
     parse_u32,
     parse_u64,
-    parse_usize,
-    read_to_end
+    read_pid_file,
+    read_to_end,
+    to_string_lossy
 };
+use platform::{self, clock_t, pid_t};
 
 /// Process status information.
 ///
 /// See `man 5 proc` and `Linux/fs/proc/array.c`.
-#[derive(Debug, Default, PartialEq, Eq, Hash)]
+///
+/// The default value has an empty command, zero numeric fields, and the default state.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stat {
     /// Process ID (i.e., Thread Group ID).
     pub pid: pid_t,
-    /// Filename of the executable.
+    /// Filename of the executable, with invalid UTF-8 sequences replaced by `U+FFFD`.
     pub command: String,
     /// Current state of the process.
     pub state: State,
@@ -37,22 +49,25 @@ pub struct Stat {
     pub session: pid_t,
     /// The controlling terminal of the process. (The minor device number is contained in the
     /// combination of bits 31 to 20 and 7 to 0; the major device number is in bits 15 to 8.)
+    ///
+    /// See `Stat::tty` for a decoded value.
     pub tty_nr: pid_t,
     /// Process group ID of the controlling terminal of the process.
     pub tty_pgrp: pid_t,
     /// The kernel flags word of the process. For bit meanings, see the `PF_*` defines in the Linux
-    /// kernel source file `include/linux/sched.h`. Details depend on the kernel version.
-    pub flags: u32,
+    /// kernel source file `include/linux/sched.h`. Details depend on the kernel version, see
+    /// `ProcFlag`.
+    pub flags: ProcFlags,
     /// The number of minor faults the process has made which have not required loading a memory
     /// page from disk.
-    pub minflt: usize,
+    pub minflt: u64,
     /// The number of minor faults that the process's waited-for children have made.
-    pub cminflt: usize,
+    pub cminflt: u64,
     /// The number of major faults the process has made which have required loading a memory page
     /// from disk.
-    pub majflt: usize,
+    pub majflt: u64,
     /// The number of major faults that the process's waited-for children have made.
-    pub cmajflt: usize,
+    pub cmajflt: u64,
     /// Amount of time that this process has been scheduled in user mode, measured in clock ticks
     /// (divide by `sysconf(_SC_CLK_TCK)`). This includes guest time, `guest_time` (time spent
     /// running a virtual CPU, see below), so that applications that are not aware of the guest
@@ -85,40 +100,42 @@ pub struct Stat {
     /// `sysconf(_SC_CLK_TCK)`).
     pub start_time: u64,
     /// Virtual memory size in bytes.
-    pub vsize: usize,
+    pub vsize: u64,
     /// Resident Set Size: number of pages the process has in real memory. This is just the pages
     /// which count toward text, data, or stack space. This does not include pages which have not
     /// been demand-loaded in, or which are swapped out.
-    pub rss: usize,
+    ///
+    /// Unlike `vsize`, this is in pages; `rss_bytes` converts it to bytes.
+    pub rss: u64,
     /// Current soft limit in bytes on the rss of the process; see the description of `RLIMIT_RSS`
     /// in `getrlimit(2)`.
-    pub rsslim: usize,
+    pub rsslim: u64,
     /// The address above which program text can run.
-    pub start_code: usize,
+    pub start_code: u64,
     /// The address below which program text can run.
-    pub end_code: usize,
+    pub end_code: u64,
     /// The address of the start (i.e., bottom) of the stack.
-    pub startstack: usize,
+    pub startstack: u64,
     /// The current value of ESP (stack pointer), as found in the kernel stack page for the process.
-    pub kstkeep: usize,
+    pub kstkeep: u64,
     /// The current EIP (instruction pointer).
-    pub kstkeip: usize,
+    pub kstkeip: u64,
     /// The bitmap of pending signals. Obsolete, because it does not provide information on
     /// real-time signals; use `/proc/[pid]/status` instead.
-    pub signal: usize,
+    pub signal: u64,
     /// The bitmap of blocked signals. Obsolete, because it does not provide information on
     /// real-time signals; use `/proc/[pid]/status` instead.
-    pub blocked: usize,
+    pub blocked: u64,
     /// The bitmap of ignored signals. Obsolete, because it does not provide information on
     /// real-time signals; use `/proc/[pid]/status` instead.
-    pub sigignore: usize,
+    pub sigignore: u64,
     /// The bitmap of caught signals. Obsolete, because it does not provide information on
     /// real-time signals; use /proc/[pid]/status instead.
-    pub sigcatch: usize,
+    pub sigcatch: u64,
     /// This is the "channel" in which the process is waiting. It is the address of a location in
     /// the kernel where the process is sleeping. The corresponding symbolic name can be found in
     /// `/proc/[pid]/wchan`.
-    pub wchan: usize,
+    pub wchan: u64,
     /// Signal to be sent to parent when we die.
     pub exit_signal: i32,
     /// CPU number last executed on.
@@ -139,51 +156,152 @@ pub struct Stat {
     pub cguest_time: clock_t,
     /// Address above which program initialized and uninitialized (BSS) data are placed. Since
     /// Linux 3.3.
-    pub start_data: usize,
+    pub start_data: u64,
     /// Address below which program initialized and uninitialized (BSS) data are placed. Since
     /// Linux 3.3.
-    pub end_data: usize,
+    pub end_data: u64,
     /// Address above which program heap can be expanded with `brk(2)`. Since Linux 3.3.
-    pub start_brk: usize,
+    pub start_brk: u64,
     /// Address above which program command-line arguments (argv) are placed. Since Linux 3.5.
-    pub arg_start: usize,
+    pub arg_start: u64,
     /// Address below program command-line arguments (argv) are placed. Since Linux 3.5.
-    pub arg_end: usize,
+    pub arg_end: u64,
     /// Address above which program environment is placed. Since Linux 3.5.
-    pub env_start: usize,
+    pub env_start: u64,
     /// Address below which program environment is placed. Since Linux 3.5.
-    pub env_end: usize,
+    pub env_end: u64,
     /// The thread's exit status in the form reported by `waitpid(2)`. Since Linux 3.5.
     pub exit_code: i32,
 }
 
-named!(parse_command<String>,
-       map_res!(map_res!(preceded!(char!('('),
-                                   take_until_right_and_consume!(")")),
-                         str::from_utf8),
-                FromStr::from_str));
+/// Process status information borrowed from the contents of a `/proc/[pid]/stat` file.
+///
+/// Parsing a `StatRef` does not allocate, so it suits probing many processes at a high rate, with
+/// `stat_with_buf`. The fields are those of `Stat`, except for the command, which is not decoded.
+/// `StatRef::to_owned` converts it into a `Stat`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StatRef<'a> {
+    /// See `Stat::pid`.
+    pub pid: pid_t,
+    /// Filename of the executable, as raw bytes which may not be valid UTF-8.
+    pub command: &'a [u8],
+    /// See `Stat::state`.
+    pub state: State,
+    /// See `Stat::ppid`.
+    pub ppid: pid_t,
+    /// See `Stat::pgrp`.
+    pub pgrp: pid_t,
+    /// See `Stat::session`.
+    pub session: pid_t,
+    /// See `Stat::tty_nr`.
+    pub tty_nr: pid_t,
+    /// See `Stat::tty_pgrp`.
+    pub tty_pgrp: pid_t,
+    /// See `Stat::flags`.
+    pub flags: ProcFlags,
+    /// See `Stat::minflt`.
+    pub minflt: u64,
+    /// See `Stat::cminflt`.
+    pub cminflt: u64,
+    /// See `Stat::majflt`.
+    pub majflt: u64,
+    /// See `Stat::cmajflt`.
+    pub cmajflt: u64,
+    /// See `Stat::utime`.
+    pub utime: clock_t,
+    /// See `Stat::stime`.
+    pub stime: clock_t,
+    /// See `Stat::cutime`.
+    pub cutime: clock_t,
+    /// See `Stat::cstime`.
+    pub cstime: clock_t,
+    /// See `Stat::priority`.
+    pub priority: i32,
+    /// See `Stat::nice`.
+    pub nice: i32,
+    /// See `Stat::num_threads`.
+    pub num_threads: i32,
+    /// See `Stat::start_time`.
+    pub start_time: u64,
+    /// See `Stat::vsize`.
+    pub vsize: u64,
+    /// See `Stat::rss`.
+    pub rss: u64,
+    /// See `Stat::rsslim`.
+    pub rsslim: u64,
+    /// See `Stat::start_code`.
+    pub start_code: u64,
+    /// See `Stat::end_code`.
+    pub end_code: u64,
+    /// See `Stat::startstack`.
+    pub startstack: u64,
+    /// See `Stat::kstkeep`.
+    pub kstkeep: u64,
+    /// See `Stat::kstkeip`.
+    pub kstkeip: u64,
+    /// See `Stat::signal`.
+    pub signal: u64,
+    /// See `Stat::blocked`.
+    pub blocked: u64,
+    /// See `Stat::sigignore`.
+    pub sigignore: u64,
+    /// See `Stat::sigcatch`.
+    pub sigcatch: u64,
+    /// See `Stat::wchan`.
+    pub wchan: u64,
+    /// See `Stat::exit_signal`.
+    pub exit_signal: i32,
+    /// See `Stat::processor`.
+    pub processor: u32,
+    /// See `Stat::rt_priority`.
+    pub rt_priority: u32,
+    /// See `Stat::policy`.
+    pub policy: u32,
+    /// See `Stat::delayacct_blkio_ticks`.
+    pub delayacct_blkio_ticks: u64,
+    /// See `Stat::guest_time`.
+    pub guest_time: clock_t,
+    /// See `Stat::cguest_time`.
+    pub cguest_time: clock_t,
+    /// See `Stat::start_data`.
+    pub start_data: u64,
+    /// See `Stat::end_data`.
+    pub end_data: u64,
+    /// See `Stat::start_brk`.
+    pub start_brk: u64,
+    /// See `Stat::arg_start`.
+    pub arg_start: u64,
+    /// See `Stat::arg_end`.
+    pub arg_end: u64,
+    /// See `Stat::env_start`.
+    pub env_start: u64,
+    /// See `Stat::env_end`.
+    pub env_end: u64,
+    /// See `Stat::exit_code`.
+    pub exit_code: i32,
+}
+
+/// Parses the command in parentheses, replacing invalid UTF-8 sequences.
+///
+/// A process can set its command to any 15 bytes except NUL, including spaces, parentheses and
+/// newlines, e.g. `a) R 1 (b`, so the command can not be delimited by the first space or `)`.
+/// The fields which follow the command are numbers and the single character state, so the
+/// command extends to the last `)` of the input, and the fields are parsed from after it.
+named!(parse_command, preceded!(char!('('), take_until_right_and_consume!(")")));
 
 /// Parse the stat state format.
 named!(parse_stat_state<State>,
-       alt!(tag!("R") => { |_| State::Running  }
-          | tag!("S") => { |_| State::Sleeping }
-          | tag!("D") => { |_| State::Waiting }
-          | tag!("Z") => { |_| State::Zombie }
-          | tag!("T") => { |_| State::Stopped }
-          | tag!("t") => { |_| State::TraceStopped }
-          | tag!("W") => { |_| State::Paging }
-          | tag!("X") => { |_| State::Dead }
-          | tag!("x") => { |_| State::Dead }
-          | tag!("K") => { |_| State::Wakekill }
-          | tag!("W") => { |_| State::Waking }
-          | tag!("P") => { |_| State::Parked }));
+       map_res!(take!(1), |state: &[u8]| State::try_from(state[0])));
+
+/// Parse the process flags word.
+named!(parse_proc_flags<ProcFlags>, map!(parse_u32, ProcFlags::from_raw));
 
 // Note: this is implemented as a function insted of via `chain!` to reduce the
 // stack depth in rustc by limiting the generated AST's depth. This is a work
 // around for
 //   https://github.com/rust-lang/rust/issues/35408
 // where rustc overflows its stack. The bug affects at least rustc 1.12.
-fn parse_stat(input: &[u8]) -> IResult<&[u8], Stat> {
+fn parse_stat_ref(input: &[u8]) -> IResult<&[u8], StatRef<'_>> {
     /// Helper macro for space terminated parser.
     macro_rules! s {
         ($i:expr, $f:expr) => (terminated!($i, call!($f), space))
@@ -203,11 +321,11 @@ fn parse_stat(input: &[u8]) -> IResult<&[u8], Stat> {
     let (rest, session)               = try_parse!(rest, s!(parse_i32        ));
     let (rest, tty_nr)                = try_parse!(rest, s!(parse_i32        ));
     let (rest, tty_pgrp)              = try_parse!(rest, s!(parse_i32        ));
-    let (rest, flags)                 = try_parse!(rest, s!(parse_u32        ));
-    let (rest, minflt)                = try_parse!(rest, s!(parse_usize      ));
-    let (rest, cminflt)               = try_parse!(rest, s!(parse_usize      ));
-    let (rest, majflt)                = try_parse!(rest, s!(parse_usize      ));
-    let (rest, cmajflt)               = try_parse!(rest, s!(parse_usize      ));
+    let (rest, flags)                 = try_parse!(rest, s!(parse_proc_flags ));
+    let (rest, minflt)                = try_parse!(rest, s!(parse_u64        ));
+    let (rest, cminflt)               = try_parse!(rest, s!(parse_u64        ));
+    let (rest, majflt)                = try_parse!(rest, s!(parse_u64        ));
+    let (rest, cmajflt)               = try_parse!(rest, s!(parse_u64        ));
     let (rest, utime)                 = try_parse!(rest, s!(parse_clock      ));
     let (rest, stime)                 = try_parse!(rest, s!(parse_clock      ));
     let (rest, cutime)                = try_parse!(rest, s!(parse_clock      ));
@@ -217,21 +335,21 @@ fn parse_stat(input: &[u8]) -> IResult<&[u8], Stat> {
     let (rest, num_threads)           = try_parse!(rest, s!(parse_i32        ));
     let (rest, _itrealvalue)          = try_parse!(rest, s!(parse_i32        ));
     let (rest, start_time)            = try_parse!(rest, s!(parse_u64        ));
-    let (rest, vsize)                 = try_parse!(rest, s!(parse_usize      ));
-    let (rest, rss)                   = try_parse!(rest, s!(parse_usize      ));
-    let (rest, rsslim)                = try_parse!(rest, s!(parse_usize      ));
-    let (rest, start_code)            = try_parse!(rest, s!(parse_usize      ));
-    let (rest, end_code)              = try_parse!(rest, s!(parse_usize      ));
-    let (rest, startstack)            = try_parse!(rest, s!(parse_usize      ));
-    let (rest, kstkeep)               = try_parse!(rest, s!(parse_usize      ));
-    let (rest, kstkeip)               = try_parse!(rest, s!(parse_usize      ));
-    let (rest, signal)                = try_parse!(rest, s!(parse_usize      ));
-    let (rest, blocked)               = try_parse!(rest, s!(parse_usize      ));
-    let (rest, sigignore)             = try_parse!(rest, s!(parse_usize      ));
-    let (rest, sigcatch)              = try_parse!(rest, s!(parse_usize      ));
-    let (rest, wchan)                 = try_parse!(rest, s!(parse_usize      ));
-    let (rest, _nswap)                = try_parse!(rest, s!(parse_usize      ));
-    let (rest, _cnswap)               = try_parse!(rest, s!(parse_usize      ));
+    let (rest, vsize)                 = try_parse!(rest, s!(parse_u64        ));
+    let (rest, rss)                   = try_parse!(rest, s!(parse_u64        ));
+    let (rest, rsslim)                = try_parse!(rest, s!(parse_u64        ));
+    let (rest, start_code)            = try_parse!(rest, s!(parse_u64        ));
+    let (rest, end_code)              = try_parse!(rest, s!(parse_u64        ));
+    let (rest, startstack)            = try_parse!(rest, s!(parse_u64        ));
+    let (rest, kstkeep)               = try_parse!(rest, s!(parse_u64        ));
+    let (rest, kstkeip)               = try_parse!(rest, s!(parse_u64        ));
+    let (rest, signal)                = try_parse!(rest, s!(parse_u64        ));
+    let (rest, blocked)               = try_parse!(rest, s!(parse_u64        ));
+    let (rest, sigignore)             = try_parse!(rest, s!(parse_u64        ));
+    let (rest, sigcatch)              = try_parse!(rest, s!(parse_u64        ));
+    let (rest, wchan)                 = try_parse!(rest, s!(parse_u64        ));
+    let (rest, _nswap)                = try_parse!(rest, s!(parse_u64        ));
+    let (rest, _cnswap)               = try_parse!(rest, s!(parse_u64        ));
     let (rest, exit_signal)           = try_parse!(rest, s!(parse_i32        ));
     let (rest, processor)             = try_parse!(rest, s!(parse_u32        ));
     let (rest, rt_priority)           = try_parse!(rest, s!(parse_u32        ));
@@ -239,99 +357,485 @@ fn parse_stat(input: &[u8]) -> IResult<&[u8], Stat> {
     let (rest, delayacct_blkio_ticks) = try_parse!(rest, s!(parse_u64        ));
     let (rest, guest_time)            = try_parse!(rest, s!(parse_clock      ));
     let (rest, cguest_time)           = try_parse!(rest, s!(parse_clock      ));
-    let (rest, start_data)            = try_parse!(rest, s!(parse_usize      ));
-    let (rest, end_data)              = try_parse!(rest, s!(parse_usize      ));
-    let (rest, start_brk)             = try_parse!(rest, s!(parse_usize      ));
-    let (rest, arg_start)             = try_parse!(rest, s!(parse_usize      ));
-    let (rest, arg_end)               = try_parse!(rest, s!(parse_usize      ));
-    let (rest, env_start)             = try_parse!(rest, s!(parse_usize      ));
-    let (rest, env_end)               = try_parse!(rest, s!(parse_usize      ));
+    let (rest, start_data)            = try_parse!(rest, s!(parse_u64        ));
+    let (rest, end_data)              = try_parse!(rest, s!(parse_u64        ));
+    let (rest, start_brk)             = try_parse!(rest, s!(parse_u64        ));
+    let (rest, arg_start)             = try_parse!(rest, s!(parse_u64        ));
+    let (rest, arg_end)               = try_parse!(rest, s!(parse_u64        ));
+    let (rest, env_start)             = try_parse!(rest, s!(parse_u64        ));
+    let (rest, env_end)               = try_parse!(rest, s!(parse_u64        ));
     let (rest, exit_code)             = try_parse!(rest, l!(parse_i32        ));
 
-    IResult::Done(rest, Stat {
-        pid                   : pid,
-        command               : command,
-        state                 : state,
-        ppid                  : ppid,
-        pgrp                  : pgrp,
-        session               : session,
-        tty_nr                : tty_nr,
-        tty_pgrp              : tty_pgrp,
-        flags                 : flags,
-        minflt                : minflt,
-        cminflt               : cminflt,
-        majflt                : majflt,
-        cmajflt               : cmajflt,
-        utime                 : utime,
-        stime                 : stime,
-        cutime                : cutime,
-        cstime                : cstime,
-        priority              : priority,
-        nice                  : nice,
-        num_threads           : num_threads,
-        start_time            : start_time,
-        vsize                 : vsize,
-        rss                   : rss,
-        rsslim                : rsslim,
-        start_code            : start_code,
-        end_code              : end_code,
-        startstack            : startstack,
-        kstkeep               : kstkeep,
-        kstkeip               : kstkeip,
-        signal                : signal,
-        blocked               : blocked,
-        sigignore             : sigignore,
-        sigcatch              : sigcatch,
-        wchan                 : wchan,
-        exit_signal           : exit_signal,
-        processor             : processor,
-        rt_priority           : rt_priority,
-        policy                : policy,
-        delayacct_blkio_ticks : delayacct_blkio_ticks,
-        guest_time            : guest_time,
-        cguest_time           : cguest_time,
-        start_data            : start_data,
-        end_data              : end_data,
-        start_brk             : start_brk,
-        arg_start             : arg_start,
-        arg_end               : arg_end,
-        env_start             : env_start,
-        env_end               : env_end,
-        exit_code             : exit_code,
+    IResult::Done(rest, StatRef {
+        pid,
+        command,
+        state,
+        ppid,
+        pgrp,
+        session,
+        tty_nr,
+        tty_pgrp,
+        flags,
+        minflt,
+        cminflt,
+        majflt,
+        cmajflt,
+        utime,
+        stime,
+        cutime,
+        cstime,
+        priority,
+        nice,
+        num_threads,
+        start_time,
+        vsize,
+        rss,
+        rsslim,
+        start_code,
+        end_code,
+        startstack,
+        kstkeep,
+        kstkeip,
+        signal,
+        blocked,
+        sigignore,
+        sigcatch,
+        wchan,
+        exit_signal,
+        processor,
+        rt_priority,
+        policy,
+        delayacct_blkio_ticks,
+        guest_time,
+        cguest_time,
+        start_data,
+        end_data,
+        start_brk,
+        arg_start,
+        arg_end,
+        env_start,
+        env_end,
+        exit_code,
     })
 }
 
+/// Parses the stat file format into an owned `Stat`.
+fn parse_stat(input: &[u8]) -> IResult<&[u8], Stat> {
+    parse_stat_ref(input).map(|stat| stat.to_owned())
+}
+
+/// A terminal device, as encoded in the `tty_nr` field of `/proc/[pid]/stat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Tty(u32);
+
+impl Tty {
+    /// Returns the terminal device with the provided encoded device number, or `None` if the
+    /// number is 0, i.e. if there is no controlling terminal.
+    pub fn from_encoded(tty_nr: u32) -> Option<Tty> {
+        if tty_nr == 0 { None } else { Some(Tty(tty_nr)) }
+    }
+
+    /// Returns the terminal device with the provided major and minor device numbers.
+    pub fn from_device(major: u32, minor: u32) -> Tty {
+        // See `new_encode_dev` in `Linux/include/linux/kdev_t.h`.
+        Tty((minor & 0xff) | ((major & 0xfff) << 8) | ((minor & !0xff) << 12))
+    }
+
+    /// Returns the encoded device number.
+    pub fn encoded(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the major device number, which is in bits 19 to 8 of the encoded value.
+    ///
+    /// `proc(5)` documents only bits 15 to 8, but the kernel encodes 12 bits.
+    pub fn major(&self) -> u32 {
+        (self.0 >> 8) & 0xfff
+    }
+
+    /// Returns the minor device number, which is split across bits 31 to 20 and 7 to 0 of the
+    /// encoded value.
+    pub fn minor(&self) -> u32 {
+        (self.0 & 0xff) | ((self.0 >> 12) & 0xfff00)
+    }
+
+    /// Returns the path of the terminal device, if it has one of the well-known device numbers.
+    ///
+    /// See `Linux/Documentation/admin-guide/devices.txt`.
+    pub fn path(&self) -> Option<PathBuf> {
+        let minor = self.minor();
+        let path = match self.major() {
+            4 if minor < 64 => format!("/dev/tty{}", minor),
+            4 if minor < 256 => format!("/dev/ttyS{}", minor - 64),
+            5 if minor == 0 => "/dev/tty".to_owned(),
+            5 if minor == 1 => "/dev/console".to_owned(),
+            major @ 136..=143 => format!("/dev/pts/{}", (major - 136) * 256 + minor),
+            _ => return None,
+        };
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Converts a time in clock ticks to a duration, treating negative times as zero.
+fn clock_to_duration(ticks: clock_t) -> Duration {
+    ticks_to_duration(if ticks < 0 { 0 } else { ticks as u64 }, ticks_per_second())
+}
+
+impl Stat {
+    /// Parses the contents of a `/proc/[pid]/stat` file.
+    pub fn parse(input: &[u8]) -> Result<Stat> {
+        map_result_strict(input, parse_stat)
+    }
+
+    /// Returns the controlling terminal of the process, or `None` if there is none.
+    pub fn tty(&self) -> Option<Tty> {
+        Tty::from_encoded(self.tty_nr as u32)
+    }
+
+    /// Returns the time that this process has been scheduled in user mode.
+    pub fn utime_duration(&self) -> Duration {
+        clock_to_duration(self.utime)
+    }
+
+    /// Returns the time that this process has been scheduled in kernel mode.
+    pub fn stime_duration(&self) -> Duration {
+        clock_to_duration(self.stime)
+    }
+
+    /// Returns the time that this process's waited-for children have been scheduled in user mode.
+    pub fn cutime_duration(&self) -> Duration {
+        clock_to_duration(self.cutime)
+    }
+
+    /// Returns the time that this process's waited-for children have been scheduled in kernel
+    /// mode.
+    pub fn cstime_duration(&self) -> Duration {
+        clock_to_duration(self.cstime)
+    }
+
+    /// Returns the time the process started after system boot.
+    pub fn start_time_duration(&self) -> Duration {
+        ticks_to_duration(self.start_time, ticks_per_second())
+    }
+
+    /// Returns the resident set size in bytes.
+    pub fn rss_bytes(&self) -> u64 {
+        pages_to_bytes(self.rss, page_size())
+    }
+}
+
+impl<'a> StatRef<'a> {
+    /// Parses the contents of a `/proc/[pid]/stat` file, borrowing the command from `input`.
+    pub fn parse(input: &'a [u8]) -> Result<StatRef<'a>> {
+        map_result_strict(input, parse_stat_ref)
+    }
+
+    /// Returns the command, with invalid UTF-8 sequences replaced by `U+FFFD`.
+    ///
+    /// The command is only copied if it is not valid UTF-8.
+    pub fn command_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.command)
+    }
+
+    /// Returns the resident set size in bytes.
+    pub fn rss_bytes(&self) -> u64 {
+        pages_to_bytes(self.rss, page_size())
+    }
+
+    /// Converts the status information into an owned `Stat`, decoding the command.
+    pub fn to_owned(self) -> Stat {
+        Stat {
+            pid: self.pid,
+            command: to_string_lossy(self.command),
+            state: self.state,
+            ppid: self.ppid,
+            pgrp: self.pgrp,
+            session: self.session,
+            tty_nr: self.tty_nr,
+            tty_pgrp: self.tty_pgrp,
+            flags: self.flags,
+            minflt: self.minflt,
+            cminflt: self.cminflt,
+            majflt: self.majflt,
+            cmajflt: self.cmajflt,
+            utime: self.utime,
+            stime: self.stime,
+            cutime: self.cutime,
+            cstime: self.cstime,
+            priority: self.priority,
+            nice: self.nice,
+            num_threads: self.num_threads,
+            start_time: self.start_time,
+            vsize: self.vsize,
+            rss: self.rss,
+            rsslim: self.rsslim,
+            start_code: self.start_code,
+            end_code: self.end_code,
+            startstack: self.startstack,
+            kstkeep: self.kstkeep,
+            kstkeip: self.kstkeip,
+            signal: self.signal,
+            blocked: self.blocked,
+            sigignore: self.sigignore,
+            sigcatch: self.sigcatch,
+            wchan: self.wchan,
+            exit_signal: self.exit_signal,
+            processor: self.processor,
+            rt_priority: self.rt_priority,
+            policy: self.policy,
+            delayacct_blkio_ticks: self.delayacct_blkio_ticks,
+            guest_time: self.guest_time,
+            cguest_time: self.cguest_time,
+            start_data: self.start_data,
+            end_data: self.end_data,
+            start_brk: self.start_brk,
+            arg_start: self.arg_start,
+            arg_end: self.arg_end,
+            env_start: self.env_start,
+            env_end: self.env_end,
+            exit_code: self.exit_code,
+        }
+    }
+}
+
 /// Parses the provided stat file.
 fn stat_file(file: &mut File) -> Result<Stat> {
-    let mut buf = [0; 1024]; // A typical statm file is about 300 bytes
-    map_result(parse_stat(try!(read_to_end(file, &mut buf))))
+    let mut buf = Vec::with_capacity(1024); // A typical stat file is about 300 bytes
+    Stat::parse(read_to_end(file, &mut buf)?)
 }
 
 /// Returns status information for the process with the provided pid.
 pub fn stat(pid: pid_t) -> Result<Stat> {
-    stat_file(&mut try!(File::open(&format!("/proc/{}/stat", pid))))
+    stat_file(&mut platform::open(format!("/proc/{}/stat", pid))?)
 }
 
 /// Returns status information for the current process.
 pub fn stat_self() -> Result<Stat> {
-    stat_file(&mut try!(File::open("/proc/self/stat")))
+    stat_file(&mut platform::open("/proc/self/stat")?)
+}
+
+/// Returns status information for the process with the provided pid, read into a buffer provided
+/// by the caller.
+///
+/// Unlike `stat`, this does not allocate once `buf` has grown to fit the file, so reusing a buffer
+/// across the processes of a scrape avoids an allocation per process. The command is borrowed from
+/// `buf`.
+pub fn stat_with_buf(buf: &mut Vec<u8>, pid: pid_t) -> Result<StatRef<'_>> {
+    StatRef::parse(read_pid_file(buf, pid, "stat")?)
+}
+
+/// Returns status information for the process with the provided pid, reading the file
+/// asynchronously.
+#[cfg(feature = "async")]
+pub fn stat_async(pid: pid_t) -> impl Future<Output = Result<Stat>> + Send {
+    platform::read_async(format!("/proc/{}/stat", pid), Stat::parse)
+}
+
+/// Returns status information for the current process, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn stat_self_async() -> impl Future<Output = Result<Stat>> + Send {
+    platform::read_async("/proc/self/stat".to_owned(), Stat::parse)
+}
+
+/// Returns the wall-clock time at which a process started, given the boot time and the process's
+/// start time in clock ticks after boot.
+///
+/// The result is clamped to `now`, since the boot time is only accurate to the second.
+fn wall_clock_start_time(boot_time: SystemTime,
+                         start_ticks: u64,
+                         ticks_per_second: u64,
+                         now: SystemTime) -> SystemTime {
+    let start_time = boot_time + ticks_to_duration(start_ticks, ticks_per_second);
+    if start_time > now { now } else { start_time }
+}
+
+/// Returns the time at which the process with the provided pid started.
+///
+/// The start time is computed from `Stat::start_time`, the clock tick rate, and `boot_time()`,
+/// the latter two of which are cached after the first call.
+///
+/// `btime` includes the time the system spent suspended. On kernels which measure process start
+/// times with a clock which stops during suspend, the computed start time of a process started
+/// after a suspend is early by the time spent suspended.
+pub fn start_time(pid: pid_t) -> Result<SystemTime> {
+    let boot_time = boot_time()?;
+    let start_ticks = stat(pid)?.start_time;
+    Ok(wall_clock_start_time(boot_time, start_ticks, ticks_per_second(), SystemTime::now()))
+}
+
+/// Returns the time at which the current process started.
+///
+/// See `start_time` for caveats.
+pub fn start_time_self() -> Result<SystemTime> {
+    let boot_time = boot_time()?;
+    let start_ticks = stat_self()?.start_time;
+    Ok(wall_clock_start_time(boot_time, start_ticks, ticks_per_second(), SystemTime::now()))
+}
+
+/// Returns how long a process has been running, given the system uptime and the process's start
+/// time after boot.
+///
+/// The result is clamped to zero, since the uptime and the start time are read at different times
+/// and with different precisions.
+fn process_age(uptime: Duration, start_time: Duration) -> Duration {
+    uptime.checked_sub(start_time).unwrap_or_default()
+}
+
+/// Returns how long the process with the provided pid has been running.
+///
+/// The age is computed from `Stat::start_time` and `/proc/uptime`, so unlike `start_time` it is not
+/// affected by changes to the wall clock.
+pub fn age(pid: pid_t) -> Result<Duration> {
+    let start_time = stat(pid)?.start_time_duration();
+    Ok(process_age(uptime()?.uptime, start_time))
+}
+
+/// Returns how long the current process has been running.
+pub fn age_self() -> Result<Duration> {
+    let start_time = stat_self()?.start_time_duration();
+    Ok(process_age(uptime()?.uptime, start_time))
+}
+
+/// The CPU time consumed by a process and by its waited-for children.
+///
+/// The default value has every time set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuTimeSummary {
+    /// Time that the process has been scheduled in user mode.
+    pub user: Duration,
+    /// Time that the process has been scheduled in kernel mode.
+    pub system: Duration,
+    /// Time that the process's waited-for children have been scheduled in user mode.
+    pub children_user: Duration,
+    /// Time that the process's waited-for children have been scheduled in kernel mode.
+    pub children_system: Duration,
+}
+
+impl CpuTimeSummary {
+    /// Returns the CPU time of a process from its stat file.
+    pub fn from_stat(stat: &Stat) -> CpuTimeSummary {
+        CpuTimeSummary {
+            user: stat.utime_duration(),
+            system: stat.stime_duration(),
+            children_user: stat.cutime_duration(),
+            children_system: stat.cstime_duration(),
+        }
+    }
+
+    /// Returns the CPU time of the process, in user and kernel mode, as reported by `ps`.
+    pub fn total(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// Returns the CPU time of the process and of its waited-for children, as reported by
+    /// `ps --cumulative`.
+    pub fn cumulative(&self) -> Duration {
+        self.total() + self.children_user + self.children_system
+    }
+}
+
+/// Returns the CPU time consumed by the process with the provided pid and its waited-for children.
+pub fn cpu_time(pid: pid_t) -> Result<CpuTimeSummary> {
+    Ok(CpuTimeSummary::from_stat(&stat(pid)?))
+}
+
+/// Returns the CPU time consumed by the current process and its waited-for children.
+pub fn cpu_time_self() -> Result<CpuTimeSummary> {
+    Ok(CpuTimeSummary::from_stat(&stat_self()?))
 }
 
 #[cfg(test)]
 pub mod tests {
-    use parsers::tests::unwrap;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use libc::{self, clock_t, pid_t};
+
+    use fixtures::fixture;
+    use parsers::tests::{allocations, unwrap};
     use pid::State;
+    use sysconf::ticks_per_second;
     use super::{
+        CpuTimeSummary,
+        Stat,
+        StatRef,
+        Tty,
+        age,
+        age_self,
+        cpu_time,
+        cpu_time_self,
         parse_command,
         parse_stat,
+        process_age,
+        start_time,
+        start_time_self,
         stat,
-        stat_self
+        stat_self,
+        stat_with_buf,
+        wall_clock_start_time
     };
 
     #[test]
     fn test_parse_command() {
-        assert_eq!("cat", &unwrap(parse_command(b"(cat)")));
-        assert_eq!("cat )  (( )) ", &unwrap(parse_command(b"(cat )  (( )) )")));
+        assert_eq!(b"cat", unwrap(parse_command(b"(cat)")));
+        assert_eq!(b"cat )  (( )) ", unwrap(parse_command(b"(cat )  (( )) )")));
+        assert_eq!(b"cat\xff", unwrap(parse_command(b"(cat\xff)")));
+        assert_eq!(b"", unwrap(parse_command(b"()")));
+        assert_eq!(b")", unwrap(parse_command(b"())")));
+        assert!(parse_command(b"(cat").is_err());
+    }
+
+    /// Test that commands which mimic the following fields do not shift them.
+    #[test]
+    fn test_parse_stat_hostile_command() {
+        let fields = b" S 1 19853 19435 34819 19853 4218880 98 0 0 0 0 0 0 0 20 0 1 0 279674171 112295936 \
+                       180 18446744073709551615 4194304 4238772 140736513999744 140736513999080 \
+                       139957028908944 0 0 0 0 0 0 0 17 15 0 0 0 0 0 6339648 6341408 17817600 \
+                       140736514006312 140736514006332 140736514006332 140736514007019 0\n";
+        for command in &[&b"a) R 1 (b"[..], b"a b c", b"x\ny", b")", b"(", b") (", b"))", b"1 2 3)", b""] {
+            let mut text = b"19853 (".to_vec();
+            text.extend(*command);
+            text.push(b')');
+            text.extend(&fields[..]);
+
+            let stat = Stat::parse(&text).unwrap();
+            assert_eq!(String::from_utf8_lossy(command), stat.command);
+            assert_eq!(19853, stat.pid);
+            assert_eq!(State::Sleeping, stat.state);
+            assert_eq!(1, stat.ppid);
+            assert_eq!(279674171, stat.start_time);
+            assert_eq!(0, stat.exit_code);
+        }
+    }
+
+    /// Test that threads with commands which mimic the following fields can be parsed.
+    #[test]
+    fn test_stat_hostile_command() {
+        thread::spawn(|| {
+            let tid = unsafe { libc::syscall(libc::SYS_gettid) } as pid_t;
+            for name in &[&b"a) R 1 (b\0"[..], b"x\ny) Z 2 (\0", b") ) )\0"] {
+                assert_eq!(0, unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) });
+                let stat = stat(tid).unwrap();
+                assert_eq!(String::from_utf8_lossy(&name[..name.len() - 1]), stat.command);
+                assert_eq!(tid, stat.pid);
+                assert_eq!(State::Running, stat.state);
+                assert_eq!(unsafe { libc::getppid() }, stat.ppid);
+            }
+        }).join().unwrap();
+    }
+
+    /// Test that a thread whose command is not valid UTF-8 can be parsed.
+    #[test]
+    fn test_stat_invalid_utf8_command() {
+        thread::spawn(|| {
+            let name = b"bad\xffname\0";
+            assert_eq!(0, unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) });
+            let tid = unsafe { libc::syscall(libc::SYS_gettid) } as pid_t;
+            assert_eq!("bad\u{FFFD}name", stat(tid).unwrap().command);
+        }).join().unwrap();
     }
 
     /// Test that the system stat files can be parsed.
@@ -341,6 +845,140 @@ pub mod tests {
         stat(1).unwrap();
     }
 
+    /// Test that the borrowed and owned parsers agree.
+    #[test]
+    fn test_parse_stat_ref() {
+        let text = fixture("6.18", "self/stat");
+        let stat = StatRef::parse(&text).unwrap();
+        assert_eq!(b"cat", stat.command);
+        assert_eq!(Stat::parse(&text).unwrap(), stat.to_owned());
+
+        let text = b"1 (bad\xffname) S 0 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 10 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 \
+                     17 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n";
+        let stat = StatRef::parse(text).unwrap();
+        assert_eq!(b"bad\xffname", stat.command);
+        assert_eq!("bad\u{FFFD}name", stat.command_lossy());
+        assert_eq!(Stat::parse(text).unwrap(), stat.to_owned());
+        assert!(StatRef::parse(&text[..text.len() - 3]).is_err());
+    }
+
+    /// Test that reading the stat file into a reused buffer does not allocate.
+    #[test]
+    fn test_stat_with_buf() {
+        let pid = stat_self().unwrap().pid;
+        let mut buf = Vec::with_capacity(1024);
+        let (command, _) = allocations(|| stat_with_buf(&mut buf, pid).unwrap().command.to_vec());
+        assert_eq!(stat_self().unwrap().command.as_bytes(), &command[..]);
+
+        let (read_pid, count) = allocations(|| stat_with_buf(&mut buf, pid).map(|stat| stat.pid));
+        assert_eq!(pid, read_pid.unwrap());
+        assert_eq!(0, count);
+        assert!(allocations(|| stat(pid)).1 > 0);
+
+        assert!(stat_with_buf(&mut buf, pid_t::MAX).is_err());
+    }
+
+    /// Test that the current process started a few seconds at most before the test.
+    #[test]
+    fn test_start_time() {
+        let now = SystemTime::now();
+        let started = start_time_self().unwrap();
+        // The boot time is rounded to whole seconds, and the start time to clock ticks.
+        let rounding = Duration::from_secs(1) + Duration::from_secs(1) / ticks_per_second() as u32;
+        assert!(started <= now + rounding, "{:?} is after {:?}", started, now);
+        let elapsed = now.duration_since(started).unwrap_or_default();
+        assert!(elapsed < Duration::from_secs(5) + rounding, "started {:?} before the test", elapsed);
+        start_time(1).unwrap();
+    }
+
+    #[test]
+    fn test_stat_durations() {
+        let ticks = ticks_per_second() as clock_t;
+        let stat = Stat { utime: 3 * ticks, stime: ticks / 2, cutime: -1, cstime: 0,
+                          start_time: 10 * ticks as u64, ..Default::default() };
+        assert_eq!(Duration::from_secs(3), stat.utime_duration());
+        assert_eq!(Duration::from_millis(500), stat.stime_duration());
+        assert_eq!(Duration::from_secs(0), stat.cutime_duration());
+        assert_eq!(Duration::from_secs(0), stat.cstime_duration());
+        assert_eq!(Duration::from_secs(10), stat.start_time_duration());
+    }
+
+    #[test]
+    fn test_process_age() {
+        assert_eq!(Duration::from_millis(1500), process_age(Duration::from_secs(10), Duration::from_millis(8500)));
+        // The uptime may be read with a lower precision than the start time.
+        assert_eq!(Duration::from_secs(0), process_age(Duration::from_secs(10), Duration::from_millis(10005)));
+    }
+
+    /// Test that the age of the current process grows with the time elapsed during the test.
+    #[test]
+    fn test_age() {
+        let test_start = Instant::now();
+        let earlier = age_self().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let later = age_self().unwrap();
+        let elapsed = test_start.elapsed();
+
+        // The uptime and the start time are measured in centiseconds and clock ticks.
+        let precision = Duration::from_millis(20);
+        assert!(later + precision >= elapsed, "{:?} {:?}", later, elapsed);
+        assert!(later + precision >= earlier + Duration::from_millis(100), "{:?} {:?}", earlier, later);
+        assert!(later <= earlier + elapsed + precision, "{:?} {:?} {:?}", earlier, later, elapsed);
+        assert!(age(1).unwrap() + precision >= later);
+    }
+
+    #[test]
+    fn test_cpu_time() {
+        let ticks = ticks_per_second() as clock_t;
+        let stat = Stat { utime: 3 * ticks, stime: ticks / 2, cutime: ticks, cstime: -1, ..Default::default() };
+        let summary = CpuTimeSummary::from_stat(&stat);
+        assert_eq!(CpuTimeSummary {
+                       user: Duration::from_secs(3),
+                       system: Duration::from_millis(500),
+                       children_user: Duration::from_secs(1),
+                       children_system: Duration::from_secs(0),
+                   },
+                   summary);
+        assert_eq!(Duration::from_millis(3500), summary.total());
+        assert_eq!(Duration::from_millis(4500), summary.cumulative());
+
+        cpu_time_self().unwrap();
+        cpu_time(1).unwrap();
+    }
+
+    #[test]
+    fn test_tty() {
+        let pts = Stat { tty_nr: 34819, ..Default::default() }.tty().unwrap();
+        assert_eq!((136, 3), (pts.major(), pts.minor()));
+        assert_eq!(Some(PathBuf::from("/dev/pts/3")), pts.path());
+
+        let tty = Tty::from_device(4, 1);
+        assert_eq!(0x401, tty.encoded());
+        assert_eq!(Some(PathBuf::from("/dev/tty1")), tty.path());
+
+        // Minor numbers above 255 are split across the encoded value.
+        let pts = Tty::from_device(137, 0x1234);
+        assert_eq!(0x01208934, pts.encoded());
+        assert_eq!((137, 0x1234), (pts.major(), pts.minor()));
+        assert_eq!(Some(PathBuf::from("/dev/pts/4916")), Tty::from_device(136, 4916).path());
+        assert_eq!(Some(PathBuf::from("/dev/pts/260")), Tty::from_device(137, 4).path());
+
+        assert_eq!(Some(PathBuf::from("/dev/ttyS0")), Tty::from_device(4, 64).path());
+        assert_eq!(Some(PathBuf::from("/dev/console")), Tty::from_device(5, 1).path());
+        assert_eq!(None, Tty::from_device(8, 1).path());
+        assert_eq!(None, Stat::default().tty());
+    }
+
+    #[test]
+    fn test_wall_clock_start_time() {
+        let boot_time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let now = boot_time + Duration::from_secs(3600);
+        assert_eq!(boot_time + Duration::from_millis(12340),
+                   wall_clock_start_time(boot_time, 1234, 100, now));
+        // A process started within the last second may appear to start after `now`.
+        assert_eq!(now, wall_clock_start_time(boot_time, 360_050, 100, now));
+    }
+
     #[test]
     fn test_parse_stat() {
         let text = b"19853 (cat) R 19435 19853 19435 34819 19853 4218880 98 0 0 0 0 0 0 0 20 0 1 0 \
@@ -348,6 +986,7 @@ pub mod tests {
                      140736513999080 139957028908944 0 0 0 0 0 0 0 17 15 0 0 0 0 0 6339648 6341408 \
                      17817600 140736514006312 140736514006332 140736514006332 140736514007019 0\n";
         let stat = unwrap(parse_stat(text));
+        assert_eq!(stat, Stat::parse(text).unwrap());
 
         assert_eq!(19853, stat.pid);
         assert_eq!("cat", &stat.command);
@@ -357,7 +996,8 @@ pub mod tests {
         assert_eq!(19435, stat.session);
         assert_eq!(34819, stat.tty_nr);
         assert_eq!(19853, stat.tty_pgrp);
-        assert_eq!(4218880, stat.flags);
+        assert_eq!(4218880, stat.flags.raw());
+        assert!(!stat.flags.is_kernel_thread());
         assert_eq!(98, stat.minflt);
         assert_eq!(0, stat.cminflt);
         assert_eq!(0, stat.majflt);
@@ -405,20 +1045,30 @@ pub mod tests {
 mod benches {
     extern crate test;
 
-    use std::fs::File;
-
-    use parsers::read_to_end;
-    use super::{parse_stat, stat};
+    use fixtures::fixture;
+    use super::{parse_stat, parse_stat_ref, stat, stat_with_buf};
 
     #[bench]
     fn bench_stat(b: &mut test::Bencher) {
         b.iter(|| test::black_box(stat(1)));
     }
 
+    /// Reads the stat file into a reused buffer, without allocating.
+    #[bench]
+    fn bench_stat_with_buf(b: &mut test::Bencher) {
+        let mut buf = Vec::with_capacity(1024);
+        b.iter(|| test::black_box(stat_with_buf(&mut buf, 1).map(|stat| stat.pid)));
+    }
+
     #[bench]
     fn bench_stat_parse(b: &mut test::Bencher) {
-        let mut buf = [0; 256];
-        let stat = read_to_end(&mut File::open("/proc/1/stat").unwrap(), &mut buf).unwrap();
-        b.iter(|| test::black_box(parse_stat(stat)));
+        let stat = fixture("6.18", "self/stat");
+        b.iter(|| test::black_box(parse_stat(&stat)));
+    }
+
+    #[bench]
+    fn bench_stat_ref_parse(b: &mut test::Bencher) {
+        let stat = fixture("6.18", "self/stat");
+        b.iter(|| test::black_box(parse_stat_ref(&stat)));
     }
 }
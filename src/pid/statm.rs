@@ -1,67 +1,135 @@
 //! Process memory usage information from `/proc/[pid]/statm`.
 
+use std::fmt;
 use std::fs::File;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::io::Result;
 
-use libc::pid_t;
 use nom::{digit, line_ending, space};
 
-use parsers::{map_result, parse_usize, read_to_end};
+use parsers::{map_result_strict, parse_u64, read_pid_file, read_to_end};
+use sysconf::{page_size, pages_to_bytes};
+use platform::{self, pid_t};
 
 /// Process memory usage information.
 ///
 /// All values are in units of pages.
 ///
 /// See `man 5 proc` and `Linux/fs/proc/array.c`.
-#[derive(Debug, Default, PartialEq, Eq, Hash)]
+///
+/// The default value has every size set to zero pages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Statm {
     /// Total virtual memory size.
-    pub size: usize,
+    pub size: u64,
     /// Resident non-swapped memory.
-    pub resident: usize,
+    pub resident: u64,
     /// Shared memory.
-    pub share: usize,
+    pub share: u64,
     /// Resident executable memory.
-    pub text: usize,
+    pub text: u64,
     /// Resident data and stack memory.
-    pub data: usize,
+    pub data: u64,
+}
+
+impl Statm {
+    /// Parses the contents of a `/proc/[pid]/statm` file.
+    pub fn parse(input: &[u8]) -> Result<Statm> {
+        map_result_strict(input, parse_statm)
+    }
+
+    /// Returns the total virtual memory size in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        pages_to_bytes(self.size, page_size())
+    }
+
+    /// Returns the resident non-swapped memory in bytes.
+    pub fn resident_bytes(&self) -> u64 {
+        pages_to_bytes(self.resident, page_size())
+    }
+
+    /// Returns the shared memory in bytes.
+    pub fn share_bytes(&self) -> u64 {
+        pages_to_bytes(self.share, page_size())
+    }
+
+    /// Returns the resident executable memory in bytes.
+    pub fn text_bytes(&self) -> u64 {
+        pages_to_bytes(self.text, page_size())
+    }
+
+    /// Returns the resident data and stack memory in bytes.
+    pub fn data_bytes(&self) -> u64 {
+        pages_to_bytes(self.data, page_size())
+    }
+}
+
+/// Formats the memory usage in the same format as `/proc/[pid]/statm`.
+impl fmt::Display for Statm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {} 0 {} 0", self.size, self.resident, self.share, self.text, self.data)
+    }
 }
 
 /// Parses the statm file format.
 named!(parse_statm<Statm>,
-    chain!(size: parse_usize     ~ space ~
-           resident: parse_usize ~ space ~
-           share: parse_usize    ~ space ~
-           text: parse_usize     ~ space ~
-           digit                 ~ space ~         // lib - unused since linux 2.6
-           data: parse_usize     ~ space ~
-           digit                 ~ line_ending,    // dt - unused since linux 2.6
-           || { Statm { size: size,
-                        resident: resident,
-                        share: share,
-                        text: text,
-                        data: data } }));
+    do_parse!(size: parse_u64     >> space >>
+              resident: parse_u64 >> space >>
+              share: parse_u64    >> space >>
+              text: parse_u64     >> space >>
+              digit               >> space >>         // lib - unused since linux 2.6
+              data: parse_u64     >> space >>
+              digit               >> line_ending >>   // dt - unused since linux 2.6
+              (Statm { size,
+                       resident,
+                       share,
+                       text,
+                       data })));
 
 /// Parses the provided statm file.
 fn statm_file(file: &mut File) -> Result<Statm> {
-    let mut buf = [0; 256]; // A typical statm file is about 25 bytes
-    map_result(parse_statm(try!(read_to_end(file, &mut buf))))
+    let mut buf = Vec::with_capacity(256); // A typical statm file is about 25 bytes
+    Statm::parse(read_to_end(file, &mut buf)?)
 }
 
 /// Returns memory status information for the process with the provided pid.
 pub fn statm(pid: pid_t) -> Result<Statm> {
-    statm_file(&mut try!(File::open(&format!("/proc/{}/statm", pid))))
+    statm_file(&mut platform::open(format!("/proc/{}/statm", pid))?)
 }
 
 /// Returns memory status information for the current process.
 pub fn statm_self() -> Result<Statm> {
-    statm_file(&mut try!(File::open("/proc/self/statm")))
+    statm_file(&mut platform::open("/proc/self/statm")?)
+}
+
+/// Returns memory status information for the process with the provided pid, read into a buffer
+/// provided by the caller.
+///
+/// Unlike `statm`, this does not allocate once `buf` has grown to fit the file.
+pub fn statm_with_buf(buf: &mut Vec<u8>, pid: pid_t) -> Result<Statm> {
+    Statm::parse(read_pid_file(buf, pid, "statm")?)
+}
+
+/// Returns memory status information for the process with the provided pid, reading the file
+/// asynchronously.
+#[cfg(feature = "async")]
+pub fn statm_async(pid: pid_t) -> impl Future<Output = Result<Statm>> + Send {
+    platform::read_async(format!("/proc/{}/statm", pid), Statm::parse)
+}
+
+/// Returns memory status information for the current process, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn statm_self_async() -> impl Future<Output = Result<Statm>> + Send {
+    platform::read_async("/proc/self/statm".to_owned(), Statm::parse)
 }
 
 #[cfg(test)]
 mod tests {
-    use parsers::tests::unwrap;
-    use super::{parse_statm, statm, statm_self};
+    use parsers::tests::{allocations, unwrap};
+    use super::{Statm, parse_statm, statm, statm_self, statm_with_buf};
+    use sysconf::page_size;
 
     /// Test that the system statm files can be parsed.
     #[test]
@@ -70,16 +138,51 @@ mod tests {
         statm(1).unwrap();
     }
 
+    /// Test that reading the statm file into a reused buffer does not allocate.
+    #[test]
+    fn test_statm_with_buf() {
+        let mut buf = Vec::with_capacity(256);
+        let (statm, count) = allocations(|| statm_with_buf(&mut buf, 1));
+        assert!(statm.unwrap().size > 0);
+        assert_eq!(0, count);
+    }
+
     #[test]
     fn test_parse_statm() {
         let statm_text = b"11837 2303 1390 330 0 890 0\n";
         let statm = unwrap(parse_statm(statm_text));
+        assert_eq!(statm, Statm::parse(statm_text).unwrap());
         assert_eq!(11837, statm.size);
         assert_eq!(2303, statm.resident);
         assert_eq!(1390, statm.share);
         assert_eq!(330, statm.text);
         assert_eq!(890, statm.data);
     }
+
+    /// Test that page counts which do not fit in 32 bits are parsed intact.
+    #[test]
+    fn test_parse_statm_large() {
+        let statm = Statm::parse(b"4294967296 4294967297 1390 330 0 8589934592 0\n").unwrap();
+        assert_eq!(1 << 32, statm.size);
+        assert_eq!((1 << 32) + 1, statm.resident);
+        assert_eq!(1 << 33, statm.data);
+    }
+
+    #[test]
+    fn test_statm_bytes() {
+        let statm = Statm { size: 5, resident: 4, share: 3, text: 2, data: 1 };
+        assert_eq!(5 * page_size(), statm.size_bytes());
+        assert_eq!(4 * page_size(), statm.resident_bytes());
+        assert_eq!(3 * page_size(), statm.share_bytes());
+        assert_eq!(2 * page_size(), statm.text_bytes());
+        assert_eq!(page_size(), statm.data_bytes());
+    }
+
+    #[test]
+    fn test_display_statm() {
+        let statm_text = b"11837 2303 1390 330 0 890 0\n";
+        assert_eq!("11837 2303 1390 330 0 890 0", unwrap(parse_statm(statm_text)).to_string());
+    }
 }
 
 #[cfg(all(test, rustc_nightly))]
@@ -98,7 +201,7 @@ mod benches {
 
     #[bench]
     fn bench_statm_parse(b: &mut test::Bencher) {
-        let mut buf = [0; 256];
+        let mut buf = Vec::with_capacity(256);
         let statm = read_to_end(&mut File::open("/proc/1/statm").unwrap(), &mut buf).unwrap();
         b.iter(|| test::black_box(parse_statm(statm)));
     }
@@ -1,53 +1,140 @@
 //! Process status information information from `/proc/[pid]/status`.
 
 use std::fs::File;
+#[cfg(feature = "async")]
+use std::future::Future;
 use std::io::Result;
+use std::str;
 
-use libc::{gid_t, mode_t, pid_t, uid_t};
 use nom::{IResult, line_ending, multispace, not_line_ending, space};
 
 use parsers::{
-    map_result,
+    map_result_strict,
     parse_i32,
     parse_bit,
     parse_i32s,
-    parse_kb,
+    parse_kb_value,
     parse_line,
     parse_u32,
-    parse_u32_mask_list,
     parse_u32_octal,
     parse_u32s,
     parse_u64,
     parse_u64_hex,
     read_to_end
 };
-use pid::State;
+use pid::{Capabilities, CpuSet, State};
+use platform::{self, gid_t, mode_t, pid_t, uid_t};
 
 /// The Secure Computing state of a process.
-#[derive(Debug, PartialEq, Eq, Hash)]
+///
+/// The default mode is `Disabled`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SeccompMode {
+    #[default]
     Disabled,
     Strict,
     Filter,
 }
 
-impl Default for SeccompMode {
-    fn default() -> SeccompMode {
-        SeccompMode::Disabled
-    }
-}
-
 named!(parse_seccomp_mode<SeccompMode>,
        alt!(tag!("0") => { |_| SeccompMode::Disabled }
           | tag!("1") => { |_| SeccompMode::Strict   }
           | tag!("2") => { |_| SeccompMode::Filter   }));
 
+/// The Speculative Store Bypass (Spectre variant 4) mitigation state of a process, see
+/// `PR_GET_SPECULATION_CTRL` in `prctl(2)`.
+///
+/// The default state is `Unknown`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpeculationStoreBypass {
+    /// The kernel could not determine the state (`unknown`).
+    #[default]
+    Unknown,
+    /// The CPU is not vulnerable (`not vulnerable`).
+    NotVulnerable,
+    /// Mitigated for the thread, and the mitigation cannot be disabled (`thread force mitigated`).
+    ThreadForceMitigated,
+    /// Mitigated for the thread with `prctl(2)` or seccomp (`thread mitigated`).
+    ThreadMitigated,
+    /// Not mitigated for the thread, which may enable the mitigation (`thread vulnerable`).
+    ThreadVulnerable,
+    /// Mitigated for every process (`globally mitigated`).
+    GloballyMitigated,
+    /// Not mitigated, and the mitigation cannot be enabled (`vulnerable`).
+    Vulnerable,
+    /// A state which is not known to this library, e.g. one introduced by a newer kernel.
+    Other(String),
+}
+
+impl SpeculationStoreBypass {
+    fn from_description(description: String) -> SpeculationStoreBypass {
+        match &description[..] {
+            "unknown" => SpeculationStoreBypass::Unknown,
+            "not vulnerable" => SpeculationStoreBypass::NotVulnerable,
+            "thread force mitigated" => SpeculationStoreBypass::ThreadForceMitigated,
+            "thread mitigated" => SpeculationStoreBypass::ThreadMitigated,
+            "thread vulnerable" => SpeculationStoreBypass::ThreadVulnerable,
+            "globally mitigated" => SpeculationStoreBypass::GloballyMitigated,
+            "vulnerable" => SpeculationStoreBypass::Vulnerable,
+            _ => SpeculationStoreBypass::Other(description),
+        }
+    }
+}
+
+/// The indirect branch speculation (Spectre variant 2) mitigation state of a process, see
+/// `PR_GET_SPECULATION_CTRL` in `prctl(2)`.
+///
+/// The default state is `Unknown`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpeculationIndirectBranch {
+    /// The kernel could not determine the state (`unknown`).
+    #[default]
+    Unknown,
+    /// The CPU is not affected (`not affected`).
+    NotAffected,
+    /// Speculation is disabled for the thread, and cannot be enabled (`conditional force
+    /// disabled`).
+    ConditionalForceDisabled,
+    /// Speculation is disabled for the thread with `prctl(2)` or seccomp (`conditional disabled`).
+    ConditionalDisabled,
+    /// Speculation is enabled for the thread, which may disable it (`conditional enabled`).
+    ConditionalEnabled,
+    /// Speculation is enabled for every process (`always enabled`).
+    AlwaysEnabled,
+    /// Speculation is disabled for every process (`always disabled`).
+    AlwaysDisabled,
+    /// A state which is not known to this library, e.g. one introduced by a newer kernel.
+    Other(String),
+}
+
+impl SpeculationIndirectBranch {
+    fn from_description(description: String) -> SpeculationIndirectBranch {
+        match &description[..] {
+            "unknown" => SpeculationIndirectBranch::Unknown,
+            "not affected" => SpeculationIndirectBranch::NotAffected,
+            "conditional force disabled" => SpeculationIndirectBranch::ConditionalForceDisabled,
+            "conditional disabled" => SpeculationIndirectBranch::ConditionalDisabled,
+            "conditional enabled" => SpeculationIndirectBranch::ConditionalEnabled,
+            "always enabled" => SpeculationIndirectBranch::AlwaysEnabled,
+            "always disabled" => SpeculationIndirectBranch::AlwaysDisabled,
+            _ => SpeculationIndirectBranch::Other(description),
+        }
+    }
+}
+
 /// Process status information.
 ///
 /// See `man 5 proc` and `Linux/fs/proc/array.c`.
-#[derive(Default, Debug, PartialEq, Eq, Hash)]
+///
+/// The default value has empty strings and lists, zero numeric fields, and the default state and
+/// Secure Computing mode. Fields missing from a status file are left at their default.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Status {
-    /// Filename of the executable.
+    /// Filename of the executable, with invalid UTF-8 sequences replaced by `U+FFFD`.
     pub command: String,
     /// File mode creation mask (since Linux 4.7).
     pub umask: mode_t,
@@ -91,43 +178,43 @@ pub struct Status {
     pub ns_pgids: Vec<pid_t>,
     /// Session IDs of the process for each namespace to which it belongs.
     pub ns_sids: Vec<pid_t>,
-    /// Peak virtual memory size (kB).
-    pub vm_peak: usize,
-    /// Virtual memory size (kB).
-    pub vm_size: usize,
-    /// Locked memory size (kB) (see mlock(3)).
-    pub vm_locked: usize,
-    /// Pinned memory size (since Linux 3.2). These are pages that can't be moved because
+    /// Peak virtual memory size in bytes.
+    pub vm_peak: u64,
+    /// Virtual memory size in bytes.
+    pub vm_size: u64,
+    /// Locked memory size in bytes (see mlock(3)).
+    pub vm_locked: u64,
+    /// Pinned memory size in bytes (since Linux 3.2). These are pages that can't be moved because
     /// something needs to directly access physical memory.
-    pub vm_pin: usize,
-    /// Peak resident size (kB) ("high water mark").
-    pub vm_hwm: usize,
-    /// Resident set size (kB). Comprised of `vm_rss_anon`, `vm_rss_file`,
+    pub vm_pin: u64,
+    /// Peak resident size in bytes ("high water mark").
+    pub vm_hwm: u64,
+    /// Resident set size in bytes. Comprised of `vm_rss_anon`, `vm_rss_file`,
     /// and `vm_rss_shared`.
-    pub vm_rss: usize,
-    /// Size of resident anonymous memory (kB) (since Linux 4.5).
-    pub vm_rss_anon: usize,
-    /// Size of resident file mappings (kB) (since Linux 4.5).
-    pub vm_rss_file: usize,
-    /// Size of resident shared memory (kB) (since Linux 4.5). Includes SysV
+    pub vm_rss: u64,
+    /// Size of resident anonymous memory in bytes (since Linux 4.5).
+    pub vm_rss_anon: u64,
+    /// Size of resident file mappings in bytes (since Linux 4.5).
+    pub vm_rss_file: u64,
+    /// Size of resident shared memory in bytes (since Linux 4.5). Includes SysV
     /// shm, mapping of tmpfs and shared anonymous mappings.
-    pub vm_rss_shared: usize,
-    /// Size of data segments (kB).
-    pub vm_data: usize,
-    /// Size of stack segments (kB).
-    pub vm_stack: usize,
-    /// Size of text (executable) segments (kB).
-    pub vm_exe: usize,
-    /// Shared library code size (kB).
-    pub vm_lib: usize,
-    /// Page table entries size (since Linux 2.6.10).
-    pub vm_pte: usize,
-    /// Size of second-level page tables (since Linux 4.0).
-    pub vm_pmd: usize,
-    /// Swapped-out-virtual memory size (since Linux 2.6.34).
-    pub vm_swap: usize,
-    /// Size of hugetlb memory portions (since Linux 4.4).
-    pub hugetlb_pages: usize,
+    pub vm_rss_shared: u64,
+    /// Size of data segments in bytes.
+    pub vm_data: u64,
+    /// Size of stack segments in bytes.
+    pub vm_stack: u64,
+    /// Size of text (executable) segments in bytes.
+    pub vm_exe: u64,
+    /// Shared library code size in bytes.
+    pub vm_lib: u64,
+    /// Page table entries size in bytes (since Linux 2.6.10).
+    pub vm_pte: u64,
+    /// Size of second-level page tables in bytes (since Linux 4.0).
+    pub vm_pmd: u64,
+    /// Swapped-out-virtual memory size in bytes (since Linux 2.6.34).
+    pub vm_swap: u64,
+    /// Size of hugetlb memory portions in bytes (since Linux 4.4).
+    pub hugetlb_pages: u64,
     /// Number of threads in process containing this thread.
     pub threads: u32,
     /// The number of currently queued signals for this real user ID
@@ -146,44 +233,47 @@ pub struct Status {
     /// Mask indicating signals being caught.
     pub sig_caught: u64,
     /// Mask of capabilities enabled in inheritable sets (see capabilities(7)).
-    pub cap_inherited: u64,
+    pub cap_inherited: Capabilities,
     /// Mask of capabilities enabled in permitted sets.
-    pub cap_permitted: u64,
+    pub cap_permitted: Capabilities,
     /// Mask of capabilities enabled in effective sets.
-    pub cap_effective: u64,
+    pub cap_effective: Capabilities,
     /// Capability Bounding set (since Linux 2.6.26).
-    pub cap_bounding: u64,
+    pub cap_bounding: Capabilities,
     /// Ambient capability set (since Linux 4.3).
-    pub cap_ambient: u64,
+    pub cap_ambient: Capabilities,
     /// Whether the process can acquire new privileges (since Linux 4.10)
     pub no_new_privs: bool,
     /// Secure Computing mode of the process (since Linux 3.8, see seccomp(2)).
     /// This field is provided only if the kernel was built with the
     /// `CONFIG_SECCOMP` kernel configuration option enabled.
-    pub seccomp: SeccompMode,
+    pub seccomp: Option<SeccompMode>,
+    /// Number of seccomp filters attached to the process (since Linux 5.9).
+    pub seccomp_filters: Option<u32>,
+    /// Speculative Store Bypass mitigation state (since Linux 4.17).
+    pub speculation_store_bypass: Option<SpeculationStoreBypass>,
+    /// Indirect branch speculation mitigation state (since Linux 5.1).
+    pub speculation_indirect_branch: Option<SpeculationIndirectBranch>,
     /// CPUs on which this process may run (since Linux 2.6.24, see cpuset(7)).
-    ///
-    /// The slice represents a bitmask in the same format as `BitVec`.
-    pub cpus_allowed: Box<[u8]>,
+    pub cpus_allowed: CpuSet,
     /// Memory nodes allowed to this process (since Linux 2.6.24, see cpuset(7)).
-    ///
-    /// The slice represents a bitmask in the same format as `BitVec`.
-    pub mems_allowed: Box<[u8]>,
+    pub mems_allowed: CpuSet,
     /// Number of voluntary context switches.
     pub voluntary_ctxt_switches: u64,
     /// Number of involuntary context switches.
     pub nonvoluntary_ctxt_switches: u64,
 }
 
+impl Status {
+    /// Parses the contents of a `/proc/[pid]/status` file.
+    pub fn parse(input: &[u8]) -> Result<Status> {
+        map_result_strict(input, parse_status)
+    }
+}
+
 /// Parse the status state format.
 named!(parse_status_state<State>,
-       alt!(tag!("R (running)") => { |_| State::Running  }
-          | tag!("S (sleeping)") => { |_| State::Sleeping }
-          | tag!("D (disk sleep)") => { |_| State::Waiting }
-          | tag!("T (stopped)") => { |_| State::Stopped }
-          | tag!("t (tracing stop)") => { |_| State::TraceStopped }
-          | tag!("X (dead)") => { |_| State::Dead }
-          | tag!("Z (zombie)") => { |_| State::Zombie }));
+       map_res!(map_res!(not_line_ending, str::from_utf8), str::parse));
 
 named!(parse_command<String>,   delimited!(tag!("Name:\t"),      parse_line,         line_ending));
 named!(parse_umask<mode_t>,     delimited!(tag!("Umask:\t"),     parse_u32_octal,    line_ending));
@@ -194,12 +284,12 @@ named!(parse_tid<pid_t>,        delimited!(tag!("Pid:\t"),       parse_i32,
 named!(parse_ppid<pid_t>,       delimited!(tag!("PPid:\t"),      parse_i32,          line_ending));
 named!(parse_tracer_pid<pid_t>, delimited!(tag!("TracerPid:\t"), parse_i32,          line_ending));
 
-named!(parse_uid<(uid_t, uid_t, uid_t, uid_t)>, chain!(tag!("Uid:\t") ~ real: parse_u32 ~ space ~ effective: parse_u32
-                                                                      ~ space ~ saved: parse_u32 ~ space ~ fs: parse_u32 ~ line_ending,
-                                                                   || { (real, effective, saved, fs) }));
-named!(parse_gid<(gid_t, gid_t, gid_t, gid_t)>, chain!(tag!("Gid:\t") ~ real: parse_u32 ~ space ~ effective: parse_u32
-                                                                      ~ space ~ saved: parse_u32 ~ space ~ fs: parse_u32 ~ line_ending,
-                                                                   || { (real, effective, saved, fs) }));
+named!(parse_uid<(uid_t, uid_t, uid_t, uid_t)>, do_parse!(tag!("Uid:\t") >> real: parse_u32 >> space >> effective: parse_u32
+                                                                         >> space >> saved: parse_u32 >> space >> fs: parse_u32 >> line_ending
+                                                                         >> ((real, effective, saved, fs))));
+named!(parse_gid<(gid_t, gid_t, gid_t, gid_t)>, do_parse!(tag!("Gid:\t") >> real: parse_u32 >> space >> effective: parse_u32
+                                                                         >> space >> saved: parse_u32 >> space >> fs: parse_u32 >> line_ending
+                                                                         >> ((real, effective, saved, fs))));
 
 named!(parse_fd_allocated<u32>,   delimited!(tag!("FDSize:\t"), parse_u32,  line_ending));
 named!(parse_groups<Vec<gid_t> >, delimited!(tag!("Groups:\t"), parse_u32s, multispace));
@@ -209,23 +299,23 @@ named!(parse_ns_tids<Vec<pid_t> >,  delimited!(tag!("NSpid:\t"),  parse_i32s, li
 named!(parse_ns_pgids<Vec<pid_t> >, delimited!(tag!("NSpgid:\t"), parse_i32s, line_ending));
 named!(parse_ns_sids<Vec<pid_t> >,  delimited!(tag!("NSsid:\t"),  parse_i32s, line_ending));
 
-named!(parse_vm_peak<usize>,        delimited!(tag!("VmPeak:"),       parse_kb, line_ending));
-named!(parse_vm_size<usize>,        delimited!(tag!("VmSize:"),       parse_kb, line_ending));
-named!(parse_vm_locked<usize>,      delimited!(tag!("VmLck:"),        parse_kb, line_ending));
-named!(parse_vm_pin<usize>,         delimited!(tag!("VmPin:"),        parse_kb, line_ending));
-named!(parse_vm_hwm<usize>,         delimited!(tag!("VmHWM:"),        parse_kb, line_ending));
-named!(parse_vm_rss<usize>,         delimited!(tag!("VmRSS:"),        parse_kb, line_ending));
-named!(parse_vm_rss_anon<usize>,    delimited!(tag!("RssAnon:"),      parse_kb, line_ending));
-named!(parse_vm_rss_file<usize>,    delimited!(tag!("RssFile:"),      parse_kb, line_ending));
-named!(parse_vm_rss_shared<usize>,  delimited!(tag!("RssShmem:"),     parse_kb, line_ending));
-named!(parse_vm_data<usize>,        delimited!(tag!("VmData:"),       parse_kb, line_ending));
-named!(parse_vm_stack<usize>,       delimited!(tag!("VmStk:"),        parse_kb, line_ending));
-named!(parse_vm_exe<usize>,         delimited!(tag!("VmExe:"),        parse_kb, line_ending));
-named!(parse_vm_lib<usize>,         delimited!(tag!("VmLib:"),        parse_kb, line_ending));
-named!(parse_vm_pte<usize>,         delimited!(tag!("VmPTE:"),        parse_kb, line_ending));
-named!(parse_vm_pmd<usize>,         delimited!(tag!("VmPMD:"),        parse_kb, line_ending));
-named!(parse_vm_swap<usize>,        delimited!(tag!("VmSwap:"),       parse_kb, line_ending));
-named!(parse_hugetlb_pages<usize>,  delimited!(tag!("HugetlbPages:"), parse_kb, line_ending));
+named!(parse_vm_peak<u64>,          delimited!(tag!("VmPeak:"),       parse_kb_value, line_ending));
+named!(parse_vm_size<u64>,          delimited!(tag!("VmSize:"),       parse_kb_value, line_ending));
+named!(parse_vm_locked<u64>,        delimited!(tag!("VmLck:"),        parse_kb_value, line_ending));
+named!(parse_vm_pin<u64>,           delimited!(tag!("VmPin:"),        parse_kb_value, line_ending));
+named!(parse_vm_hwm<u64>,           delimited!(tag!("VmHWM:"),        parse_kb_value, line_ending));
+named!(parse_vm_rss<u64>,           delimited!(tag!("VmRSS:"),        parse_kb_value, line_ending));
+named!(parse_vm_rss_anon<u64>,      delimited!(tag!("RssAnon:"),      parse_kb_value, line_ending));
+named!(parse_vm_rss_file<u64>,      delimited!(tag!("RssFile:"),      parse_kb_value, line_ending));
+named!(parse_vm_rss_shared<u64>,    delimited!(tag!("RssShmem:"),     parse_kb_value, line_ending));
+named!(parse_vm_data<u64>,          delimited!(tag!("VmData:"),       parse_kb_value, line_ending));
+named!(parse_vm_stack<u64>,         delimited!(tag!("VmStk:"),        parse_kb_value, line_ending));
+named!(parse_vm_exe<u64>,           delimited!(tag!("VmExe:"),        parse_kb_value, line_ending));
+named!(parse_vm_lib<u64>,           delimited!(tag!("VmLib:"),        parse_kb_value, line_ending));
+named!(parse_vm_pte<u64>,           delimited!(tag!("VmPTE:"),        parse_kb_value, line_ending));
+named!(parse_vm_pmd<u64>,           delimited!(tag!("VmPMD:"),        parse_kb_value, line_ending));
+named!(parse_vm_swap<u64>,          delimited!(tag!("VmSwap:"),       parse_kb_value, line_ending));
+named!(parse_hugetlb_pages<u64>,    delimited!(tag!("HugetlbPages:"), parse_kb_value, line_ending));
 
 named!(parse_threads<u32>, delimited!(tag!("Threads:\t"), parse_u32, line_ending));
 
@@ -237,19 +327,39 @@ named!(parse_sig_blocked<u64>,         delimited!(tag!("SigBlk:\t"), parse_u64_h
 named!(parse_sig_ignored<u64>,         delimited!(tag!("SigIgn:\t"), parse_u64_hex, line_ending));
 named!(parse_sig_caught<u64>,          delimited!(tag!("SigCgt:\t"), parse_u64_hex, line_ending));
 
-named!(parse_cap_inherited<u64>, delimited!(tag!("CapInh:\t"), parse_u64_hex, line_ending));
-named!(parse_cap_permitted<u64>, delimited!(tag!("CapPrm:\t"), parse_u64_hex, line_ending));
-named!(parse_cap_effective<u64>, delimited!(tag!("CapEff:\t"), parse_u64_hex, line_ending));
-named!(parse_cap_bounding<u64>,  delimited!(tag!("CapBnd:\t"), parse_u64_hex, line_ending));
-named!(parse_cap_ambient<u64>,  delimited!(tag!("CapAmb:\t"), parse_u64_hex, line_ending));
+/// Parses a capability set in hexadecimal format.
+named!(parse_capabilities<Capabilities>, map!(parse_u64_hex, Capabilities::from_raw));
+
+named!(parse_cap_inherited<Capabilities>, delimited!(tag!("CapInh:\t"), parse_capabilities, line_ending));
+named!(parse_cap_permitted<Capabilities>, delimited!(tag!("CapPrm:\t"), parse_capabilities, line_ending));
+named!(parse_cap_effective<Capabilities>, delimited!(tag!("CapEff:\t"), parse_capabilities, line_ending));
+named!(parse_cap_bounding<Capabilities>,  delimited!(tag!("CapBnd:\t"), parse_capabilities, line_ending));
+named!(parse_cap_ambient<Capabilities>,  delimited!(tag!("CapAmb:\t"), parse_capabilities, line_ending));
 
 named!(parse_no_new_privs<bool>,       delimited!(tag!("NoNewPrivs:\t"),   parse_bit,           line_ending));
 named!(parse_seccomp<SeccompMode>,     delimited!(tag!("Seccomp:\t"),      parse_seccomp_mode,  line_ending));
-named!(parse_cpus_allowed<Box<[u8]> >, delimited!(tag!("Cpus_allowed:\t"), parse_u32_mask_list, line_ending));
-named!(parse_mems_allowed<Box<[u8]> >, delimited!(tag!("Mems_allowed:\t"), parse_u32_mask_list, line_ending));
+named!(parse_seccomp_filters<u32>,     delimited!(tag!("Seccomp_filters:\t"), parse_u32,        line_ending));
 
-named!(parse_cpus_allowed_list<()>, chain!(tag!("Cpus_allowed_list:\t") ~ not_line_ending ~ line_ending, || { () }));
-named!(parse_mems_allowed_list<()>, chain!(tag!("Mems_allowed_list:\t") ~ not_line_ending ~ line_ending, || { () }));
+named!(parse_speculation_store_bypass<SpeculationStoreBypass>,
+       delimited!(tag!("Speculation_Store_Bypass:\t"),
+                  map!(parse_line, SpeculationStoreBypass::from_description),
+                  line_ending));
+named!(parse_speculation_indirect_branch<SpeculationIndirectBranch>,
+       delimited!(tag!("SpeculationIndirectBranch:\t"),
+                  map!(parse_line, SpeculationIndirectBranch::from_description),
+                  line_ending));
+
+/// Parses a set of CPUs or memory nodes in the mask format.
+named!(parse_cpu_mask<CpuSet>, map_res!(map_res!(not_line_ending, str::from_utf8), CpuSet::from_mask));
+
+named!(parse_cpus_allowed<CpuSet>, delimited!(tag!("Cpus_allowed:\t"), parse_cpu_mask, line_ending));
+named!(parse_mems_allowed<CpuSet>, delimited!(tag!("Mems_allowed:\t"), parse_cpu_mask, line_ending));
+
+named!(parse_cpus_allowed_list<()>, do_parse!(tag!("Cpus_allowed_list:\t") >> not_line_ending >> line_ending >> ()));
+named!(parse_mems_allowed_list<()>, do_parse!(tag!("Mems_allowed_list:\t") >> not_line_ending >> line_ending >> ()));
+
+/// Consumes a line which is not recognized, e.g. a field added in a newer kernel version.
+named!(parse_unknown_line<()>, do_parse!(not_line_ending >> line_ending >> ()));
 
 named!(parse_voluntary_ctxt_switches<u64>,    delimited!(tag!("voluntary_ctxt_switches:\t"),    parse_u64, line_ending));
 named!(parse_nonvoluntary_ctxt_switches<u64>, delimited!(tag!("nonvoluntary_ctxt_switches:\t"), parse_u64, line_ending));
@@ -315,13 +425,17 @@ fn parse_status(i: &[u8]) -> IResult<&[u8], Status> {
                | parse_cap_ambient   => { |value| status.cap_ambient   = value }
 
                | parse_no_new_privs  => { |value| status.no_new_privs  = value }
-               | parse_seccomp       => { |value| status.seccomp       = value }
+               | parse_seccomp       => { |value| status.seccomp       = Some(value) }
+               | parse_seccomp_filters => { |value| status.seccomp_filters = Some(value) }
+               | parse_speculation_store_bypass    => { |value| status.speculation_store_bypass    = Some(value) }
+               | parse_speculation_indirect_branch => { |value| status.speculation_indirect_branch = Some(value) }
                | parse_cpus_allowed  => { |value| status.cpus_allowed  = value }
                | parse_cpus_allowed_list
                | parse_mems_allowed  => { |value| status.mems_allowed  = value }
                | parse_mems_allowed_list
                | parse_voluntary_ctxt_switches    => { |value| status.voluntary_ctxt_switches    = value }
                | parse_nonvoluntary_ctxt_switches => { |value| status.nonvoluntary_ctxt_switches = value }
+               | parse_unknown_line
             )
         ),
         { |_| { status }})
@@ -329,24 +443,42 @@ fn parse_status(i: &[u8]) -> IResult<&[u8], Status> {
 
 /// Parses the provided status file.
 fn status_file(file: &mut File) -> Result<Status> {
-    let mut buf = [0; 2048]; // A typical status file is about 1000 bytes
-    map_result(parse_status(try!(read_to_end(file, &mut buf))))
+    let mut buf = Vec::with_capacity(2048); // A typical status file is about 1000 bytes
+    Status::parse(read_to_end(file, &mut buf)?)
 }
 
 /// Returns memory status information for the process with the provided pid.
 pub fn status(pid: pid_t) -> Result<Status> {
-    status_file(&mut try!(File::open(&format!("/proc/{}/status", pid))))
+    status_file(&mut platform::open(format!("/proc/{}/status", pid))?)
 }
 
 /// Returns memory status information for the current process.
 pub fn status_self() -> Result<Status> {
-    status_file(&mut try!(File::open("/proc/self/status")))
+    status_file(&mut platform::open("/proc/self/status")?)
+}
+
+/// Returns status information for the process with the provided pid, reading the file
+/// asynchronously.
+#[cfg(feature = "async")]
+pub fn status_async(pid: pid_t) -> impl Future<Output = Result<Status>> + Send {
+    platform::read_async(format!("/proc/{}/status", pid), Status::parse)
+}
+
+/// Returns status information for the current process, reading the file asynchronously.
+#[cfg(feature = "async")]
+pub fn status_self_async() -> impl Future<Output = Result<Status>> + Send {
+    platform::read_async("/proc/self/status".to_owned(), Status::parse)
 }
 
 #[cfg(test)]
 mod tests {
-    use parsers::tests::unwrap;
-    use super::{SeccompMode, parse_status, status, status_self};
+    use std::thread;
+
+    use libc::{self, pid_t};
+
+    use parsers::tests::{fixture_file, unwrap};
+    use super::{SeccompMode, SpeculationIndirectBranch, SpeculationStoreBypass, Status, parse_status, status,
+                status_file, status_self};
     use pid::State;
 
     /// Test that the system status files can be parsed.
@@ -356,6 +488,36 @@ mod tests {
         status(1).unwrap();
     }
 
+    /// Test that lines which are not recognized, e.g. fields added by newer kernels, are skipped.
+    #[test]
+    fn test_parse_status_unknown_lines() {
+        let status = b"Name:\tsystemd\n\
+                       Umask:\t0000\n\
+                       State:\tS (sleeping)\n\
+                       Tgid:\t1\n\
+                       Kthread:\t0\n\
+                       x86_Thread_features:\t\n\
+                       voluntary_ctxt_switches:\t242129\n\
+                       nonvoluntary_ctxt_switches:\t1748\n";
+        let status = unwrap(parse_status(status));
+        assert_eq!("systemd", status.command);
+        assert_eq!(State::Sleeping, status.state);
+        assert_eq!(1, status.pid);
+        assert_eq!(242129, status.voluntary_ctxt_switches);
+        assert_eq!(1748, status.nonvoluntary_ctxt_switches);
+    }
+
+    /// Test that a thread whose command is not valid UTF-8 can be parsed.
+    #[test]
+    fn test_status_invalid_utf8_command() {
+        thread::spawn(|| {
+            let name = b"bad\xffname\0";
+            assert_eq!(0, unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) });
+            let tid = unsafe { libc::syscall(libc::SYS_gettid) } as pid_t;
+            assert_eq!("bad\u{FFFD}name", status(tid).unwrap().command);
+        }).join().unwrap();
+    }
+
     #[test]
     fn test_parse_status() {
         let status_text = b"Name:\tsystemd\n\
@@ -413,6 +575,7 @@ mod tests {
                             nonvoluntary_ctxt_switches:\t1748\n";
 
         let status = unwrap(parse_status(status_text));
+        assert_eq!(status, Status::parse(status_text).unwrap());
         assert_eq!("systemd", status.command);
         assert_eq!(18, status.umask);
         assert_eq!(State::Sleeping, status.state);
@@ -435,21 +598,21 @@ mod tests {
         assert_eq!(vec![1], status.ns_tids);
         assert_eq!(vec![1], status.ns_pgids);
         assert_eq!(vec![1], status.ns_sids);
-        assert_eq!(10927688, status.vm_peak);
-        assert_eq!(47348, status.vm_size);
+        assert_eq!(10927688 * 1024, status.vm_peak);
+        assert_eq!(47348 * 1024, status.vm_size);
         assert_eq!(0, status.vm_locked);
         assert_eq!(0, status.vm_pin);
-        assert_eq!(9212, status.vm_hwm);
-        assert_eq!(9212, status.vm_rss);
-        assert_eq!(3700, status.vm_rss_anon);
-        assert_eq!(5768, status.vm_rss_file);
+        assert_eq!(9212 * 1024, status.vm_hwm);
+        assert_eq!(9212 * 1024, status.vm_rss);
+        assert_eq!(3700 * 1024, status.vm_rss_anon);
+        assert_eq!(5768 * 1024, status.vm_rss_file);
         assert_eq!(0, status.vm_rss_shared);
-        assert_eq!(3424, status.vm_data);
-        assert_eq!(136, status.vm_stack);
-        assert_eq!(1320, status.vm_exe);
-        assert_eq!(3848, status.vm_lib);
-        assert_eq!(108, status.vm_pte);
-        assert_eq!(12, status.vm_pmd);
+        assert_eq!(3424 * 1024, status.vm_data);
+        assert_eq!(136 * 1024, status.vm_stack);
+        assert_eq!(1320 * 1024, status.vm_exe);
+        assert_eq!(3848 * 1024, status.vm_lib);
+        assert_eq!(108 * 1024, status.vm_pte);
+        assert_eq!(12 * 1024, status.vm_pmd);
         assert_eq!(0, status.vm_swap);
         assert_eq!(0, status.hugetlb_pages);
         assert_eq!(1, status.threads);
@@ -460,20 +623,92 @@ mod tests {
         assert_eq!(0x7be3c0fe28014a03, status.sig_blocked);
         assert_eq!(0x0000000000001000, status.sig_ignored);
         assert_eq!(0x00000001800004ec, status.sig_caught);
-        assert_eq!(0x0000000000000000, status.cap_inherited);
-        assert_eq!(0x0000003fffffffff, status.cap_permitted);
-        assert_eq!(0x0000003fffffffff, status.cap_effective);
-        assert_eq!(0x0000003fffffffff, status.cap_bounding);
-        assert_eq!(0x0000000000000000, status.cap_ambient);
-        assert_eq!(false, status.no_new_privs);
-        assert_eq!(SeccompMode::Disabled, status.seccomp);
-        assert_eq!(&[0xff, 0xff, 0x00, 0x00], &*status.cpus_allowed);
-        let mems_allowed: &mut [u8] = &mut [0; 64];
-        mems_allowed[0] = 0x80;
-        assert_eq!(mems_allowed, &*status.mems_allowed);
+        assert_eq!(0x0000000000000000, status.cap_inherited.raw());
+        assert_eq!(0x0000003fffffffff, status.cap_permitted.raw());
+        assert_eq!(0x0000003fffffffff, status.cap_effective.raw());
+        assert_eq!(0x0000003fffffffff, status.cap_bounding.raw());
+        assert_eq!(0x0000000000000000, status.cap_ambient.raw());
+        assert!(!status.no_new_privs);
+        assert_eq!(Some(SeccompMode::Disabled), status.seccomp);
+        assert_eq!(None, status.seccomp_filters);
+        assert_eq!(None, status.speculation_store_bypass);
+        assert_eq!(None, status.speculation_indirect_branch);
+        assert_eq!("0-15", status.cpus_allowed.to_string());
+        assert_eq!(16, status.cpus_allowed.count());
+        assert_eq!(vec![0], status.mems_allowed.iter().collect::<Vec<_>>());
         assert_eq!(242129, status.voluntary_ctxt_switches);
         assert_eq!(1748, status.nonvoluntary_ctxt_switches);
     }
+
+    /// Test the security fields of a newer kernel, and their absence on an older kernel.
+    #[test]
+    fn test_parse_status_security() {
+        let status = Status::parse(b"Name:\tsshd\n\
+                                     NoNewPrivs:\t1\n\
+                                     Seccomp:\t2\n\
+                                     Seccomp_filters:\t3\n\
+                                     Speculation_Store_Bypass:\tthread force mitigated\n\
+                                     SpeculationIndirectBranch:\tconditional enabled\n\
+                                     Cpus_allowed:\tff\n").unwrap();
+        assert!(status.no_new_privs);
+        assert_eq!(Some(SeccompMode::Filter), status.seccomp);
+        assert_eq!(Some(3), status.seccomp_filters);
+        assert_eq!(Some(SpeculationStoreBypass::ThreadForceMitigated), status.speculation_store_bypass);
+        assert_eq!(Some(SpeculationIndirectBranch::ConditionalEnabled), status.speculation_indirect_branch);
+        assert_eq!(8, status.cpus_allowed.count());
+
+        // Linux 3.2, before the seccomp and speculation fields.
+        let status = Status::parse(b"Name:\tinit\n\
+                                     State:\tS (sleeping)\n\
+                                     CapBnd:\tffffffffffffffff\n\
+                                     Cpus_allowed:\tff\n").unwrap();
+        assert_eq!(None, status.seccomp);
+        assert_eq!(None, status.seccomp_filters);
+        assert_eq!(None, status.speculation_store_bypass);
+        assert_eq!(None, status.speculation_indirect_branch);
+
+        let status = Status::parse(b"Seccomp:\t1\n\
+                                     Speculation_Store_Bypass:\tnot vulnerable\n\
+                                     SpeculationIndirectBranch:\tsomething new\n").unwrap();
+        assert_eq!(Some(SeccompMode::Strict), status.seccomp);
+        assert_eq!(Some(SpeculationStoreBypass::NotVulnerable), status.speculation_store_bypass);
+        assert_eq!(Some(SpeculationIndirectBranch::Other("something new".to_owned())),
+                   status.speculation_indirect_branch);
+    }
+
+    /// Test that status files larger than the typical read buffer are parsed completely. Machines
+    /// with many NUMA nodes have very long `Mems_allowed` lines.
+    #[test]
+    fn test_status_file_large() {
+        let mut mems_allowed = vec!["00000000"; 511];
+        mems_allowed.push("00000001");
+        let status_text = format!("Name:\tsystemd\n\
+                                   State:\tS (sleeping)\n\
+                                   Tgid:\t1\n\
+                                   Mems_allowed:\t{}\n\
+                                   Mems_allowed_list:\t0\n\
+                                   voluntary_ctxt_switches:\t242129\n\
+                                   nonvoluntary_ctxt_switches:\t1748\n",
+                                  mems_allowed.join(","));
+        assert!(status_text.len() > 4096);
+
+        let status = status_file(&mut fixture_file(status_text.as_bytes())).unwrap();
+        assert_eq!("systemd", status.command);
+        assert_eq!(1, status.pid);
+        assert_eq!(vec![0], status.mems_allowed.iter().collect::<Vec<_>>());
+        assert_eq!(242129, status.voluntary_ctxt_switches);
+        assert_eq!(1748, status.nonvoluntary_ctxt_switches);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_status() {
+        use serde_json;
+
+        let status = status_self().unwrap();
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(status, serde_json::from_str(&json).unwrap());
+    }
 }
 
 #[cfg(all(test, rustc_nightly))]
@@ -492,7 +727,7 @@ mod benches {
 
     #[bench]
     fn bench_status_parse(b: &mut test::Bencher) {
-        let mut buf = [0; 2048];
+        let mut buf = Vec::with_capacity(2048);
         let status = read_to_end(&mut File::open("/proc/1/status").unwrap(), &mut buf).unwrap();
         b.iter(|| test::black_box(parse_status(status)));
     }
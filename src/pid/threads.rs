@@ -0,0 +1,260 @@
+//! Process-level summaries of the threads in `/proc/[pid]/task/`.
+
+use std::collections::HashMap;
+use std::io::Result;
+use std::thread;
+use std::time::Duration;
+
+use parsers::is_exited;
+use pid::{Schedstat, Stat, State};
+use pid::dir::PidDir;
+use platform::{self, pid_t};
+
+/// The CPU time of a thread.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThreadCpu {
+    /// Thread ID.
+    pub tid: pid_t,
+    /// Name of the thread, see `Stat::command`.
+    pub command: String,
+    /// State of the thread.
+    pub state: State,
+    /// Time the thread has spent running on a CPU since it started, see `Schedstat::run_time`.
+    pub run_time: Duration,
+    /// Time the thread has spent running on a CPU during the interval of the summary.
+    pub recent_run_time: Duration,
+}
+
+/// The number of threads in each state.
+///
+/// The default value has every count set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThreadStates {
+    /// Running or runnable threads.
+    pub running: u32,
+    /// Threads sleeping in an interruptible wait, including idle kernel threads.
+    pub sleeping: u32,
+    /// Threads waiting in uninterruptible disk sleep.
+    pub disk_sleep: u32,
+    /// Threads stopped by a signal or by a tracer.
+    pub stopped: u32,
+    /// Threads which have exited, but have not yet been reaped.
+    pub zombie: u32,
+    /// Threads in any other state.
+    pub other: u32,
+}
+
+impl ThreadStates {
+    fn add(&mut self, state: State) {
+        let count = match state {
+            State::Running => &mut self.running,
+            State::Sleeping | State::Idle => &mut self.sleeping,
+            State::Waiting => &mut self.disk_sleep,
+            State::Stopped | State::TraceStopped => &mut self.stopped,
+            State::Zombie => &mut self.zombie,
+            _ => &mut self.other,
+        };
+        *count += 1;
+    }
+}
+
+/// A summary of the threads of a process.
+///
+/// The counts are those of the threads at the end of the interval of the summary. The threads are
+/// read one by one, so the summary is not an atomic snapshot: threads which exit while they are
+/// read are counted in `skipped`, and threads created meanwhile may be missing.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThreadSummary {
+    /// The number of threads which were read.
+    pub threads: u32,
+    /// The number of threads which exited before they could be read.
+    pub skipped: u32,
+    /// The threads with the most CPU time during the interval, in decreasing order of
+    /// `recent_run_time`.
+    pub top: Vec<ThreadCpu>,
+    /// The total time the threads have spent waiting on a run queue for a CPU, see
+    /// `Schedstat::wait_time`.
+    pub run_queue_wait: Duration,
+    /// The number of threads in each state.
+    pub states: ThreadStates,
+}
+
+/// Adds a thread to a summary, keeping the `top_n` threads with the most recent CPU time.
+fn add_thread(summary: &mut ThreadSummary, top_n: usize, thread: ThreadCpu, wait_time: Duration) {
+    summary.threads += 1;
+    summary.run_queue_wait += wait_time;
+    summary.states.add(thread.state);
+    let idx = summary.top.iter()
+                         .position(|top| (thread.recent_run_time, -thread.tid) > (top.recent_run_time, -top.tid))
+                         .unwrap_or(summary.top.len());
+    if idx < top_n {
+        summary.top.insert(idx, thread);
+        summary.top.truncate(top_n);
+    }
+}
+
+/// Reads the stat and schedstat files of each thread of a process directory.
+///
+/// Returns the number of threads which exited before they could be read.
+fn walk_threads<F>(dir: &str, mut f: F) -> Result<u32> where F: FnMut(pid_t, Stat, Schedstat) {
+    let mut skipped = 0;
+    let mut buf = Vec::with_capacity(1024); // A typical stat file is about 300 bytes.
+    for entry in platform::read_dir(format!("{}/task", dir))? {
+        let tid = match entry?.file_name().to_str().and_then(|name| name.parse::<pid_t>().ok()) {
+            Some(tid) => tid,
+            None => continue,
+        };
+        // Both files are read through a handle of the thread directory, so they are of the same thread
+        // even if it exits and its tid is reused in between.
+        let thread = PidDir::open_path(tid, format!("{}/task/{}", dir, tid)).and_then(|thread| {
+            let stat = thread.read::<Stat>(&mut buf)?;
+            Ok((stat, thread.read::<Schedstat>(&mut buf)?))
+        });
+        match thread {
+            Ok((stat, schedstat)) => f(tid, stat, schedstat),
+            Err(ref error) if is_exited(error) => skipped += 1,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(skipped)
+}
+
+/// Summarizes the threads of a process directory over an interval.
+fn thread_summary_dir(dir: &str, top_n: usize, interval: Duration) -> Result<ThreadSummary> {
+    // The start time tells a thread from a later one which reused its tid.
+    let mut earlier = HashMap::new();
+    walk_threads(dir, |tid, stat, schedstat| {
+        earlier.insert(tid, (stat.start_time, schedstat.run_time));
+    })?;
+    thread::sleep(interval);
+
+    let mut summary = ThreadSummary::default();
+    let skipped = walk_threads(dir, |tid, stat, schedstat| {
+        let recent_run_time = match earlier.get(&tid) {
+            Some(&(start_time, run_time)) if start_time == stat.start_time => {
+                schedstat.run_time.saturating_sub(run_time)
+            }
+            // The thread started during the interval.
+            _ => schedstat.run_time,
+        };
+        let thread = ThreadCpu {
+            tid,
+            command: stat.command,
+            state: stat.state,
+            run_time: schedstat.run_time,
+            recent_run_time,
+        };
+        add_thread(&mut summary, top_n, thread, schedstat.wait_time);
+    })?;
+    summary.skipped = skipped;
+    Ok(summary)
+}
+
+/// Returns a summary of the threads of the process with the provided pid, including the `top_n`
+/// threads with the most CPU time over the provided interval.
+///
+/// The threads are read at the start and at the end of the interval, and ranked by the increase of
+/// their CPU time in between. The CPU time of each thread is read from
+/// `/proc/[pid]/task/[tid]/schedstat`, so the kernel must be built with `CONFIG_SCHED_INFO`.
+///
+/// This blocks the current thread for the duration of the interval.
+pub fn thread_summary(pid: pid_t, top_n: usize, interval: Duration) -> Result<ThreadSummary> {
+    thread_summary_dir(&format!("/proc/{}", pid), top_n, interval)
+}
+
+/// Returns a summary of the threads of the current process, see `thread_summary`.
+pub fn thread_summary_self(top_n: usize, interval: Duration) -> Result<ThreadSummary> {
+    thread_summary_dir("/proc/self", top_n, interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, mpsc};
+    use std::thread;
+    use std::time::Duration;
+    use std::process;
+
+    use libc::{self, pid_t};
+
+    use pid::State;
+    use super::{ThreadCpu, ThreadStates, ThreadSummary, add_thread, thread_summary, thread_summary_self};
+
+    fn thread(tid: pid_t, state: State, recent_millis: u64) -> ThreadCpu {
+        ThreadCpu {
+            tid,
+            command: "worker".to_owned(),
+            state,
+            run_time: Duration::from_secs(1),
+            recent_run_time: Duration::from_millis(recent_millis),
+        }
+    }
+
+    #[test]
+    fn test_add_thread() {
+        let mut summary = ThreadSummary::default();
+        add_thread(&mut summary, 2, thread(10, State::Sleeping, 5), Duration::from_millis(1));
+        add_thread(&mut summary, 2, thread(11, State::Running, 50), Duration::from_millis(2));
+        add_thread(&mut summary, 2, thread(12, State::Waiting, 1), Duration::from_millis(3));
+        add_thread(&mut summary, 2, thread(13, State::Idle, 50), Duration::from_millis(4));
+        add_thread(&mut summary, 2, thread(14, State::Zombie, 20), Duration::from_millis(5));
+
+        assert_eq!(5, summary.threads);
+        assert_eq!(0, summary.skipped);
+        // Ties are ranked by thread ID.
+        assert_eq!(vec![thread(11, State::Running, 50), thread(13, State::Idle, 50)], summary.top);
+        assert_eq!(Duration::from_millis(15), summary.run_queue_wait);
+        assert_eq!(ThreadStates { running: 1, sleeping: 2, disk_sleep: 1, zombie: 1, ..ThreadStates::default() },
+                   summary.states);
+
+        let mut summary = ThreadSummary::default();
+        add_thread(&mut summary, 0, thread(10, State::Running, 5), Duration::from_millis(1));
+        assert!(summary.top.is_empty());
+        assert_eq!(1, summary.threads);
+    }
+
+    /// Test that busy threads are ranked before idle threads.
+    #[test]
+    fn test_thread_summary() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let mut threads = Vec::new();
+        for busy in &[false, true, false, true] {
+            let busy = *busy;
+            let stop = stop.clone();
+            let sender = sender.clone();
+            threads.push(thread::spawn(move || {
+                let tid = unsafe { libc::syscall(libc::SYS_gettid) } as pid_t;
+                sender.send((tid, busy)).unwrap();
+                while !stop.load(Ordering::Relaxed) {
+                    if !busy {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                }
+            }));
+        }
+        let tids: Vec<(pid_t, bool)> = receiver.iter().take(4).collect();
+        let summary = thread_summary(process::id() as pid_t, usize::MAX, Duration::from_millis(300));
+        stop.store(true, Ordering::Relaxed);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let summary = summary.unwrap();
+        assert!(summary.threads >= 5, "{:?}", summary);
+        assert_eq!(summary.threads as usize, summary.top.len());
+        assert!(summary.states.running >= 1);
+        let rank = |tid: pid_t| summary.top.iter().position(|thread| thread.tid == tid).unwrap();
+        let busy = tids.iter().filter(|&&(_, busy)| busy).map(|&(tid, _)| rank(tid)).max().unwrap();
+        let idle = tids.iter().filter(|&&(_, busy)| !busy).map(|&(tid, _)| rank(tid)).min().unwrap();
+        assert!(busy < idle, "{:?}", summary);
+        for thread in &summary.top {
+            assert!(thread.recent_run_time <= thread.run_time, "{:?}", thread);
+        }
+
+        assert_eq!(1, thread_summary_self(1, Duration::from_millis(0)).unwrap().top.len());
+    }
+}
@@ -0,0 +1,198 @@
+//! Waiting for the exit of an arbitrary process by polling `/proc/[pid]/stat`.
+
+use std::cmp;
+use std::io::{ErrorKind, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parsers::is_exited;
+use pid::{Stat, State, stat};
+use platform::pid_t;
+
+/// The delay before the second poll.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(1);
+
+/// The maximum delay between two polls.
+const MAX_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The outcome of `wait_for_exit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WaitOutcome {
+    /// The process exited, or is a zombie.
+    Exited,
+    /// The timeout expired before the process exited.
+    TimedOut,
+    /// No process with the pid existed when the wait started.
+    NeverExisted,
+}
+
+/// The result of a single poll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Poll {
+    /// The process is running, with the provided start time in clock ticks.
+    Running(u64),
+    /// The process has exited, or is a zombie.
+    Exited,
+    /// There is no process with the pid.
+    Missing,
+    /// The stat file of the process can not be read.
+    Unreadable,
+}
+
+/// Returns the result of a poll from a read of the stat file of a process.
+///
+/// A process which is reaped while its stat file is read fails the read with `ESRCH` rather than
+/// `NotFound`; it existed when the file was opened, so it has exited.
+fn poll_result(stat: Result<Stat>) -> Result<Poll> {
+    match stat {
+        Ok(stat) => match stat.state {
+            State::Zombie | State::Dead => Ok(Poll::Exited),
+            _ => Ok(Poll::Running(stat.start_time)),
+        },
+        Err(ref error) if error.kind() == ErrorKind::NotFound => Ok(Poll::Missing),
+        Err(ref error) if is_exited(error) => Ok(Poll::Exited),
+        Err(ref error) if error.kind() == ErrorKind::PermissionDenied => Ok(Poll::Unreadable),
+        Err(error) => Err(error),
+    }
+}
+
+/// Reads the stat file of a process once.
+fn poll(pid: pid_t) -> Result<Poll> {
+    poll_result(stat(pid))
+}
+
+/// Returns whether the process which started at `start_time` has exited, given a later poll.
+///
+/// A process with a different start time has reused the pid, so the original process has exited.
+/// While the stat file can not be read, pid reuse can not be detected and the process is assumed to
+/// be running.
+fn has_exited(start_time: Option<u64>, poll: Poll) -> bool {
+    match poll {
+        Poll::Running(time) => start_time.is_some_and(|start_time| time != start_time),
+        Poll::Exited | Poll::Missing => true,
+        Poll::Unreadable => false,
+    }
+}
+
+/// Waits until the process with the provided pid exits, or until the timeout expires.
+///
+/// Unlike `waitpid(2)`, this works for processes which are not children of the current process.
+/// `/proc/[pid]/stat` is polled with an exponential backoff from 1 ms to 100 ms, so the exit is
+/// detected with a delay of up to 100 ms. A zombie process, which has exited but has not yet been
+/// reaped by its parent, is considered to have exited.
+///
+/// The start time of the process is captured at the first poll, and a process with a different
+/// start time is considered to have reused the pid of the exited process. If the stat file can not
+/// be read, for example because the process changed its credentials and `/proc` is mounted with
+/// `hidepid=1`, the process is assumed to be running until it can be read again or disappears.
+///
+/// The timeout is measured with a monotonic clock, so it is not affected by changes to the wall
+/// clock. A timeout of zero polls the process once.
+pub fn wait_for_exit(pid: pid_t, timeout: Option<Duration>) -> Result<WaitOutcome> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let start_time = match poll(pid)? {
+        Poll::Running(start_time) => Some(start_time),
+        Poll::Exited => return Ok(WaitOutcome::Exited),
+        Poll::Missing => return Ok(WaitOutcome::NeverExisted),
+        Poll::Unreadable => None,
+    };
+
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let delay = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(WaitOutcome::TimedOut);
+                }
+                cmp::min(backoff, deadline - now)
+            }
+            None => backoff,
+        };
+        thread::sleep(delay);
+        backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+
+        let poll = poll(pid)?;
+        if has_exited(start_time, poll) {
+            return Ok(WaitOutcome::Exited);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind};
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    use libc::{self, pid_t};
+
+    use pid::{Stat, State, stat};
+    use super::{Poll, WaitOutcome, has_exited, poll_result, wait_for_exit};
+
+    #[test]
+    fn test_poll_result() {
+        let running = Stat { state: State::Sleeping, start_time: 100, ..Stat::default() };
+        assert_eq!(Poll::Running(100), poll_result(Ok(running)).unwrap());
+        let zombie = Stat { state: State::Zombie, start_time: 100, ..Stat::default() };
+        assert_eq!(Poll::Exited, poll_result(Ok(zombie)).unwrap());
+
+        assert_eq!(Poll::Missing, poll_result(Err(Error::from(ErrorKind::NotFound))).unwrap());
+        // The process was reaped while its stat file was read.
+        assert_eq!(Poll::Exited, poll_result(Err(Error::from_raw_os_error(libc::ESRCH))).unwrap());
+        assert_eq!(Poll::Unreadable, poll_result(Err(Error::from(ErrorKind::PermissionDenied))).unwrap());
+        assert_eq!(Some(libc::EIO), poll_result(Err(Error::from_raw_os_error(libc::EIO))).unwrap_err().raw_os_error());
+    }
+
+    #[test]
+    fn test_has_exited() {
+        assert!(!has_exited(Some(100), Poll::Running(100)));
+        // The pid was reused by a process started later.
+        assert!(has_exited(Some(100), Poll::Running(250)));
+        assert!(has_exited(Some(100), Poll::Exited));
+        assert!(has_exited(Some(100), Poll::Missing));
+        assert!(!has_exited(Some(100), Poll::Unreadable));
+        // The start time is unknown if the first poll could not read the stat file.
+        assert!(!has_exited(None, Poll::Running(250)));
+        assert!(has_exited(None, Poll::Missing));
+    }
+
+    /// Test that the exit of a child is observed, without reaping it.
+    #[test]
+    fn test_wait_for_exit() {
+        let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+        let pid = child.id() as pid_t;
+
+        let outcome = wait_for_exit(pid, Some(Duration::from_secs(10)));
+        // The child is a zombie until it is reaped.
+        let state = stat(pid).map(|stat| stat.state);
+        child.wait().unwrap();
+
+        assert_eq!(WaitOutcome::Exited, outcome.unwrap());
+        assert_eq!(State::Zombie, state.unwrap());
+    }
+
+    #[test]
+    fn test_wait_for_exit_timeout() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = child.id() as pid_t;
+
+        let start = Instant::now();
+        let outcome = wait_for_exit(pid, Some(Duration::from_millis(50)));
+        let elapsed = start.elapsed();
+        let immediate = wait_for_exit(pid, Some(Duration::from_secs(0)));
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(WaitOutcome::TimedOut, outcome.unwrap());
+        assert!(elapsed >= Duration::from_millis(50) && elapsed < Duration::from_secs(5), "{:?}", elapsed);
+        assert_eq!(WaitOutcome::TimedOut, immediate.unwrap());
+    }
+
+    #[test]
+    fn test_wait_for_exit_never_existed() {
+        // Larger than the maximum pid_max of 2^22.
+        assert_eq!(WaitOutcome::NeverExisted, wait_for_exit(pid_t::MAX, None).unwrap());
+    }
+}
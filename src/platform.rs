@@ -0,0 +1,289 @@
+//! Platform types and access to the `/proc` filesystem.
+//!
+//! The probes are only supported on Linux. On other targets the platform types are defined as they
+//! are on Linux, so that the public API is the same on every target, and opening a `/proc` file
+//! returns an `ErrorKind::Unsupported` error.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fs::{File, Metadata, ReadDir};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+
+#[cfg(target_os = "linux")]
+pub use libc::{RLIM_INFINITY, clock_t, gid_t, mode_t, pid_t, rlim_t, rlimit, uid_t};
+
+#[cfg(not(target_os = "linux"))]
+pub use self::types::*;
+
+/// The platform types, as defined by the Linux targets of `libc`.
+#[cfg(not(target_os = "linux"))]
+#[allow(non_camel_case_types)]
+mod types {
+    pub type pid_t = i32;
+    pub type uid_t = u32;
+    pub type gid_t = u32;
+    pub type mode_t = u32;
+    #[cfg(target_pointer_width = "64")]
+    pub type clock_t = i64;
+    #[cfg(not(target_pointer_width = "64"))]
+    pub type clock_t = i32;
+    pub type rlim_t = u64;
+
+    pub const RLIM_INFINITY: rlim_t = !0;
+
+    /// A resource limit, see `getrlimit(2)`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct rlimit {
+        pub rlim_cur: rlim_t,
+        pub rlim_max: rlim_t,
+    }
+}
+
+/// Returns the error for an operation which is not supported on the target.
+pub fn unsupported() -> Error {
+    Error::new(ErrorKind::Unsupported, "procinfo is only supported on Linux")
+}
+
+/// Opens a file in `/proc`.
+#[cfg(target_os = "linux")]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<File> {
+    File::open(path)
+}
+
+/// Opens a file in `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn open<P: AsRef<Path>>(_path: P) -> Result<File> {
+    Err(unsupported())
+}
+
+/// Writes `contents` to an existing file in `/proc`.
+#[cfg(target_os = "linux")]
+pub fn write<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<()> {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    OpenOptions::new().write(true).open(path)?.write_all(contents)
+}
+
+/// Writes `contents` to an existing file in `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn write<P: AsRef<Path>>(_path: P, _contents: &[u8]) -> Result<()> {
+    Err(unsupported())
+}
+
+/// Reads a directory in `/proc`.
+#[cfg(target_os = "linux")]
+pub fn read_dir<P: AsRef<Path>>(path: P) -> Result<ReadDir> {
+    ::std::fs::read_dir(path)
+}
+
+/// Reads a directory in `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn read_dir<P: AsRef<Path>>(_path: P) -> Result<ReadDir> {
+    Err(unsupported())
+}
+
+/// Reads a symbolic link in `/proc`.
+#[cfg(target_os = "linux")]
+pub fn read_link<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    ::std::fs::read_link(path)
+}
+
+/// Reads a symbolic link in `/proc`.
+#[cfg(not(target_os = "linux"))]
+pub fn read_link<P: AsRef<Path>>(_path: P) -> Result<PathBuf> {
+    Err(unsupported())
+}
+
+/// Opens a directory in `/proc`, so that the files in it can be opened with `open_at`.
+#[cfg(target_os = "linux")]
+pub fn open_dir<P: AsRef<Path>>(path: P) -> Result<File> {
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new().read(true).custom_flags(libc::O_DIRECTORY).open(path)
+}
+
+/// Opens a directory in `/proc`, so that the files in it can be opened with `open_at`.
+#[cfg(not(target_os = "linux"))]
+pub fn open_dir<P: AsRef<Path>>(_path: P) -> Result<File> {
+    Err(unsupported())
+}
+
+/// Opens a file relative to a directory opened with `open_dir`, see `openat(2)`.
+#[cfg(target_os = "linux")]
+pub fn open_at(dir: &File, name: &str) -> Result<File> {
+    use std::ffi::CString;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let name = CString::new(name).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+    let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Opens a file relative to a directory opened with `open_dir`, see `openat(2)`.
+#[cfg(not(target_os = "linux"))]
+pub fn open_at(_dir: &File, _name: &str) -> Result<File> {
+    Err(unsupported())
+}
+
+/// Returns the device and inode numbers of a file, which identify it on the system.
+#[cfg(target_os = "linux")]
+pub fn file_id(metadata: &Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+/// Returns the device and inode numbers of a file, which identify it on the system.
+#[cfg(not(target_os = "linux"))]
+pub fn file_id(_metadata: &Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+/// Returns whether a path in `/proc` exists, without opening it.
+#[cfg(target_os = "linux")]
+pub fn exists<P: AsRef<Path>>(path: P) -> bool {
+    ::std::fs::symlink_metadata(path).is_ok()
+}
+
+/// Returns whether a path in `/proc` exists, without opening it.
+#[cfg(not(target_os = "linux"))]
+pub fn exists<P: AsRef<Path>>(_path: P) -> bool {
+    false
+}
+
+/// Returns the magic number of the type of the filesystem containing a path, see `statfs(2)`.
+#[cfg(target_os = "linux")]
+pub fn filesystem_type<P: AsRef<Path>>(path: P) -> Result<u64> {
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+    let mut stat: libc::statfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(stat.f_type as u64)
+}
+
+/// Returns the magic number of the type of the filesystem containing a path, see `statfs(2)`.
+#[cfg(not(target_os = "linux"))]
+pub fn filesystem_type<P: AsRef<Path>>(_path: P) -> Result<u64> {
+    Err(unsupported())
+}
+
+/// A future which reads a file in `/proc` with `tokio::fs`, and then parses its contents.
+#[cfg(feature = "async")]
+pub struct ReadFuture<T> {
+    read: Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send>>,
+    parse: fn(&[u8]) -> Result<T>,
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for ReadFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>> {
+        match self.read.as_mut().poll(cx) {
+            Poll::Ready(Ok(buf)) => Poll::Ready((self.parse)(&buf)),
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Reads a file in `/proc` asynchronously, and parses its contents with `parse`.
+///
+/// The file is read on the blocking thread pool of the current tokio runtime.
+#[cfg(all(feature = "async", target_os = "linux"))]
+pub fn read_async<T>(path: String, parse: fn(&[u8]) -> Result<T>) -> ReadFuture<T> {
+    ReadFuture { read: Box::pin(::tokio::fs::read(path)), parse }
+}
+
+/// Reads a file in `/proc` asynchronously, and parses its contents with `parse`.
+///
+/// The file is read on the blocking thread pool of the current tokio runtime.
+#[cfg(all(feature = "async", not(target_os = "linux")))]
+pub fn read_async<T>(_path: String, parse: fn(&[u8]) -> Result<T>) -> ReadFuture<T> {
+    ReadFuture { read: Box::pin(::std::future::ready(Err(unsupported()))), parse }
+}
+
+/// Converts raw bytes to an `OsString`.
+///
+/// On targets where OS strings are not arbitrary bytes, invalid UTF-8 sequences are replaced.
+#[cfg(unix)]
+pub fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).to_owned()
+}
+
+/// Converts raw bytes to an `OsString`.
+///
+/// On targets where OS strings are not arbitrary bytes, invalid UTF-8 sequences are replaced.
+#[cfg(not(unix))]
+pub fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Returns the raw bytes of an `OsStr`.
+///
+/// On targets where OS strings are not arbitrary bytes, invalid sequences are replaced.
+#[cfg(unix)]
+pub fn os_str_as_bytes(string: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(string.as_bytes())
+}
+
+/// Returns the raw bytes of an `OsStr`.
+///
+/// On targets where OS strings are not arbitrary bytes, invalid sequences are replaced.
+#[cfg(not(unix))]
+pub fn os_str_as_bytes(string: &OsStr) -> Cow<'_, [u8]> {
+    Cow::Owned(string.to_string_lossy().into_owned().into_bytes())
+}
+
+/// Converts the raw bytes of a path to a `PathBuf`.
+///
+/// On targets where paths are not arbitrary bytes, invalid UTF-8 sequences are replaced.
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+/// Converts the raw bytes of a path to a `PathBuf`.
+///
+/// On targets where paths are not arbitrary bytes, invalid UTF-8 sequences are replaced.
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::open;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open() {
+        open("/proc/self/stat").unwrap();
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn test_open_unsupported() {
+        use std::io::ErrorKind;
+        assert_eq!(ErrorKind::Unsupported, open("/proc/self/stat").unwrap_err().kind());
+    }
+}
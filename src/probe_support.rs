@@ -0,0 +1,270 @@
+//! The probes which are available on the running kernel.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::str;
+use std::sync::OnceLock;
+
+use parsers::read_to_end;
+use platform;
+
+/// The version of a kernel, from its release string, e.g. `6.1.0-18-amd64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KernelVersion {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch level, or 0 if the release has none.
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    /// Returns the version with the provided components.
+    pub fn new(major: u32, minor: u32, patch: u32) -> KernelVersion {
+        KernelVersion { major, minor, patch }
+    }
+
+    /// Parses a kernel release string, such as the contents of `/proc/sys/kernel/osrelease`.
+    ///
+    /// The suffix of a distribution or release candidate, e.g. `-18-amd64` or `-rc1`, is ignored.
+    pub fn parse(input: &[u8]) -> Result<KernelVersion> {
+        let release = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?.trim();
+        let numbers = release.split(|c: char| !c.is_ascii_digit() && c != '.').next().unwrap_or("");
+        let mut components = numbers.split('.').map(str::parse::<u32>);
+        match (components.next(), components.next(), components.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), patch) => {
+                Ok(KernelVersion::new(major, minor, patch.and_then(|patch| patch.ok()).unwrap_or(0)))
+            }
+            _ => Err(Error::new(ErrorKind::InvalidInput, format!("unable to parse kernel release: {:?}", release))),
+        }
+    }
+}
+
+impl fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Returns the version of the running kernel.
+pub fn kernel_version() -> Result<KernelVersion> {
+    kernel_version_in(Path::new("/proc"))
+}
+
+/// Reads the kernel version from a proc directory.
+pub fn kernel_version_in(root: &Path) -> Result<KernelVersion> {
+    let mut buf = Vec::with_capacity(64);
+    let mut file = platform::open(root.join("sys/kernel/osrelease"))?;
+    KernelVersion::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// The probes which are available on a kernel, see `probe_support`.
+///
+/// The availability of a file depends on the kernel version and configuration, and on the options
+/// of the procfs mount. The per-process files are those of the current process.
+///
+/// The default value has no available probes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProbeSupport {
+    /// The version of the kernel, or `None` if `/proc/sys/kernel/osrelease` can not be read.
+    pub kernel_version: Option<KernelVersion>,
+    /// `/proc/pressure/` (since Linux 4.20, with `CONFIG_PSI`).
+    pub pressure: bool,
+    /// `/proc/diskstats`.
+    pub diskstats: bool,
+    /// The discard counters of `/proc/diskstats` (since Linux 4.18). Without them, the discard
+    /// fields of `DiskStat` are zero.
+    pub diskstats_discards: bool,
+    /// The flush counters of `/proc/diskstats` (since Linux 5.5). Without them, the flush fields
+    /// of `DiskStat` are zero.
+    pub diskstats_flushes: bool,
+    /// `/proc/net/dev_snmp6/` (with `CONFIG_IPV6`, unless IPv6 is disabled).
+    pub dev_snmp6: bool,
+    /// `/proc/zoneinfo`.
+    pub zoneinfo: bool,
+    /// `/proc/[pid]/io` (with `CONFIG_TASK_IO_ACCOUNTING`).
+    pub io: bool,
+    /// `/proc/[pid]/schedstat` (with `CONFIG_SCHED_INFO`).
+    pub schedstat: bool,
+    /// `/proc/[pid]/smaps` (with `CONFIG_PROC_PAGE_MONITOR`).
+    pub smaps: bool,
+    /// `/proc/[pid]/smaps_rollup` (since Linux 4.14, with `CONFIG_PROC_PAGE_MONITOR`).
+    pub smaps_rollup: bool,
+    /// `/proc/[pid]/task/[tid]/children` (with `CONFIG_PROC_CHILDREN`).
+    pub children: bool,
+    /// `/proc/[pid]/uid_map` and `/proc/[pid]/gid_map` (with `CONFIG_USER_NS`).
+    pub id_maps: bool,
+    /// `/proc/[pid]/ns/`.
+    pub namespaces: bool,
+    /// `/proc/[pid]/fdinfo/`.
+    pub fdinfo: bool,
+    /// `/proc/[pid]/map_files/` (since Linux 4.3, or with `CONFIG_CHECKPOINT_RESTORE` before).
+    pub map_files: bool,
+}
+
+impl ProbeSupport {
+    /// Probes the support of the kernel whose procfs is mounted at `root`, without caching.
+    ///
+    /// The files are only `stat`ed, not read, except for the kernel version.
+    pub fn probe<P: AsRef<Path>>(root: P) -> ProbeSupport {
+        let root = root.as_ref();
+        let exists = |path: &str| platform::exists(root.join(path));
+        let kernel_version = kernel_version_in(root).ok();
+        let diskstats = exists("diskstats");
+        // The counters of diskstats can only be told apart by the kernel version, or by reading it.
+        let since = |major, minor| {
+            diskstats && kernel_version.is_some_and(|version| version >= KernelVersion::new(major, minor, 0))
+        };
+        ProbeSupport {
+            kernel_version,
+            pressure: exists("pressure"),
+            diskstats,
+            diskstats_discards: since(4, 18),
+            diskstats_flushes: since(5, 5),
+            dev_snmp6: exists("net/dev_snmp6"),
+            zoneinfo: exists("zoneinfo"),
+            io: exists("self/io"),
+            schedstat: exists("self/schedstat"),
+            smaps: exists("self/smaps"),
+            smaps_rollup: exists("self/smaps_rollup"),
+            children: has_children(root),
+            id_maps: exists("self/uid_map"),
+            namespaces: exists("self/ns"),
+            fdinfo: exists("self/fdinfo"),
+            map_files: exists("self/map_files"),
+        }
+    }
+
+    /// Returns the rows of the support matrix: the file of each probe, and whether it is available.
+    fn rows(&self) -> [(&'static str, bool); 15] {
+        [("/proc/pressure", self.pressure),
+         ("/proc/diskstats", self.diskstats),
+         ("/proc/diskstats discards", self.diskstats_discards),
+         ("/proc/diskstats flushes", self.diskstats_flushes),
+         ("/proc/net/dev_snmp6", self.dev_snmp6),
+         ("/proc/zoneinfo", self.zoneinfo),
+         ("/proc/[pid]/io", self.io),
+         ("/proc/[pid]/schedstat", self.schedstat),
+         ("/proc/[pid]/smaps", self.smaps),
+         ("/proc/[pid]/smaps_rollup", self.smaps_rollup),
+         ("/proc/[pid]/task/[tid]/children", self.children),
+         ("/proc/[pid]/uid_map", self.id_maps),
+         ("/proc/[pid]/ns", self.namespaces),
+         ("/proc/[pid]/fdinfo", self.fdinfo),
+         ("/proc/[pid]/map_files", self.map_files)]
+    }
+}
+
+/// Returns whether the threads of the current process have a `children` file.
+fn has_children(root: &Path) -> bool {
+    match platform::read_dir(root.join("self/task")).ok().and_then(|mut tasks| tasks.next()) {
+        Some(Ok(task)) => platform::exists(task.path().join("children")),
+        _ => false,
+    }
+}
+
+/// Formats the support matrix, one probe per line, for bug reports.
+impl fmt::Display for ProbeSupport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kernel_version {
+            Some(version) => writeln!(f, "{:<32} {}", "kernel version", version)?,
+            None => writeln!(f, "{:<32} unknown", "kernel version")?,
+        }
+        for &(file, available) in &self.rows() {
+            writeln!(f, "{:<32} {}", file, if available { "yes" } else { "no" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the probes which are available on the running kernel.
+///
+/// The probes are checked on the first call, and cached for the lifetime of the process, so
+/// that a monitoring agent can decide which collectors to enable at startup. Use
+/// `ProbeSupport::probe` to probe again, or to probe a procfs mounted elsewhere.
+pub fn probe_support() -> ProbeSupport {
+    static PROBE_SUPPORT: OnceLock<ProbeSupport> = OnceLock::new();
+    *PROBE_SUPPORT.get_or_init(|| ProbeSupport::probe("/proc"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use fixtures::fixture_path;
+    use super::{KernelVersion, ProbeSupport, kernel_version, probe_support};
+
+    #[test]
+    fn test_parse_kernel_version() {
+        assert_eq!(KernelVersion::new(6, 1, 0), KernelVersion::parse(b"6.1.0-18-amd64\n").unwrap());
+        assert_eq!(KernelVersion::new(4, 14, 0), KernelVersion::parse(b"4.14\n").unwrap());
+        assert_eq!(KernelVersion::new(5, 10, 0), KernelVersion::parse(b"5.10-rc1").unwrap());
+        assert_eq!(KernelVersion::new(6, 18, 44), KernelVersion::parse(b"6.18.44-fc-v130\n").unwrap());
+        assert_eq!(KernelVersion::new(3, 10, 0), KernelVersion::parse(b"3.10.0-1160.el7.x86_64\n").unwrap());
+        assert!(KernelVersion::parse(b"6").is_err());
+        assert!(KernelVersion::parse(b"linux").is_err());
+        assert!(KernelVersion::new(4, 20, 0) > KernelVersion::new(4, 19, 255));
+        assert_eq!("4.19.255", KernelVersion::new(4, 19, 255).to_string());
+    }
+
+    /// Test a tree of an old kernel, with only some of the files present.
+    #[test]
+    fn test_probe_fixture() {
+        let root = env::temp_dir().join(format!("procinfo-probe-support-{}", process::id()));
+        for dir in &["sys/kernel", "self/task/100", "self/ns", "net"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("sys/kernel/osrelease"), b"4.19.0-18-amd64\n").unwrap();
+        for file in &["diskstats", "self/io", "self/smaps", "self/task/100/children"] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+        let support = ProbeSupport::probe(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(ProbeSupport {
+                       kernel_version: Some(KernelVersion::new(4, 19, 0)),
+                       diskstats: true,
+                       diskstats_discards: true,
+                       io: true,
+                       smaps: true,
+                       children: true,
+                       namespaces: true,
+                       ..ProbeSupport::default()
+                   },
+                   support);
+
+        let matrix = support.to_string();
+        assert!(matrix.starts_with("kernel version                   4.19.0\n"), "{}", matrix);
+        assert!(matrix.contains("/proc/pressure                   no\n"), "{}", matrix);
+        assert!(matrix.contains("/proc/diskstats flushes          no\n"), "{}", matrix);
+        assert!(matrix.contains("/proc/[pid]/io                   yes\n"), "{}", matrix);
+        assert_eq!(16, matrix.lines().count());
+
+        assert_eq!(ProbeSupport::default(), ProbeSupport::probe("/nonexistent/proc"));
+        assert!(ProbeSupport::default().to_string().starts_with("kernel version                   unknown\n"));
+    }
+
+    /// Test the captured tree of a recent kernel.
+    #[test]
+    fn test_probe_captured() {
+        let support = ProbeSupport::probe(fixture_path("6.18", ""));
+        assert_eq!(Some(KernelVersion::new(6, 18, 44)), support.kernel_version);
+        assert!(support.diskstats && support.diskstats_discards && support.diskstats_flushes);
+        assert!(support.dev_snmp6);
+        assert!(support.io && support.schedstat);
+        assert!(!support.smaps_rollup);
+    }
+
+    #[test]
+    fn test_probe_support() {
+        let support = probe_support();
+        assert_eq!(Some(kernel_version().unwrap()), support.kernel_version);
+        assert!(support.namespaces);
+        assert!(support.fdinfo);
+        assert_eq!(support, super::probe_support());
+    }
+}
@@ -0,0 +1,383 @@
+//! Snapshots of the common system-wide probes, for periodic collection by metrics exporters.
+
+use std::collections::BTreeMap;
+use std::io::{Error, Result};
+use std::time::{Duration, Instant, SystemTime};
+
+use delta::{DeltaTracker, DeltaWarning, monotonic_delta};
+use loadavg::{LoadAvg, loadavg};
+use meminfo::{MemInfo, meminfo};
+use stat::{CpuTime, KernelStat, kernel_stat};
+use uptime::{Uptime, uptime};
+use vmstat::{VmStat, vmstat};
+
+/// A probe read by `Snapshot::capture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Probe {
+    /// `/proc/loadavg`.
+    LoadAvg,
+    /// `/proc/meminfo`.
+    MemInfo,
+    /// `/proc/stat`.
+    KernelStat,
+    /// `/proc/uptime`.
+    Uptime,
+    /// `/proc/vmstat`.
+    VmStat,
+}
+
+/// The number of probes read by `Snapshot::capture`.
+const PROBES: usize = 5;
+
+/// The system-wide probes read at one point in time.
+///
+/// A section is `None` if its probe failed, and the error is kept in `errors`.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// When the snapshot was captured, for measuring the time between snapshots.
+    pub timestamp: Instant,
+    /// The system time at which the snapshot was captured, for labelling exported metrics.
+    pub system_time: SystemTime,
+    /// The system load, from `/proc/loadavg`.
+    pub loadavg: Option<LoadAvg>,
+    /// The system memory usage, from `/proc/meminfo`.
+    pub meminfo: Option<MemInfo>,
+    /// The kernel and system statistics, from `/proc/stat`.
+    pub kernel_stat: Option<KernelStat>,
+    /// The system uptime, from `/proc/uptime`.
+    pub uptime: Option<Uptime>,
+    /// The virtual memory statistics, from `/proc/vmstat`.
+    pub vmstat: Option<VmStat>,
+    /// The errors of the probes which failed.
+    pub errors: Vec<(Probe, Error)>,
+}
+
+/// Returns the value read by a probe, or records its error and returns `None`.
+fn section<T>(probe: Probe, result: Result<T>, errors: &mut Vec<(Probe, Error)>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            errors.push((probe, error));
+            None
+        }
+    }
+}
+
+impl Snapshot {
+    /// Reads the system-wide probes.
+    ///
+    /// A probe which fails does not abort the capture: its section is `None`, and its error is
+    /// kept in `errors`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first probe if every probe failed, for instance because `/proc` is
+    /// not mounted.
+    pub fn capture() -> Result<Snapshot> {
+        let timestamp = Instant::now();
+        let system_time = SystemTime::now();
+        let mut errors = Vec::new();
+        let loadavg = section(Probe::LoadAvg, loadavg(), &mut errors);
+        let meminfo = section(Probe::MemInfo, meminfo(), &mut errors);
+        let kernel_stat = section(Probe::KernelStat, kernel_stat(), &mut errors);
+        let uptime = section(Probe::Uptime, uptime(), &mut errors);
+        let vmstat = section(Probe::VmStat, vmstat(), &mut errors);
+
+        if errors.len() == PROBES {
+            return Err(errors.swap_remove(0).1);
+        }
+        Ok(Snapshot { timestamp, system_time, loadavg, meminfo, kernel_stat, uptime, vmstat, errors })
+    }
+
+    /// Returns the error of the provided probe, or `None` if it succeeded.
+    pub fn error(&self, probe: Probe) -> Option<&Error> {
+        self.errors.iter().find(|&&(failed, _)| failed == probe).map(|(_, error)| error)
+    }
+
+    /// Returns the CPU utilization and the counter increases between an earlier snapshot and this
+    /// one.
+    ///
+    /// A delta is `None` if its section is missing from either snapshot, or if a counter was
+    /// reset, as counters are when the system rebooted between the snapshots, or appear to be when
+    /// the snapshots are passed in the wrong order. Counters which wrapped are corrected for the
+    /// wrap. Either is flagged by `SnapshotDelta::warning`.
+    pub fn diff(&self, earlier: &Snapshot) -> SnapshotDelta {
+        let mut tracker = DeltaTracker::default();
+        let stats = match (&earlier.kernel_stat, &self.kernel_stat) {
+            (Some(earlier), Some(later)) if earlier.boot_time != later.boot_time => {
+                tracker.warning = Some(DeltaWarning::Reset);
+                None
+            }
+            (Some(earlier), Some(later)) => Some((earlier, later)),
+            _ => None,
+        };
+
+        let cpu = stats.and_then(|(earlier, later)| CpuUtilization::between(&earlier.cpu, &later.cpu, &mut tracker));
+        let mut counter = |field: fn(&KernelStat) -> u64| {
+            stats.and_then(|(earlier, later)| tracker.delta(field(earlier), field(later)).corrected())
+        };
+        let interrupts = counter(|stat| stat.interrupts);
+        let context_switches = counter(|stat| stat.context_switches);
+        let processes = counter(|stat| stat.processes);
+        let softirqs = counter(|stat| stat.softirqs);
+
+        SnapshotDelta {
+            elapsed: self.timestamp.saturating_duration_since(earlier.timestamp),
+            cpu,
+            interrupts,
+            context_switches,
+            processes,
+            softirqs,
+            vmstat: match (&earlier.vmstat, &self.vmstat) {
+                (Some(earlier), Some(later)) => Some(vmstat_delta(earlier, later)),
+                _ => None,
+            },
+            warning: tracker.warning,
+        }
+    }
+}
+
+/// Returns the change of each virtual memory statistic reported in both snapshots.
+fn vmstat_delta(earlier: &VmStat, later: &VmStat) -> BTreeMap<String, i64> {
+    later.stats
+         .iter()
+         .filter_map(|(name, &value)| {
+             earlier.get(name).map(|earlier| (name.clone(), value.wrapping_sub(earlier) as i64))
+         })
+         .collect()
+}
+
+/// The fraction of the CPU time of all CPUs spent in each mode between two snapshots.
+///
+/// The fractions of the modes, excluding the guest modes, sum to 1.
+///
+/// The default value has every fraction set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuUtilization {
+    /// Fraction of time spent in user mode.
+    pub user: f64,
+    /// Fraction of time spent in user mode with low priority (nice).
+    pub nice: f64,
+    /// Fraction of time spent in system mode.
+    pub system: f64,
+    /// Fraction of time spent in the idle task.
+    pub idle: f64,
+    /// Fraction of time spent waiting for I/O to complete.
+    pub iowait: f64,
+    /// Fraction of time spent servicing interrupts.
+    pub irq: f64,
+    /// Fraction of time spent servicing softirqs.
+    pub softirq: f64,
+    /// Fraction of time stolen by other operating systems running in a virtualized environment.
+    pub steal: f64,
+    /// Fraction of time spent running guests. This is included in `user`.
+    pub guest: f64,
+    /// Fraction of time spent running niced guests. This is included in `nice`.
+    pub guest_nice: f64,
+}
+
+impl CpuUtilization {
+    /// Returns the utilization between two samples of CPU time, or `None` if a time was reset.
+    ///
+    /// Samples taken within the same clock tick carry no utilization information, and have every
+    /// fraction set to zero.
+    fn between(earlier: &CpuTime, later: &CpuTime, tracker: &mut DeltaTracker) -> Option<CpuUtilization> {
+        let delta = CpuTime {
+            user: tracker.delta(earlier.user, later.user).corrected()?,
+            nice: tracker.delta(earlier.nice, later.nice).corrected()?,
+            system: tracker.delta(earlier.system, later.system).corrected()?,
+            idle: tracker.delta(earlier.idle, later.idle).corrected()?,
+            // The iowait time of an idle CPU may go backwards, see `man 5 proc`, which is not
+            // worth a warning.
+            iowait: monotonic_delta(earlier.iowait, later.iowait).saturating(),
+            irq: tracker.delta(earlier.irq, later.irq).corrected()?,
+            softirq: tracker.delta(earlier.softirq, later.softirq).corrected()?,
+            steal: tracker.delta(earlier.steal, later.steal).corrected()?,
+            guest: tracker.delta(earlier.guest, later.guest).corrected()?,
+            guest_nice: tracker.delta(earlier.guest_nice, later.guest_nice).corrected()?,
+        };
+        let total = delta.total();
+        let fraction = |ticks: u64| if total == 0 { 0.0 } else { ticks as f64 / total as f64 };
+        Some(CpuUtilization {
+            user: fraction(delta.user),
+            nice: fraction(delta.nice),
+            system: fraction(delta.system),
+            idle: fraction(delta.idle),
+            iowait: fraction(delta.iowait),
+            irq: fraction(delta.irq),
+            softirq: fraction(delta.softirq),
+            steal: fraction(delta.steal),
+            guest: fraction(delta.guest),
+            guest_nice: fraction(delta.guest_nice),
+        })
+    }
+
+    /// Returns the fraction of time spent neither idle nor waiting for I/O.
+    pub fn busy(&self) -> f64 {
+        self.user + self.nice + self.system + self.irq + self.softirq + self.steal
+    }
+}
+
+/// The changes between two snapshots, see `Snapshot::diff`.
+///
+/// The default value has no elapsed time, and every delta missing.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SnapshotDelta {
+    /// Time between the snapshots.
+    pub elapsed: Duration,
+    /// The CPU utilization of all CPUs.
+    pub cpu: Option<CpuUtilization>,
+    /// Number of interrupts serviced.
+    pub interrupts: Option<u64>,
+    /// Number of context switches.
+    pub context_switches: Option<u64>,
+    /// Number of forks.
+    pub processes: Option<u64>,
+    /// Number of softirqs serviced.
+    pub softirqs: Option<u64>,
+    /// The change of each virtual memory statistic reported in both snapshots. Event counters,
+    /// such as `pgfault`, only increase, while page counts, such as `nr_free_pages`, may decrease.
+    pub vmstat: Option<BTreeMap<String, i64>>,
+    /// The most severe irregularity of the counters of `/proc/stat` between the snapshots.
+    pub warning: Option<DeltaWarning>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hint;
+    use std::io::{Error, ErrorKind};
+    use std::time::{Duration, Instant, SystemTime};
+
+    use delta::DeltaWarning;
+    use stat::{CpuTime, KernelStat};
+    use vmstat::VmStat;
+    use super::{CpuUtilization, Probe, Snapshot, section};
+
+    /// Returns a snapshot with only the provided sections.
+    fn snapshot(kernel_stat: Option<KernelStat>, vmstat: Option<VmStat>) -> Snapshot {
+        Snapshot {
+            timestamp: Instant::now(),
+            system_time: SystemTime::now(),
+            loadavg: None,
+            meminfo: None,
+            kernel_stat,
+            uptime: None,
+            vmstat,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_section() {
+        let mut errors = Vec::new();
+        assert_eq!(Some(1), section(Probe::LoadAvg, Ok(1), &mut errors));
+        assert_eq!(None::<u32>, section(Probe::VmStat, Err(Error::from(ErrorKind::NotFound)), &mut errors));
+        assert_eq!(1, errors.len());
+
+        let mut snapshot = snapshot(None, None);
+        snapshot.errors = errors;
+        assert_eq!(ErrorKind::NotFound, snapshot.error(Probe::VmStat).unwrap().kind());
+        assert!(snapshot.error(Probe::LoadAvg).is_none());
+    }
+
+    #[test]
+    fn test_diff() {
+        let earlier_stat = KernelStat {
+            cpu: CpuTime { user: 100, system: 50, idle: 1000, iowait: 10, ..CpuTime::default() },
+            context_switches: 1000,
+            interrupts: 500,
+            ..KernelStat::default()
+        };
+        let later_stat = KernelStat {
+            cpu: CpuTime { user: 130, system: 60, idle: 1060, iowait: 10, ..CpuTime::default() },
+            context_switches: 1200,
+            interrupts: 400,
+            ..KernelStat::default()
+        };
+        let earlier_vmstat = VmStat {
+            stats: vec![("nr_free_pages".to_owned(), 1000), ("pgfault".to_owned(), 10), ("pswpin".to_owned(), 0)]
+                .into_iter().collect(),
+        };
+        let later_vmstat = VmStat {
+            stats: vec![("nr_free_pages".to_owned(), 900), ("pgfault".to_owned(), 25)].into_iter().collect(),
+        };
+        let earlier = snapshot(Some(earlier_stat), Some(earlier_vmstat));
+        let later = snapshot(Some(later_stat), Some(later_vmstat));
+
+        let delta = later.diff(&earlier);
+        assert_eq!(Some(CpuUtilization { user: 0.3, system: 0.1, idle: 0.6, ..CpuUtilization::default() }),
+                   delta.cpu);
+        assert!((delta.cpu.unwrap().busy() - 0.4).abs() < 1e-9);
+        assert_eq!(Some(200), delta.context_switches);
+        // The interrupt counter decreased.
+        assert_eq!(None, delta.interrupts);
+        assert_eq!(Some(DeltaWarning::Reset), delta.warning);
+        assert_eq!(Some(0), delta.processes);
+        let vmstat = delta.vmstat.unwrap();
+        assert_eq!(2, vmstat.len());
+        assert_eq!(Some(&-100), vmstat.get("nr_free_pages"));
+        assert_eq!(Some(&15), vmstat.get("pgfault"));
+
+        // The CPU times decrease in the reverse order.
+        assert_eq!(None, earlier.diff(&later).cpu);
+
+        // The counters of a 32-bit kernel wrapped.
+        let wrapped_stat = KernelStat {
+            cpu: CpuTime { user: 0xffff_ffff, ..CpuTime::default() },
+            context_switches: 0xffff_ff00,
+            ..KernelStat::default()
+        };
+        let wrapping_stat = KernelStat {
+            cpu: CpuTime { user: 9, idle: 10, ..CpuTime::default() },
+            context_switches: 0x100,
+            ..KernelStat::default()
+        };
+        let delta = snapshot(Some(wrapping_stat), None).diff(&snapshot(Some(wrapped_stat.clone()), None));
+        assert_eq!(Some(CpuUtilization { user: 0.5, idle: 0.5, ..CpuUtilization::default() }), delta.cpu);
+        assert_eq!(Some(0x200), delta.context_switches);
+        assert_eq!(Some(DeltaWarning::Wrapped), delta.warning);
+
+        // The system rebooted between the snapshots.
+        let rebooted = KernelStat { boot_time: 1, ..KernelStat::default() };
+        let delta = snapshot(Some(rebooted), None).diff(&snapshot(Some(wrapped_stat), None));
+        assert_eq!(None, delta.cpu);
+        assert_eq!(None, delta.context_switches);
+        assert_eq!(Some(DeltaWarning::Reset), delta.warning);
+
+        let missing = later.diff(&snapshot(None, None));
+        assert_eq!(None, missing.cpu);
+        assert_eq!(None, missing.context_switches);
+        assert_eq!(None, missing.vmstat);
+        assert_eq!(None, missing.warning);
+    }
+
+    /// Test that the CPU utilization of a busy loop between two snapshots is positive and sane.
+    #[test]
+    fn test_capture() {
+        let earlier = Snapshot::capture().unwrap();
+        assert!(earlier.errors.is_empty(), "{:?}", earlier.errors);
+        assert!(earlier.loadavg.is_some());
+        assert!(earlier.meminfo.is_some());
+        assert!(earlier.uptime.is_some());
+
+        let start = Instant::now();
+        let mut iterations = 0u64;
+        while start.elapsed() < Duration::from_millis(200) {
+            iterations = hint::black_box(iterations + 1);
+        }
+        let later = Snapshot::capture().unwrap();
+
+        let delta = later.diff(&earlier);
+        assert!(delta.elapsed >= Duration::from_millis(200));
+        let cpu = delta.cpu.unwrap();
+        assert!(cpu.busy() > 0.0 && cpu.busy() <= 1.0 + 1e-9, "{:?}", cpu);
+        let total = cpu.user + cpu.nice + cpu.system + cpu.idle + cpu.iowait + cpu.irq + cpu.softirq + cpu.steal;
+        assert!((total - 1.0).abs() < 1e-9, "{:?}", cpu);
+        assert!(delta.context_switches.unwrap() > 0);
+        assert!(delta.vmstat.unwrap().contains_key("pgfault"));
+        assert_eq!(None, delta.warning);
+    }
+}
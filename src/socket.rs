@@ -0,0 +1,370 @@
+//! Socket tables from `/proc/net/tcp`, `/proc/net/udp`, `/proc/net/unix`, and their IPv6
+//! counterparts.
+
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Result};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str;
+
+use net::addr::parse_socket_addr_hex;
+use parsers::read_to_end;
+use platform::{self, uid_t};
+
+/// The state of a TCP socket, or of a UDP socket, which only uses `Established` and `Close`.
+///
+/// See `Linux/include/net/tcp_states.h`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TcpState {
+    Established,
+    SynSent,
+    SynRecv,
+    FinWait1,
+    FinWait2,
+    TimeWait,
+    #[default]
+    Close,
+    CloseWait,
+    LastAck,
+    Listen,
+    Closing,
+    /// A connection request which has not yet been accepted (since Linux 4.4).
+    NewSynRecv,
+}
+
+impl TcpState {
+    /// Returns the state with the provided kernel code, or `None` if the code is unknown.
+    fn from_code(code: u8) -> Option<TcpState> {
+        Some(match code {
+            1 => TcpState::Established,
+            2 => TcpState::SynSent,
+            3 => TcpState::SynRecv,
+            4 => TcpState::FinWait1,
+            5 => TcpState::FinWait2,
+            6 => TcpState::TimeWait,
+            7 => TcpState::Close,
+            8 => TcpState::CloseWait,
+            9 => TcpState::LastAck,
+            10 => TcpState::Listen,
+            11 => TcpState::Closing,
+            12 => TcpState::NewSynRecv,
+            _ => return None,
+        })
+    }
+}
+
+/// An IPv4 or IPv6 socket, from a row of `/proc/net/tcp`, `/proc/net/udp`, or their IPv6
+/// counterparts.
+///
+/// See `Linux/net/ipv4/tcp_ipv4.c` and `Linux/net/ipv4/udp.c`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InetSocket {
+    /// The local address.
+    pub local: SocketAddr,
+    /// The remote address, which is unspecified for listening and unconnected sockets.
+    pub remote: SocketAddr,
+    /// The state of the socket.
+    pub state: TcpState,
+    /// The number of bytes in the send queue.
+    pub tx_queue: u32,
+    /// The number of bytes in the receive queue, or for a listening TCP socket, the number of
+    /// connections waiting to be accepted.
+    pub rx_queue: u32,
+    /// The effective user ID of the creator of the socket.
+    pub uid: uid_t,
+    /// The inode of the socket, which matches the targets of the `/proc/[pid]/fd/` links.
+    pub inode: u64,
+}
+
+/// A Unix domain socket, from a row of `/proc/net/unix`.
+///
+/// See `Linux/net/unix/af_unix.c`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnixSocket {
+    /// The number of references to the socket.
+    pub ref_count: u32,
+    /// The socket flags; `__SO_ACCEPTCON` (`0x10000`) is set for listening sockets.
+    pub flags: u32,
+    /// The socket type: `SOCK_STREAM` (1), `SOCK_DGRAM` (2), or `SOCK_SEQPACKET` (5).
+    pub socket_type: u16,
+    /// The connection state: unconnected (1), connecting (2), connected (3), or disconnecting (4).
+    pub state: u8,
+    /// The inode of the socket, which matches the targets of the `/proc/[pid]/fd/` links.
+    pub inode: u64,
+    /// The bound path, or `None` for an unbound socket. Abstract socket names are prefixed with
+    /// `@`.
+    pub path: Option<PathBuf>,
+}
+
+impl UnixSocket {
+    /// Returns whether the socket is listening for connections.
+    pub fn is_listening(&self) -> bool {
+        self.flags & 0x10000 != 0
+    }
+}
+
+/// Returns the error for a socket table row which could not be parsed.
+fn parse_error(line: &[u8]) -> Error {
+    Error::new(ErrorKind::InvalidInput,
+               format!("unable to parse socket table row: {:?}", String::from_utf8_lossy(line)))
+}
+
+/// Splits the next whitespace-separated field from a row.
+fn next_field(row: &[u8]) -> Option<(&[u8], &[u8])> {
+    let start = row.iter().position(|b| !b.is_ascii_whitespace())?;
+    let row = &row[start..];
+    let end = row.iter().position(u8::is_ascii_whitespace).unwrap_or(row.len());
+    Some((&row[..end], &row[end..]))
+}
+
+/// Parses a hexadecimal field.
+fn hex(field: &[u8]) -> Option<u32> {
+    u32::from_str_radix(str::from_utf8(field).ok()?, 16).ok()
+}
+
+/// Parses a decimal field.
+fn decimal<T: str::FromStr>(field: &[u8]) -> Option<T> {
+    str::from_utf8(field).ok()?.parse().ok()
+}
+
+impl InetSocket {
+    /// Parses a row of a `/proc/net/tcp`, `/proc/net/udp`, or IPv6 socket table, without the
+    /// trailing newline.
+    pub fn parse(row: &[u8]) -> Result<InetSocket> {
+        InetSocket::parse_fields(row).ok_or_else(|| parse_error(row))
+    }
+
+    fn parse_fields(row: &[u8]) -> Option<InetSocket> {
+        let (slot, rest) = next_field(row)?;
+        if slot.last() != Some(&b':') {
+            return None;
+        }
+        let (local, rest) = next_field(rest)?;
+        let (remote, rest) = next_field(rest)?;
+        let (state, rest) = next_field(rest)?;
+        let (queues, rest) = next_field(rest)?;
+        let (_timer, rest) = next_field(rest)?;
+        let (_retransmits, rest) = next_field(rest)?;
+        let (uid, rest) = next_field(rest)?;
+        let (_timeout, rest) = next_field(rest)?;
+        let (inode, _) = next_field(rest)?;
+
+        let colon = queues.iter().position(|&b| b == b':')?;
+        Some(InetSocket {
+            local: parse_socket_addr_hex(local).ok()?,
+            remote: parse_socket_addr_hex(remote).ok()?,
+            state: TcpState::from_code(u8::try_from(hex(state)?).ok()?)?,
+            tx_queue: hex(&queues[..colon])?,
+            rx_queue: hex(&queues[colon + 1..])?,
+            uid: decimal(uid)?,
+            inode: decimal(inode)?,
+        })
+    }
+}
+
+impl UnixSocket {
+    /// Parses a row of the `/proc/net/unix` socket table, without the trailing newline.
+    pub fn parse(row: &[u8]) -> Result<UnixSocket> {
+        UnixSocket::parse_fields(row).ok_or_else(|| parse_error(row))
+    }
+
+    fn parse_fields(row: &[u8]) -> Option<UnixSocket> {
+        let (slot, rest) = next_field(row)?;
+        if slot.last() != Some(&b':') {
+            return None;
+        }
+        let (ref_count, rest) = next_field(rest)?;
+        let (_protocol, rest) = next_field(rest)?;
+        let (flags, rest) = next_field(rest)?;
+        let (socket_type, rest) = next_field(rest)?;
+        let (state, rest) = next_field(rest)?;
+        let (inode, rest) = next_field(rest)?;
+        // The path is separated by a single space, and may itself contain spaces.
+        let path = match rest.split_first() {
+            None => None,
+            Some((&b' ', path)) if !path.is_empty() => Some(platform::path_from_bytes(path)),
+            Some(_) => return None,
+        };
+        Some(UnixSocket {
+            ref_count: hex(ref_count)?,
+            flags: hex(flags)?,
+            socket_type: u16::try_from(hex(socket_type)?).ok()?,
+            state: u8::try_from(hex(state)?).ok()?,
+            inode: decimal(inode)?,
+            path,
+        })
+    }
+}
+
+/// Parses a socket table, skipping the header row.
+pub fn parse_table<T>(input: &[u8], parse_row: fn(&[u8]) -> Result<T>) -> Result<Vec<T>> {
+    input.split(|&b| b == b'\n')
+         .skip(1)
+         .filter(|row| !row.is_empty())
+         .map(parse_row)
+         .collect()
+}
+
+/// Reads a socket table, or returns no sockets if `optional` and the table does not exist, as the
+/// IPv6 tables do not when IPv6 is disabled.
+fn read_table<T>(path: &str, parse_row: fn(&[u8]) -> Result<T>, optional: bool) -> Result<Vec<T>> {
+    let mut file = match platform::open(path) {
+        Err(ref error) if optional && error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        result => result?,
+    };
+    let mut buf = Vec::with_capacity(16 * 1024);
+    parse_table(read_to_end(&mut file, &mut buf)?, parse_row)
+}
+
+/// Reads the IPv4 and IPv6 socket tables of a protocol, such as `tcp` and `tcp6`, from a `net`
+/// directory.
+pub fn read_inet_tables(dir: &str, protocol: &str) -> Result<Vec<InetSocket>> {
+    let mut sockets = read_table(&format!("{}/{}", dir, protocol), InetSocket::parse, false)?;
+    sockets.extend(read_table(&format!("{}/{}6", dir, protocol), InetSocket::parse, true)?);
+    Ok(sockets)
+}
+
+/// Reads the Unix domain socket table from a `net` directory.
+pub fn read_unix_table(dir: &str) -> Result<Vec<UnixSocket>> {
+    read_table(&format!("{}/unix", dir), UnixSocket::parse, false)
+}
+
+/// Returns the TCP sockets of the network namespace of the current process, from `/proc/net/tcp`
+/// and `/proc/net/tcp6`.
+pub fn tcp() -> Result<Vec<InetSocket>> {
+    read_inet_tables("/proc/net", "tcp")
+}
+
+/// Returns the UDP sockets of the network namespace of the current process, from `/proc/net/udp`
+/// and `/proc/net/udp6`.
+pub fn udp() -> Result<Vec<InetSocket>> {
+    read_inet_tables("/proc/net", "udp")
+}
+
+/// Returns the Unix domain sockets of the network namespace of the current process, from
+/// `/proc/net/unix`.
+pub fn unix() -> Result<Vec<UnixSocket>> {
+    read_unix_table("/proc/net")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{TcpListener, UdpSocket};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    use super::{InetSocket, TcpState, UnixSocket, parse_table, tcp, udp, unix};
+
+    /// A TCP table with a listening IPv4 socket and an established connection.
+    pub const TCP: &[u8] = b"  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  \
+                             timeout inode\n   \
+                             0: 0100007F:0CEA 00000000:0000 0A 00000000:00000003 00:00000000 00000000  1000        \
+                             0 25519 1 0000000000000000 100 0 0 10 0\n   \
+                             1: 0100007F:0CEA 0100007F:D5B4 01 00000010:00000000 02:000A7F2E 00000000  1000        \
+                             0 25520 2 0000000000000000 20 4 30 10 -1\n";
+
+    /// A TCP6 table with a listening IPv6 socket.
+    pub const TCP6: &[u8] = b"  sl  local_address                         remote_address                        st \
+                              tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+                              0: 00000000000000000000000001000000:01BB 00000000000000000000000000000000:0000 0A \
+                              00000000:00000000 00:00000000 00000000     0        0 30311 1 0000000000000000 100 0 \
+                              0 10 0\n";
+
+    /// A UDP table with a bound IPv4 socket.
+    pub const UDP: &[u8] = b"   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  \
+                             timeout inode ref pointer drops\n  \
+                             412: 3500007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        \
+                             0 18807 2 0000000000000000 0\n";
+
+    /// A Unix domain socket table with a listening socket, an abstract socket, and an unbound socket.
+    pub const UNIX: &[u8] = b"Num       RefCount Protocol Flags    Type St Inode Path\n\
+                              0000000000000000: 00000002 00000000 00010000 0001 01 25538 /run/my socket\n\
+                              0000000000000000: 00000003 00000000 00000000 0002 03 18466 @/org/kernel/udev\n\
+                              0000000000000000: 00000003 00000000 00000000 0001 03   658\n";
+
+    #[test]
+    fn test_parse_inet_table() {
+        let sockets = parse_table(TCP, InetSocket::parse).unwrap();
+        assert_eq!(vec![InetSocket {
+                            local: "127.0.0.1:3306".parse().unwrap(),
+                            remote: "0.0.0.0:0".parse().unwrap(),
+                            state: TcpState::Listen,
+                            tx_queue: 0,
+                            rx_queue: 3,
+                            uid: 1000,
+                            inode: 25519,
+                        },
+                        InetSocket {
+                            local: "127.0.0.1:3306".parse().unwrap(),
+                            remote: "127.0.0.1:54708".parse().unwrap(),
+                            state: TcpState::Established,
+                            tx_queue: 16,
+                            rx_queue: 0,
+                            uid: 1000,
+                            inode: 25520,
+                        }],
+                   sockets);
+
+        let sockets = parse_table(TCP6, InetSocket::parse).unwrap();
+        assert_eq!("[::1]:443".parse(), Ok(sockets[0].local));
+        assert_eq!(TcpState::Listen, sockets[0].state);
+
+        let sockets = parse_table(UDP, InetSocket::parse).unwrap();
+        assert_eq!("127.0.0.53:53".parse(), Ok(sockets[0].local));
+        assert_eq!(TcpState::Close, sockets[0].state);
+        assert_eq!(101, sockets[0].uid);
+
+        assert!(parse_table(b"header\n", InetSocket::parse).unwrap().is_empty());
+        for row in &[&b"0: 0100007F:0CEA 00000000:0000 0A"[..],
+                     b"0: 0100007F:0CEA 00000000:0000 0D 00000000:00000000 00:00000000 00000000 0 0 1",
+                     b"0 0100007F:0CEA 00000000:0000 0A 00000000:00000000 00:00000000 00000000 0 0 1",
+                     b"0: 0100007F:0CEA 00000000:0000 0A 0000000000000000 00:00000000 00000000 0 0 1"] {
+            assert!(InetSocket::parse(row).is_err(), "{:?}", String::from_utf8_lossy(row));
+        }
+    }
+
+    #[test]
+    fn test_parse_unix_table() {
+        let sockets = parse_table(UNIX, UnixSocket::parse).unwrap();
+        assert_eq!(UnixSocket {
+                       ref_count: 2,
+                       flags: 0x10000,
+                       socket_type: 1,
+                       state: 1,
+                       inode: 25538,
+                       path: Some(PathBuf::from("/run/my socket")),
+                   },
+                   sockets[0]);
+        assert!(sockets[0].is_listening());
+        assert_eq!(Some(PathBuf::from("@/org/kernel/udev")), sockets[1].path);
+        assert!(!sockets[1].is_listening());
+        assert_eq!(None, sockets[2].path);
+        assert_eq!(658, sockets[2].inode);
+
+        assert!(UnixSocket::parse(b"0000000000000000: 00000002 00000000 00010000 0001").is_err());
+    }
+
+    /// Test that sockets opened by the current process are in the system socket tables.
+    #[test]
+    fn test_sockets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        assert!(tcp().unwrap().iter().any(|socket| socket.local == address && socket.state == TcpState::Listen));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        assert!(udp().unwrap().iter().any(|socket| socket.local == address));
+
+        let path = env::temp_dir().join(format!("procinfo-test-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let found = unix().unwrap().into_iter().find(|socket| socket.path.as_ref() == Some(&path));
+        drop(listener);
+        fs::remove_file(&path).unwrap();
+        assert!(found.unwrap().is_listening());
+    }
+}
@@ -0,0 +1,348 @@
+//! Kernel and system statistics from `/proc/stat`.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use nom::{IResult, line_ending, not_line_ending, space};
+
+use parsers::{map_result, parse_u32, parse_u64, parse_u64s, read_to_end};
+use platform;
+
+/// Time spent by a CPU (or all CPUs) in each mode, measured in clock ticks (divide by
+/// `sysconf(_SC_CLK_TCK)`).
+///
+/// Columns missing on older kernels are zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CpuTime {
+    /// Time spent in user mode.
+    pub user: u64,
+    /// Time spent in user mode with low priority (nice).
+    pub nice: u64,
+    /// Time spent in system mode.
+    pub system: u64,
+    /// Time spent in the idle task.
+    pub idle: u64,
+    /// Time waiting for I/O to complete (since Linux 2.5.41). This value is not reliable, see
+    /// `man 5 proc`.
+    pub iowait: u64,
+    /// Time servicing interrupts (since Linux 2.6.0).
+    pub irq: u64,
+    /// Time servicing softirqs (since Linux 2.6.0).
+    pub softirq: u64,
+    /// Stolen time, which is the time spent in other operating systems when running in a
+    /// virtualized environment (since Linux 2.6.11).
+    pub steal: u64,
+    /// Time spent running a virtual CPU for guest operating systems (since Linux 2.6.24). This
+    /// is included in `user`.
+    pub guest: u64,
+    /// Time spent running a niced guest (since Linux 2.6.33). This is included in `nice`.
+    pub guest_nice: u64,
+}
+
+impl CpuTime {
+    fn from_values(values: &[u64]) -> CpuTime {
+        let value = |idx: usize| values.get(idx).cloned().unwrap_or(0);
+        CpuTime {
+            user: value(0),
+            nice: value(1),
+            system: value(2),
+            idle: value(3),
+            iowait: value(4),
+            irq: value(5),
+            softirq: value(6),
+            steal: value(7),
+            guest: value(8),
+            guest_nice: value(9),
+        }
+    }
+
+    /// Returns the total time accounted in every mode.
+    ///
+    /// Guest time is already included in `user` and `nice`, so it is not counted twice.
+    pub fn total(&self) -> u64 {
+        self.user
+            .saturating_add(self.nice)
+            .saturating_add(self.system)
+            .saturating_add(self.idle)
+            .saturating_add(self.iowait)
+            .saturating_add(self.irq)
+            .saturating_add(self.softirq)
+            .saturating_add(self.steal)
+    }
+}
+
+/// Kernel and system statistics.
+///
+/// See `man 5 proc` and `Linux/fs/proc/stat.c`.
+///
+/// The default value has zero CPU times and counters, and no individual CPUs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KernelStat {
+    /// Time spent by all CPUs in each mode.
+    pub cpu: CpuTime,
+    /// Time spent by each online CPU in each mode, keyed by CPU number.
+    pub cpus: BTreeMap<u32, CpuTime>,
+    /// Number of interrupts serviced since boot.
+    pub interrupts: u64,
+    /// Number of context switches since boot.
+    pub context_switches: u64,
+    /// Time at which the system booted, in seconds since the Unix epoch.
+    pub boot_time: u64,
+    /// Number of forks since boot.
+    pub processes: u64,
+    /// Number of processes in runnable state (since Linux 2.5.45).
+    pub procs_running: u32,
+    /// Number of processes blocked waiting for I/O to complete (since Linux 2.5.45).
+    pub procs_blocked: u32,
+    /// Number of softirqs serviced since boot (since Linux 2.6.31).
+    pub softirqs: u64,
+}
+
+impl KernelStat {
+    /// Parses the contents of a `/proc/stat` file.
+    pub fn parse(input: &[u8]) -> Result<KernelStat> {
+        map_result(input, parse_kernel_stat)
+    }
+
+    /// Returns the number of online CPUs, which have a `cpuN` line.
+    pub fn cpu_count(&self) -> u32 {
+        self.cpus.len() as u32
+    }
+}
+
+/// Parses the label at the start of a line.
+named!(parse_label<&'a str>, map_res!(is_not!(" \n"), str::from_utf8));
+
+/// Parses the CPU times of a `cpu` or `cpuN` line.
+named!(parse_cpu_time<CpuTime>,
+       do_parse!(space >> values: parse_u64s >> (CpuTime::from_values(&values))));
+
+/// Parses a line with a total followed by per-source counts, keeping the total.
+named!(parse_total<u64>,
+       do_parse!(space >> total: parse_u64 >> not_line_ending >> (total)));
+
+named!(parse_value_u32<u32>, preceded!(space, parse_u32));
+named!(parse_value_u64<u64>, preceded!(space, parse_u64));
+
+/// Parses the stat file format.
+///
+/// Lines which are not recognized, such as `page` and `swap` on old kernels, are skipped.
+fn parse_kernel_stat(input: &[u8]) -> IResult<&[u8], KernelStat> {
+    /// Helper macro for parsing the value of a line into a field.
+    macro_rules! field {
+        ($i:expr, $parser:ident => $field:expr) => ({
+            let (rest, value) = try_parse!($i, $parser);
+            $field = value;
+            rest
+        })
+    }
+
+    let mut stat = KernelStat::default();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (i, label) = try_parse!(rest, parse_label);
+        let i = match label {
+            "cpu"           => field!(i, parse_cpu_time  => stat.cpu),
+            "intr"          => field!(i, parse_total     => stat.interrupts),
+            "softirq"       => field!(i, parse_total     => stat.softirqs),
+            "ctxt"          => field!(i, parse_value_u64 => stat.context_switches),
+            "btime"         => field!(i, parse_value_u64 => stat.boot_time),
+            "processes"     => field!(i, parse_value_u64 => stat.processes),
+            "procs_running" => field!(i, parse_value_u32 => stat.procs_running),
+            "procs_blocked" => field!(i, parse_value_u32 => stat.procs_blocked),
+            _ => match label.strip_prefix("cpu").map(str::parse) {
+                Some(Ok(id)) => {
+                    let (i, time) = try_parse!(i, parse_cpu_time);
+                    stat.cpus.insert(id, time);
+                    i
+                }
+                _ => try_parse!(i, not_line_ending).0,
+            },
+        };
+        rest = try_parse!(i, line_ending).0;
+    }
+    IResult::Done(rest, stat)
+}
+
+/// Returns kernel and system statistics.
+pub fn kernel_stat() -> Result<KernelStat> {
+    // A stat file is a few kilobytes, mostly due to the per-interrupt counts.
+    let mut buf = Vec::with_capacity(4096);
+    let mut file = platform::open("/proc/stat")?;
+    KernelStat::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// Returns kernel and system statistics, reading `/proc/stat` asynchronously.
+#[cfg(feature = "async")]
+pub fn kernel_stat_async() -> impl Future<Output = Result<KernelStat>> + Send {
+    platform::read_async("/proc/stat".to_owned(), KernelStat::parse)
+}
+
+/// Returns the number of online CPUs, from the `cpuN` lines of `/proc/stat`.
+///
+/// Offline CPUs are not counted. CPUs outside the affinity mask of the current process are
+/// counted, since they contribute to the system load averages.
+pub fn cpu_count_online() -> Result<u32> {
+    match kernel_stat()?.cpu_count() {
+        0 => Err(Error::new(ErrorKind::InvalidInput, "no online CPUs in /proc/stat")),
+        count => Ok(count),
+    }
+}
+
+static BOOT_TIME: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the time at which the system booted.
+///
+/// The boot time is read from `/proc/stat` on the first call and cached. The kernel derives it
+/// from the current time less the time since boot, so it has a resolution of one second and
+/// shifts if the system clock is stepped; caching it keeps the values derived from it
+/// consistent.
+pub fn boot_time() -> Result<SystemTime> {
+    let mut secs = BOOT_TIME.load(Ordering::Relaxed);
+    if secs == 0 {
+        secs = kernel_stat()?.boot_time;
+        BOOT_TIME.store(secs, Ordering::Relaxed);
+    }
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use parsers::tests::unwrap;
+    use super::{CpuTime, KernelStat, boot_time, cpu_count_online, kernel_stat, parse_kernel_stat};
+
+    /// Test that the system stat file can be parsed.
+    #[test]
+    fn test_kernel_stat() {
+        let stat = kernel_stat().unwrap();
+        assert!(!stat.cpus.is_empty());
+        assert!(stat.boot_time > 0);
+    }
+
+    #[test]
+    fn test_cpu_count_online() {
+        let count = cpu_count_online().unwrap();
+        assert!(count >= 1);
+        assert_eq!(kernel_stat().unwrap().cpu_count(), count);
+    }
+
+    #[test]
+    fn test_cpu_count() {
+        let single = b"cpu  1000 0 200 9000\n\
+                       cpu0 1000 0 200 9000\n\
+                       ctxt 115315\n";
+        assert_eq!(1, unwrap(parse_kernel_stat(single)).cpu_count());
+
+        // Offline CPUs have no line, so they are not counted.
+        let offline = b"cpu  3000 0 600 27000\n\
+                        cpu0 1000 0 200 9000\n\
+                        cpu2 1000 0 200 9000\n\
+                        cpu3 1000 0 200 9000\n";
+        assert_eq!(3, unwrap(parse_kernel_stat(offline)).cpu_count());
+
+        assert_eq!(0, unwrap(parse_kernel_stat(b"cpu  1000 0 200 9000\n")).cpu_count());
+    }
+
+    #[test]
+    fn test_boot_time() {
+        let boot_time = boot_time().unwrap();
+        assert!(boot_time < SystemTime::now());
+        assert_eq!(boot_time, super::boot_time().unwrap());
+    }
+
+    #[test]
+    fn test_parse_kernel_stat() {
+        let text = b"cpu  22694 10 3013 62037 659 0 1 33 0 0\n\
+                     cpu0 12694 10 2013 31037 359 0 1 13 0 0\n\
+                     cpu2 10000 0 1000 31000 300 0 0 20\n\
+                     intr 107778 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1 1 2\n\
+                     ctxt 253219\n\
+                     btime 1792004471\n\
+                     processes 12908\n\
+                     procs_running 3\n\
+                     procs_blocked 1\n\
+                     softirq 53898 0 22012 3 2222 0 0 9 0 2 29650\n";
+        let stat = unwrap(parse_kernel_stat(text));
+        assert_eq!(stat, KernelStat::parse(text).unwrap());
+
+        assert_eq!(CpuTime { user: 22694, nice: 10, system: 3013, idle: 62037, iowait: 659,
+                             irq: 0, softirq: 1, steal: 33, guest: 0, guest_nice: 0 },
+                   stat.cpu);
+        assert_eq!(88447, stat.cpu.total());
+        assert_eq!(vec![0, 2], stat.cpus.keys().cloned().collect::<Vec<_>>());
+        assert_eq!(20, stat.cpus[&2].steal);
+        assert_eq!(0, stat.cpus[&2].guest);
+        assert_eq!(107778, stat.interrupts);
+        assert_eq!(253219, stat.context_switches);
+        assert_eq!(1792004471, stat.boot_time);
+        assert_eq!(12908, stat.processes);
+        assert_eq!(3, stat.procs_running);
+        assert_eq!(1, stat.procs_blocked);
+        assert_eq!(53898, stat.softirqs);
+    }
+
+    /// Test that lines from old kernels are skipped.
+    #[test]
+    fn test_parse_kernel_stat_old_kernel() {
+        let text = b"cpu  1000 0 200 9000\n\
+                     cpu0 1000 0 200 9000\n\
+                     page 5741 1808\n\
+                     swap 1 0\n\
+                     intr 1462898\n\
+                     ctxt 115315\n\
+                     btime 769041601\n\
+                     processes 86031\n";
+        let stat = unwrap(parse_kernel_stat(text));
+        assert_eq!(10200, stat.cpu.total());
+        assert_eq!(1462898, stat.interrupts);
+        assert_eq!(769041601, stat.boot_time);
+        assert_eq!(0, stat.softirqs);
+    }
+}
+
+#[cfg(all(test, rustc_nightly))]
+mod benches {
+    extern crate test;
+
+    use super::{kernel_stat, parse_kernel_stat};
+
+    /// Returns a stat file of a machine with 64 CPUs and 256 interrupt sources.
+    fn kernel_stat_fixture() -> Vec<u8> {
+        let mut text = String::from("cpu  2269400 1000 301300 6203700 65900 0 100 3300 0 0\n");
+        for cpu in 0..64 {
+            text.push_str(&format!("cpu{} 35459 15 4707 96932 1029 0 1 51 0 0\n", cpu));
+        }
+        text.push_str("intr 10777800");
+        for source in 0..256 {
+            text.push_str(&format!(" {}", source * 31));
+        }
+        text.push_str("\nctxt 25321900\n\
+                       btime 1792004471\n\
+                       processes 1290800\n\
+                       procs_running 3\n\
+                       procs_blocked 0\n\
+                       softirq 5389800 0 2201200 300 222200 0 0 900 0 200 2965000\n");
+        text.into_bytes()
+    }
+
+    #[bench]
+    fn bench_kernel_stat(b: &mut test::Bencher) {
+        b.iter(|| test::black_box(kernel_stat()));
+    }
+
+    #[bench]
+    fn bench_kernel_stat_parse(b: &mut test::Bencher) {
+        let stat = kernel_stat_fixture();
+        b.iter(|| test::black_box(parse_kernel_stat(&stat)));
+    }
+}
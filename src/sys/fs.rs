@@ -0,0 +1,63 @@
+//! Filesystem kernel parameters from `/proc/sys/fs/`.
+//!
+//! The epoll and inotify limits apply to each real user ID, see `inotify(7)` and `epoll(7)`. Their
+//! usage by a single process can be read with `pid::inotify_usage`.
+
+use std::io::Result;
+
+use sys::read;
+use sysconf::{page_size, pages_to_bytes};
+
+/// Returns the maximum number of file descriptors which all epoll instances of a user may watch
+/// (`fs.epoll.max_user_watches`, since Linux 2.6.28).
+pub fn epoll_max_user_watches() -> Result<u64> {
+    read("fs.epoll.max_user_watches")
+}
+
+/// Returns the maximum number of watches which all inotify instances of a user may have
+/// (`fs.inotify.max_user_watches`).
+pub fn inotify_max_user_watches() -> Result<u32> {
+    read("fs.inotify.max_user_watches")
+}
+
+/// Returns the maximum number of inotify instances which a user may create
+/// (`fs.inotify.max_user_instances`).
+pub fn inotify_max_user_instances() -> Result<u32> {
+    read("fs.inotify.max_user_instances")
+}
+
+/// Returns the maximum size in bytes to which an unprivileged process may set the capacity of a
+/// pipe with `F_SETPIPE_SZ` (`fs.pipe-max-size`, since Linux 2.6.35).
+pub fn pipe_max_size() -> Result<u64> {
+    read("fs.pipe-max-size")
+}
+
+/// Returns the number of pages which the pipes of an unprivileged user may use before new pipes
+/// are limited to a single page, or 0 if there is no limit (`fs.pipe-user-pages-soft`, since Linux
+/// 4.5).
+pub fn pipe_user_pages_soft() -> Result<u64> {
+    read("fs.pipe-user-pages-soft")
+}
+
+/// Returns the soft limit on the memory used by the pipes of an unprivileged user in bytes, see
+/// `pipe_user_pages_soft`.
+pub fn pipe_user_bytes_soft() -> Result<u64> {
+    Ok(pages_to_bytes(pipe_user_pages_soft()?, page_size()))
+}
+
+#[cfg(test)]
+mod tests {
+    use sysconf::page_size;
+    use super::{epoll_max_user_watches, inotify_max_user_instances, inotify_max_user_watches, pipe_max_size,
+                pipe_user_bytes_soft, pipe_user_pages_soft};
+
+    /// Test that the filesystem kernel parameters of the system can be read.
+    #[test]
+    fn test_fs() {
+        assert!(epoll_max_user_watches().unwrap() > 0);
+        assert!(inotify_max_user_watches().unwrap() > 0);
+        assert!(inotify_max_user_instances().unwrap() > 0);
+        assert!(pipe_max_size().unwrap() >= page_size());
+        assert_eq!(pipe_user_pages_soft().unwrap() * page_size(), pipe_user_bytes_soft().unwrap());
+    }
+}
@@ -0,0 +1,138 @@
+//! Kernel parameters from `/proc/sys/`, see `sysctl(8)`.
+//!
+//! Parameters are identified by their `sysctl` key, in which the components of the path under
+//! `/proc/sys/` are separated by dots, such as `net.ipv4.ip_forward`. A component which itself
+//! contains a dot, such as the name of the VLAN interface `eth0.100`, is written with a slash in its
+//! place: `net.ipv4.conf.eth0/100.rp_filter`. Keys may also be written in the path form, with
+//! slashes as separators, in which case dots are part of the component: if the first separator of
+//! a key is a slash, as in `net/ipv4/conf/eth0.100/rp_filter`, the key is in the path form.
+
+use std::fmt::Display;
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use parsers::{read_to_end, to_string_lossy};
+use platform;
+
+pub mod fs;
+pub mod net;
+
+/// Returns the path of the file in `/proc/sys/` corresponding to a `sysctl` key.
+///
+/// Returns an `ErrorKind::InvalidInput` error if the key has an empty component, or a `.` or `..`
+/// component, so that keys cannot refer to files outside of `/proc/sys/`.
+pub fn path(key: &str) -> Result<PathBuf> {
+    let path_form = key.find(['.', '/']).is_some_and(|idx| key[idx..].starts_with('/'));
+
+    let mut path = PathBuf::from("/proc/sys");
+    let (separator, escape) = if path_form { ('/', None) } else { ('.', Some('/')) };
+    for component in key.split(separator) {
+        if component.is_empty() || component == "." || component == ".." {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("invalid sysctl key: {:?}", key)));
+        }
+        match escape {
+            Some(escape) => path.push(component.replace(escape, ".")),
+            None => path.push(component),
+        }
+    }
+    Ok(path)
+}
+
+/// Reads the raw value of a kernel parameter, without the trailing newline.
+fn read_string(key: &str) -> Result<String> {
+    let mut buf = Vec::with_capacity(64);
+    let value = to_string_lossy(read_to_end(&mut platform::open(path(key)?)?, &mut buf)?);
+    Ok(value.trim_end_matches('\n').to_owned())
+}
+
+/// Returns the error for a kernel parameter value which could not be parsed.
+fn parse_error(key: &str, value: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse sysctl {}: {:?}", key, value))
+}
+
+/// Parses the raw value of a kernel parameter.
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T> {
+    value.trim().parse().map_err(|_| parse_error(key, value))
+}
+
+/// Parses the raw value of a kernel parameter which holds several whitespace-separated values.
+fn parse_vec<T: FromStr>(key: &str, value: &str) -> Result<Vec<T>> {
+    value.split_whitespace()
+         .map(|field| field.parse().map_err(|_| parse_error(key, value)))
+         .collect()
+}
+
+/// Reads the value of a kernel parameter, such as `kernel.pid_max`.
+///
+/// Use `String` to read the raw value. Returns an `ErrorKind::InvalidInput` error if the value
+/// can not be parsed as a `T`.
+pub fn read<T: FromStr>(key: &str) -> Result<T> {
+    parse(key, &read_string(key)?)
+}
+
+/// Reads the values of a kernel parameter which holds several whitespace-separated values, such as
+/// `net.ipv4.tcp_mem`.
+pub fn read_vec<T: FromStr>(key: &str) -> Result<Vec<T>> {
+    parse_vec(key, &read_string(key)?)
+}
+
+/// Sets the value of a kernel parameter.
+///
+/// Setting most parameters requires `CAP_SYS_ADMIN`, and the kernel validates the value.
+pub fn write<T: Display>(key: &str, value: T) -> Result<()> {
+    platform::write(path(key)?, value.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::path::Path;
+
+    use libc;
+
+    use super::{path, read, read_vec, write};
+
+    #[test]
+    fn test_path() {
+        assert_eq!(Path::new("/proc/sys/net/ipv4/ip_forward"), path("net.ipv4.ip_forward").unwrap());
+        assert_eq!(Path::new("/proc/sys/net/ipv4/ip_forward"), path("net/ipv4/ip_forward").unwrap());
+        assert_eq!(Path::new("/proc/sys/kernel/pid_max"), path("kernel.pid_max").unwrap());
+        assert_eq!(Path::new("/proc/sys/net/ipv4/conf/eth0.100/rp_filter"),
+                   path("net.ipv4.conf.eth0/100.rp_filter").unwrap());
+        assert_eq!(Path::new("/proc/sys/net/ipv4/conf/eth0.100/rp_filter"),
+                   path("net/ipv4/conf/eth0.100/rp_filter").unwrap());
+
+        for key in &["", "kernel.", ".kernel", "kernel..pid_max", "../etc", "net/ipv4/..", "/net/ipv4",
+                     "kernel/./pid_max"] {
+            assert_eq!(ErrorKind::InvalidInput, path(key).unwrap_err().kind(), "{:?}", key);
+        }
+    }
+
+    /// Test that kernel parameters present on every system can be read.
+    #[test]
+    fn test_read() {
+        assert!(read::<u32>("kernel.pid_max").unwrap() > 0);
+        assert_eq!("Linux", read::<String>("kernel.ostype").unwrap());
+        assert_eq!(3, read_vec::<u64>("fs.file-nr").unwrap().len());
+        assert_eq!(3, read_vec::<u64>("fs/file-nr").unwrap().len());
+
+        assert_eq!(ErrorKind::InvalidInput, read::<u32>("kernel.ostype").unwrap_err().kind());
+        assert_eq!(ErrorKind::InvalidInput, read_vec::<u32>("kernel.ostype").unwrap_err().kind());
+        assert_eq!(ErrorKind::NotFound, read::<u32>("kernel.does_not_exist").unwrap_err().kind());
+    }
+
+    /// Test that a kernel parameter can be set, by setting it to its current value.
+    ///
+    /// The test is skipped if the process is not allowed to set kernel parameters.
+    #[test]
+    fn test_write() {
+        let swappiness = read::<u32>("vm.swappiness").unwrap();
+        match write("vm.swappiness", swappiness) {
+            Err(ref error) if error.kind() == ErrorKind::PermissionDenied
+                           || error.raw_os_error() == Some(libc::EROFS) => return,
+            result => result.unwrap(),
+        }
+        assert_eq!(swappiness, read::<u32>("vm.swappiness").unwrap());
+    }
+}
@@ -0,0 +1,169 @@
+//! Networking kernel parameters from `/proc/sys/net/`.
+
+use std::io::Result;
+use std::str::FromStr;
+
+use sys::{parse_error, parse_vec, read, read_string};
+use sysconf::{page_size, pages_to_bytes};
+
+/// Parses a kernel parameter which holds two whitespace-separated values.
+fn parse_pair<T: FromStr + Copy>(key: &str, value: &str) -> Result<(T, T)> {
+    match parse_vec(key, value)?[..] {
+        [a, b] => Ok((a, b)),
+        _ => Err(parse_error(key, value)),
+    }
+}
+
+/// Parses a kernel parameter which holds three whitespace-separated values.
+fn parse_triple<T: FromStr + Copy>(key: &str, value: &str) -> Result<(T, T, T)> {
+    match parse_vec(key, value)?[..] {
+        [a, b, c] => Ok((a, b, c)),
+        _ => Err(parse_error(key, value)),
+    }
+}
+
+/// Returns the range of local ports used by TCP and UDP to choose the local port, as the first and
+/// last port of the range (`net.ipv4.ip_local_port_range`).
+pub fn ip_local_port_range() -> Result<(u16, u16)> {
+    let key = "net.ipv4.ip_local_port_range";
+    parse_pair(key, &read_string(key)?)
+}
+
+/// Returns the TCP memory thresholds in pages: the `low`, `pressure` and `high` number of pages
+/// allocated by TCP sockets (`net.ipv4.tcp_mem`).
+///
+/// See `tcp(7)` for the meaning of each threshold.
+pub fn tcp_mem() -> Result<(u64, u64, u64)> {
+    let key = "net.ipv4.tcp_mem";
+    parse_triple(key, &read_string(key)?)
+}
+
+/// Returns the TCP memory thresholds in bytes, see `tcp_mem`.
+pub fn tcp_mem_bytes() -> Result<(u64, u64, u64)> {
+    tcp_mem().map(tcp_mem_to_bytes)
+}
+
+/// Converts the TCP memory thresholds from pages to bytes.
+fn tcp_mem_to_bytes((low, pressure, high): (u64, u64, u64)) -> (u64, u64, u64) {
+    let page_size = page_size();
+    (pages_to_bytes(low, page_size), pages_to_bytes(pressure, page_size), pages_to_bytes(high, page_size))
+}
+
+/// Returns the maximum number of queued connection requests which have not yet been acknowledged
+/// by the connecting client (`net.ipv4.tcp_max_syn_backlog`).
+pub fn tcp_max_syn_backlog() -> Result<u32> {
+    read("net.ipv4.tcp_max_syn_backlog")
+}
+
+/// Returns the maximum backlog of a listening socket, to which larger `listen(2)` backlogs are
+/// truncated (`net.core.somaxconn`).
+pub fn somaxconn() -> Result<u32> {
+    read("net.core.somaxconn")
+}
+
+/// Returns the default TCP congestion control algorithm (`net.ipv4.tcp_congestion_control`).
+pub fn tcp_congestion_control() -> Result<String> {
+    read("net.ipv4.tcp_congestion_control")
+}
+
+/// Returns the TCP congestion control algorithms which are available, that is, built in or loaded
+/// as modules (`net.ipv4.tcp_available_congestion_control`).
+pub fn tcp_available_congestion_control() -> Result<Vec<String>> {
+    let key = "net.ipv4.tcp_available_congestion_control";
+    parse_vec(key, &read_string(key)?)
+}
+
+/// The networking kernel parameters which are commonly monitored or tuned.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Summary {
+    /// The range of local ports, see `ip_local_port_range`.
+    pub ip_local_port_range: (u16, u16),
+    /// The TCP memory thresholds in pages, see `tcp_mem`.
+    pub tcp_mem: (u64, u64, u64),
+    /// The maximum number of queued connection requests, see `tcp_max_syn_backlog`.
+    pub tcp_max_syn_backlog: u32,
+    /// The maximum backlog of a listening socket, see `somaxconn`.
+    pub somaxconn: u32,
+    /// The default TCP congestion control algorithm, see `tcp_congestion_control`.
+    pub tcp_congestion_control: String,
+    /// The available TCP congestion control algorithms, see `tcp_available_congestion_control`.
+    pub tcp_available_congestion_control: Vec<String>,
+}
+
+impl Summary {
+    /// Returns the TCP memory thresholds in bytes.
+    pub fn tcp_mem_bytes(&self) -> (u64, u64, u64) {
+        tcp_mem_to_bytes(self.tcp_mem)
+    }
+}
+
+/// Returns the networking kernel parameters which are commonly monitored or tuned.
+pub fn summary() -> Result<Summary> {
+    Ok(Summary {
+        ip_local_port_range: ip_local_port_range()?,
+        tcp_mem: tcp_mem()?,
+        tcp_max_syn_backlog: tcp_max_syn_backlog()?,
+        somaxconn: somaxconn()?,
+        tcp_congestion_control: tcp_congestion_control()?,
+        tcp_available_congestion_control: tcp_available_congestion_control()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use sysconf::page_size;
+    use super::{Summary, ip_local_port_range, parse_pair, parse_triple, somaxconn, summary,
+                tcp_available_congestion_control, tcp_congestion_control, tcp_max_syn_backlog,
+                tcp_mem, tcp_mem_bytes};
+
+    #[test]
+    fn test_parse_pair() {
+        assert_eq!((32768, 60999), parse_pair::<u16>("key", "32768\t60999\n").unwrap());
+        assert_eq!((1024, 65535), parse_pair::<u16>("key", "1024 65535").unwrap());
+
+        for value in &["", "32768", "32768\t60999\t1", "32768\t65536", "a\tb"] {
+            assert_eq!(ErrorKind::InvalidInput,
+                       parse_pair::<u16>("key", value).unwrap_err().kind(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_parse_triple() {
+        assert_eq!((70812, 94416, 141624), parse_triple::<u64>("key", "70812\t94416\t141624\n").unwrap());
+
+        for value in &["", "70812\t94416", "70812\t94416\t141624\t1", "70812\t94416\t-1"] {
+            assert_eq!(ErrorKind::InvalidInput,
+                       parse_triple::<u64>("key", value).unwrap_err().kind(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_summary_tcp_mem_bytes() {
+        let summary = Summary { tcp_mem: (1, 2, u64::MAX), ..Summary::default() };
+        assert_eq!((page_size(), 2 * page_size(), u64::MAX), summary.tcp_mem_bytes());
+    }
+
+    /// Test that the networking kernel parameters of the system can be read.
+    #[test]
+    fn test_net() {
+        let (first, last) = ip_local_port_range().unwrap();
+        assert!(first <= last);
+
+        let (low, pressure, high) = tcp_mem().unwrap();
+        assert!(low <= pressure && pressure <= high);
+        assert_eq!(high * page_size(), tcp_mem_bytes().unwrap().2);
+
+        assert!(tcp_max_syn_backlog().unwrap() > 0);
+        assert!(somaxconn().unwrap() > 0);
+
+        let congestion_control = tcp_congestion_control().unwrap();
+        assert!(tcp_available_congestion_control().unwrap().contains(&congestion_control));
+
+        let summary = summary().unwrap();
+        assert_eq!((first, last), summary.ip_local_port_range);
+        assert_eq!(congestion_control, summary.tcp_congestion_control);
+    }
+}
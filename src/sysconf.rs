@@ -0,0 +1,117 @@
+//! Cached system configuration values from `sysconf(3)`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+
+/// The kernel's `USER_HZ` on every mainstream architecture, used if `sysconf` fails.
+const DEFAULT_TICKS_PER_SECOND: usize = 100;
+
+/// The page size on most architectures, used if `sysconf` fails.
+const DEFAULT_PAGE_SIZE: usize = 4096;
+
+static TICKS_PER_SECOND: AtomicUsize = AtomicUsize::new(0);
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// A `sysconf` variable.
+#[derive(Clone, Copy)]
+enum Variable {
+    ClockTicks,
+    PageSize,
+}
+
+/// Returns the value of a `sysconf` variable, or `None` if it is not available.
+#[cfg(target_os = "linux")]
+fn sysconf(variable: Variable) -> Option<usize> {
+    use libc::{self, _SC_CLK_TCK, _SC_PAGESIZE};
+
+    let name = match variable {
+        Variable::ClockTicks => _SC_CLK_TCK,
+        Variable::PageSize => _SC_PAGESIZE,
+    };
+    match unsafe { libc::sysconf(name) } {
+        value if value > 0 => Some(value as usize),
+        _ => None,
+    }
+}
+
+/// Returns the value of a `sysconf` variable, or `None` if it is not available.
+#[cfg(not(target_os = "linux"))]
+fn sysconf(_variable: Variable) -> Option<usize> {
+    None
+}
+
+/// Returns the value of a `sysconf` variable, caching it in `cache`.
+///
+/// Racing threads may each query the value, but they always store the same value.
+fn cached_sysconf(cache: &AtomicUsize, variable: Variable, default: usize) -> u64 {
+    let mut value = cache.load(Ordering::Relaxed);
+    if value == 0 {
+        value = sysconf(variable).unwrap_or(default);
+        cache.store(value, Ordering::Relaxed);
+    }
+    value as u64
+}
+
+/// Returns the number of clock ticks per second (`sysconf(_SC_CLK_TCK)`).
+///
+/// Values in `/proc` which are measured in clock ticks, such as `Stat::utime`, are divided by this
+/// value to convert them to seconds. The value is queried once and cached, since it cannot change
+/// while the system is running.
+pub fn ticks_per_second() -> u64 {
+    cached_sysconf(&TICKS_PER_SECOND, Variable::ClockTicks, DEFAULT_TICKS_PER_SECOND)
+}
+
+/// Returns the size of a page in bytes (`sysconf(_SC_PAGESIZE)`).
+///
+/// Values in `/proc` which are measured in pages, such as `Statm::resident`, are multiplied by this
+/// value to convert them to bytes. The value is queried once and cached, since it cannot change
+/// while the system is running.
+pub fn page_size() -> u64 {
+    cached_sysconf(&PAGE_SIZE, Variable::PageSize, DEFAULT_PAGE_SIZE)
+}
+
+/// Converts a number of clock ticks to a duration.
+pub fn ticks_to_duration(ticks: u64, ticks_per_second: u64) -> Duration {
+    let nanos = (ticks % ticks_per_second) * 1_000_000_000 / ticks_per_second;
+    Duration::new(ticks / ticks_per_second, nanos as u32)
+}
+
+/// Converts a number of pages to bytes, saturating at `u64::MAX`.
+pub fn pages_to_bytes(pages: u64, page_size: u64) -> u64 {
+    pages.saturating_mul(page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{page_size, pages_to_bytes, ticks_per_second, ticks_to_duration};
+
+    #[test]
+    fn test_ticks_per_second() {
+        assert!(ticks_per_second() > 0);
+        assert_eq!(ticks_per_second(), ticks_per_second());
+    }
+
+    #[test]
+    fn test_page_size() {
+        assert!(page_size().is_power_of_two());
+        assert_eq!(page_size(), page_size());
+    }
+
+    #[test]
+    fn test_ticks_to_duration() {
+        assert_eq!(Duration::from_millis(2500), ticks_to_duration(250, 100));
+        assert_eq!(Duration::new(1, 1_000_000_000 / 3), ticks_to_duration(4, 3));
+        assert_eq!(Duration::from_secs(u64::MAX / 100), ticks_to_duration(u64::MAX / 100 * 100, 100));
+    }
+
+    #[test]
+    fn test_pages_to_bytes() {
+        assert_eq!(0, pages_to_bytes(0, 4096));
+        assert_eq!(8388608, pages_to_bytes(2048, 4096));
+        assert_eq!(8388608, pages_to_bytes(128, 65536));
+        assert_eq!(u64::MAX, pages_to_bytes(u64::MAX / 2, 4096));
+    }
+}
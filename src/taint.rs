@@ -0,0 +1,242 @@
+//! Kernel taint flags from `/proc/sys/kernel/tainted`.
+
+use std::fmt;
+use std::io::Result;
+
+use sys;
+
+/// A reason for which the kernel is tainted.
+///
+/// See `Linux/Documentation/admin-guide/tainted-kernels.rst`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TaintFlag {
+    /// A proprietary module was loaded (`P`).
+    ProprietaryModule,
+    /// A module was force loaded (`F`).
+    ForcedModule,
+    /// The kernel is running on an out of specification system (`S`).
+    CpuOutOfSpec,
+    /// A module was force unloaded (`R`).
+    ForcedRmmod,
+    /// A machine check exception occurred (`M`).
+    MachineCheck,
+    /// A bad page was referenced, or some unexpected page flags were seen (`B`).
+    BadPage,
+    /// The taint was requested by a userspace application (`U`).
+    User,
+    /// The kernel died recently, i.e. there was an oops or a BUG (`D`).
+    Die,
+    /// An ACPI table was overridden by the user (`A`).
+    OverriddenAcpiTable,
+    /// The kernel issued a warning (`W`).
+    Warn,
+    /// A staging driver was loaded (`C`).
+    Crap,
+    /// A workaround for a bug in the platform firmware was applied (`I`).
+    FirmwareWorkaround,
+    /// An externally-built, out-of-tree, module was loaded (`O`).
+    OotModule,
+    /// An unsigned module was loaded (`E`).
+    UnsignedModule,
+    /// A soft lockup occurred (`L`).
+    SoftLockup,
+    /// The kernel was live patched (`K`).
+    Livepatch,
+    /// An auxiliary taint, defined for and used by distributions (`X`).
+    Aux,
+    /// The kernel was built with the struct randomization plugin (`T`).
+    Randstruct,
+    /// An in-kernel test was run (`N`, since Linux 6.0).
+    Test,
+}
+
+/// Every taint flag, with its bit, letter and name.
+const TAINTS: [(TaintFlag, u32, char, &str); 19] = [
+    (TaintFlag::ProprietaryModule,   0, 'P', "TAINT_PROPRIETARY_MODULE"),
+    (TaintFlag::ForcedModule,        1, 'F', "TAINT_FORCED_MODULE"),
+    (TaintFlag::CpuOutOfSpec,        2, 'S', "TAINT_CPU_OUT_OF_SPEC"),
+    (TaintFlag::ForcedRmmod,         3, 'R', "TAINT_FORCED_RMMOD"),
+    (TaintFlag::MachineCheck,        4, 'M', "TAINT_MACHINE_CHECK"),
+    (TaintFlag::BadPage,             5, 'B', "TAINT_BAD_PAGE"),
+    (TaintFlag::User,                6, 'U', "TAINT_USER"),
+    (TaintFlag::Die,                 7, 'D', "TAINT_DIE"),
+    (TaintFlag::OverriddenAcpiTable, 8, 'A', "TAINT_OVERRIDDEN_ACPI_TABLE"),
+    (TaintFlag::Warn,                9, 'W', "TAINT_WARN"),
+    (TaintFlag::Crap,               10, 'C', "TAINT_CRAP"),
+    (TaintFlag::FirmwareWorkaround, 11, 'I', "TAINT_FIRMWARE_WORKAROUND"),
+    (TaintFlag::OotModule,          12, 'O', "TAINT_OOT_MODULE"),
+    (TaintFlag::UnsignedModule,     13, 'E', "TAINT_UNSIGNED_MODULE"),
+    (TaintFlag::SoftLockup,         14, 'L', "TAINT_SOFTLOCKUP"),
+    (TaintFlag::Livepatch,          15, 'K', "TAINT_LIVEPATCH"),
+    (TaintFlag::Aux,                16, 'X', "TAINT_AUX"),
+    (TaintFlag::Randstruct,         17, 'T', "TAINT_RANDSTRUCT"),
+    (TaintFlag::Test,               18, 'N', "TAINT_TEST"),
+];
+
+impl TaintFlag {
+    /// Returns the bit of the flag in the taint mask.
+    pub fn bit(&self) -> u64 {
+        1 << TAINTS[*self as usize].1
+    }
+
+    /// Returns the letter which the kernel uses for the flag in oops reports, e.g. `W`.
+    pub fn letter(&self) -> char {
+        TAINTS[*self as usize].2
+    }
+
+    /// Returns the name of the flag, e.g. `TAINT_WARN`.
+    pub fn name(&self) -> &'static str {
+        TAINTS[*self as usize].3
+    }
+}
+
+/// The taint mask of the kernel.
+///
+/// Bits which do not correspond to a known flag are preserved, and are included in `raw()`.
+///
+/// The default value is untainted. When serialized with the `serde` feature, the flags are
+/// represented as the raw mask.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TaintFlags(u64);
+
+impl TaintFlags {
+    /// Returns the flags with the provided mask.
+    pub fn from_raw(raw: u64) -> TaintFlags {
+        TaintFlags(raw)
+    }
+
+    /// Returns the taint mask, including unknown bits.
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the bits of the mask which do not correspond to a known flag.
+    pub fn unknown(&self) -> u64 {
+        TAINTS.iter().fold(self.0, |raw, &(flag, _, _, _)| raw & !flag.bit())
+    }
+
+    /// Returns `true` if the kernel is tainted for any reason, including unknown ones.
+    pub fn is_tainted(&self) -> bool {
+        self.0 != 0
+    }
+
+    /// Returns `true` if the flag is set.
+    pub fn has(&self, flag: TaintFlag) -> bool {
+        self.0 & flag.bit() != 0
+    }
+
+    /// Returns an iterator over the known flags which are set, in order of bit.
+    pub fn iter(&self) -> TaintFlagsIter {
+        TaintFlagsIter { raw: self.0, idx: 0 }
+    }
+}
+
+impl IntoIterator for &TaintFlags {
+    type Item = TaintFlag;
+    type IntoIter = TaintFlagsIter;
+
+    fn into_iter(self) -> TaintFlagsIter {
+        self.iter()
+    }
+}
+
+/// Formats the flags like the `Tainted:` field of a kernel oops report, without the padding: the
+/// letters of the set flags in order of bit, e.g. `PWO`, with `G` in place of `P` if no proprietary
+/// module was loaded, e.g. `GWO`. An untainted kernel is formatted as `Not tainted`.
+///
+/// Unknown bits are not formatted.
+impl fmt::Display for TaintFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_tainted() {
+            return f.write_str("Not tainted");
+        }
+        if !self.has(TaintFlag::ProprietaryModule) {
+            f.write_str("G")?;
+        }
+        for flag in self {
+            write!(f, "{}", flag.letter())?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the known flags which are set in a taint mask.
+#[derive(Clone, Debug)]
+pub struct TaintFlagsIter {
+    raw: u64,
+    idx: usize,
+}
+
+impl Iterator for TaintFlagsIter {
+    type Item = TaintFlag;
+
+    fn next(&mut self) -> Option<TaintFlag> {
+        while let Some(&(flag, _, _, _)) = TAINTS.get(self.idx) {
+            self.idx += 1;
+            if self.raw & flag.bit() != 0 {
+                return Some(flag);
+            }
+        }
+        None
+    }
+}
+
+/// Returns the taint flags of the running kernel (`kernel.tainted`).
+pub fn kernel_tainted() -> Result<TaintFlags> {
+    sys::read("kernel.tainted").map(TaintFlags::from_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TAINTS, TaintFlag, TaintFlags, kernel_tainted};
+
+    #[test]
+    fn test_taint_bits() {
+        for (idx, &(flag, bit, _, _)) in TAINTS.iter().enumerate() {
+            assert_eq!(idx, flag as usize);
+            assert_eq!(idx as u32, bit);
+        }
+        assert_eq!(0x200, TaintFlag::Warn.bit());
+        assert_eq!('O', TaintFlag::OotModule.letter());
+        assert_eq!("TAINT_UNSIGNED_MODULE", TaintFlag::UnsignedModule.name());
+    }
+
+    #[test]
+    fn test_taint_flags() {
+        let untainted = TaintFlags::from_raw(0);
+        assert!(!untainted.is_tainted());
+        assert_eq!(0, untainted.iter().count());
+        assert_eq!("Not tainted", untainted.to_string());
+        assert_eq!(TaintFlags::default(), untainted);
+
+        // A warning and an out-of-tree module.
+        let warn = TaintFlags::from_raw(4608);
+        assert!(warn.is_tainted());
+        assert!(warn.has(TaintFlag::Warn) && warn.has(TaintFlag::OotModule));
+        assert!(!warn.has(TaintFlag::ProprietaryModule));
+        assert_eq!(vec![TaintFlag::Warn, TaintFlag::OotModule], warn.iter().collect::<Vec<_>>());
+        assert_eq!("GWO", warn.to_string());
+
+        // A proprietary, out-of-tree, unsigned module, such as a graphics driver.
+        let proprietary = TaintFlags::from_raw(12289);
+        assert_eq!(vec![TaintFlag::ProprietaryModule, TaintFlag::OotModule, TaintFlag::UnsignedModule],
+                   (&proprietary).into_iter().collect::<Vec<_>>());
+        assert_eq!("POE", proprietary.to_string());
+
+        // An oops, followed by a warning, with a bit unknown to this library.
+        let unknown = TaintFlags::from_raw(1 << 7 | 1 << 9 | 1 << 40);
+        assert_eq!(1 << 40, unknown.unknown());
+        assert_eq!("GDW", unknown.to_string());
+        assert_eq!(1 << 7 | 1 << 9 | 1 << 40, unknown.raw());
+        assert_eq!("G", TaintFlags::from_raw(1 << 40).to_string());
+    }
+
+    /// Test that the taint flags of the running kernel can be read.
+    #[test]
+    fn test_kernel_tainted() {
+        let tainted = kernel_tainted().unwrap();
+        assert_eq!(tainted.is_tainted(), tainted.to_string() != "Not tainted");
+    }
+}
@@ -0,0 +1,92 @@
+//! System uptime from `/proc/uptime`.
+
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::Result;
+use std::time::Duration;
+
+use nom::{digit, line_ending, space};
+
+use parsers::{map_result_strict, parse_u64, read_to_end};
+use platform;
+
+/// The time since boot, and the time spent idle.
+///
+/// See `man 5 proc` and `Linux/fs/proc/uptime.c`.
+///
+/// The default value has both durations set to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Uptime {
+    /// Time since boot, including time spent in suspend.
+    pub uptime: Duration,
+    /// Time spent idle, summed over every CPU, so it may exceed `uptime` on multi-CPU systems.
+    pub idle: Duration,
+}
+
+impl Uptime {
+    /// Parses the contents of a `/proc/uptime` file.
+    pub fn parse(input: &[u8]) -> Result<Uptime> {
+        map_result_strict(input, parse_uptime)
+    }
+}
+
+/// Converts the fractional digits of a number of seconds to nanoseconds, ignoring digits beyond
+/// nanosecond precision.
+fn fraction_to_nanos(fraction: &[u8]) -> u32 {
+    (0..9).fold(0, |nanos, idx| {
+        nanos * 10 + fraction.get(idx).map_or(0, |digit| u32::from(digit - b'0'))
+    })
+}
+
+/// Parses a number of seconds with a fractional part, e.g. `350735.47`.
+named!(parse_seconds<Duration>,
+       do_parse!(secs: parse_u64 >> tag!(".") >> fraction: digit >>
+                 (Duration::new(secs, fraction_to_nanos(fraction)))));
+
+/// Parses the uptime file format.
+named!(parse_uptime<Uptime>,
+       do_parse!(uptime: parse_seconds >> space >>
+                 idle: parse_seconds   >> line_ending >>
+                 (Uptime { uptime, idle })));
+
+/// Returns the time since boot, and the time spent idle.
+pub fn uptime() -> Result<Uptime> {
+    let mut buf = Vec::with_capacity(64); // A typical uptime file is about 25 bytes.
+    let mut file = platform::open("/proc/uptime")?;
+    Uptime::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// Returns the time since boot, and the time spent idle, reading `/proc/uptime` asynchronously.
+#[cfg(feature = "async")]
+pub fn uptime_async() -> impl Future<Output = Result<Uptime>> + Send {
+    platform::read_async("/proc/uptime".to_owned(), Uptime::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Uptime, uptime};
+
+    #[test]
+    fn test_parse_uptime() {
+        assert_eq!(Uptime { uptime: Duration::new(350735, 470_000_000), idle: Duration::new(234388, 900_000_000) },
+                   Uptime::parse(b"350735.47 234388.90\n").unwrap());
+        assert_eq!(Uptime { uptime: Duration::new(0, 1), idle: Duration::new(1, 123_456_789) },
+                   Uptime::parse(b"0.000000001 1.1234567899\n").unwrap());
+
+        for input in &[&b""[..], b"350735 234388.90\n", b"350735.47\n", b"350735.47 234388.90",
+                       b"350735.47 234388.90 1.00\n", b"-1.00 1.00\n"] {
+            assert!(Uptime::parse(input).is_err(), "{:?}", input);
+        }
+    }
+
+    /// Test that the system uptime file can be parsed.
+    #[test]
+    fn test_uptime() {
+        let earlier = uptime().unwrap();
+        assert!(earlier.uptime > Duration::from_secs(0));
+        assert!(uptime().unwrap().uptime >= earlier.uptime);
+    }
+}
@@ -0,0 +1,101 @@
+//! Virtual memory statistics from `/proc/vmstat`.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::Result;
+use std::str;
+
+use nom::{IResult, line_ending, space};
+
+use parsers::{map_result_strict, parse_u64, read_to_end};
+use platform;
+
+/// Virtual memory statistics.
+///
+/// The set of statistics varies widely between kernel versions and configurations, so they are
+/// kept by name. Statistics prefixed with `nr_` are current counts of pages, and most others are
+/// event counters which increase monotonically since boot, such as `pgfault` and `pswpin`.
+///
+/// See `Linux/mm/vmstat.c`.
+///
+/// The default value has no statistics.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VmStat {
+    /// The statistics, by name.
+    pub stats: BTreeMap<String, u64>,
+}
+
+impl VmStat {
+    /// Parses the contents of a `/proc/vmstat` file.
+    pub fn parse(input: &[u8]) -> Result<VmStat> {
+        map_result_strict(input, parse_vmstat)
+    }
+
+    /// Returns the statistic with the provided name, or `None` if the kernel does not report it.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.stats.get(name).cloned()
+    }
+}
+
+/// Parses the name at the start of a line.
+named!(parse_name<&'a str>, map_res!(is_not!(" \n"), str::from_utf8));
+
+/// Parses the vmstat file format.
+fn parse_vmstat(input: &[u8]) -> IResult<&[u8], VmStat> {
+    let mut vmstat = VmStat::default();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let (i, (name, value)) = try_parse!(rest, terminated!(separated_pair!(parse_name, space, parse_u64),
+                                                              line_ending));
+        vmstat.stats.insert(name.to_owned(), value);
+        rest = i;
+    }
+    IResult::Done(rest, vmstat)
+}
+
+/// Returns the virtual memory statistics.
+pub fn vmstat() -> Result<VmStat> {
+    let mut buf = Vec::with_capacity(8192); // A typical vmstat file is about 5KiB.
+    let mut file = platform::open("/proc/vmstat")?;
+    VmStat::parse(read_to_end(&mut file, &mut buf)?)
+}
+
+/// Returns the virtual memory statistics, reading `/proc/vmstat` asynchronously.
+#[cfg(feature = "async")]
+pub fn vmstat_async() -> impl Future<Output = Result<VmStat>> + Send {
+    platform::read_async("/proc/vmstat".to_owned(), VmStat::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VmStat, vmstat};
+
+    #[test]
+    fn test_parse_vmstat() {
+        let vmstat = VmStat::parse(b"nr_free_pages 646319\n\
+                                     nr_zone_inactive_anon 52036\n\
+                                     pgfault 1164386337\n\
+                                     pswpin 18446744073709551615\n").unwrap();
+        assert_eq!(4, vmstat.stats.len());
+        assert_eq!(Some(646319), vmstat.get("nr_free_pages"));
+        assert_eq!(Some(1164386337), vmstat.get("pgfault"));
+        assert_eq!(Some(u64::MAX), vmstat.get("pswpin"));
+        assert_eq!(None, vmstat.get("pswpout"));
+
+        assert!(VmStat::parse(b"").unwrap().stats.is_empty());
+        for input in &[&b"pgfault\n"[..], b"pgfault -1\n", b"pgfault 1", b"pgfault 1 2\n"] {
+            assert!(VmStat::parse(input).is_err(), "{:?}", input);
+        }
+    }
+
+    /// Test that the system vmstat file can be parsed.
+    #[test]
+    fn test_vmstat() {
+        let vmstat = vmstat().unwrap();
+        assert!(vmstat.get("nr_free_pages").is_some());
+        assert!(vmstat.get("pgfault").unwrap() > 0);
+    }
+}
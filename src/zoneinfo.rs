@@ -0,0 +1,286 @@
+//! Memory zone information from `/proc/zoneinfo`.
+
+use std::cmp;
+use std::io::{Error, ErrorKind, Result};
+use std::str;
+
+use parsers::read_to_end;
+use platform;
+use sysconf::{page_size, pages_to_bytes};
+
+/// A memory zone of a NUMA node, such as `DMA32` or `Normal`.
+///
+/// All sizes are counts of pages, and have `_bytes` accessors which convert them to bytes. Fields
+/// which are not reported by the kernel are zero.
+///
+/// See `Linux/mm/vmstat.c`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Zone {
+    /// The NUMA node of the zone.
+    pub node: u32,
+    /// The name of the zone.
+    pub name: String,
+    /// Number of free pages.
+    pub free: u64,
+    /// The min watermark, below which only atomic and emergency allocations may proceed.
+    pub min: u64,
+    /// The low watermark, below which `kswapd` starts reclaiming pages.
+    pub low: u64,
+    /// The high watermark, above which `kswapd` stops reclaiming pages.
+    pub high: u64,
+    /// Number of pages spanned by the zone, including holes.
+    pub spanned: u64,
+    /// Number of physical pages in the zone.
+    pub present: u64,
+    /// Number of pages managed by the page allocator (since Linux 3.8).
+    pub managed: u64,
+    /// Number of pages reserved in the zone for allocations which could be satisfied by each
+    /// higher zone, see `/proc/sys/vm/lowmem_reserve_ratio`.
+    pub protection: Vec<u64>,
+}
+
+/// Returns the error for a zoneinfo line which could not be parsed.
+fn parse_error(line: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("unable to parse zoneinfo line: {:?}", line))
+}
+
+/// Parses a zone header line, e.g. `Node 0, zone   Normal`.
+fn parse_header(line: &str) -> Option<(u32, String)> {
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("Node") {
+        return None;
+    }
+    let node = fields.next()?.trim_end_matches(',').parse().ok()?;
+    if fields.next() != Some("zone") {
+        return None;
+    }
+    Some((node, fields.next()?.to_owned()))
+}
+
+/// Parses the protection array of a zone, e.g. `(0, 3024, 5998, 5998)`.
+fn parse_protection(value: &str) -> Option<Vec<u64>> {
+    let value = value.trim();
+    if !value.starts_with('(') || !value.ends_with(')') {
+        return None;
+    }
+    value[1..value.len() - 1].split(',').map(|field| field.trim().parse().ok()).collect()
+}
+
+impl Zone {
+    /// Parses the section of a single zone of a `/proc/zoneinfo` file, starting with the
+    /// `Node <node>, zone <name>` header line.
+    ///
+    /// The per-node statistics, per-zone statistics, and per-CPU page sets are ignored.
+    pub fn parse(input: &[u8]) -> Result<Zone> {
+        let input = str::from_utf8(input).map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        let mut lines = input.lines();
+        let header = lines.next().unwrap_or("");
+        let (node, name) = parse_header(header).ok_or_else(|| parse_error(header))?;
+        let mut zone = Zone { node, name, ..Zone::default() };
+
+        for line in lines {
+            let mut fields = line.split_whitespace();
+            let (key, value) = match (fields.next(), fields.next(), fields.next()) {
+                (Some("pages"), Some("free"), value) => ("pages free", value),
+                (Some("protection:"), _, _) => {
+                    let value = &line[line.find(':').unwrap() + 1..];
+                    zone.protection = parse_protection(value).ok_or_else(|| parse_error(line))?;
+                    continue;
+                }
+                (Some(key), value, _) => (key, value),
+                _ => continue,
+            };
+            // The watermarks and sizes are formatted as `<name> <pages>`, unlike the page set
+            // fields, such as `high: <pages>`, which are ignored.
+            let field = match key {
+                "pages free" => &mut zone.free,
+                "min"        => &mut zone.min,
+                "low"        => &mut zone.low,
+                "high"       => &mut zone.high,
+                "spanned"    => &mut zone.spanned,
+                "present"    => &mut zone.present,
+                "managed"    => &mut zone.managed,
+                _ => continue,
+            };
+            *field = value.and_then(|value| value.parse().ok()).ok_or_else(|| parse_error(line))?;
+        }
+        Ok(zone)
+    }
+
+    /// Returns the number of pages of the zone which are reserved, and are not available to
+    /// userspace allocations: the high watermark plus the largest protection, bounded by the
+    /// number of managed pages.
+    ///
+    /// This is the zone's contribution to the kernel's `totalreserve_pages`.
+    pub fn reserved(&self) -> u64 {
+        let protection = self.protection.iter().cloned().max().unwrap_or(0);
+        let managed = if self.managed > 0 { self.managed } else { self.present };
+        cmp::min(protection.saturating_add(self.high), managed)
+    }
+
+    /// Returns the free memory in bytes.
+    pub fn free_bytes(&self) -> u64 {
+        pages_to_bytes(self.free, page_size())
+    }
+
+    /// Returns the min watermark in bytes.
+    pub fn min_bytes(&self) -> u64 {
+        pages_to_bytes(self.min, page_size())
+    }
+
+    /// Returns the low watermark in bytes.
+    pub fn low_bytes(&self) -> u64 {
+        pages_to_bytes(self.low, page_size())
+    }
+
+    /// Returns the high watermark in bytes.
+    pub fn high_bytes(&self) -> u64 {
+        pages_to_bytes(self.high, page_size())
+    }
+
+    /// Returns the memory spanned by the zone, including holes, in bytes.
+    pub fn spanned_bytes(&self) -> u64 {
+        pages_to_bytes(self.spanned, page_size())
+    }
+
+    /// Returns the physical memory of the zone in bytes.
+    pub fn present_bytes(&self) -> u64 {
+        pages_to_bytes(self.present, page_size())
+    }
+
+    /// Returns the memory managed by the page allocator in bytes.
+    pub fn managed_bytes(&self) -> u64 {
+        pages_to_bytes(self.managed, page_size())
+    }
+
+    /// Returns the reserved memory of the zone in bytes, see `reserved`.
+    pub fn reserved_bytes(&self) -> u64 {
+        pages_to_bytes(self.reserved(), page_size())
+    }
+}
+
+/// Parses the zoneinfo file format.
+pub fn parse_zoneinfo(input: &[u8]) -> Result<Vec<Zone>> {
+    let mut zones = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for line in input.split(|&b| b == b'\n') {
+        if line.starts_with(b"Node ") {
+            if let Some(start) = start {
+                zones.push(Zone::parse(&input[start..offset])?);
+            }
+            start = Some(offset);
+        }
+        offset += line.len() + 1;
+    }
+    if let Some(start) = start {
+        zones.push(Zone::parse(&input[start..])?);
+    }
+    Ok(zones)
+}
+
+/// Returns the memory zones of every NUMA node.
+pub fn zoneinfo() -> Result<Vec<Zone>> {
+    let mut buf = Vec::with_capacity(32 * 1024); // A typical zoneinfo file is about 10KiB per CPU.
+    let mut file = platform::open("/proc/zoneinfo")?;
+    parse_zoneinfo(read_to_end(&mut file, &mut buf)?)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Zone, parse_zoneinfo, zoneinfo};
+
+    /// A zoneinfo file with two zones, without the per-node and per-zone statistics.
+    pub const ZONEINFO: &[u8] = b"Node 0, zone      DMA
+  per-node stats
+      nr_inactive_anon 52036
+      nr_active_anon 3
+  pages free     3840
+        boost    0
+        min      42
+        low      52
+        high     62
+        promo    72
+        spanned  4095
+        present  3998
+        managed  3840
+        cma      0
+        protection: (0, 3024, 5998, 5998, 5998)
+      nr_free_pages 3840
+  pagesets
+    cpu: 0
+              count:    0
+              high:     0
+              batch:    1
+  vm stats threshold: 6
+  node_unreclaimable:  0
+  start_pfn:           1
+Node 0, zone    DMA32
+  pages free     646319
+        boost    0
+        min      8498
+        low      10622
+        high     12746
+        promo    14870
+        spanned  1044480
+        present  782288
+        managed  774334
+        cma      0
+        protection: (0, 0, 2974, 2974, 2974)
+  pagesets
+    cpu: 0
+              count:    1320
+              high:     10622
+              batch:    63
+  node_unreclaimable:  0
+  start_pfn:           4096
+";
+
+    #[test]
+    fn test_parse_zoneinfo() {
+        let zones = parse_zoneinfo(ZONEINFO).unwrap();
+        assert_eq!(vec![Zone {
+                            node: 0,
+                            name: "DMA".to_owned(),
+                            free: 3840,
+                            min: 42,
+                            low: 52,
+                            high: 62,
+                            spanned: 4095,
+                            present: 3998,
+                            managed: 3840,
+                            protection: vec![0, 3024, 5998, 5998, 5998],
+                        },
+                        Zone {
+                            node: 0,
+                            name: "DMA32".to_owned(),
+                            free: 646319,
+                            min: 8498,
+                            low: 10622,
+                            high: 12746,
+                            spanned: 1044480,
+                            present: 782288,
+                            managed: 774334,
+                            protection: vec![0, 0, 2974, 2974, 2974],
+                        }],
+                   zones);
+
+        // The reserve is bounded by the managed pages.
+        assert_eq!(3840, zones[0].reserved());
+        assert_eq!(2974 + 12746, zones[1].reserved());
+
+        assert!(parse_zoneinfo(b"").unwrap().is_empty());
+        assert!(Zone::parse(b"Node 0, zone DMA\n        min      lots\n").is_err());
+        assert!(Zone::parse(b"Node 0, zone DMA\n        protection: (0, 1\n").is_err());
+        assert!(Zone::parse(b"Node zero, zone DMA\n").is_err());
+    }
+
+    /// Test that the system zoneinfo file can be parsed.
+    #[test]
+    fn test_zoneinfo() {
+        let zones = zoneinfo().unwrap();
+        assert!(!zones.is_empty());
+        assert!(zones.iter().any(|zone| zone.managed > 0 && zone.high > zone.low && zone.low > zone.min));
+    }
+}